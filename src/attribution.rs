@@ -0,0 +1,387 @@
+//! Marketing attribution: the `utm_*` parameters of the landing page's URL and the referrer are
+//! captured into a first-touch cookie, written once and never overwritten, and a last-touch
+//! cookie, overwritten by later visits according to a
+//! [LastTouchOverwrite](enum.LastTouchOverwrite.html) rule.
+//!
+//! Direct visits (without campaign parameters nor external referrer) and referrals from the site
+//! itself aren't touches, so they never overwrite anything.
+
+use crate::cookies::{self, CookieOptions};
+use std::time::Duration;
+
+/// Name of the first-touch cookie.
+pub const FIRST_TOUCH_COOKIE_NAME: &str = "attribution_first_touch";
+
+/// Name of the last-touch cookie.
+pub const LAST_TOUCH_COOKIE_NAME: &str = "attribution_last_touch";
+
+/// Maximum lifetime of the cookies: browsers cap cookie expiration dates to
+/// [MAX_EXPIRY](../cookies/constant.MAX_EXPIRY.html), so longer lifetimes are capped to it.
+pub const MAX_LIFETIME: Duration = cookies::MAX_EXPIRY;
+
+/// The attribution data of a visit.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct Touch {
+    /// `utm_source` parameter.
+    pub source: Option<String>,
+
+    /// `utm_medium` parameter.
+    pub medium: Option<String>,
+
+    /// `utm_campaign` parameter.
+    pub campaign: Option<String>,
+
+    /// `utm_term` parameter.
+    pub term: Option<String>,
+
+    /// `utm_content` parameter.
+    pub content: Option<String>,
+
+    /// External referrer (`document.referrer`).
+    pub referrer: Option<String>,
+}
+
+fn decode_query_component(component: &str) -> Option<String> {
+    urlencoding::decode(&component.replace('+', " ")).ok()
+}
+
+fn referrer_host(referrer: &str) -> &str {
+    let without_scheme = referrer
+        .split_once("://")
+        .map_or(referrer, |(_, rest)| rest);
+
+    without_scheme
+        .split(['/', '?', '#', ':'])
+        .next()
+        .unwrap_or_default()
+}
+
+impl Touch {
+    /// Captures the touch of a visit from the query string of the page's URL (with or without
+    /// the leading `?`), the referrer and the page's host. Returns `None` for a direct visit or a
+    /// referral from `host`.
+    pub fn from_visit(query: &str, referrer: &str, host: &str) -> Option<Self> {
+        let mut touch = Self::default();
+
+        for pair in query.trim_start_matches('?').split('&') {
+            let (key, value) = match pair.split_once('=') {
+                Some((key, value)) if !value.is_empty() => (key, value),
+                _ => continue,
+            };
+
+            let field = match key {
+                "utm_source" => &mut touch.source,
+                "utm_medium" => &mut touch.medium,
+                "utm_campaign" => &mut touch.campaign,
+                "utm_term" => &mut touch.term,
+                "utm_content" => &mut touch.content,
+                _ => continue,
+            };
+
+            *field = decode_query_component(value);
+        }
+
+        let referrer_host = referrer_host(referrer);
+
+        if !referrer_host.is_empty() && !referrer_host.eq_ignore_ascii_case(host) {
+            touch.referrer = Some(referrer.to_owned());
+        }
+
+        if touch.has_campaign() || touch.referrer.is_some() {
+            Some(touch)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the touch has at least one `utm_*` parameter.
+    pub fn has_campaign(&self) -> bool {
+        self.source.is_some()
+            || self.medium.is_some()
+            || self.campaign.is_some()
+            || self.term.is_some()
+            || self.content.is_some()
+    }
+
+    fn fields(&self) -> [(&'static str, &Option<String>); 6] {
+        [
+            ("source", &self.source),
+            ("medium", &self.medium),
+            ("campaign", &self.campaign),
+            ("term", &self.term),
+            ("content", &self.content),
+            ("referrer", &self.referrer),
+        ]
+    }
+
+    /// Returns the value of a touch cookie: the fields as an URI encoded query string.
+    pub fn cookie_value(&self) -> String {
+        self.fields()
+            .iter()
+            .filter_map(|(key, value)| {
+                value
+                    .as_ref()
+                    .map(|value| format!("{}={}", key, urlencoding::encode(value)))
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// Parses the value of a touch cookie. Unknown and undecodable fields are ignored.
+    pub fn parse(value: &str) -> Self {
+        let mut touch = Self::default();
+
+        for (key, value) in value.split('&').filter_map(|pair| pair.split_once('=')) {
+            let field = match key {
+                "source" => &mut touch.source,
+                "medium" => &mut touch.medium,
+                "campaign" => &mut touch.campaign,
+                "term" => &mut touch.term,
+                "content" => &mut touch.content,
+                "referrer" => &mut touch.referrer,
+                _ => continue,
+            };
+
+            *field = urlencoding::decode(value).ok();
+        }
+
+        touch
+    }
+}
+
+/// When a touch overwrites the last-touch cookie.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LastTouchOverwrite {
+    /// Every touch overwrites it.
+    Always,
+
+    /// Touches without `utm_*` parameters (referrals) overwrite it only if it doesn't hold a
+    /// campaign touch, so organic referrals don't erase paid campaigns.
+    PreferCampaigns,
+}
+
+/// Configuration of the attribution cookies.
+#[derive(Clone, Debug)]
+pub struct AttributionConfig {
+    /// Lifetime of the first-touch cookie, capped to [MAX_LIFETIME](constant.MAX_LIFETIME.html).
+    /// The default is 90 days.
+    pub first_touch_lifetime: Duration,
+
+    /// Lifetime of the last-touch cookie, capped to [MAX_LIFETIME](constant.MAX_LIFETIME.html).
+    /// The default is 30 days.
+    pub last_touch_lifetime: Duration,
+
+    /// When a touch overwrites the last-touch cookie. The default is `PreferCampaigns`.
+    pub last_touch_overwrite: LastTouchOverwrite,
+
+    /// Options of the cookies, whose expiration date is replaced according to the lifetimes.
+    /// The default is `CookieOptions::ROOT`.
+    pub options: CookieOptions<'static>,
+}
+
+impl Default for AttributionConfig {
+    fn default() -> Self {
+        Self {
+            first_touch_lifetime: Duration::from_secs(90 * 24 * 60 * 60),
+            last_touch_lifetime: Duration::from_secs(30 * 24 * 60 * 60),
+            last_touch_overwrite: LastTouchOverwrite::PreferCampaigns,
+            options: CookieOptions::ROOT,
+        }
+    }
+}
+
+impl AttributionConfig {
+    fn options(&self, lifetime: Duration) -> CookieOptions<'static> {
+        self.options
+            .clone()
+            .expires_after(lifetime.min(MAX_LIFETIME))
+    }
+}
+
+/// Returns the touch stored in the first-touch cookie, if it exists.
+pub fn first_touch(cookie_string: &str) -> Option<Touch> {
+    cookies::get_raw(cookie_string, FIRST_TOUCH_COOKIE_NAME).map(|value| Touch::parse(&value))
+}
+
+/// Returns the touch stored in the last-touch cookie, if it exists.
+pub fn last_touch(cookie_string: &str) -> Option<Touch> {
+    cookies::get_raw(cookie_string, LAST_TOUCH_COOKIE_NAME).map(|value| Touch::parse(&value))
+}
+
+/// Returns the cookie strings that record `touch`: the first-touch cookie if it doesn't exist
+/// yet, and the last-touch cookie according to `config.last_touch_overwrite`.
+pub fn record(cookie_string: &str, touch: &Touch, config: &AttributionConfig) -> Vec<String> {
+    let mut cookie_strings = Vec::new();
+    let value = touch.cookie_value();
+
+    if first_touch(cookie_string).is_none() {
+        cookie_strings.push(cookies::set_raw(
+            FIRST_TOUCH_COOKIE_NAME,
+            &value,
+            &config.options(config.first_touch_lifetime),
+        ));
+    }
+
+    let overwrite = match (config.last_touch_overwrite, last_touch(cookie_string)) {
+        (LastTouchOverwrite::PreferCampaigns, Some(last_touch)) => {
+            touch.has_campaign() || !last_touch.has_campaign()
+        }
+        _ => true,
+    };
+
+    if overwrite {
+        cookie_strings.push(cookies::set_raw(
+            LAST_TOUCH_COOKIE_NAME,
+            &value,
+            &config.options(config.last_touch_lifetime),
+        ));
+    }
+
+    cookie_strings
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{AttributionConfig, Touch};
+
+    /// Captures the touch of the current page from `location.search` and `document.referrer`,
+    /// records it, and returns it. Returns `None` for a direct visit or an internal referral.
+    pub fn capture(config: &AttributionConfig) -> Option<Touch> {
+        let location = web_sys::window().unwrap().location();
+        let touch = Touch::from_visit(
+            &location.search().unwrap(),
+            &crate::expect_js(crate::try_document()).referrer(),
+            &location.hostname().unwrap(),
+        )?;
+
+        for cookie_string in super::record(&crate::cookie_string(), &touch, config) {
+            crate::set_cookie_string(&cookie_string);
+        }
+
+        Some(touch)
+    }
+
+    /// Returns the touch stored in the first-touch cookie, if it exists.
+    pub fn first_touch() -> Option<Touch> {
+        super::first_touch(&crate::cookie_string())
+    }
+
+    /// Returns the touch stored in the last-touch cookie, if it exists.
+    pub fn last_touch() -> Option<Touch> {
+        super::last_touch(&crate::cookie_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie_pair(cookie_string: &str) -> &str {
+        cookie_string.split(';').next().unwrap()
+    }
+
+    #[test]
+    fn test_from_visit() {
+        let touch = Touch::from_visit(
+            "?utm_source=news+letter&utm_medium=email&utm_campaign=spring%20sale&utm_term=&page=2",
+            "",
+            "example.com",
+        )
+        .unwrap();
+        assert_eq!(touch.source.as_deref(), Some("news letter"));
+        assert_eq!(touch.medium.as_deref(), Some("email"));
+        assert_eq!(touch.campaign.as_deref(), Some("spring sale"));
+        assert_eq!(touch.term, None);
+        assert_eq!(touch.referrer, None);
+        assert!(touch.has_campaign());
+
+        let touch = Touch::from_visit("", "https://search.test/?q=a", "example.com").unwrap();
+        assert_eq!(touch.referrer.as_deref(), Some("https://search.test/?q=a"));
+        assert!(!touch.has_campaign());
+
+        assert_eq!(Touch::from_visit("page=2", "", "example.com"), None);
+        assert_eq!(
+            Touch::from_visit("", "https://Example.com:8080/a", "example.com"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cookie_value() {
+        let touch = Touch {
+            source: Some("a&b=c".to_owned()),
+            referrer: Some("https://search.test/?q=a;b".to_owned()),
+            ..Touch::default()
+        };
+        let value = touch.cookie_value();
+        assert!(!value.contains(';'));
+        assert_eq!(Touch::parse(&value), touch);
+        assert_eq!(
+            Touch::parse("unknown=1&term=x"),
+            Touch {
+                term: Some("x".to_owned()),
+                ..Touch::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let config = AttributionConfig::default();
+        let campaign = Touch::from_visit("utm_source=ads", "", "example.com").unwrap();
+        let referral = Touch::from_visit("", "https://blog.test/", "example.com").unwrap();
+
+        let cookie_strings = record("", &campaign, &config);
+        assert_eq!(cookie_strings.len(), 2);
+        assert_eq!(
+            cookie_pair(&cookie_strings[0]),
+            "attribution_first_touch=source=ads"
+        );
+        assert_eq!(
+            cookie_pair(&cookie_strings[1]),
+            "attribution_last_touch=source=ads"
+        );
+        assert!(cookie_strings[0].contains(";expires="));
+
+        let cookie_string = "attribution_first_touch=source=ads; attribution_last_touch=source=ads";
+        assert!(record(cookie_string, &referral, &config).is_empty());
+
+        let cookie_strings = record(
+            cookie_string,
+            &referral,
+            &AttributionConfig {
+                last_touch_overwrite: LastTouchOverwrite::Always,
+                ..AttributionConfig::default()
+            },
+        );
+        assert_eq!(cookie_strings.len(), 1);
+        assert!(cookie_strings[0].starts_with("attribution_last_touch=referrer="));
+
+        let cookie_string = "attribution_first_touch=referrer=x; attribution_last_touch=referrer=x";
+        assert_eq!(record(cookie_string, &referral, &config).len(), 1);
+        assert_eq!(record(cookie_string, &campaign, &config).len(), 1);
+
+        assert_eq!(
+            first_touch(cookie_string).unwrap().referrer.as_deref(),
+            Some("x")
+        );
+        assert_eq!(last_touch("other=1"), None);
+    }
+
+    #[test]
+    fn test_lifetime_cap() {
+        let config = AttributionConfig {
+            first_touch_lifetime: Duration::from_secs(10 * 365 * 24 * 60 * 60),
+            ..AttributionConfig::default()
+        };
+
+        let options = config.options(config.first_touch_lifetime);
+        let expires = cookies::parse_date(options.expires.as_deref().unwrap()).unwrap();
+        let max_expires = chrono::Utc::now().timestamp_millis() + MAX_LIFETIME.as_millis() as i64;
+        assert!((expires - max_expires).abs() < 60_000);
+    }
+}
@@ -0,0 +1,244 @@
+//! In-memory cookie backend of the root functions on targets other than WebAssembly, such as the
+//! host target of unit tests or of server-side rendering code, where there is no document.
+//!
+//! Once a [TestBackend](struct.TestBackend.html) is installed with
+//! `wasm_cookies::set_test_backend`, the root functions (`get`, `set`, `all`, `delete`...) read
+//! and write its [SimulatedJar](../jar/struct.SimulatedJar.html) as `document.cookie` would on
+//! the page at its URL. Without a backend, the fallible functions return
+//! `WasmCookiesError::UnsupportedTarget`, and the other ones install an empty
+//! `TestBackend::new()` first.
+//!
+//! Available only on targets other than WebAssembly.
+
+use crate::cookies::Cookie;
+use crate::jar::SimulatedJar;
+use crate::url;
+
+/// Default URL of the simulated page.
+pub const DEFAULT_URL: &str = "https://localhost/";
+
+/// Cookies of a simulated page, stored in memory.
+#[derive(Clone, Debug)]
+pub struct TestBackend {
+    jar: SimulatedJar,
+    url: String,
+}
+
+impl Default for TestBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestBackend {
+    /// Creates an empty backend for the page [DEFAULT_URL](constant.DEFAULT_URL.html).
+    pub fn new() -> Self {
+        Self {
+            jar: SimulatedJar::new(),
+            url: DEFAULT_URL.to_owned(),
+        }
+    }
+
+    /// Sets the URL of the simulated page, which gives the host, the path and the secure context
+    /// of the cookies.
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Returns the URL of the simulated page.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the stored cookies.
+    pub fn jar(&self) -> &SimulatedJar {
+        &self.jar
+    }
+
+    /// Returns the host of the simulated page, or an empty string if its URL is malformed.
+    pub fn host(&self) -> String {
+        url::parse(&self.url).map_or_else(String::new, |url| url.host)
+    }
+
+    /// Returns the path of the simulated page, or `/` if its URL is malformed.
+    pub fn path(&self) -> &str {
+        url::parse(&self.url).map_or("/", |url| url.path)
+    }
+
+    /// Returns true if the simulated page is a secure context (its scheme is `https`).
+    pub fn is_secure_context(&self) -> bool {
+        url::parse(&self.url).is_some_and(|url| url.is_secure())
+    }
+
+    /// Returns the cookie string as `document.cookie` would return it at `now` (timestamp in
+    /// milliseconds, UTC).
+    pub fn cookie_string(&self, now: i64) -> String {
+        self.jar.cookie_header(&self.url, now).unwrap_or_default()
+    }
+
+    /// Stores a cookie string as setting `document.cookie` would do at `now`. Returns false if
+    /// the cookie was ignored, for example because it is `HttpOnly`.
+    pub fn set_cookie_string(&mut self, cookie_string: &str, now: i64) -> bool {
+        match Cookie::parse_set_cookie(cookie_string) {
+            Some(cookie) if !cookie.http_only => self.jar.set_cookie(&self.url, cookie_string, now),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookies::CookieOptions;
+
+    #[test]
+    fn test_backend() {
+        let mut backend = TestBackend::new().with_url("http://example.com/app/page");
+        assert_eq!(backend.host(), "example.com");
+        assert_eq!(backend.path(), "/app/page");
+        assert!(!backend.is_secure_context());
+
+        assert!(backend.set_cookie_string("a=1;path=/", 0));
+        assert!(!backend.set_cookie_string("b=2;secure", 0));
+        assert!(!backend.set_cookie_string("c=3;HttpOnly", 0));
+        assert!(backend.set_cookie_string("d=4;path=/app;max-age=1", 0));
+        assert_eq!(backend.cookie_string(0), "d=4; a=1");
+        assert_eq!(backend.cookie_string(1000), "a=1");
+    }
+
+    #[test]
+    fn test_root_functions() {
+        crate::set_test_backend(None);
+        assert!(matches!(
+            crate::try_cookie_string(),
+            Err(crate::WasmCookiesError::UnsupportedTarget)
+        ));
+
+        assert_eq!(crate::get_raw("a"), None);
+        assert!(crate::test_backend().is_some());
+
+        crate::set_test_backend(Some(TestBackend::new()));
+        crate::set_raw("a", "1", &CookieOptions::ROOT);
+        assert_eq!(crate::get_raw("a").as_deref(), Some("1"));
+        assert_eq!(crate::current_domain(), "localhost");
+
+        crate::delete_raw("a");
+        assert!(crate::test_backend().unwrap().jar().is_empty());
+        crate::set_test_backend(None);
+    }
+
+    #[test]
+    fn test_policy() {
+        use crate::{CookiePolicy, PolicyViolation, TransactionError, WasmCookiesError};
+
+        crate::set_test_backend(Some(TestBackend::new()));
+        crate::set_policy(Some(CookiePolicy {
+            blocked_names: vec!["sdk_internal_*".to_owned()],
+            ..CookiePolicy::default()
+        }));
+        let blocked =
+            PolicyViolation::NameBlocked("sdk_internal_id".to_owned(), "sdk_internal_*".to_owned());
+
+        crate::set_raw("sdk_internal_id", "1", &CookieOptions::ROOT);
+        assert_eq!(
+            crate::increment_raw("sdk_internal_id", 1, &CookieOptions::ROOT),
+            0
+        );
+        assert!(matches!(
+            crate::try_set_raw("sdk_internal_id", "1", &CookieOptions::ROOT),
+            Err(WasmCookiesError::Policy(violation)) if violation == blocked
+        ));
+
+        #[cfg(feature = "encoding")]
+        {
+            assert_eq!(
+                crate::increment("sdk_internal_id", 1, &CookieOptions::ROOT),
+                0
+            );
+            assert!(matches!(
+                crate::try_increment("sdk_internal_id", 1, &CookieOptions::ROOT),
+                Err(WasmCookiesError::Policy(violation)) if violation == blocked
+            ));
+        }
+
+        assert_eq!(
+            crate::transaction(|tx| -> Result<(), ()> {
+                tx.set_raw("sdk_id", "1", &CookieOptions::ROOT);
+                tx.set_raw("sdk_internal_id", "1", &CookieOptions::ROOT);
+                Ok(())
+            }),
+            Err(TransactionError::Policy(blocked))
+        );
+        assert!(crate::test_backend().unwrap().jar().is_empty());
+
+        crate::set_raw("sdk_id", "1", &CookieOptions::ROOT);
+        assert_eq!(crate::get_raw("sdk_id").as_deref(), Some("1"));
+
+        // The rule's path is used by both the write and the deletion, so the cookie set from a
+        // nested page is deleted.
+        #[cfg(feature = "encoding")]
+        {
+            crate::set_test_backend(Some(
+                TestBackend::new().with_url("https://localhost/app/page"),
+            ));
+            crate::set_policy(Some(CookiePolicy {
+                rules: vec![crate::CookieRule {
+                    pattern: "*".to_owned(),
+                    path: Some("/".to_owned()),
+                    ..crate::CookieRule::default()
+                }],
+                ..CookiePolicy::default()
+            }));
+
+            crate::set("theme", "dark", &CookieOptions::default());
+            assert_eq!(crate::test_backend().unwrap().jar().len(), 1);
+            crate::delete("theme");
+            assert!(crate::test_backend().unwrap().jar().is_empty());
+        }
+
+        crate::set_policy(None);
+        crate::set_test_backend(None);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_middleware() {
+        use crate::CookieMiddleware;
+
+        struct Prefix;
+
+        impl CookieMiddleware for Prefix {
+            fn name(&self, name: String) -> String {
+                format!("app_{}", name)
+            }
+
+            fn on_set(&self, cookie: &mut Cookie<'static>) -> bool {
+                cookie.value = format!("v_{}", cookie.value).into();
+                true
+            }
+
+            fn on_get(&self, _name: &str, value: String) -> Option<String> {
+                value.strip_prefix("v_").map(str::to_owned)
+            }
+        }
+
+        crate::set_test_backend(Some(TestBackend::new()));
+        crate::register_middleware(Prefix);
+
+        crate::set("theme", "dark", &CookieOptions::ROOT);
+        assert_eq!(crate::get_raw("theme").as_deref(), Some("v_dark"));
+        assert_eq!(crate::get_lossy("theme").as_deref(), Some("dark"));
+        assert_eq!(crate::take("theme", None).unwrap().unwrap(), "dark");
+        assert!(crate::test_backend().unwrap().jar().is_empty());
+
+        crate::set("theme", "dark", &CookieOptions::ROOT);
+        assert!(crate::delete_existing("theme"));
+        assert_eq!(crate::increment("count", 2, &CookieOptions::ROOT), 2);
+        crate::delete("count");
+        assert!(crate::test_backend().unwrap().jar().is_empty());
+
+        crate::clear_middleware();
+        crate::set_test_backend(None);
+    }
+}
@@ -0,0 +1,79 @@
+//! URL-safe Base64 without padding (RFC 4648 section 5), used to encode binary data in cookie
+//! values without depending on a Base64 crate.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes `bytes` with the URL-safe alphabet, without padding.
+pub(crate) fn encode_url_safe(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 4).div_ceil(3));
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+
+    encoded
+}
+
+/// Decodes URL-safe Base64, with or without padding. Returns `None` if it is invalid.
+pub(crate) fn decode_url_safe(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+
+    for chunk in encoded.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+
+        let mut group = 0u32;
+
+        for (i, c) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|a| a == c)? as u32;
+            group |= value << (18 - 6 * i);
+        }
+
+        for i in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        let vectors: [(&[u8], &str); 7] = [
+            (b"", ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+            (b"fooba", "Zm9vYmE"),
+            (&[0xfb, 0xff, 0xbf], "-_-_"),
+        ];
+
+        for (bytes, encoded) in vectors {
+            assert_eq!(encode_url_safe(bytes), encoded);
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        for encoded in ["", "Zg", "Zm8", "Zm9v", "Zm9vYg", "Zm9vYmE", "-_-_"] {
+            assert_eq!(encode_url_safe(&decode_url_safe(encoded).unwrap()), encoded);
+        }
+
+        assert_eq!(decode_url_safe("Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(decode_url_safe("Z"), None);
+        assert_eq!(decode_url_safe("Zm9+"), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Cookie-backed settings for [Bevy](https://crates.io/crates/bevy) web games, so the settings
+//! are visible to the server without going through JavaScript.
+//!
+//! The [browser::CookieSettingsPlugin](browser/struct.CookieSettingsPlugin.html) loads a `serde`
+//! settings struct from a cookie at startup, inserts it as a
+//! [browser::Settings](browser/struct.Settings.html) resource, and saves it at the end of each
+//! frame where it changed. The settings are stored as a preferences bundle (see
+//! [prefs](../prefs/index.html)), so they must be serialized as an object.
+//!
+//! Available only with the `bevy` feature. The plugin is only available on WebAssembly targets
+//! (`wasm32` and `wasm64`).
+
+use crate::cookies::CookieOptions;
+use crate::prefs::Prefs;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Default name of the settings cookie.
+pub const COOKIE_NAME: &str = "settings";
+
+/// Returns the settings stored in the cookie `name` of `cookie_string`, or the default settings
+/// if the cookie is missing or can't be deserialized as `T`.
+pub fn load<T: DeserializeOwned + Default>(cookie_string: &str, name: &str) -> T {
+    Prefs::load(cookie_string, name)
+        .to_struct()
+        .unwrap_or_default()
+}
+
+/// Returns the cookie string that stores `settings` in the cookie `name`, or an error if they
+/// can't be serialized as an object.
+pub fn save<T: Serialize>(
+    name: &str,
+    settings: &T,
+    options: &CookieOptions<'static>,
+) -> Result<String, serde_json::Error> {
+    let mut prefs = Prefs::new(name).with_options(options.clone());
+    prefs.set_struct(settings)?;
+    Ok(prefs.to_cookie_string())
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use crate::cookies::CookieOptions;
+    use bevy::app::{App, Last, Plugin};
+    use bevy::ecs::prelude::{DetectChanges, Res, Resource};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::marker::PhantomData;
+    use std::ops::{Deref, DerefMut};
+
+    /// Bounds of the settings types.
+    pub trait CookieSettings:
+        Serialize + DeserializeOwned + Default + Send + Sync + 'static
+    {
+    }
+
+    impl<T: Serialize + DeserializeOwned + Default + Send + Sync + 'static> CookieSettings for T {}
+
+    /// Resource holding the settings, saved to their cookie when they change.
+    pub struct Settings<T: CookieSettings> {
+        value: T,
+        name: String,
+        options: CookieOptions<'static>,
+    }
+
+    // Implemented manually, since the derive macro expects a direct dependency on `bevy_ecs`.
+    impl<T: CookieSettings> Resource for Settings<T> {}
+
+    impl<T: CookieSettings> Deref for Settings<T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.value
+        }
+    }
+
+    impl<T: CookieSettings> DerefMut for Settings<T> {
+        fn deref_mut(&mut self) -> &mut T {
+            &mut self.value
+        }
+    }
+
+    /// Plugin loading the settings `T` from their cookie, and saving them when they change.
+    pub struct CookieSettingsPlugin<T> {
+        name: String,
+        options: CookieOptions<'static>,
+        settings: PhantomData<fn() -> T>,
+    }
+
+    impl<T> CookieSettingsPlugin<T> {
+        /// Creates a plugin storing the settings in the cookie `name`.
+        ///
+        /// The options should have an expiration date, so the settings outlive the browser
+        /// session.
+        pub fn new(name: impl Into<String>, options: CookieOptions<'static>) -> Self {
+            Self {
+                name: name.into(),
+                options,
+                settings: PhantomData,
+            }
+        }
+    }
+
+    impl<T: CookieSettings> Plugin for CookieSettingsPlugin<T> {
+        fn build(&self, app: &mut App) {
+            app.insert_resource(Settings {
+                value: super::load::<T>(&crate::cookie_string(), &self.name),
+                name: self.name.clone(),
+                options: self.options.clone(),
+            })
+            .add_systems(Last, save_settings::<T>);
+        }
+    }
+
+    fn save_settings<T: CookieSettings>(settings: Res<Settings<T>>) {
+        if !settings.is_changed() || settings.is_added() {
+            return;
+        }
+
+        // Settings which can't be stored are kept in memory only.
+        if let Ok(cookie_string) = super::save(&settings.name, &settings.value, &settings.options) {
+            crate::set_cookie_string(&cookie_string);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_load_save() {
+        let mut settings = BTreeMap::new();
+        settings.insert("volume".to_owned(), 0.5);
+
+        let cookie_string = save(COOKIE_NAME, &settings, &CookieOptions::ROOT).unwrap();
+        assert!(cookie_string.starts_with("settings=%7B%22volume%22%3A0.5%7D;"));
+
+        let cookie_string = cookie_string.split(';').next().unwrap();
+        assert_eq!(
+            load::<BTreeMap<String, f64>>(cookie_string, COOKIE_NAME),
+            settings
+        );
+        assert!(load::<BTreeMap<String, f64>>("settings=1", COOKIE_NAME).is_empty());
+        assert!(save(COOKIE_NAME, &1, &CookieOptions::ROOT).is_err());
+    }
+}
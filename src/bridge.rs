@@ -0,0 +1,545 @@
+//! `postMessage` protocol which lets a trusted embedded widget (in a cross-origin iframe) request
+//! scoped cookie reads and writes from its parent page.
+//!
+//! The parent page runs a [BridgeHost](struct.BridgeHost.html), which only answers the origins
+//! and the cookie names it has been configured for. The embedded page uses a
+//! [BridgeClient](struct.BridgeClient.html), which only accepts answers from the parent origin.
+//! Names and values are URI encoded and decoded by the host, as with the root module's `set`,
+//! `get` and `delete`.
+//!
+//! The host owns the options of the cookies: the embedded page can only request a lifetime,
+//! which is capped by the host, so it can't widen the scope of a cookie with its path or domain.
+//!
+//! The protocol and the host's access checks are available on every target.
+
+use crate::cookies::{self, CookieOptions};
+use crate::policy::matches_pattern;
+#[cfg(target_family = "wasm")]
+use std::cell::RefCell;
+#[cfg(target_family = "wasm")]
+use std::collections::HashMap;
+#[cfg(target_family = "wasm")]
+use std::rc::Rc;
+use std::time::Duration;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+#[cfg(target_family = "wasm")]
+use web_sys::{MessageEvent, Window};
+
+const MESSAGE_PREFIX: &str = "wasm-cookies-bridge:";
+
+/// A request sent by the embedded page to the parent page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BridgeRequest {
+    /// Get a cookie. The field is the name.
+    Get(String),
+
+    /// Set a cookie. The fields are the name, the value and the requested lifetime, in whole
+    /// seconds, or `None` for the host's default expiration.
+    Set(String, String, Option<Duration>),
+
+    /// Delete a cookie. The field is the name.
+    Delete(String),
+}
+
+/// The answer of the parent page to a [BridgeRequest](enum.BridgeRequest.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BridgeResponse {
+    /// The value of the cookie, if it exists and could be decoded, answering a `Get` request.
+    Value(Option<String>),
+
+    /// The `Set` or `Delete` request has been executed.
+    Done,
+
+    /// The origin or the cookie name isn't allowed by the host.
+    Denied,
+}
+
+fn encode_fields(id: u32, fields: &[&str]) -> String {
+    let mut message = format!("{}{}", MESSAGE_PREFIX, id);
+
+    for field in fields {
+        message.push(':');
+        message.push_str(&urlencoding::encode(field));
+    }
+
+    message
+}
+
+fn decode_fields(message: &str) -> Option<(u32, Vec<String>)> {
+    let mut fields = message.strip_prefix(MESSAGE_PREFIX)?.split(':');
+    let id = fields.next()?.parse().ok()?;
+    let fields = fields
+        .map(urlencoding::decode)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    Some((id, fields))
+}
+
+/// Returns the message that sends `request`, identified by `id`.
+pub fn encode_request(id: u32, request: &BridgeRequest) -> String {
+    match request {
+        BridgeRequest::Get(name) => encode_fields(id, &["get", name]),
+        BridgeRequest::Set(name, value, lifetime) => {
+            let lifetime =
+                lifetime.map_or_else(String::new, |lifetime| lifetime.as_secs().to_string());
+            encode_fields(id, &["set", name, value, &lifetime])
+        }
+        BridgeRequest::Delete(name) => encode_fields(id, &["delete", name]),
+    }
+}
+
+/// Parses a message sent with [encode_request](fn.encode_request.html).
+/// Returns `None` if the message isn't a request of this protocol.
+pub fn decode_request(message: &str) -> Option<(u32, BridgeRequest)> {
+    let (id, mut fields) = decode_fields(message)?;
+
+    let request = match fields.len() {
+        2 if fields[0] == "get" => BridgeRequest::Get(fields.pop()?),
+        2 if fields[0] == "delete" => BridgeRequest::Delete(fields.pop()?),
+        4 if fields[0] == "set" => {
+            let lifetime = match fields.pop()?.as_str() {
+                "" => None,
+                seconds => Some(Duration::from_secs(seconds.parse().ok()?)),
+            };
+            let value = fields.pop()?;
+            BridgeRequest::Set(fields.pop()?, value, lifetime)
+        }
+        _ => return None,
+    };
+
+    Some((id, request))
+}
+
+/// Returns the message that answers the request identified by `id`.
+pub fn encode_response(id: u32, response: &BridgeResponse) -> String {
+    match response {
+        BridgeResponse::Value(Some(value)) => encode_fields(id, &["value", value]),
+        BridgeResponse::Value(None) => encode_fields(id, &["none"]),
+        BridgeResponse::Done => encode_fields(id, &["done"]),
+        BridgeResponse::Denied => encode_fields(id, &["denied"]),
+    }
+}
+
+/// Parses a message sent with [encode_response](fn.encode_response.html).
+/// Returns `None` if the message isn't a response of this protocol.
+pub fn decode_response(message: &str) -> Option<(u32, BridgeResponse)> {
+    let (id, mut fields) = decode_fields(message)?;
+
+    let response = match fields.len() {
+        2 if fields[0] == "value" => BridgeResponse::Value(Some(fields.pop()?)),
+        1 if fields[0] == "none" => BridgeResponse::Value(None),
+        1 if fields[0] == "done" => BridgeResponse::Done,
+        1 if fields[0] == "denied" => BridgeResponse::Denied,
+        _ => return None,
+    };
+
+    Some((id, response))
+}
+
+/// Cookies the embedded pages can access, with the options the host sets them with.
+#[derive(Clone, Debug)]
+pub struct BridgeScope {
+    /// Cookie name pattern, where `*` matches any sequence of characters (for example
+    /// `widget_*`).
+    pub pattern: String,
+
+    /// Options the cookies are set and deleted with.
+    pub options: CookieOptions<'static>,
+
+    /// Longest lifetime the embedded pages can request for the cookies. Longer lifetimes are
+    /// capped. If `None`, the requested lifetime is ignored, and the expiration date of
+    /// `options` is used.
+    pub max_lifetime: Option<Duration>,
+}
+
+/// Access rules of the parent page.
+#[derive(Default, Clone, Debug)]
+pub struct BridgeHost {
+    /// Origins allowed to send requests (for example `https://widget.example.com`).
+    pub allowed_origins: Vec<String>,
+
+    /// Cookies the embedded pages can access. For a given cookie name, only the first matching
+    /// scope applies.
+    pub scopes: Vec<BridgeScope>,
+}
+
+impl BridgeHost {
+    /// Returns the scope of the cookie `name` if the requests of `origin` on it are allowed.
+    pub fn scope(&self, origin: &str, name: &str) -> Option<&BridgeScope> {
+        if !self.allowed_origins.iter().any(|allowed| allowed == origin) {
+            return None;
+        }
+
+        self.scopes
+            .iter()
+            .find(|scope| matches_pattern(&scope.pattern, name))
+    }
+
+    /// Handles the request of `origin` against `cookie_string`, and returns the response and,
+    /// for a write, the cookie string to write.
+    pub fn handle(
+        &self,
+        origin: &str,
+        request: &BridgeRequest,
+        cookie_string: &str,
+    ) -> (BridgeResponse, Option<String>) {
+        let name = match request {
+            BridgeRequest::Get(name)
+            | BridgeRequest::Set(name, _, _)
+            | BridgeRequest::Delete(name) => name,
+        };
+
+        let scope = match self.scope(origin, name) {
+            Some(scope) => scope,
+            None => return (BridgeResponse::Denied, None),
+        };
+
+        match request {
+            BridgeRequest::Get(name) => {
+                let value = cookies::get(cookie_string, name).and_then(Result::ok);
+                (BridgeResponse::Value(value), None)
+            }
+
+            BridgeRequest::Set(name, value, lifetime) => {
+                let options = match (lifetime, scope.max_lifetime) {
+                    (Some(lifetime), Some(max_lifetime)) => scope
+                        .options
+                        .clone()
+                        .expires_after((*lifetime).min(max_lifetime)),
+                    _ => scope.options.clone(),
+                };
+
+                (
+                    BridgeResponse::Done,
+                    Some(cookies::set(name, value, &options)),
+                )
+            }
+
+            BridgeRequest::Delete(name) => {
+                let options = scope.options.clone().expires_at_timestamp(0);
+                (BridgeResponse::Done, Some(cookies::set(name, "", &options)))
+            }
+        }
+    }
+}
+
+/// Parent page side of the bridge, listening to the messages of the embedded pages.
+///
+/// The listener stops answering when dropped.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub struct BridgeHostListener {
+    window: Window,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+#[cfg(target_family = "wasm")]
+impl BridgeHostListener {
+    /// Starts answering the requests allowed by `host`.
+    pub fn listen(host: BridgeHost) -> Self {
+        let window = web_sys::window().unwrap();
+
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let request = event
+                .data()
+                .as_string()
+                .and_then(|message| decode_request(&message));
+
+            let (id, request) = match request {
+                Some(request) => request,
+                None => return,
+            };
+
+            let origin = event.origin();
+            let (response, write) = host.handle(&origin, &request, &crate::cookie_string());
+
+            if let Some(cookie_string) = write {
+                crate::set_cookie_string(&cookie_string);
+            }
+
+            if let Some(source) = event.source() {
+                let message = JsValue::from_str(&encode_response(id, &response));
+                let _ = source
+                    .unchecked_into::<Window>()
+                    .post_message(&message, &origin);
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        window
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())
+            .unwrap();
+
+        Self { window, on_message }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl Drop for BridgeHostListener {
+    fn drop(&mut self) {
+        let _ = self.window.remove_event_listener_with_callback(
+            "message",
+            self.on_message.as_ref().unchecked_ref(),
+        );
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[derive(Default)]
+struct ClientState {
+    next_id: u32,
+    pending: HashMap<u32, Box<dyn FnOnce(BridgeResponse)>>,
+}
+
+/// Embedded page side of the bridge, sending requests to the parent page.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub struct BridgeClient {
+    window: Window,
+    parent_origin: String,
+    state: Rc<RefCell<ClientState>>,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+#[cfg(target_family = "wasm")]
+impl BridgeClient {
+    /// Creates a client sending requests to the parent page, whose origin must be `parent_origin`
+    /// (for example `https://www.example.com`).
+    pub fn new(parent_origin: &str) -> Self {
+        let window = web_sys::window().unwrap();
+        let state = Rc::new(RefCell::new(ClientState::default()));
+        let handler_state = Rc::clone(&state);
+        let expected_origin = parent_origin.to_owned();
+
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            if event.origin() != expected_origin {
+                return;
+            }
+
+            let response = event
+                .data()
+                .as_string()
+                .and_then(|message| decode_response(&message));
+
+            if let Some((id, response)) = response {
+                let callback = handler_state.borrow_mut().pending.remove(&id);
+
+                if let Some(callback) = callback {
+                    callback(response);
+                }
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        window
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())
+            .unwrap();
+
+        Self {
+            window,
+            parent_origin: parent_origin.to_owned(),
+            state,
+            on_message,
+        }
+    }
+
+    /// Sends `request` to the parent page, and passes its response to `callback`.
+    pub fn request(
+        &self,
+        request: &BridgeRequest,
+        callback: impl FnOnce(BridgeResponse) + 'static,
+    ) {
+        let id = {
+            let mut state = self.state.borrow_mut();
+            let id = state.next_id;
+            state.next_id = state.next_id.wrapping_add(1);
+            state.pending.insert(id, Box::new(callback));
+            id
+        };
+
+        let message = JsValue::from_str(&encode_request(id, request));
+        let parent = self.window.parent().unwrap().unwrap();
+        parent.post_message(&message, &self.parent_origin).unwrap();
+    }
+
+    /// Passes the URI decoded cookie, if it exists and access is allowed, to `callback`.
+    pub fn get(&self, name: &str, callback: impl FnOnce(Option<String>) + 'static) {
+        self.request(&BridgeRequest::Get(name.to_owned()), |response| {
+            callback(match response {
+                BridgeResponse::Value(value) => value,
+                _ => None,
+            })
+        });
+    }
+
+    /// Sets a cookie, with URI encoded name and value, and passes true to `callback` if access
+    /// is allowed.
+    ///
+    /// The cookie is set with the options of the host. `lifetime` is the requested lifetime,
+    /// which the host may cap or ignore, or `None` for the host's default expiration.
+    pub fn set(
+        &self,
+        name: &str,
+        value: &str,
+        lifetime: Option<Duration>,
+        callback: impl FnOnce(bool) + 'static,
+    ) {
+        let request = BridgeRequest::Set(name.to_owned(), value.to_owned(), lifetime);
+
+        self.request(&request, |response| {
+            callback(response == BridgeResponse::Done)
+        });
+    }
+
+    /// Deletes a cookie, URI encoding its name, and passes true to `callback` if access
+    /// is allowed.
+    pub fn delete(&self, name: &str, callback: impl FnOnce(bool) + 'static) {
+        self.request(&BridgeRequest::Delete(name.to_owned()), |response| {
+            callback(response == BridgeResponse::Done)
+        });
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl Drop for BridgeClient {
+    fn drop(&mut self) {
+        let _ = self.window.remove_event_listener_with_callback(
+            "message",
+            self.on_message.as_ref().unchecked_ref(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        let requests = [
+            BridgeRequest::Get("key:1".to_owned()),
+            BridgeRequest::Set(
+                "key 1".to_owned(),
+                "value;1".to_owned(),
+                Some(Duration::from_secs(3600)),
+            ),
+            BridgeRequest::Set("key".to_owned(), "value".to_owned(), None),
+            BridgeRequest::Delete("key%1".to_owned()),
+        ];
+
+        for (id, request) in requests.iter().enumerate() {
+            let message = encode_request(id as u32, request);
+            assert_eq!(decode_request(&message), Some((id as u32, request.clone())));
+            assert_eq!(decode_response(&message), None);
+        }
+
+        let responses = [
+            BridgeResponse::Value(Some("value:1".to_owned())),
+            BridgeResponse::Value(None),
+            BridgeResponse::Done,
+            BridgeResponse::Denied,
+        ];
+
+        for (id, response) in responses.iter().enumerate() {
+            let message = encode_response(id as u32, response);
+            assert_eq!(
+                decode_response(&message),
+                Some((id as u32, response.clone()))
+            );
+            assert_eq!(decode_request(&message), None);
+        }
+
+        assert_eq!(decode_request("wasm-cookies-bridge:1:get"), None);
+        assert_eq!(
+            decode_request("wasm-cookies-bridge:1:set:key:value:domain%3Dexample.com"),
+            None
+        );
+        assert_eq!(decode_request("other message"), None);
+    }
+
+    #[test]
+    fn test_host() {
+        let host = BridgeHost {
+            allowed_origins: vec!["https://widget.example.com".to_owned()],
+            scopes: vec![BridgeScope {
+                pattern: "widget_*".to_owned(),
+                options: CookieOptions::SECURE_ROOT,
+                max_lifetime: Some(Duration::from_secs(24 * 60 * 60)),
+            }],
+        };
+
+        let origin = "https://widget.example.com";
+        let cookie_string = "widget_theme=dark%20blue; session=secret";
+
+        assert_eq!(
+            host.handle(
+                origin,
+                &BridgeRequest::Get("widget_theme".to_owned()),
+                cookie_string
+            ),
+            (BridgeResponse::Value(Some("dark blue".to_owned())), None)
+        );
+
+        assert_eq!(
+            host.handle(
+                origin,
+                &BridgeRequest::Get("session".to_owned()),
+                cookie_string
+            ),
+            (BridgeResponse::Denied, None)
+        );
+
+        assert_eq!(
+            host.handle(
+                "https://evil.example.com",
+                &BridgeRequest::Get("widget_theme".to_owned()),
+                cookie_string
+            ),
+            (BridgeResponse::Denied, None)
+        );
+
+        assert_eq!(
+            host.handle(
+                origin,
+                &BridgeRequest::Set("widget_theme".to_owned(), "light".to_owned(), None),
+                cookie_string
+            ),
+            (
+                BridgeResponse::Done,
+                Some("widget_theme=light;path=/;secure;samesite=lax".to_owned())
+            )
+        );
+
+        let (_, write) = host.handle(
+            origin,
+            &BridgeRequest::Set(
+                "widget_theme".to_owned(),
+                "light".to_owned(),
+                Some(Duration::from_secs(365 * 24 * 60 * 60)),
+            ),
+            cookie_string,
+        );
+        let write = write.unwrap();
+        let options = CookieOptions::parse_attributes(write.split_once(';').unwrap().1);
+        assert_eq!(options.path.as_deref(), Some("/"));
+        assert_eq!(options.domain, None);
+        let expires = cookies::parse_date(options.expires.as_deref().unwrap()).unwrap();
+        assert!((expires - cookies::now_timestamp() - 24 * 60 * 60 * 1000).abs() < 60_000);
+
+        assert_eq!(
+            host.handle(
+                origin,
+                &BridgeRequest::Delete("widget_theme".to_owned()),
+                cookie_string
+            ),
+            (
+                BridgeResponse::Done,
+                Some(
+                    "widget_theme=;path=/;expires=Thu, 01 Jan 1970 00:00:00 GMT;secure;samesite=lax"
+                        .to_owned()
+                )
+            )
+        );
+    }
+}
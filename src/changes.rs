@@ -0,0 +1,192 @@
+//! Change stream: structured diffs between successive snapshots of the cookies (see
+//! [cookies::snapshot](../cookies/fn.snapshot.html)), listing the cookies that were added,
+//! removed or modified with their old and new values.
+//!
+//! Browsers don't notify scripts of cookie changes everywhere, so in a browser the cookies are
+//! polled (see [browser::changes](browser/fn.changes.html)).
+
+use crate::cookies::{self, CookieSnapshot};
+
+/// A change of a cookie, with undecoded name and values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CookieChange {
+    /// The cookie didn't exist.
+    Added {
+        /// The name of the cookie.
+        name: String,
+        /// The value of the cookie.
+        value: String,
+    },
+
+    /// The cookie doesn't exist anymore.
+    Removed {
+        /// The name of the cookie.
+        name: String,
+        /// The last value of the cookie.
+        value: String,
+    },
+
+    /// The value of the cookie changed.
+    Modified {
+        /// The name of the cookie.
+        name: String,
+        /// The previous value of the cookie.
+        old_value: String,
+        /// The new value of the cookie.
+        new_value: String,
+    },
+}
+
+impl CookieChange {
+    /// Returns the undecoded name of the changed cookie.
+    pub fn name(&self) -> &str {
+        match self {
+            CookieChange::Added { name, .. }
+            | CookieChange::Removed { name, .. }
+            | CookieChange::Modified { name, .. } => name,
+        }
+    }
+}
+
+/// Returns the changes from `old` to `new`, sorted by name.
+pub fn diff(old: &CookieSnapshot, new: &CookieSnapshot) -> Vec<CookieChange> {
+    let (old, new) = (old.cookies(), new.cookies());
+
+    let mut changes: Vec<CookieChange> = new
+        .iter()
+        .filter_map(|(name, value)| match old.get(name) {
+            None => Some(CookieChange::Added {
+                name: name.clone(),
+                value: value.clone(),
+            }),
+            Some(old_value) if old_value != value => Some(CookieChange::Modified {
+                name: name.clone(),
+                old_value: old_value.clone(),
+                new_value: value.clone(),
+            }),
+            Some(_) => None,
+        })
+        .collect();
+
+    changes.extend(
+        old.iter()
+            .filter(|(name, _)| !new.contains_key(*name))
+            .map(|(name, value)| CookieChange::Removed {
+                name: name.clone(),
+                value: value.clone(),
+            }),
+    );
+
+    changes.sort_by(|a, b| a.name().cmp(b.name()));
+    changes
+}
+
+/// Keeps the last snapshot of the cookies, to compute the changes of each new cookie string.
+#[derive(Default, Clone, Debug)]
+pub struct ChangeTracker {
+    last: CookieSnapshot,
+}
+
+impl ChangeTracker {
+    /// Creates a tracker whose first snapshot is `cookie_string`.
+    pub fn new(cookie_string: &str) -> Self {
+        Self {
+            last: cookies::snapshot(cookie_string),
+        }
+    }
+
+    /// Returns the changes since the last cookie string, and keeps `cookie_string` as the last
+    /// one.
+    pub fn update(&mut self, cookie_string: &str) -> Vec<CookieChange> {
+        let snapshot = cookies::snapshot(cookie_string);
+        let changes = diff(&self.last, &snapshot);
+        self.last = snapshot;
+        changes
+    }
+}
+
+/// Browser functions, which watch the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{ChangeTracker, CookieChange};
+    use std::time::Duration;
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    /// A stream of cookie changes, stopped when dropped.
+    pub struct ChangeStream {
+        interval_id: i32,
+        _on_interval: Closure<dyn FnMut()>,
+    }
+
+    impl Drop for ChangeStream {
+        fn drop(&mut self) {
+            web_sys::window()
+                .unwrap()
+                .clear_interval_with_handle(self.interval_id);
+        }
+    }
+
+    /// Checks the cookies every `interval`, and calls `callback` with the changes since the
+    /// previous check, if any.
+    ///
+    /// The returned handle must be kept alive as long as the changes are needed.
+    pub fn changes(
+        interval: Duration,
+        mut callback: impl FnMut(Vec<CookieChange>) + 'static,
+    ) -> ChangeStream {
+        let mut tracker = ChangeTracker::new(&crate::cookie_string());
+
+        let on_interval = Closure::wrap(Box::new(move || {
+            let changes = tracker.update(&crate::cookie_string());
+
+            if !changes.is_empty() {
+                callback(changes);
+            }
+        }) as Box<dyn FnMut()>);
+
+        let interval_id = web_sys::window()
+            .unwrap()
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                on_interval.as_ref().unchecked_ref(),
+                interval.as_millis().min(i32::MAX as u128) as i32,
+            )
+            .unwrap();
+
+        ChangeStream {
+            interval_id,
+            _on_interval: on_interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker() {
+        let mut tracker = ChangeTracker::new("a=1; b=2; c=3");
+        assert_eq!(
+            tracker.update("c=4; a=1; d=5"),
+            [
+                CookieChange::Removed {
+                    name: "b".to_owned(),
+                    value: "2".to_owned()
+                },
+                CookieChange::Modified {
+                    name: "c".to_owned(),
+                    old_value: "3".to_owned(),
+                    new_value: "4".to_owned()
+                },
+                CookieChange::Added {
+                    name: "d".to_owned(),
+                    value: "5".to_owned()
+                },
+            ]
+        );
+        assert!(tracker.update("a=1; c=4; d=5").is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! Values with an integrity checksum, to detect cookies truncated or mangled by intermediaries
+//! or browser extensions.
+//!
+//! The stored value is followed by `.` and the CRC-32 of the stored value, as 8 hexadecimal
+//! digits. This is not a security feature: anyone can compute a valid checksum, so use the
+//! [remember_me](../remember_me/index.html) tokens or a signature to detect tampering.
+
+#[cfg(feature = "encoding")]
+use crate::cookies::GetDecodeError;
+use crate::cookies::{self, CookieOptions};
+use crate::crc32::crc32;
+
+/// Error returned when reading a cookie with a checksum.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ChecksumError {
+    /// The checksum is missing or doesn't match the value.
+    Corrupted,
+
+    /// URI decoding error on the value.
+    #[cfg(feature = "encoding")]
+    Decode(GetDecodeError),
+}
+
+/// Returns `value` followed by its checksum.
+pub fn seal(value: &str) -> String {
+    format!("{}.{:08x}", value, crc32(value.as_bytes()))
+}
+
+/// Returns the value of `sealed` without its checksum, or `None` if the checksum is missing or
+/// doesn't match the value.
+pub fn unseal(sealed: &str) -> Option<&str> {
+    let (value, checksum) = sealed.rsplit_once('.')?;
+
+    if checksum.len() == 8 && u32::from_str_radix(checksum, 16).ok()? == crc32(value.as_bytes()) {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// Return the cookie string that sets a cookie with a checksum, with non encoded name and value.
+pub fn set_raw(name: &str, value: &str, options: &CookieOptions) -> String {
+    cookies::set_raw(name, &seal(value), options)
+}
+
+/// If it exists, returns the undecoded value of a cookie set with [set_raw](fn.set_raw.html),
+/// or `ChecksumError::Corrupted` if its checksum doesn't match.
+pub fn get_raw(cookie_string: &str, name: &str) -> Option<Result<String, ChecksumError>> {
+    cookies::get_raw(cookie_string, name).map(|sealed| {
+        unseal(&sealed)
+            .map(str::to_owned)
+            .ok_or(ChecksumError::Corrupted)
+    })
+}
+
+/// Return the cookie string that sets a cookie with a checksum, with URI encoded name and value
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)).
+/// The checksum is computed on the encoded value.
+#[cfg(feature = "encoding")]
+pub fn set(name: &str, value: &str, options: &CookieOptions) -> String {
+    set_raw(
+        &urlencoding::encode(name),
+        &urlencoding::encode(value),
+        options,
+    )
+}
+
+/// If it exists, returns the URI decoded value of a cookie set with [set](fn.set.html),
+/// or an error if its checksum doesn't match or its URI decoding fails.
+#[cfg(feature = "encoding")]
+pub fn get(cookie_string: &str, name: &str) -> Option<Result<String, ChecksumError>> {
+    get_raw(cookie_string, &urlencoding::encode(name)).map(|result| {
+        cookies::decode_value(name, result?, crate::encoding::Encoding::Standard)
+            .map_err(ChecksumError::Decode)
+    })
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::ChecksumError;
+    use crate::cookies::CookieOptions;
+
+    /// Sets a cookie with a checksum, with non encoded name and value.
+    pub fn set_raw(name: &str, value: &str, options: &CookieOptions) {
+        crate::set_cookie_string(&super::set_raw(name, value, options));
+    }
+
+    /// If it exists, returns the undecoded value of a cookie set with
+    /// [set_raw](fn.set_raw.html), or `ChecksumError::Corrupted` if its checksum doesn't match.
+    pub fn get_raw(name: &str) -> Option<Result<String, ChecksumError>> {
+        super::get_raw(&crate::cookie_string(), name)
+    }
+
+    /// Sets a cookie with a checksum, with URI encoded name and value
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)).
+    #[cfg(feature = "encoding")]
+    pub fn set(name: &str, value: &str, options: &CookieOptions) {
+        crate::set_cookie_string(&super::set(name, value, options));
+    }
+
+    /// If it exists, returns the URI decoded value of a cookie set with [set](fn.set.html),
+    /// or an error if its checksum doesn't match or its URI decoding fails.
+    #[cfg(feature = "encoding")]
+    pub fn get(name: &str) -> Option<Result<String, ChecksumError>> {
+        super::get(&crate::cookie_string(), name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal() {
+        let sealed = seal("cart.v2");
+        assert_eq!(unseal(&sealed), Some("cart.v2"));
+        assert_eq!(unseal(&sealed[..sealed.len() - 1]), None);
+        assert_eq!(unseal(&sealed.replace("cart", "cast")), None);
+        assert_eq!(unseal("cart"), None);
+        assert_eq!(seal(""), ".00000000");
+    }
+
+    #[test]
+    fn test_get_raw() {
+        let cookie_string = set_raw("cart", "1,2,3", &CookieOptions::default());
+        let (pair, _) = cookie_string.split_once(';').unwrap();
+        assert_eq!(get_raw(pair, "cart").unwrap().unwrap(), "1,2,3");
+        assert!(matches!(
+            get_raw(&pair.replace("1,2", "1,"), "cart"),
+            Some(Err(ChecksumError::Corrupted))
+        ));
+        assert!(get_raw(pair, "other").is_none());
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_get() {
+        let cookie_string = set("my cart", "1 2", &CookieOptions::default());
+        let (pair, _) = cookie_string.split_once(';').unwrap();
+        assert_eq!(get(pair, "my cart").unwrap().unwrap(), "1 2");
+        assert!(matches!(
+            get(&format!("a={}", seal("%AA")), "a"),
+            Some(Err(ChecksumError::Decode(_)))
+        ));
+    }
+}
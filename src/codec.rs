@@ -0,0 +1,384 @@
+//! Per-name codecs, which transform the values of the cookies on their way to and from the
+//! browser (compression, signing, encryption, checksums, or any other transformation
+//! implemented with [CookieCodec](trait.CookieCodec.html)).
+//!
+//! A [CodecRouter](struct.CodecRouter.html) maps cookie name patterns to codecs, so the right
+//! codec is applied to each cookie without the call sites having to remember it. It is a
+//! [middleware](../middleware/index.html): register it with `wasm_cookies::register_middleware`
+//! in a browser, or push it to a [MiddlewareChain](../middleware/struct.MiddlewareChain.html).
+
+use crate::base64::{decode_url_safe, encode_url_safe};
+use crate::checksum;
+use crate::cookies::Cookie;
+use crate::lz;
+use crate::middleware::CookieMiddleware;
+use crate::policy::matches_pattern;
+#[cfg(feature = "crypto")]
+use crate::sha256::{constant_time_eq, hmac_sha256};
+#[cfg(feature = "encryption")]
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+#[cfg(feature = "encryption")]
+use aes_gcm::{Aes256Gcm, Nonce};
+use std::borrow::Cow;
+
+/// A reversible transformation of cookie values.
+pub trait CookieCodec {
+    /// Returns the value to store for the cookie `name`.
+    fn encode(&self, name: &str, value: &str) -> String;
+
+    /// Returns the value of the cookie `name` from its stored value, or `None` if the stored
+    /// value is invalid (for example if its signature doesn't match).
+    fn decode(&self, name: &str, value: &str) -> Option<String>;
+}
+
+/// Codec which leaves the values unchanged.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Plain;
+
+impl CookieCodec for Plain {
+    fn encode(&self, _name: &str, value: &str) -> String {
+        value.to_owned()
+    }
+
+    fn decode(&self, _name: &str, value: &str) -> Option<String> {
+        Some(value.to_owned())
+    }
+}
+
+/// Codec which stores the values in URL-safe Base64, so they only contain characters allowed in
+/// cookie values and can be stored without URI encoding.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Base64;
+
+impl CookieCodec for Base64 {
+    fn encode(&self, _name: &str, value: &str) -> String {
+        encode_url_safe(value.as_bytes())
+    }
+
+    fn decode(&self, _name: &str, value: &str) -> Option<String> {
+        String::from_utf8(decode_url_safe(value)?).ok()
+    }
+}
+
+/// Codec which compresses the values (with the LZ77 compression used by
+/// [saves](../saves/index.html)) and stores them in URL-safe Base64. Values with few repetitions
+/// get larger, so it suits large values such as serialized states.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Compressed;
+
+impl CookieCodec for Compressed {
+    fn encode(&self, _name: &str, value: &str) -> String {
+        encode_url_safe(&lz::compress(value.as_bytes()))
+    }
+
+    fn decode(&self, _name: &str, value: &str) -> Option<String> {
+        String::from_utf8(lz::decompress(&decode_url_safe(value)?)?).ok()
+    }
+}
+
+/// Codec which appends a checksum to the values, to detect corrupted cookies (see the
+/// [checksum](../checksum/index.html) module).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Checksummed;
+
+impl CookieCodec for Checksummed {
+    fn encode(&self, _name: &str, value: &str) -> String {
+        checksum::seal(value)
+    }
+
+    fn decode(&self, _name: &str, value: &str) -> Option<String> {
+        checksum::unseal(value).map(str::to_owned)
+    }
+}
+
+/// Codec which appends `.` and the HMAC-SHA-256 of the cookie's name and value (in URL-safe
+/// Base64) to the values, to detect tampering. The values stay readable.
+///
+/// Available only with the `crypto` feature.
+#[cfg(feature = "crypto")]
+#[derive(Clone, Debug)]
+pub struct Signed {
+    key: Vec<u8>,
+}
+
+#[cfg(feature = "crypto")]
+impl Signed {
+    /// Creates a codec signing with `key`.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self { key: key.into() }
+    }
+
+    fn signature(&self, name: &str, value: &str) -> String {
+        encode_url_safe(&hmac_sha256(
+            &self.key,
+            format!("{}={}", name, value).as_bytes(),
+        ))
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl CookieCodec for Signed {
+    fn encode(&self, name: &str, value: &str) -> String {
+        format!("{}.{}", value, self.signature(name, value))
+    }
+
+    fn decode(&self, name: &str, value: &str) -> Option<String> {
+        let (value, signature) = value.rsplit_once('.')?;
+
+        if constant_time_eq(signature.as_bytes(), self.signature(name, value).as_bytes()) {
+            Some(value.to_owned())
+        } else {
+            None
+        }
+    }
+}
+
+/// Size in bytes of the nonce of each encryption.
+#[cfg(feature = "encryption")]
+const NONCE_BYTES: usize = 12;
+
+/// Codec which encrypts the values with AES-256-GCM (with the
+/// [aes-gcm crate](https://crates.io/crates/aes-gcm)), authenticating the cookie's name, and
+/// stores the random nonce followed by the ciphertext in URL-safe Base64.
+///
+/// A key shipped in the bundle can be read by the scripts of the page, so the encryption hides
+/// the values from whoever only sees the cookies, not from the page itself (see also the
+/// [session](../session/index.html) module).
+///
+/// Available only with the `encryption` feature.
+#[cfg(feature = "encryption")]
+pub struct Encrypted {
+    cipher: Aes256Gcm,
+    random: RandomSource,
+}
+
+/// Function filling its buffer with random bytes.
+#[cfg(feature = "encryption")]
+type RandomSource = Box<dyn Fn(&mut [u8])>;
+
+#[cfg(feature = "encryption")]
+impl std::fmt::Debug for Encrypted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Encrypted").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl Encrypted {
+    /// Creates a codec encrypting with `key`, whose nonces are generated by `random`, which
+    /// must fill its buffer with cryptographically secure random bytes: a nonce must never be
+    /// reused with the same key.
+    pub fn new(key: &[u8; 32], random: impl Fn(&mut [u8]) + 'static) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(key.into()),
+            random: Box::new(random),
+        }
+    }
+
+    /// Creates a codec encrypting with `key`, whose nonces are generated with
+    /// `crypto.getRandomValues`.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    #[cfg(target_family = "wasm")]
+    pub fn with_browser_random(key: &[u8; 32]) -> Self {
+        Self::new(key, crate::fill_random)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl CookieCodec for Encrypted {
+    fn encode(&self, name: &str, value: &str) -> String {
+        let mut bytes = vec![0; NONCE_BYTES];
+        (self.random)(&mut bytes);
+
+        let payload = Payload {
+            msg: value.as_bytes(),
+            aad: name.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&bytes), payload)
+            .expect("AES-GCM encrypts payloads of any cookie size");
+
+        bytes.extend(ciphertext);
+        encode_url_safe(&bytes)
+    }
+
+    fn decode(&self, name: &str, value: &str) -> Option<String> {
+        let bytes = decode_url_safe(value)?;
+
+        if bytes.len() < NONCE_BYTES {
+            return None;
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_BYTES);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: name.as_bytes(),
+        };
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .ok()?;
+
+        String::from_utf8(plaintext).ok()
+    }
+}
+
+/// A routing table from cookie name patterns to codecs. For a given cookie name, only the first
+/// matching route applies, and the values of the cookies matching no route are unchanged.
+///
+/// The patterns are matched against the names as rewritten by the middleware registered before
+/// the router.
+#[derive(Default)]
+pub struct CodecRouter {
+    routes: Vec<(String, Box<dyn CookieCodec>)>,
+}
+
+impl std::fmt::Debug for CodecRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CodecRouter")
+            .field(
+                "routes",
+                &self
+                    .routes
+                    .iter()
+                    .map(|(pattern, _)| pattern)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl CodecRouter {
+    /// Creates an empty routing table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a route at the end of the table. `pattern` is a cookie name, where `*` matches any
+    /// sequence of characters.
+    pub fn route(mut self, pattern: impl Into<String>, codec: impl CookieCodec + 'static) -> Self {
+        self.routes.push((pattern.into(), Box::new(codec)));
+        self
+    }
+
+    /// Returns the codec of the first route matching the cookie `name`, if any.
+    pub fn codec(&self, name: &str) -> Option<&dyn CookieCodec> {
+        self.routes
+            .iter()
+            .find(|(pattern, _)| matches_pattern(pattern, name))
+            .map(|(_, codec)| &**codec)
+    }
+
+    /// Returns the value to store for the cookie `name`.
+    pub fn encode(&self, name: &str, value: &str) -> String {
+        match self.codec(name) {
+            Some(codec) => codec.encode(name, value),
+            None => value.to_owned(),
+        }
+    }
+
+    /// Returns the value of the cookie `name` from its stored value, or `None` if the stored
+    /// value is invalid for the cookie's codec.
+    pub fn decode(&self, name: &str, value: &str) -> Option<String> {
+        match self.codec(name) {
+            Some(codec) => codec.decode(name, value),
+            None => Some(value.to_owned()),
+        }
+    }
+}
+
+impl CookieMiddleware for CodecRouter {
+    fn on_set(&self, cookie: &mut Cookie<'static>) -> bool {
+        cookie.value = Cow::Owned(self.encode(&cookie.name, &cookie.value));
+        true
+    }
+
+    fn on_get(&self, name: &str, value: String) -> Option<String> {
+        self.decode(name, &value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookies::CookieOptions;
+    use crate::middleware::MiddlewareChain;
+
+    fn router() -> CodecRouter {
+        CodecRouter::new()
+            .route("session", Base64)
+            .route("state", Compressed)
+            .route("cart_*", Checksummed)
+            .route("*", Plain)
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn test_signed() {
+        let codec = Signed::new("secret");
+        let stored = codec.encode("session", "user=42");
+        assert!(stored.starts_with("user=42."));
+        assert_eq!(codec.decode("session", &stored).unwrap(), "user=42");
+        assert_eq!(codec.decode("other", &stored), None);
+        assert_eq!(codec.decode("session", &stored.replace("42", "43")), None);
+        assert_eq!(codec.decode("session", "user=42"), None);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypted() {
+        let codec = Encrypted::new(&[7; 32], |bytes| bytes.fill(1));
+        let stored = codec.encode("session", "user=42");
+        assert!(!stored.contains("user"));
+        assert_eq!(codec.decode("session", &stored).unwrap(), "user=42");
+        assert_eq!(codec.decode("other", &stored), None);
+        assert_eq!(codec.decode("session", &stored[1..]), None);
+        assert_eq!(
+            Encrypted::new(&[8; 32], |_| {}).decode("session", &stored),
+            None
+        );
+    }
+
+    #[test]
+    fn test_router() {
+        let router = router();
+        assert_eq!(router.encode("cart_items", "3"), checksum::seal("3"));
+        assert_eq!(router.decode("cart_items", "3.00000000"), None);
+        assert_eq!(router.encode("theme", "dark"), "dark");
+
+        let state = "[1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1]";
+        assert!(router.encode("state", state).len() < state.len());
+        assert_eq!(
+            router
+                .decode("state", &router.encode("state", state))
+                .unwrap(),
+            state
+        );
+        assert_eq!(
+            router
+                .decode("session", &router.encode("session", "1"))
+                .unwrap(),
+            "1"
+        );
+        assert_eq!(
+            format!("{:?}", router),
+            r#"CodecRouter { routes: ["session", "state", "cart_*", "*"] }"#
+        );
+    }
+
+    #[test]
+    fn test_middleware() {
+        let mut chain = MiddlewareChain::new();
+        chain.push(router());
+
+        let cookie = chain.set("cart_items", "3", &CookieOptions::ROOT).unwrap();
+        assert_eq!(cookie.value, checksum::seal("3"));
+        assert_eq!(
+            chain.get("cart_items", cookie.value.into_owned()).unwrap(),
+            "3"
+        );
+        assert_eq!(chain.get("cart_items", "3".to_owned()), None);
+    }
+}
@@ -4,21 +4,29 @@
 //! Instead of reading the browser's cookie string, functions in this module take it as an
 //! argument. Instead of writing to the browser's cookie string, they return it.
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "encoding")]
+use crate::encoding::Encoding;
+#[cfg(any(not(target_family = "wasm"), target_os = "emscripten"))]
 use chrono::offset::Utc;
-#[cfg(not(target_arch = "wasm32"))]
-use chrono::NaiveDateTime;
-#[cfg(target_arch = "wasm32")]
+#[cfg(any(not(target_family = "wasm"), target_os = "emscripten"))]
+use chrono::DateTime;
+#[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
 use js_sys::Date;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
+use std::iter::FromIterator;
 use std::time::Duration;
+#[cfg(feature = "encoding")]
 use urlencoding::FromUrlEncodingError;
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
 use wasm_bindgen::JsValue;
 
 /// URI decoding error on a key or a value, when calling `wasm_cookie::all`.
+#[cfg(feature = "encoding")]
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum AllDecodeError {
     /// URI decoding error on a key.
     ///
@@ -33,29 +41,136 @@ pub enum AllDecodeError {
     Value(String, FromUrlEncodingError),
 }
 
-fn process_key_value_str(key_value_str: &str) -> Result<(&str, &str), ()> {
-    match key_value_str.split_once('=') {
-        Some((key, value)) => Ok((key.trim(), value.trim())),
-        None => Err(()),
+/// URI decoding error on the value of a cookie, when calling `wasm_cookie::get`.
+#[cfg(feature = "encoding")]
+#[derive(Debug)]
+pub struct GetDecodeError {
+    /// The name of the cookie, as passed to `get`.
+    pub name: String,
+
+    /// The undecoded value of the cookie.
+    pub raw_value: String,
+
+    /// The URI decoding error.
+    pub error: FromUrlEncodingError,
+}
+
+/// Reason why a name-value pair is invalid, returned by [parse_pair](fn.parse_pair.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PairError {
+    /// There is no `=` in the pair.
+    MissingEquals,
+
+    /// The name is empty once trimmed.
+    EmptyName,
+}
+
+/// Parses a name-value pair (`name=value`, such as one of the `;`-separated pairs of a cookie
+/// string or of a `Cookie` header), with undecoded name and value.
+///
+/// The pair is split at the first `=`, and the spaces and horizontal tabs around the name and
+/// the value are trimmed. Pairs without `=` or with an empty name are invalid, and are ignored
+/// by browsers (see [RFC 6265](https://datatracker.ietf.org/doc/html/rfc6265#section-5.2)).
+///
+/// Trimming happens before URI decoding, so the whitespace inside the value and the URI encoded
+/// spaces (`%20`) at its ends are preserved.
+pub fn parse_pair(pair: &str) -> Result<(&str, &str), PairError> {
+    let is_blank = |c: char| c == ' ' || c == '\t';
+    let (name, value) = pair.split_once('=').ok_or(PairError::MissingEquals)?;
+    let name = name.trim_matches(is_blank);
+
+    if name.is_empty() {
+        Err(PairError::EmptyName)
+    } else {
+        Ok((name, value.trim_matches(is_blank)))
     }
 }
 
-/// Returns all cookies as key-value pairs, with undecoded keys and values.
-pub fn all_iter_raw(cookie_string: &str) -> impl Iterator<Item = (&str, &str)> {
-    cookie_string.split(';').filter_map(|key_value_str| {
-        match process_key_value_str(key_value_str) {
-            Ok((key, value)) => Some((key, value)),
-            Err(_) => None,
+/// Current timestamp in milliseconds, UTC.
+pub(crate) fn now_timestamp() -> i64 {
+    #[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
+    let now = Date::now() as i64;
+    #[cfg(any(not(target_family = "wasm"), target_os = "emscripten"))]
+    let now = Utc::now().timestamp_millis();
+    now
+}
+
+/// Number of milliseconds in `duration`, saturating instead of overflowing.
+pub(crate) fn duration_millis(duration: Duration) -> i64 {
+    i64::try_from(duration.as_millis()).unwrap_or(i64::MAX)
+}
+
+/// Timestamp in milliseconds, UTC, `duration` from now, saturating instead of overflowing.
+fn timestamp_after(duration: Duration) -> i64 {
+    now_timestamp().saturating_add(duration_millis(duration))
+}
+
+/// Parses a GMT date string (as produced by `Date.prototype.toUTCString()`) or an ISO 8601 date
+/// string to a timestamp in milliseconds, UTC.
+pub(crate) fn parse_date(date: &str) -> Option<i64> {
+    #[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
+    let timestamp = Some(Date::parse(date))
+        .filter(|timestamp| !timestamp.is_nan())
+        .map(|timestamp| timestamp as i64);
+
+    #[cfg(any(not(target_family = "wasm"), target_os = "emscripten"))]
+    let timestamp = DateTime::parse_from_rfc2822(date)
+        .or_else(|_| DateTime::parse_from_rfc3339(date))
+        .ok()
+        .map(|date| date.timestamp_millis());
+
+    timestamp
+}
+
+/// Iterator over the key-value pairs of a cookie string, which knows its exact length since the
+/// pairs are counted upfront.
+struct AllIterRaw<'a> {
+    key_value_strs: std::str::Split<'a, char>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for AllIterRaw<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for key_value_str in &mut self.key_value_strs {
+            if let Ok(key_value) = parse_pair(key_value_str) {
+                self.remaining -= 1;
+                return Some(key_value);
+            }
         }
-    })
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for AllIterRaw<'_> {}
+
+impl std::iter::FusedIterator for AllIterRaw<'_> {}
+
+/// Returns all cookies as key-value pairs, with undecoded keys and values.
+pub fn all_iter_raw(cookie_string: &str) -> impl ExactSizeIterator<Item = (&str, &str)> {
+    AllIterRaw {
+        key_value_strs: cookie_string.split(';'),
+        remaining: cookie_string
+            .split(';')
+            .filter(|key_value_str| parse_pair(key_value_str).is_ok())
+            .count(),
+    }
 }
 
 /// Returns all cookies as key-value pairs, with URI decoded keys and values
 /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
 /// or an error if URI decoding fails on a key or a value.
+#[cfg(feature = "encoding")]
 pub fn all_iter(
     cookie_string: &str,
-) -> impl Iterator<Item = Result<(String, String), AllDecodeError>> + '_ {
+) -> impl ExactSizeIterator<Item = Result<(String, String), AllDecodeError>> + '_ {
     all_iter_raw(cookie_string).map(|(key, value)| match urlencoding::decode(key) {
         Ok(key) => match urlencoding::decode(value) {
             Ok(value) => Ok((key, value)),
@@ -66,25 +181,319 @@ pub fn all_iter(
     })
 }
 
+pub(crate) fn collect_raw(cookie_string: &str, duplicates: Duplicates) -> HashMap<String, String> {
+    let cookies = all_iter_raw(cookie_string);
+    let mut map = HashMap::with_capacity(cookies.len());
+
+    match duplicates {
+        Duplicates::FirstWins => {
+            for (key, value) in cookies {
+                map.entry(key.to_owned())
+                    .or_insert_with(|| value.to_owned());
+            }
+        }
+        Duplicates::LastWins => {
+            map.extend(cookies.map(|(key, value)| (key.to_owned(), value.to_owned())));
+        }
+    }
+
+    map
+}
+
 /// Returns all cookies, with undecoded keys and values.
+///
+/// Not available with the `small` feature: use [all_iter_raw](fn.all_iter_raw.html) instead.
+#[cfg(not(feature = "small"))]
 pub fn all_raw(cookie_string: &str) -> HashMap<String, String> {
+    collect_raw(cookie_string, Duplicates::LastWins)
+}
+
+/// Returns all cookies, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+/// or an error if URI decoding fails on a key or a value.
+///
+/// Not available with the `small` feature: use [all_iter](fn.all_iter.html) instead.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all(cookie_string: &str) -> Result<HashMap<String, String>, AllDecodeError> {
+    all_iter(cookie_string).collect()
+}
+
+/// Collects all cookies, with undecoded keys and values, into any collection of pairs: for
+/// example a `HashMap` with a faster hasher than the default SipHash, a `BTreeMap` or a `Vec`.
+///
+/// The keys and values can be any type convertible from `&str`, such as `String`, `Box<str>` or
+/// compact string types (like `compact_str::CompactString` or `smol_str::SmolStr`), which store
+/// short strings inline without allocating.
+///
+/// With maps, the last cookie wins when several cookies have the same name, like with
+/// [all_raw](fn.all_raw.html). Unlike it, this function is available with the `small` feature.
+pub fn all_raw_into<M, K, V>(cookie_string: &str) -> M
+where
+    K: for<'a> From<&'a str>,
+    V: for<'a> From<&'a str>,
+    M: FromIterator<(K, V)>,
+{
     all_iter_raw(cookie_string)
-        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .map(|(key, value)| (K::from(key), V::from(value)))
+        .collect()
+}
+
+/// Collects all cookies, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)), into any collection of
+/// pairs of any string types (see [all_raw_into](fn.all_raw_into.html)), or returns an error if
+/// URI decoding fails on a key or a value.
+///
+/// The keys and values which don't need decoding are converted without intermediate `String`.
+#[cfg(feature = "encoding")]
+pub fn all_into<M, K, V>(cookie_string: &str) -> Result<M, AllDecodeError>
+where
+    K: for<'a> From<&'a str>,
+    V: for<'a> From<&'a str>,
+    M: FromIterator<(K, V)>,
+{
+    fn decode(string: &str) -> Result<Cow<'_, str>, FromUrlEncodingError> {
+        if string.contains('%') {
+            urlencoding::decode(string).map(Cow::Owned)
+        } else {
+            Ok(Cow::Borrowed(string))
+        }
+    }
+
+    all_iter_raw(cookie_string)
+        .map(|(key, value)| {
+            let key = decode(key).map_err(|error| AllDecodeError::Key(key.to_owned(), error))?;
+            let value =
+                decode(value).map_err(|error| AllDecodeError::Value(key.to_string(), error))?;
+            Ok((K::from(&key), V::from(&value)))
+        })
+        .collect()
+}
+
+/// Which cookie to keep when several cookies have the same name, which happens when cookies with
+/// the same name are set for different paths or domains.
+///
+/// Browsers list cookies with longer paths first, so the first one is the most specific.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Duplicates {
+    /// Keep the first cookie, which is the most specific one.
+    /// This is the default value when calling `Duplicates::default()`.
+    #[default]
+    FirstWins,
+
+    /// Keep the last cookie, which is the least specific one.
+    LastWins,
+}
+
+/// Returns all cookies, with undecoded keys and values, keeping the cookie chosen by
+/// `duplicates` when several cookies have the same name.
+///
+/// Not available with the `small` feature.
+#[cfg(not(feature = "small"))]
+pub fn all_raw_with_duplicates(
+    cookie_string: &str,
+    duplicates: Duplicates,
+) -> HashMap<String, String> {
+    let mut cookies = HashMap::new();
+
+    for (key, value) in all_iter_raw(cookie_string) {
+        match duplicates {
+            Duplicates::LastWins => {
+                cookies.insert(key.to_owned(), value.to_owned());
+            }
+            Duplicates::FirstWins => {
+                cookies
+                    .entry(key.to_owned())
+                    .or_insert_with(|| value.to_owned());
+            }
+        }
+    }
+
+    cookies
+}
+
+/// Returns all cookies, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+/// or an error if URI decoding fails on a key or a value, keeping the cookie chosen by
+/// `duplicates` when several cookies have the same name.
+///
+/// Not available with the `small` feature.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all_with_duplicates(
+    cookie_string: &str,
+    duplicates: Duplicates,
+) -> Result<HashMap<String, String>, AllDecodeError> {
+    let mut cookies = HashMap::new();
+
+    for result in all_iter(cookie_string) {
+        let (key, value) = result?;
+
+        match duplicates {
+            Duplicates::FirstWins => {
+                cookies.entry(key).or_insert(value);
+            }
+            Duplicates::LastWins => {
+                cookies.insert(key, value);
+            }
+        }
+    }
+
+    Ok(cookies)
+}
+
+/// Returns all cookies whose key and value are successfully URI decoded
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+/// and the decoding errors of the others, so a malformed cookie doesn't hide the valid ones.
+///
+/// Not available with the `small` feature: use [all_iter](fn.all_iter.html) instead.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all_with_errors(cookie_string: &str) -> (HashMap<String, String>, Vec<AllDecodeError>) {
+    let mut cookies = HashMap::new();
+    let mut errors = Vec::new();
+
+    for result in all_iter(cookie_string) {
+        match result {
+            Ok((key, value)) => {
+                cookies.insert(key, value);
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+
+    (cookies, errors)
+}
+
+/// Returns all cookies, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)). The bytes that aren't
+/// valid UTF-8 once decoded are replaced with `U+FFFD`, so malformed cookies can't make it fail.
+///
+/// Not available with the `small` feature: use [all_iter_raw](fn.all_iter_raw.html) instead.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all_lossy_utf8(cookie_string: &str) -> HashMap<String, String> {
+    all_iter_raw(cookie_string)
+        .map(|(key, value)| {
+            (
+                Encoding::Standard.decode_lossy(key),
+                Encoding::Standard.decode_lossy(value),
+            )
+        })
         .collect()
 }
 
 /// Returns all cookies, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)). The keys and values that
+/// aren't valid UTF-8 once decoded are reinterpreted as ISO-8859-1 (Latin-1), so a cookie written
+/// by a legacy backend can't make it fail.
+///
+/// Not available with the `small` feature: use [all_iter_raw](fn.all_iter_raw.html) instead.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all_latin1_fallback(cookie_string: &str) -> HashMap<String, String> {
+    all_iter_raw(cookie_string)
+        .map(|(key, value)| {
+            (
+                Encoding::Standard.decode_latin1_fallback(key),
+                Encoding::Standard.decode_latin1_fallback(value),
+            )
+        })
+        .collect()
+}
+
+/// Returns all cookies sorted by name, with undecoded keys and values.
+pub fn all_sorted_raw(cookie_string: &str) -> BTreeMap<String, String> {
+    all_iter_raw(cookie_string)
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+/// Returns all cookies sorted by name, with URI decoded keys and values
 /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
 /// or an error if URI decoding fails on a key or a value.
-pub fn all(cookie_string: &str) -> Result<HashMap<String, String>, AllDecodeError> {
+#[cfg(feature = "encoding")]
+pub fn all_sorted(cookie_string: &str) -> Result<BTreeMap<String, String>, AllDecodeError> {
+    all_iter(cookie_string).collect()
+}
+
+/// Returns all cookies in the order of the cookie string, with undecoded keys and values.
+///
+/// Unlike the map-returning functions, cookies with the same name are all kept: browsers list
+/// cookies with longer paths first, so the first one is the most specific.
+pub fn all_ordered_raw(cookie_string: &str) -> Vec<(String, String)> {
+    all_iter_raw(cookie_string)
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+/// Returns all cookies in the order of the cookie string, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+/// or an error if URI decoding fails on a key or a value.
+///
+/// Unlike the map-returning functions, cookies with the same name are all kept: browsers list
+/// cookies with longer paths first, so the first one is the most specific.
+#[cfg(feature = "encoding")]
+pub fn all_ordered(cookie_string: &str) -> Result<Vec<(String, String)>, AllDecodeError> {
     all_iter(cookie_string).collect()
 }
 
+/// Returns the names of all cookies in the order of the cookie string, undecoded, without
+/// copying the values. Names of cookies with the same name are all kept.
+pub fn all_names_raw(cookie_string: &str) -> Vec<&str> {
+    all_iter_raw(cookie_string).map(|(key, _)| key).collect()
+}
+
+/// Returns the names of all cookies in the order of the cookie string, URI decoded
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)), without decoding the
+/// values, or an error if URI decoding fails on a name. Names of cookies with the same name are
+/// all kept.
+#[cfg(feature = "encoding")]
+pub fn names(cookie_string: &str) -> Result<Vec<String>, AllDecodeError> {
+    all_iter_raw(cookie_string)
+        .map(|(key, _)| {
+            urlencoding::decode(key).map_err(|error| AllDecodeError::Key(key.to_owned(), error))
+        })
+        .collect()
+}
+
+/// Number and size of the cookies whose names share a prefix.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Namespace {
+    /// Undecoded prefix of the names.
+    pub prefix: String,
+
+    /// Number of cookies.
+    pub count: usize,
+
+    /// Total size in bytes of the undecoded names and values, as counted by browsers against
+    /// [MAX_COOKIE_SIZE](constant.MAX_COOKIE_SIZE.html).
+    pub size: usize,
+}
+
+/// Groups the cookies by the prefix of their names, up to the first `separator` (for example
+/// `_ga` and `_gid` are in the namespace `""` with `_`, and `mp_1_mixpanel` in `mp`). A name
+/// without `separator` is its own prefix. The namespaces are sorted by decreasing size, so the
+/// largest contributors to the cookie header come first.
+pub fn namespaces(cookie_string: &str, separator: &str) -> Vec<Namespace> {
+    let mut groups = BTreeMap::<&str, Namespace>::new();
+
+    for (key, value) in all_iter_raw(cookie_string) {
+        let prefix = key.split_once(separator).map_or(key, |(prefix, _)| prefix);
+        let namespace = groups.entry(prefix).or_insert_with(|| Namespace {
+            prefix: prefix.to_owned(),
+            ..Namespace::default()
+        });
+        namespace.count += 1;
+        namespace.size += key.len() + value.len();
+    }
+
+    let mut namespaces: Vec<_> = groups.into_values().collect();
+    namespaces.sort_by_key(|namespace| std::cmp::Reverse(namespace.size));
+    namespaces
+}
+
 /// Returns undecoded cookie if it exists.
 pub fn get_raw(cookie_string: &str, name: &str) -> Option<String> {
     cookie_string
         .split(';')
-        .find_map(|key_value_str| match process_key_value_str(key_value_str) {
+        .find_map(|key_value_str| match parse_pair(key_value_str) {
             Ok((key, value)) => {
                 if key == name {
                     Some(value.to_owned())
@@ -97,37 +506,120 @@ pub fn get_raw(cookie_string: &str, name: &str) -> Option<String> {
         })
 }
 
+/// Returns undecoded cookie if it exists, choosing the cookie with `duplicates` when several
+/// cookies have the same name.
+pub fn get_raw_with_duplicates(
+    cookie_string: &str,
+    name: &str,
+    duplicates: Duplicates,
+) -> Option<String> {
+    let mut values = all_iter_raw(cookie_string)
+        .filter(|(key, _)| *key == name)
+        .map(|(_, value)| value);
+
+    match duplicates {
+        Duplicates::FirstWins => values.next(),
+        Duplicates::LastWins => values.last(),
+    }
+    .map(str::to_owned)
+}
+
 /// If it exists, returns URI decoded cookie
 /// (with the [urlencoding crate](https://crates.io/crates/urlencoding))
 /// or an error if the value's URI decoding fails.
-pub fn get(cookie_string: &str, name: &str) -> Option<Result<String, FromUrlEncodingError>> {
-    let name = urlencoding::encode(name);
+#[cfg(feature = "encoding")]
+pub fn get(cookie_string: &str, name: &str) -> Option<Result<String, GetDecodeError>> {
+    get_with(cookie_string, name, Encoding::Standard)
+}
 
-    cookie_string
-        .split(';')
-        .find_map(|key_value_str| match process_key_value_str(key_value_str) {
-            Ok((key, value)) => {
-                if key == name {
-                    Some(urlencoding::decode(value))
-                } else {
-                    None
-                }
-            }
+/// Same as [get](fn.get.html), with the given URI encoding.
+#[cfg(feature = "encoding")]
+pub fn get_with(
+    cookie_string: &str,
+    name: &str,
+    encoding: Encoding,
+) -> Option<Result<String, GetDecodeError>> {
+    get_raw(cookie_string, &encoding.encode(name)).map(|value| decode_value(name, value, encoding))
+}
 
-            Err(_) => None,
-        })
+#[cfg(feature = "encoding")]
+pub(crate) fn decode_value(
+    name: &str,
+    raw_value: String,
+    encoding: Encoding,
+) -> Result<String, GetDecodeError> {
+    encoding.decode(&raw_value).map_err(|error| GetDecodeError {
+        name: name.to_owned(),
+        raw_value,
+        error,
+    })
+}
+
+/// Same as [get](fn.get.html), choosing the cookie with `duplicates` when several cookies have
+/// the same name.
+#[cfg(feature = "encoding")]
+pub fn get_with_duplicates(
+    cookie_string: &str,
+    name: &str,
+    duplicates: Duplicates,
+) -> Option<Result<String, GetDecodeError>> {
+    get_raw_with_duplicates(cookie_string, &urlencoding::encode(name), duplicates)
+        .map(|value| decode_value(name, value, Encoding::Standard))
+}
+
+/// Returns URI decoded cookie if it exists
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)). The bytes of the value
+/// that aren't valid UTF-8 once decoded are replaced with `U+FFFD`, instead of failing.
+#[cfg(feature = "encoding")]
+pub fn get_lossy(cookie_string: &str, name: &str) -> Option<String> {
+    get_raw(cookie_string, &urlencoding::encode(name))
+        .map(|value| Encoding::Standard.decode_lossy(&value))
+}
+
+/// Returns URI decoded cookie if it exists
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)). If the decoded value
+/// isn't valid UTF-8, it is reinterpreted as ISO-8859-1 (Latin-1), as written by some legacy
+/// backends, instead of failing.
+#[cfg(feature = "encoding")]
+pub fn get_latin1_fallback(cookie_string: &str, name: &str) -> Option<String> {
+    get_raw(cookie_string, &urlencoding::encode(name))
+        .map(|value| Encoding::Standard.decode_latin1_fallback(&value))
+}
+
+/// Same as [get](fn.get.html), with the result and the option swapped so the `?` operator
+/// can be used on the decoding error: returns `Ok(None)` if the cookie doesn't exist.
+#[cfg(feature = "encoding")]
+pub fn get2(cookie_string: &str, name: &str) -> Result<Option<String>, GetDecodeError> {
+    get(cookie_string, name).transpose()
+}
+
+/// If it exists, returns the cookie whose value is a form (`a=1&b=2`, see
+/// [set_form](fn.set_form.html)) deserialized to `T`
+/// (with the [serde_urlencoded crate](https://crates.io/crates/serde_urlencoded)),
+/// or an error if the deserialization fails.
+///
+/// Available only with the `form` feature.
+#[cfg(feature = "form")]
+pub fn get_form<T: serde::de::DeserializeOwned>(
+    cookie_string: &str,
+    name: &str,
+) -> Option<Result<T, serde_urlencoded::de::Error>> {
+    get_raw(cookie_string, &urlencoding::encode(name))
+        .map(|value| serde_urlencoded::from_str(&value))
 }
 
 /// Cookies options (see [https://developer.mozilla.org/en-US/docs/Web/API/Document/cookie](https://developer.mozilla.org/en-US/docs/Web/API/Document/cookie)).
 ///
-/// You can create it by calling `CookieOptions::default()`.
+/// You can create it by calling `CookieOptions::default()` or `CookieOptions::new()`.
+/// Since `CookieOptions::new()`, the presets and some builder methods are `const`, common
+/// option sets can be stored in `const` or `static` items (see also the associated constants).
 #[derive(Default, Clone, Debug)]
 pub struct CookieOptions<'a> {
     /// If `None`, defaults to the current path of the current document location.
-    pub path: Option<&'a str>,
+    pub path: Option<Cow<'a, str>>,
 
     /// If `None`, defaults to the host portion of the current document location.
-    pub domain: Option<&'a str>,
+    pub domain: Option<Cow<'a, str>>,
 
     /// Expiration date in GMT string format.
     /// If `None`, the cookie will expire at the end of session.
@@ -142,18 +634,100 @@ pub struct CookieOptions<'a> {
     pub same_site: SameSite,
 }
 
+impl CookieOptions<'static> {
+    /// Options with the path set to `/`, so the cookie is available on the whole site.
+    pub const ROOT: Self = Self::dev();
+
+    /// Options with the path set to `/` and the `secure` flag set.
+    pub const SECURE_ROOT: Self = Self::ROOT.secure();
+
+    /// Options with the path set to `/`, the `secure` flag set and SameSite set to `Strict`.
+    pub const STRICT_SECURE_ROOT: Self = Self::strict_security();
+}
+
 impl<'a> CookieOptions<'a> {
-    /// Set the path.
+    /// Create options with the default values, usable in `const` contexts.
+    /// This is the same as `CookieOptions::default()`.
+    pub const fn new() -> Self {
+        Self {
+            path: None,
+            domain: None,
+            expires: None,
+            secure: false,
+            same_site: SameSite::Lax,
+        }
+    }
+
+    /// Preset for production: the cookie is available on the whole site (path `/`), is only
+    /// transmitted over HTTPS, and is never sent along with cross-site requests
+    /// (SameSite `Strict`).
+    ///
+    /// These options pass [validate](#method.validate) and satisfy the requirements of the
+    /// `__Host-` and `__Secure-` cookie name prefixes. The document must be in a secure context
+    /// (HTTPS or localhost), otherwise browsers reject the cookie.
+    pub const fn strict_security() -> Self {
+        Self {
+            path: Some(Cow::Borrowed("/")),
+            domain: None,
+            expires: None,
+            secure: true,
+            same_site: SameSite::Strict,
+        }
+    }
+
+    /// Preset for local development over plain HTTP: the cookie is available on the whole site
+    /// (path `/`), isn't restricted to HTTPS, and uses SameSite `Lax`.
+    ///
+    /// Don't use it in production, prefer [strict_security](#method.strict_security).
+    pub const fn dev() -> Self {
+        Self {
+            path: Some(Cow::Borrowed("/")),
+            domain: None,
+            expires: None,
+            secure: false,
+            same_site: SameSite::Lax,
+        }
+    }
+
+    /// Set the path, either borrowed or owned.
     /// The default value is the current path of the current document location.
-    pub fn with_path(mut self, path: &'a str) -> Self {
-        self.path = Some(path);
+    pub fn with_path(mut self, path: impl Into<Cow<'a, str>>) -> Self {
+        self.path = Some(path.into());
         self
     }
 
-    /// Set the domain.
+    /// Set the domain, either borrowed or owned.
     /// The default value is the host portion of the current document location.
-    pub fn with_domain(mut self, domain: &'a str) -> Self {
-        self.domain = Some(domain);
+    pub fn with_domain(mut self, domain: impl Into<Cow<'a, str>>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Set the path to a static string, usable in `const` contexts.
+    ///
+    /// A `const fn` can't free memory, so it panics if the path was set to an owned string:
+    /// outside of `const` and `static` items, use [with_path](#method.with_path).
+    pub const fn with_static_path(mut self, path: &'static str) -> Self {
+        if let Some(Cow::Owned(_)) = &self.path {
+            panic!("with_static_path can't replace an owned path, use with_path");
+        }
+
+        // The previous path owns no memory, so forgetting it leaks nothing.
+        std::mem::forget(self.path.replace(Cow::Borrowed(path)));
+        self
+    }
+
+    /// Set the domain to a static string, usable in `const` contexts.
+    ///
+    /// A `const fn` can't free memory, so it panics if the domain was set to an owned string:
+    /// outside of `const` and `static` items, use [with_domain](#method.with_domain).
+    pub const fn with_static_domain(mut self, domain: &'static str) -> Self {
+        if let Some(Cow::Owned(_)) = &self.domain {
+            panic!("with_static_domain can't replace an owned domain, use with_domain");
+        }
+
+        // The previous domain owns no memory, so forgetting it leaks nothing.
+        std::mem::forget(self.domain.replace(Cow::Borrowed(domain)));
         self
     }
 
@@ -162,21 +736,27 @@ impl<'a> CookieOptions<'a> {
     /// `date` must be a GMT string (see <https://developer.mozilla.org/fr/docs/Web/JavaScript/Reference/Global_Objects/Date/toUTCString>).
     ///
     /// The default behavior of the cookie is to expire at the end of session.
-    pub fn expires_at_date(mut self, date: &'a str) -> Self {
-        self.expires = Some(Cow::Borrowed(date));
+    pub fn expires_at_date(mut self, date: impl Into<Cow<'a, str>>) -> Self {
+        self.expires = Some(date.into());
         self
     }
 
     /// Expires the cookie at a specific timestamp (in milliseconds, UTC, with leap seconds ignored).
     /// The default behavior of the cookie is to expire at the end of session.
+    ///
+    /// The timestamp is clamped between 0 (`Thu, 01 Jan 1970 00:00:00 GMT`, so earlier
+    /// timestamps still delete the cookie) and
+    /// [MAX_EXPIRES_TIMESTAMP](constant.MAX_EXPIRES_TIMESTAMP.html), so the date is always valid.
     pub fn expires_at_timestamp(mut self, timestamp: i64) -> Self {
-        #[cfg(target_arch = "wasm32")]
+        let timestamp = timestamp.clamp(0, MAX_EXPIRES_TIMESTAMP);
+
+        #[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
         let date: String = Date::new(&JsValue::from_f64(timestamp as f64))
             .to_utc_string()
             .into();
 
-        #[cfg(not(target_arch = "wasm32"))]
-        let date = NaiveDateTime::from_timestamp_millis(timestamp)
+        #[cfg(any(not(target_family = "wasm"), target_os = "emscripten"))]
+        let date = DateTime::from_timestamp_millis(timestamp)
             .unwrap()
             .format("%a, %d %b %Y %T GMT")
             .to_string();
@@ -187,16 +767,47 @@ impl<'a> CookieOptions<'a> {
 
     /// Expires the cookie after a certain duration.
     /// The default behavior of the cookie is to expire at the end of session.
+    ///
+    /// Durations too long to be represented are clamped (see
+    /// [expires_at_timestamp](#method.expires_at_timestamp)).
     pub fn expires_after(self, duration: Duration) -> Self {
-        #[cfg(target_arch = "wasm32")]
-        let now = Date::now() as i64;
-        #[cfg(not(target_arch = "wasm32"))]
-        let now = Utc::now().timestamp_millis();
-        self.expires_at_timestamp(now + duration.as_millis() as i64)
+        self.expires_at_timestamp(timestamp_after(duration))
+    }
+
+    /// Expires the cookie after `hours` hours.
+    /// The default behavior of the cookie is to expire at the end of session.
+    pub fn expires_in_hours(self, hours: u64) -> Self {
+        self.expires_after(Duration::from_secs(hours.saturating_mul(60 * 60)))
+    }
+
+    /// Expires the cookie after `days` days.
+    /// The default behavior of the cookie is to expire at the end of session.
+    pub fn expires_in_days(self, days: u64) -> Self {
+        self.expires_after(Duration::from_secs(days.saturating_mul(24 * 60 * 60)))
+    }
+
+    /// Expires the cookie after `weeks` weeks.
+    /// The default behavior of the cookie is to expire at the end of session.
+    pub fn expires_in_weeks(self, weeks: u64) -> Self {
+        self.expires_after(Duration::from_secs(weeks.saturating_mul(7 * 24 * 60 * 60)))
+    }
+
+    /// Moves the expiration date back to `max` from now if it is later, so it doesn't exceed what
+    /// the browser will honor (see [MAX_EXPIRY](constant.MAX_EXPIRY.html) and the other caps).
+    /// Session cookies and unparsable dates are left unchanged.
+    pub fn clamp_expiry(self, max: Duration) -> Self {
+        let max_timestamp = timestamp_after(max);
+
+        match self.expires.as_deref().and_then(parse_date) {
+            Some(timestamp) if timestamp > max_timestamp => {
+                self.expires_at_timestamp(max_timestamp)
+            }
+            _ => self,
+        }
     }
 
     /// Set the cookie to be only transmitted over secure protocol as HTTPS.
-    pub fn secure(mut self) -> Self {
+    pub const fn secure(mut self) -> Self {
         self.secure = true;
         self
     }
@@ -208,104 +819,1377 @@ impl<'a> CookieOptions<'a> {
         self.same_site = same_site;
         self
     }
-}
-
-/// SameSite value for [CookieOptions](struct.CookieOptions.html).
-///
-/// SameSite prevents the browser from sending the cookie along with cross-site requests
-/// (see [https://developer.mozilla.org/en-US/docs/Web/HTTP/Cookies#SameSite_attribute](https://developer.mozilla.org/en-US/docs/Web/HTTP/Cookies#SameSite_attribute)).
-#[derive(Clone, Debug)]
-pub enum SameSite {
-    /// The `Lax` value value will send the cookie for all same-site requests and top-level navigation GET requests.
-    /// This is sufficient for user tracking, but it will prevent many CSRF attacks.
-    /// This is the default value when calling `SameSite::default()`.
-    Lax,
 
-    /// The `Strict` value will prevent the cookie from being sent by the browser to the
-    /// target site in all cross-site browsing contexts, even when following a regular link.
-    Strict,
-
-    /// The `None` value explicitly states no restrictions will be applied.
-    /// The cookie will be sent in all requests - both cross-site and same-site.
-    None,
-}
+    /// Set the SameSite value, usable in `const` contexts.
+    ///
+    /// A `const fn` can't free memory, so it panics if the value was a `SameSite::Other`:
+    /// outside of `const` and `static` items, use [with_same_site](#method.with_same_site).
+    pub const fn with_static_same_site(mut self, same_site: SameSite) -> Self {
+        if let SameSite::Other(_) = &self.same_site {
+            panic!("with_static_same_site can't replace SameSite::Other, use with_same_site");
+        }
 
-impl Default for SameSite {
-    fn default() -> Self {
-        Self::Lax
+        // The previous value owns no memory, so forgetting it leaks nothing.
+        std::mem::forget(std::mem::replace(&mut self.same_site, same_site));
+        self
     }
-}
 
-impl SameSite {
-    fn cookie_string_value(&self) -> &'static str {
-        match self {
-            SameSite::Lax => "lax",
-            SameSite::Strict => "strict",
-            SameSite::None => "none",
+    /// Converts the options to owned options, which don't borrow any string.
+    pub fn into_owned(self) -> CookieOptions<'static> {
+        CookieOptions {
+            path: self.path.map(|path| Cow::Owned(path.into_owned())),
+            domain: self.domain.map(|domain| Cow::Owned(domain.into_owned())),
+            expires: self.expires.map(|expires| Cow::Owned(expires.into_owned())),
+            secure: self.secure,
+            same_site: self.same_site,
         }
     }
-}
 
-/// Return the cookie string that sets a cookie, with non encoded name and value.
-pub fn set_raw(name: &str, value: &str, options: &CookieOptions) -> String {
-    let mut cookie_string = name.to_owned();
-    cookie_string.push('=');
-    cookie_string.push_str(value);
+    /// Returns new options where the fields set in `overrides` replace the ones of `self`.
+    ///
+    /// Since every field of [CookieOverrides](struct.CookieOverrides.html) can be left unset,
+    /// an override can also turn `secure` off or set SameSite back to `Lax`:
+    ///
+    /// ```
+    /// use wasm_cookies::{CookieOptions, CookieOverrides, SameSite};
+    ///
+    /// let global_defaults = CookieOptions::STRICT_SECURE_ROOT;
+    /// let feature_defaults = CookieOverrides::new().with_same_site(SameSite::Lax);
+    ///
+    /// let options = global_defaults.merge(
+    ///     &CookieOverrides::new()
+    ///         .with_domain("example.com")
+    ///         .or_defaults(&feature_defaults),
+    /// );
+    ///
+    /// assert_eq!(
+    ///     options.to_attribute_string(),
+    ///     "path=/;domain=example.com;secure;samesite=lax"
+    /// );
+    /// ```
+    pub fn merge(&self, overrides: &CookieOverrides<'a>) -> Self {
+        Self {
+            path: overrides.path.clone().or_else(|| self.path.clone()),
+            domain: overrides.domain.clone().or_else(|| self.domain.clone()),
+            expires: overrides.expires.clone().or_else(|| self.expires.clone()),
+            secure: overrides.secure.unwrap_or(self.secure),
+            same_site: overrides
+                .same_site
+                .clone()
+                .unwrap_or_else(|| self.same_site.clone()),
+        }
+    }
+
+    /// Returns the attributes part of a cookie string, as used in `document.cookie` and
+    /// `Set-Cookie` headers (for example `path=/;secure;samesite=lax`).
+    pub fn to_attribute_string(&self) -> String {
+        self.to_attribute_string_with_format(AttributeFormat::Compact)
+    }
+
+    /// Same as [to_attribute_string](#method.to_attribute_string), in the given format (for
+    /// example `Path=/; Secure; SameSite=Lax` with `AttributeFormat::Canonical`).
+    pub fn to_attribute_string_with_format(&self, format: AttributeFormat) -> String {
+        let mut attribute_string = String::with_capacity(self.attributes_len(format));
+        self.write_attributes(&mut attribute_string, format)
+            .expect("writing to a String can't fail");
+        attribute_string.drain(..format.separator().len());
+        attribute_string
+    }
+
+    /// Parses the attributes part of a cookie string, as used in `document.cookie` and
+    /// `Set-Cookie` headers (for example `Path=/; Secure; SameSite=Lax`).
+    ///
+    /// Following [RFC 6265](https://datatracker.ietf.org/doc/html/rfc6265#section-5.2),
+    /// attribute names are case-insensitive, and unknown attributes are ignored.
+    /// Unknown SameSite values are kept as `SameSite::Other`.
+    /// If an attribute appears several times, the last one wins.
+    pub fn parse_attributes(attribute_string: &'a str) -> Self {
+        let mut options = Self::new();
+
+        for attribute in attribute_string.split(';') {
+            let (name, value) = match attribute.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+
+            if name.eq_ignore_ascii_case("path") {
+                options.path = Some(Cow::Borrowed(value));
+            } else if name.eq_ignore_ascii_case("domain") {
+                options.domain = Some(Cow::Borrowed(value));
+            } else if name.eq_ignore_ascii_case("expires") {
+                options.expires = Some(Cow::Borrowed(value));
+            } else if name.eq_ignore_ascii_case("secure") {
+                options.secure = true;
+            } else if name.eq_ignore_ascii_case("samesite") {
+                if let Some(same_site) = SameSite::from_cookie_string_value(value) {
+                    options.same_site = same_site;
+                }
+            }
+        }
+
+        options
+    }
+
+    /// Returns the exact length of the attributes written by `write_attributes`, so cookie
+    /// strings are built in one allocation.
+    fn attributes_len(&self, format: AttributeFormat) -> usize {
+        let [path_name, domain_name, expires_name, secure_name, same_site_name] = format.names();
+        let same_site = same_site_name.len() + self.same_site.value(format).len();
+        let path = self
+            .path
+            .as_ref()
+            .map_or(0, |path| path_name.len() + path.len());
+        let domain = self
+            .domain
+            .as_ref()
+            .map_or(0, |domain| domain_name.len() + domain.len());
+        let expires = self
+            .expires
+            .as_ref()
+            .map_or(0, |expires_str| expires_name.len() + expires_str.len());
+        let secure = if self.secure { secure_name.len() } else { 0 };
+
+        path + domain + expires + secure + same_site
+    }
+
+    // The pieces are written with `write_str` rather than `write!`, which goes through the
+    // slower formatting machinery.
+    fn write_attributes(&self, writer: &mut impl Write, format: AttributeFormat) -> fmt::Result {
+        let [path_name, domain_name, expires_name, secure_name, same_site_name] = format.names();
+
+        if let Some(path) = &self.path {
+            writer.write_str(path_name)?;
+            writer.write_str(path)?;
+        }
+
+        if let Some(domain) = &self.domain {
+            writer.write_str(domain_name)?;
+            writer.write_str(domain)?;
+        }
+
+        if let Some(expires_str) = &self.expires {
+            writer.write_str(expires_name)?;
+            writer.write_str(expires_str)?;
+        }
+
+        if self.secure {
+            writer.write_str(secure_name)?;
+        }
+
+        writer.write_str(same_site_name)?;
+        writer.write_str(self.same_site.value(format))
+    }
+
+    /// Checks the options for values that are valid but probably don't behave as intended,
+    /// and returns the corresponding warnings. An empty vector means no problem was found.
+    ///
+    /// ```
+    /// use wasm_cookies::CookieOptions;
+    ///
+    /// debug_assert!(CookieOptions::SECURE_ROOT.validate().is_empty());
+    /// ```
+    pub fn validate(&self) -> Vec<CookieOptionsWarning> {
+        let mut warnings = Vec::new();
+
+        if let Some(path) = &self.path {
+            if !path.starts_with('/') {
+                warnings.push(CookieOptionsWarning::RelativePath);
+            }
+        }
+
+        if let Some(domain) = &self.domain {
+            if domain.starts_with('.') {
+                warnings.push(CookieOptionsWarning::DomainLeadingDot);
+            }
+        }
+
+        if let Some(expires) = &self.expires {
+            match parse_date(expires) {
+                Some(timestamp) if timestamp <= now_timestamp() => {
+                    warnings.push(CookieOptionsWarning::ExpiresInPast)
+                }
+                Some(_) => {}
+                None => warnings.push(CookieOptionsWarning::InvalidExpires),
+            }
+        }
+
+        if let SameSite::None = self.same_site {
+            if !self.secure {
+                warnings.push(CookieOptionsWarning::SameSiteNoneWithoutSecure);
+            }
+        }
+
+        if !self.same_site.is_valid() {
+            warnings.push(CookieOptionsWarning::InvalidSameSite);
+        }
+
+        warnings
+    }
+}
+
+/// Options overriding some fields of other options (see
+/// [CookieOptions::merge](struct.CookieOptions.html#method.merge)).
+///
+/// Unlike in [CookieOptions](struct.CookieOptions.html), every field can be left unset, so
+/// setting `secure` to false or SameSite to `Lax` is an override too.
+#[derive(Default, Clone, Debug)]
+pub struct CookieOverrides<'a> {
+    /// Path, if overridden.
+    pub path: Option<Cow<'a, str>>,
+
+    /// Domain, if overridden.
+    pub domain: Option<Cow<'a, str>>,
+
+    /// Expiration date in GMT string format, if overridden.
+    pub expires: Option<Cow<'a, str>>,
+
+    /// `secure` flag, if overridden.
+    pub secure: Option<bool>,
+
+    /// SameSite value, if overridden.
+    pub same_site: Option<SameSite>,
+}
+
+impl<'a> CookieOverrides<'a> {
+    /// Creates overrides with no field set, usable in `const` contexts.
+    pub const fn new() -> Self {
+        Self {
+            path: None,
+            domain: None,
+            expires: None,
+            secure: None,
+            same_site: None,
+        }
+    }
+
+    /// Overrides the path, either borrowed or owned.
+    pub fn with_path(mut self, path: impl Into<Cow<'a, str>>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Overrides the domain, either borrowed or owned.
+    pub fn with_domain(mut self, domain: impl Into<Cow<'a, str>>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Overrides the expiration date, which must be a GMT string.
+    pub fn expires_at_date(mut self, date: impl Into<Cow<'a, str>>) -> Self {
+        self.expires = Some(date.into());
+        self
+    }
+
+    /// Overrides the `secure` flag.
+    pub const fn with_secure(mut self, secure: bool) -> Self {
+        self.secure = Some(secure);
+        self
+    }
+
+    /// Overrides the SameSite value.
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Fills the unset fields of these overrides with the ones of `defaults`, which allows
+    /// chaining several layers of defaults before merging them into options (see
+    /// [CookieOptions::merge](struct.CookieOptions.html#method.merge)).
+    pub fn or_defaults(self, defaults: &CookieOverrides<'a>) -> Self {
+        Self {
+            path: self.path.or_else(|| defaults.path.clone()),
+            domain: self.domain.or_else(|| defaults.domain.clone()),
+            expires: self.expires.or_else(|| defaults.expires.clone()),
+            secure: self.secure.or(defaults.secure),
+            same_site: self.same_site.or_else(|| defaults.same_site.clone()),
+        }
+    }
+}
+
+impl<'a> From<CookieOptions<'a>> for CookieOverrides<'a> {
+    /// Overrides `secure`, SameSite, and the fields of `options` that are `Some`.
+    fn from(options: CookieOptions<'a>) -> Self {
+        Self {
+            path: options.path,
+            domain: options.domain,
+            expires: options.expires,
+            secure: Some(options.secure),
+            same_site: Some(options.same_site),
+        }
+    }
+}
+
+/// A cookie with its name, value and options, as found in a `Set-Cookie` header.
+#[derive(Default, Clone, Debug)]
+pub struct Cookie<'a> {
+    /// The name, either borrowed or owned.
+    pub name: Cow<'a, str>,
+
+    /// The value, either borrowed or owned.
+    pub value: Cow<'a, str>,
+
+    /// The options.
+    pub options: CookieOptions<'a>,
+
+    /// If true, the cookie is inaccessible to JavaScript (and to this crate in a browser).
+    pub http_only: bool,
+}
+
+impl<'a> Cookie<'a> {
+    /// Create a cookie with default options.
+    pub fn new(name: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Parses the value of a `Set-Cookie` header (for example `key=value; Path=/; HttpOnly`),
+    /// with undecoded name and value.
+    ///
+    /// Returns `None` if there is no `=` in the name-value pair, in which case browsers ignore
+    /// the header (see [RFC 6265](https://datatracker.ietf.org/doc/html/rfc6265#section-5.2)).
+    pub fn parse_set_cookie(header: &'a str) -> Option<Self> {
+        let (pair, attributes) = header.split_once(';').unwrap_or((header, ""));
+        let (name, value) = parse_pair(pair).ok()?;
+
+        let http_only = attributes
+            .split(';')
+            .any(|attribute| attribute.trim().eq_ignore_ascii_case("httponly"));
+
+        Some(Self {
+            name: Cow::Borrowed(name),
+            value: Cow::Borrowed(value),
+            options: CookieOptions::parse_attributes(attributes),
+            http_only,
+        })
+    }
+
+    /// Converts the cookie to an owned cookie, which doesn't borrow any string.
+    pub fn into_owned(self) -> Cookie<'static> {
+        Cookie {
+            name: Cow::Owned(self.name.into_owned()),
+            value: Cow::Owned(self.value.into_owned()),
+            options: self.options.into_owned(),
+            http_only: self.http_only,
+        }
+    }
+}
+
+/// Splits the value of several `Set-Cookie` headers joined with commas (as returned by
+/// `Headers.get` in JavaScript, or by HTTP libraries which combine repeated headers) into the
+/// values of the individual headers.
+///
+/// A comma only separates two headers if it is followed by a name-value pair, so the commas in
+/// `Expires` dates (such as `Expires=Thu, 01 Jan 2026 00:00:00 GMT`) and in values are kept.
+pub fn split_set_cookie_header(header: &str) -> Vec<&str> {
+    let mut headers = Vec::new();
+    let mut start = 0;
+
+    for (index, _) in header.match_indices(',') {
+        let rest = &header[index + 1..];
+        let next_pair = rest.split([';', ',']).next().unwrap_or("");
+
+        if parse_pair(next_pair).is_ok() {
+            headers.push(header[start..index].trim());
+            start = index + 1;
+        }
+    }
+
+    headers.push(header[start..].trim());
+    headers.retain(|header| !header.is_empty());
+    headers
+}
+
+/// Joins the values of several `Set-Cookie` headers into one value, separated by commas, which
+/// can be split with [split_set_cookie_header](fn.split_set_cookie_header.html).
+///
+/// Only use it where a single header is expected: browsers require one `Set-Cookie` header per
+/// cookie.
+pub fn fold_set_cookie_headers<'a>(headers: impl IntoIterator<Item = &'a str>) -> String {
+    headers.into_iter().collect::<Vec<_>>().join(", ")
+}
+
+/// SameSite value for [CookieOptions](struct.CookieOptions.html).
+///
+/// SameSite prevents the browser from sending the cookie along with cross-site requests
+/// (see [https://developer.mozilla.org/en-US/docs/Web/HTTP/Cookies#SameSite_attribute](https://developer.mozilla.org/en-US/docs/Web/HTTP/Cookies#SameSite_attribute)).
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SameSite {
+    /// The `Lax` value value will send the cookie for all same-site requests and top-level navigation GET requests.
+    /// This is sufficient for user tracking, but it will prevent many CSRF attacks.
+    /// This is the default value when calling `SameSite::default()`.
+    #[default]
+    Lax,
+
+    /// The `Strict` value will prevent the cookie from being sent by the browser to the
+    /// target site in all cross-site browsing contexts, even when following a regular link.
+    Strict,
+
+    /// The `None` value explicitly states no restrictions will be applied.
+    /// The cookie will be sent in all requests - both cross-site and same-site.
+    None,
+
+    /// Any other value, written as is in the cookie string.
+    /// This allows using values introduced by browsers after this version of the crate.
+    ///
+    /// The value must be a token: if it is empty or contains `;`, `,`, whitespace or a control
+    /// character, the default value `Lax` is written instead, so it can't add other attributes
+    /// to the cookie (see [is_valid](#method.is_valid)).
+    Other(String),
+}
+
+impl SameSite {
+    /// Returns false if the value is a `SameSite::Other` that isn't a token, and is written as
+    /// `Lax` instead.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            SameSite::Other(value) => is_token(value),
+            _ => true,
+        }
+    }
+
+    fn value(&self, format: AttributeFormat) -> &str {
+        match (self, format) {
+            (SameSite::Other(value), _) if is_token(value) => value,
+            (SameSite::Strict, AttributeFormat::Canonical) => "Strict",
+            (SameSite::None, AttributeFormat::Canonical) => "None",
+            (_, AttributeFormat::Canonical) => "Lax",
+            (SameSite::Strict, _) => "strict",
+            (SameSite::None, _) => "none",
+            _ => "lax",
+        }
+    }
+
+    pub(crate) fn from_cookie_string_value(value: &str) -> Option<Self> {
+        if !is_token(value) {
+            None
+        } else if value.eq_ignore_ascii_case("lax") {
+            Some(SameSite::Lax)
+        } else if value.eq_ignore_ascii_case("strict") {
+            Some(SameSite::Strict)
+        } else if value.eq_ignore_ascii_case("none") {
+            Some(SameSite::None)
+        } else {
+            Some(SameSite::Other(value.to_owned()))
+        }
+    }
+}
+
+/// Deserializes the SameSite value from its string (`"Strict"`, `"lax"`...), case-insensitively.
+///
+/// Available only with the `json` feature.
+#[cfg(feature = "json")]
+impl<'de> serde::Deserialize<'de> for SameSite {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        SameSite::from_cookie_string_value(&value).ok_or_else(|| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(&value), &"a token")
+        })
+    }
+}
 
-    if let Some(path) = options.path {
-        cookie_string.push_str(";path=");
-        cookie_string.push_str(path);
+/// Serializes the SameSite value as its capitalized string (`"Strict"`, `"Lax"`...).
+///
+/// Available only with the `json` feature.
+#[cfg(feature = "json")]
+impl serde::Serialize for SameSite {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.value(AttributeFormat::Canonical))
     }
+}
+
+/// Returns true if `value` is a non-empty attribute value without `;`, `,`, whitespace or control
+/// characters.
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c == ';' || c == ',' || c.is_whitespace() || c.is_control())
+}
+
+/// Warning returned by [CookieOptions::validate](struct.CookieOptions.html#method.validate).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CookieOptionsWarning {
+    /// The path doesn't start with `/`.
+    /// Browsers ignore such a path and use the default path instead.
+    RelativePath,
+
+    /// The domain starts with a dot.
+    /// Browsers ignore the leading dot: the cookie is always available on subdomains
+    /// when a domain is set.
+    DomainLeadingDot,
+
+    /// The expiration date is in the past, so the cookie will be deleted instead of set.
+    ExpiresInPast,
+
+    /// The expiration date is not a valid GMT string, so browsers will ignore it and the cookie
+    /// will expire at the end of session.
+    InvalidExpires,
+
+    /// SameSite is `None` but the `secure` flag isn't set.
+    /// Browsers reject such cookies.
+    SameSiteNoneWithoutSecure,
+
+    /// SameSite is a `SameSite::Other` value that isn't a token, so `Lax` is written instead
+    /// (see [SameSite::is_valid](enum.SameSite.html#method.is_valid)).
+    InvalidSameSite,
+}
+
+/// Return the cookie string that sets a cookie, with non encoded name and value.
+///
+/// The characters that would add attributes or headers to the write (`;`, CR and LF) are
+/// percent-encoded (see [InjectionPolicy](enum.InjectionPolicy.html)), so a value like
+/// `x;domain=evil.com` can't change the scope of the cookie. Commas pass through: use
+/// [set_raw_with_injection](fn.set_raw_with_injection.html) for `Set-Cookie` header fields.
+pub fn set_raw(name: &str, value: &str, options: &CookieOptions) -> String {
+    set_raw_with_format(name, value, options, AttributeFormat::Compact)
+}
+
+/// Same as [set_raw](fn.set_raw.html), with the given handling of the characters that would add
+/// attributes or cookies to the write. Returns the first such character found with
+/// `InjectionPolicy::Reject`.
+pub fn set_raw_with_injection(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    injection: InjectionPolicy,
+) -> Result<String, RejectionReason> {
+    match injection {
+        InjectionPolicy::Escape => Ok(set_raw(name, value, options)),
+
+        InjectionPolicy::EscapeCommas => {
+            let escape = |string: &str| escape_injection(string).replace(',', "%2C");
+            let mut cookie_string = String::new();
+            write_cookie_string(
+                &mut cookie_string,
+                &escape(name),
+                &escape(value),
+                options,
+                AttributeFormat::Compact,
+            );
+            Ok(cookie_string)
+        }
+
+        InjectionPolicy::Reject => {
+            if let Some(c) = name.chars().find(|c| is_injection_char(*c)) {
+                Err(RejectionReason::InvalidName(c))
+            } else if let Some(c) = value.chars().find(|c| is_injection_char(*c)) {
+                Err(RejectionReason::InvalidValue(c))
+            } else {
+                Ok(set_raw(name, value, options))
+            }
+        }
 
-    if let Some(domain) = options.domain {
-        cookie_string.push_str(";domain=");
-        cookie_string.push_str(domain);
+        InjectionPolicy::Allow => {
+            let mut cookie_string = String::new();
+            write_cookie_string(
+                &mut cookie_string,
+                name,
+                value,
+                options,
+                AttributeFormat::Compact,
+            );
+            Ok(cookie_string)
+        }
     }
+}
+
+/// Handling of the characters of raw names and values that would add attributes or cookies to
+/// the write: `;` (attribute separator), `,` (separator of folded `Set-Cookie` headers), CR and
+/// LF (header separators).
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InjectionPolicy {
+    /// `;`, CR and LF are percent-encoded (`%3B`, `%0D` and `%0A`). Browsers never store them
+    /// in a cookie, so no readable value is changed. Commas are kept, since they are harmless
+    /// in `document.cookie` and common in raw values.
+    #[default]
+    Escape,
+
+    /// Same as `Escape`, and commas are percent-encoded too (`%2C`), for `Set-Cookie` headers
+    /// which may be folded into one (see
+    /// [fold_set_cookie_headers](fn.fold_set_cookie_headers.html)).
+    EscapeCommas,
+
+    /// The cookie is rejected if it contains any of the characters, commas included.
+    Reject,
+
+    /// The characters are written as is. Only use it with trusted names and values.
+    Allow,
+}
+
+fn is_injection_char(c: char) -> bool {
+    matches!(c, ';' | ',' | '\r' | '\n')
+}
 
-    if let Some(expires_str) = &options.expires {
-        cookie_string.push_str(";expires=");
-        cookie_string.push_str(expires_str);
+/// Returns `string` with `;`, CR and LF percent-encoded.
+fn escape_injection(string: &str) -> Cow<'_, str> {
+    if !string.contains([';', '\r', '\n']) {
+        return Cow::Borrowed(string);
     }
 
-    if options.secure {
-        cookie_string.push_str(";secure");
+    let mut escaped = String::with_capacity(string.len() + 8);
+
+    for c in string.chars() {
+        match c {
+            ';' => escaped.push_str("%3B"),
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            c => escaped.push(c),
+        }
     }
 
-    cookie_string.push_str(";samesite=");
-    cookie_string.push_str(options.same_site.cookie_string_value());
+    Cow::Owned(escaped)
+}
 
+/// Same as [set_raw](fn.set_raw.html), with the attributes in the given format.
+///
+/// ```
+/// use wasm_cookies::cookies::{self, AttributeFormat, CookieOptions};
+///
+/// assert_eq!(
+///     cookies::set_raw_with_format("id", "1", &CookieOptions::ROOT, AttributeFormat::Canonical),
+///     "id=1; Path=/; SameSite=Lax"
+/// );
+/// ```
+pub fn set_raw_with_format(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    format: AttributeFormat,
+) -> String {
+    let mut cookie_string = String::new();
+    write_set_cookie_with_format(&mut cookie_string, name, value, options, format);
     cookie_string
 }
 
+/// Format of the attributes of the cookie strings.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AttributeFormat {
+    /// Lowercase attribute names separated by `;` (`path=/;secure;samesite=lax`), which is the
+    /// shortest.
+    #[default]
+    Compact,
+
+    /// Capitalized attribute names separated by `; ` (`Path=/; Secure; SameSite=Lax`), as
+    /// displayed by browsers and written by most servers.
+    Canonical,
+}
+
+impl AttributeFormat {
+    fn separator(self) -> &'static str {
+        match self {
+            AttributeFormat::Compact => ";",
+            AttributeFormat::Canonical => "; ",
+        }
+    }
+
+    /// Returns the separator and the name of the `Path`, `Domain`, `Expires`, `Secure` and
+    /// `SameSite` attributes.
+    fn names(self) -> [&'static str; 5] {
+        match self {
+            AttributeFormat::Compact => {
+                [";path=", ";domain=", ";expires=", ";secure", ";samesite="]
+            }
+            AttributeFormat::Canonical => [
+                "; Path=",
+                "; Domain=",
+                "; Expires=",
+                "; Secure",
+                "; SameSite=",
+            ],
+        }
+    }
+}
+
+/// Appends to `buffer` the cookie string that sets a cookie, with non encoded name and value,
+/// reserving the needed capacity at once. Like [set_raw](fn.set_raw.html), the characters that
+/// would add attributes or headers are percent-encoded.
+///
+/// This is [set_raw](fn.set_raw.html) writing into a caller buffer, so servers generating many
+/// `Set-Cookie` headers can reuse the same allocation.
+pub fn write_set_cookie(buffer: &mut String, name: &str, value: &str, options: &CookieOptions) {
+    write_set_cookie_with_format(buffer, name, value, options, AttributeFormat::Compact);
+}
+
+/// Same as [write_set_cookie](fn.write_set_cookie.html), with the attributes in the given
+/// format.
+pub fn write_set_cookie_with_format(
+    buffer: &mut String,
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    format: AttributeFormat,
+) {
+    let (name, value) = (escape_injection(name), escape_injection(value));
+    write_cookie_string(buffer, &name, &value, options, format);
+}
+
+fn write_cookie_string(
+    buffer: &mut String,
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    format: AttributeFormat,
+) {
+    buffer.reserve(name.len() + 1 + value.len() + options.attributes_len(format));
+    buffer.push_str(name);
+    buffer.push('=');
+    buffer.push_str(value);
+    options
+        .write_attributes(buffer, format)
+        .expect("writing to a String can't fail");
+}
+
+/// A buffer reused to build cookie strings, which only allocates when a cookie string is longer
+/// than all the previous ones.
+///
+/// ```
+/// use wasm_cookies::cookies::{CookieOptions, CookieWriter};
+///
+/// let mut writer = CookieWriter::new();
+///
+/// for id in ["a", "b"] {
+///     let header = writer.write_raw("id", id, &CookieOptions::ROOT);
+///     assert!(header.starts_with("id="));
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CookieWriter {
+    buffer: String,
+    format: AttributeFormat,
+}
+
+impl CookieWriter {
+    /// Creates a writer with an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a writer whose buffer holds cookie strings of `capacity` bytes without
+    /// reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: String::with_capacity(capacity),
+            format: AttributeFormat::Compact,
+        }
+    }
+
+    /// Sets the format of the attributes.
+    pub fn with_format(mut self, format: AttributeFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Returns the cookie string that sets a cookie, with non encoded name and value, borrowed
+    /// from the buffer until the next write.
+    pub fn write_raw(&mut self, name: &str, value: &str, options: &CookieOptions) -> &str {
+        self.buffer.clear();
+        write_set_cookie_with_format(&mut self.buffer, name, value, options, self.format);
+        &self.buffer
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+}
+
 /// Return the cookie string that sets a cookie, with URI encoded name and value
 /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)).
+#[cfg(feature = "encoding")]
 pub fn set(name: &str, value: &str, options: &CookieOptions) -> String {
-    set_raw(
-        &urlencoding::encode(name),
-        &urlencoding::encode(value),
-        options,
-    )
+    set_with(name, value, options, Encoding::Standard)
 }
 
-/// Return the cookie string that deletes a cookie without encoding its name.
-pub fn delete_raw(name: &str) -> String {
-    format!("{}=;expires=Thu, 01 Jan 1970 00:00:00 GMT", name)
+/// Same as [set](fn.set.html), with the given URI encoding.
+#[cfg(feature = "encoding")]
+pub fn set_with(name: &str, value: &str, options: &CookieOptions, encoding: Encoding) -> String {
+    set_raw(&encoding.encode(name), &encoding.encode(value), options)
 }
 
-/// Return the cookie string that deletes a cookie, URI encoding its name.
-pub fn delete(name: &str) -> String {
-    delete_raw(&urlencoding::encode(name))
+/// Return the cookie string that sets a cookie whose value is `value` serialized as a form
+/// (`a=1&b=2`, with the [serde_urlencoded crate](https://crates.io/crates/serde_urlencoded)),
+/// with URI encoded name, or an error if `value` can't be serialized as a form.
+///
+/// The form is more compact and readable than JSON for small flat structs. It is already
+/// escaped, so it is stored as is.
+///
+/// Available only with the `form` feature.
+#[cfg(feature = "form")]
+pub fn set_form<T: serde::Serialize>(
+    name: &str,
+    value: &T,
+    options: &CookieOptions,
+) -> Result<String, serde_urlencoded::ser::Error> {
+    Ok(set_raw(
+        &urlencoding::encode(name),
+        &serde_urlencoded::to_string(value)?,
+        options,
+    ))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Maximum size in bytes of a cookie's name and value, as enforced by browsers.
+pub const MAX_COOKIE_SIZE: usize = 4096;
 
-    #[test]
-    fn test_all_raw() {
-        let cookies = all_raw(" key1=value1;key2=value2 ; key3  = value3");
-        assert_eq!(cookies.len(), 3);
-        assert_eq!(cookies["key1"], "value1");
+/// Latest timestamp (in milliseconds, UTC) usable as an expiration date: `Fri, 31 Dec 9999
+/// 23:59:59 GMT`, since cookie dates have four-digit years. Later timestamps are clamped to it.
+pub const MAX_EXPIRES_TIMESTAMP: i64 = 253_402_300_799_000;
+
+/// Maximum lifetime of a cookie, as enforced by browsers following RFC 6265bis
+/// (Chrome, Edge, Firefox and Safari): later expiration dates are moved back to 400 days.
+pub const MAX_EXPIRY: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+/// Maximum lifetime of a cookie set by a script in Safari, because of Intelligent Tracking
+/// Prevention.
+pub const SAFARI_SCRIPT_MAX_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Maximum lifetime of a cookie set by a script in Safari when the page was reached from a
+/// domain classified as a tracker with link decoration, because of Intelligent Tracking
+/// Prevention.
+pub const SAFARI_TRACKER_LINK_MAX_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Reason why a browser would likely reject a cookie, returned by
+/// [validate_set_raw](fn.validate_set_raw.html) and [validate_set](fn.validate_set.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RejectionReason {
+    /// The name contains a character that isn't allowed in a cookie name
+    /// (`=`, `;`, whitespace or a control character).
+    InvalidName(char),
+
+    /// The value contains a character that isn't allowed in a cookie value
+    /// (`;` or a control character).
+    InvalidValue(char),
+
+    /// The name and the value together are larger than [MAX_COOKIE_SIZE](constant.MAX_COOKIE_SIZE.html).
+    ///
+    /// The field is the actual size in bytes.
+    TooLarge(usize),
+
+    /// The name starts with `__Secure-` or `__Host-` but the `secure` flag isn't set.
+    PrefixRequiresSecure,
+
+    /// The name starts with `__Host-` but the path isn't `/`.
+    HostPrefixRequiresRootPath,
+
+    /// The name starts with `__Host-` but a domain is set.
+    HostPrefixForbidsDomain,
+
+    /// SameSite is `None` but the `secure` flag isn't set.
+    SameSiteNoneWithoutSecure,
+
+    /// The `secure` flag is set but the document isn't in a secure context (HTTPS or localhost).
+    InsecureContext,
+
+    /// The domain doesn't match the host of the current document location.
+    DomainMismatch,
+}
+
+/// Returns true if `request_host` domain-matches `cookie_domain`
+/// (see [RFC 6265 section 5.1.3](https://datatracker.ietf.org/doc/html/rfc6265#section-5.1.3)):
+/// it is `cookie_domain` or one of its subdomains. IP addresses only match themselves.
+///
+/// The comparison is case-insensitive, and a leading dot in `cookie_domain` is ignored, as
+/// browsers do with the `Domain` attribute.
+pub fn domain_matches(request_host: &str, cookie_domain: &str) -> bool {
+    let host = request_host.trim_end_matches('.').to_ascii_lowercase();
+    let domain = cookie_domain
+        .trim_start_matches('.')
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+
+    if host == domain {
+        return true;
+    }
+
+    let is_ip_address = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<std::net::IpAddr>()
+        .is_ok();
+
+    !is_ip_address && host.ends_with(&domain) && host[..host.len() - domain.len()].ends_with('.')
+}
+
+/// Returns true if `request_path` path-matches `cookie_path`
+/// (see [RFC 6265 section 5.1.4](https://datatracker.ietf.org/doc/html/rfc6265#section-5.1.4)):
+/// the cookie is sent with requests to `request_path`.
+pub fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    match request_path.strip_prefix(cookie_path) {
+        Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Returns the path browsers apply to a cookie set without path from a document at
+/// `request_path` (see [RFC 6265 section 5.1.4](https://datatracker.ietf.org/doc/html/rfc6265#section-5.1.4)):
+/// the directory of the path, without trailing `/`, or `/`.
+pub fn default_path(request_path: &str) -> &str {
+    match request_path.rfind('/') {
+        Some(index) if index > 0 && request_path.starts_with('/') => &request_path[..index],
+        _ => "/",
+    }
+}
+
+/// Sorts `cookies` in the order of a `Cookie` header
+/// (see [RFC 6265 section 5.4](https://datatracker.ietf.org/doc/html/rfc6265#section-5.4)):
+/// cookies with longer paths first, then oldest first. Cookies without path are sorted as if
+/// their path was `/`.
+///
+/// Cookies don't record their creation time, so `cookies` must be in creation order, oldest
+/// first (as returned by [SimulatedJar::all_cookies](../jar/struct.SimulatedJar.html#method.all_cookies)):
+/// the sort is stable, so cookies with paths of the same length keep this order.
+pub fn sort_by_precedence(cookies: &mut [Cookie]) {
+    cookies.sort_by_key(|cookie| {
+        std::cmp::Reverse(cookie.options.path.as_deref().map_or(1, str::len))
+    });
+}
+
+/// Reason why a cookie wouldn't be sent with a request, returned by
+/// [would_apply](fn.would_apply.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WhyNot {
+    /// The URL isn't an absolute URL with a host.
+    InvalidUrl,
+
+    /// The URL's host doesn't domain-match the cookie's domain
+    /// (see [domain_matches](fn.domain_matches.html)).
+    ///
+    /// - The first field is the URL's host.
+    /// - The second field is the cookie's domain.
+    DomainMismatch(String, String),
+
+    /// The URL's path doesn't path-match the cookie's path
+    /// (see [path_matches](fn.path_matches.html)).
+    ///
+    /// - The first field is the URL's path.
+    /// - The second field is the cookie's path.
+    PathMismatch(String, String),
+
+    /// The cookie has the `secure` flag but the URL's scheme isn't secure (`https` or `wss`).
+    InsecureScheme,
+
+    /// The cookie's expiration date has passed.
+    Expired,
+}
+
+/// Checks if `cookie` (typically parsed with
+/// [Cookie::parse_set_cookie](struct.Cookie.html#method.parse_set_cookie)) would be sent with a
+/// request to `url`, and explains why not.
+///
+/// The URL that set the cookie isn't known, so a cookie without domain is assumed to have been
+/// set by the URL's host, and a cookie without path matches any path.
+pub fn would_apply(cookie: &Cookie, url: &str) -> Result<(), WhyNot> {
+    let url = crate::url::parse(url).ok_or(WhyNot::InvalidUrl)?;
+
+    if let Some(domain) = &cookie.options.domain {
+        if !domain_matches(&url.host, domain) {
+            return Err(WhyNot::DomainMismatch(url.host, domain.to_string()));
+        }
+    }
+
+    if let Some(path) = &cookie.options.path {
+        if !path_matches(url.path, path) {
+            return Err(WhyNot::PathMismatch(url.path.to_owned(), path.to_string()));
+        }
+    }
+
+    if cookie.options.secure && !url.is_secure() {
+        return Err(WhyNot::InsecureScheme);
+    }
+
+    match cookie.options.expires.as_deref().map(parse_date) {
+        Some(Some(timestamp)) if timestamp <= now_timestamp() => Err(WhyNot::Expired),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the undecoded value of the cookie in `cookie_string`, if any, and the cookie string
+/// that replaces it, with non encoded name and value.
+pub fn replace_raw(
+    cookie_string: &str,
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+) -> (Option<String>, String) {
+    (get_raw(cookie_string, name), set_raw(name, value, options))
+}
+
+/// Returns the URI decoded value of the cookie in `cookie_string`, if any, and the cookie
+/// string that replaces it, with URI encoded name and value
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)).
+///
+/// A previous value whose URI decoding fails is returned as `None`.
+#[cfg(feature = "encoding")]
+pub fn replace(
+    cookie_string: &str,
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+) -> (Option<String>, String) {
+    (
+        get(cookie_string, name).and_then(Result::ok),
+        set(name, value, options),
+    )
+}
+
+/// Adds `by` to the integer value of a cookie, without encoding its name, and returns the new
+/// value and the cookie string that stores it.
+///
+/// The addition saturates at `i64::MIN` and `i64::MAX`. A missing cookie, or one whose value
+/// isn't an integer, counts as 0, so a corrupted counter starts over instead of failing.
+pub fn increment_raw(
+    cookie_string: &str,
+    name: &str,
+    by: i64,
+    options: &CookieOptions,
+) -> (i64, String) {
+    let value = get_raw(cookie_string, name)
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(0)
+        .saturating_add(by);
+
+    (value, set_raw(name, &value.to_string(), options))
+}
+
+/// Same as [increment_raw](fn.increment_raw.html), URI encoding the name
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)).
+#[cfg(feature = "encoding")]
+pub fn increment(
+    cookie_string: &str,
+    name: &str,
+    by: i64,
+    options: &CookieOptions,
+) -> (i64, String) {
+    increment_raw(cookie_string, &urlencoding::encode(name), by, options)
+}
+
+/// Subtracts `by` from the integer value of a cookie, without encoding its name
+/// (see [increment_raw](fn.increment_raw.html)).
+pub fn decrement_raw(
+    cookie_string: &str,
+    name: &str,
+    by: i64,
+    options: &CookieOptions,
+) -> (i64, String) {
+    increment_raw(cookie_string, name, by.saturating_neg(), options)
+}
+
+/// Subtracts `by` from the integer value of a cookie, URI encoding its name
+/// (see [increment_raw](fn.increment_raw.html)).
+#[cfg(feature = "encoding")]
+pub fn decrement(
+    cookie_string: &str,
+    name: &str,
+    by: i64,
+    options: &CookieOptions,
+) -> (i64, String) {
+    increment(cookie_string, name, by.saturating_neg(), options)
+}
+
+/// Checks, without setting it, if a cookie with non encoded name and value would likely be
+/// rejected by browsers, and returns all the reasons why.
+///
+/// The checks that depend on the document (secure context and domain scope) are only done by the
+/// root module's `validate_set_raw`. Use [validate_set_raw_in](fn.validate_set_raw_in.html) to
+/// provide the document's context manually.
+pub fn validate_set_raw(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+) -> Result<(), Vec<RejectionReason>> {
+    let mut reasons = Vec::new();
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| *c == '=' || *c == ';' || c.is_whitespace() || c.is_control())
+    {
+        reasons.push(RejectionReason::InvalidName(c));
+    }
+
+    if let Some(c) = value.chars().find(|c| *c == ';' || c.is_control()) {
+        reasons.push(RejectionReason::InvalidValue(c));
+    }
+
+    let size = name.len() + value.len();
+
+    if size > MAX_COOKIE_SIZE {
+        reasons.push(RejectionReason::TooLarge(size));
+    }
+
+    if (name.starts_with("__Secure-") || name.starts_with("__Host-")) && !options.secure {
+        reasons.push(RejectionReason::PrefixRequiresSecure);
+    }
+
+    if name.starts_with("__Host-") {
+        if options.path.as_deref() != Some("/") {
+            reasons.push(RejectionReason::HostPrefixRequiresRootPath);
+        }
+
+        if options.domain.is_some() {
+            reasons.push(RejectionReason::HostPrefixForbidsDomain);
+        }
+    }
+
+    if let SameSite::None = options.same_site {
+        if !options.secure {
+            reasons.push(RejectionReason::SameSiteNoneWithoutSecure);
+        }
+    }
+
+    if reasons.is_empty() {
+        Ok(())
+    } else {
+        Err(reasons)
+    }
+}
+
+/// Same as [validate_set_raw](fn.validate_set_raw.html), but also checks the secure context
+/// requirement and the domain scope against the given document's context.
+///
+/// - `host` is the host portion of the document location.
+/// - `is_secure_context` tells if the document is in a secure context (HTTPS or localhost).
+pub fn validate_set_raw_in(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    host: &str,
+    is_secure_context: bool,
+) -> Result<(), Vec<RejectionReason>> {
+    let mut reasons = validate_set_raw(name, value, options)
+        .err()
+        .unwrap_or_default();
+
+    if options.secure && !is_secure_context {
+        reasons.push(RejectionReason::InsecureContext);
+    }
+
+    if let Some(domain) = &options.domain {
+        if !domain_matches(host, domain) {
+            reasons.push(RejectionReason::DomainMismatch);
+        }
+    }
+
+    if reasons.is_empty() {
+        Ok(())
+    } else {
+        Err(reasons)
+    }
+}
+
+/// Checks, without setting it, if a cookie with URI encoded name and value
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding))
+/// would likely be rejected by browsers, and returns all the reasons why.
+///
+/// See [validate_set_raw](fn.validate_set_raw.html) for the checks that are done.
+#[cfg(feature = "encoding")]
+pub fn validate_set(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+) -> Result<(), Vec<RejectionReason>> {
+    validate_set_raw(
+        &urlencoding::encode(name),
+        &urlencoding::encode(value),
+        options,
+    )
+}
+
+/// Same as [validate_set](fn.validate_set.html), but also checks the secure context
+/// requirement and the domain scope against the given document's context
+/// (see [validate_set_raw_in](fn.validate_set_raw_in.html)).
+#[cfg(feature = "encoding")]
+pub fn validate_set_in(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    host: &str,
+    is_secure_context: bool,
+) -> Result<(), Vec<RejectionReason>> {
+    validate_set_raw_in(
+        &urlencoding::encode(name),
+        &urlencoding::encode(value),
+        options,
+        host,
+        is_secure_context,
+    )
+}
+
+/// Return the cookie string that deletes a cookie without encoding its name.
+pub fn delete_raw(name: &str) -> String {
+    let mut cookie_string = name.to_owned();
+    cookie_string.push_str("=;expires=Thu, 01 Jan 1970 00:00:00 GMT");
+    cookie_string
+}
+
+/// Return the cookie string that deletes a cookie, URI encoding its name.
+#[cfg(feature = "encoding")]
+pub fn delete(name: &str) -> String {
+    delete_with(name, Encoding::Standard)
+}
+
+/// Same as [delete](fn.delete.html), with the given URI encoding.
+#[cfg(feature = "encoding")]
+pub fn delete_with(name: &str, encoding: Encoding) -> String {
+    delete_raw(&encoding.encode(name))
+}
+
+/// Returns the cookie string that deletes a cookie without encoding its name, or `None` if
+/// `cookie_string` doesn't contain it.
+pub fn delete_existing_raw(cookie_string: &str, name: &str) -> Option<String> {
+    get_raw(cookie_string, name).map(|_| delete_raw(name))
+}
+
+/// Returns the cookie string that deletes a cookie, URI encoding its name, or `None` if
+/// `cookie_string` doesn't contain it.
+#[cfg(feature = "encoding")]
+pub fn delete_existing(cookie_string: &str, name: &str) -> Option<String> {
+    delete_existing_raw(cookie_string, &urlencoding::encode(name))
+}
+
+/// Returns the cookie string that deletes a cookie set with `options` (only the path and domain
+/// matter), or with the default options if `None`.
+pub(crate) fn scoped_delete_raw(name: &str, options: Option<&CookieOptions>) -> String {
+    match options {
+        Some(options) => {
+            let mut options = options.clone();
+            options.expires = Some(Cow::Borrowed("Thu, 01 Jan 1970 00:00:00 GMT"));
+            set_raw(name, "", &options)
+        }
+        None => delete_raw(name),
+    }
+}
+
+/// If it exists, returns the undecoded value of a cookie and the cookie string that deletes it,
+/// without encoding its name. Pass the `options` the cookie was set with to delete a cookie
+/// scoped to a path or a domain.
+pub fn take_raw(
+    cookie_string: &str,
+    name: &str,
+    options: Option<&CookieOptions>,
+) -> Option<(String, String)> {
+    get_raw(cookie_string, name).map(|value| (value, scoped_delete_raw(name, options)))
+}
+
+/// If it exists, returns the URI decoded value of a cookie
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)), or an error if the
+/// value's URI decoding fails, and the cookie string that deletes it, URI encoding its name.
+/// Pass the `options` the cookie was set with to delete a cookie scoped to a path or a domain.
+#[cfg(feature = "encoding")]
+pub fn take(
+    cookie_string: &str,
+    name: &str,
+    options: Option<&CookieOptions>,
+) -> Option<(Result<String, GetDecodeError>, String)> {
+    take_raw(cookie_string, &urlencoding::encode(name), options)
+        .map(|(value, deletion)| (decode_value(name, value, Encoding::Standard), deletion))
+}
+
+/// State of all accessible cookies at a given time, with undecoded names and values,
+/// returned by [snapshot](fn.snapshot.html).
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct CookieSnapshot {
+    cookies: HashMap<String, String>,
+}
+
+impl CookieSnapshot {
+    /// Returns the cookies of the snapshot, with undecoded keys and values.
+    pub fn cookies(&self) -> &HashMap<String, String> {
+        &self.cookies
+    }
+}
+
+/// Captures the state of all cookies.
+pub fn snapshot(cookie_string: &str) -> CookieSnapshot {
+    CookieSnapshot {
+        cookies: collect_raw(cookie_string, Duplicates::LastWins),
+    }
+}
+
+/// Return the cookie strings that bring the cookies from `cookie_string` back to the state of
+/// `snapshot`: cookies that changed or disappeared are set again with `options`, and cookies
+/// that didn't exist are deleted.
+///
+/// The attributes of the cookies aren't accessible from the cookie string, so restoring is best
+/// effort: the same `options` are used for all restored cookies.
+pub fn restore(
+    cookie_string: &str,
+    snapshot: &CookieSnapshot,
+    options: &CookieOptions,
+) -> Vec<String> {
+    let current = collect_raw(cookie_string, Duplicates::LastWins);
+    let mut cookie_strings: Vec<String> = current
+        .keys()
+        .filter(|name| !snapshot.cookies.contains_key(*name))
+        .map(|name| delete_raw(name))
+        .collect();
+
+    cookie_strings.extend(
+        snapshot
+            .cookies
+            .iter()
+            .filter(|(name, value)| current.get(*name) != Some(*value))
+            .map(|(name, value)| set_raw(name, value, options)),
+    );
+
+    cookie_strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pair() {
+        assert_eq!(parse_pair(" name = a=b\t"), Ok(("name", "a=b")));
+        assert_eq!(parse_pair("name="), Ok(("name", "")));
+        assert_eq!(parse_pair("name"), Err(PairError::MissingEquals));
+        assert_eq!(parse_pair(" =value"), Err(PairError::EmptyName));
+        assert_eq!(
+            parse_pair("\u{a0}name=\u{a0}"),
+            Ok(("\u{a0}name", "\u{a0}"))
+        );
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(parse_pair(" name= %20a b%20 "), Ok(("name", "%20a b%20")));
+
+        let cookie_string = "a= 1 2%20 ; b=%20";
+        assert_eq!(
+            all_iter_raw(cookie_string).collect::<Vec<_>>(),
+            [("a", "1 2%20"), ("b", "%20")]
+        );
+        #[cfg(feature = "encoding")]
+        assert_eq!(get(cookie_string, "a").unwrap().unwrap(), "1 2 ");
+    }
+
+    #[test]
+    fn test_all_iter_raw() {
+        let mut cookies = all_iter_raw(" key1=value1;invalid;key2=value2 ; ; key3  = value3");
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies.next(), Some(("key1", "value1")));
+        assert_eq!(cookies.size_hint(), (2, Some(2)));
+        assert_eq!(cookies.next(), Some(("key2", "value2")));
+        assert_eq!(cookies.next(), Some(("key3", "value3")));
+        assert_eq!(cookies.len(), 0);
+        assert_eq!(cookies.next(), None);
+        assert_eq!(all_iter_raw("").len(), 0);
+    }
+
+    #[cfg(not(feature = "small"))]
+    #[test]
+    fn test_all_raw() {
+        let cookies = all_raw(" key1=value1;key2=value2 ; key3  = value3");
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies["key1"], "value1");
         assert_eq!(cookies["key2"], "value2");
         assert_eq!(cookies["key3"], "value3");
 
@@ -313,59 +2197,638 @@ mod tests {
         assert!(cookies.is_empty());
     }
 
+    #[cfg(all(feature = "encoding", not(feature = "small")))]
+    #[test]
+    fn test_all() {
+        let cookies =
+            all("key%20%251=value%20%251  ;key%20%252=value%20%252;key%20%253  = value%20%253")
+                .unwrap();
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies["key %1"], "value %1");
+        assert_eq!(cookies["key %2"], "value %2");
+        assert_eq!(cookies["key %3"], "value %3");
+
+        let cookies = all("").unwrap();
+        assert!(cookies.is_empty());
+
+        let error = all("key1=value1;key2%AA=value2").unwrap_err();
+
+        match error {
+            AllDecodeError::Key(raw_key, _) => assert_eq!(raw_key, "key2%AA"),
+            _ => panic!(),
+        }
+
+        let error = all("key1=value1;key%202=value2%AA").unwrap_err();
+
+        match error {
+            AllDecodeError::Value(key, _) => assert_eq!(key, "key 2"),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_all_into() {
+        // Any hasher can be used, for example FxHash instead of the default SipHash.
+        type FastMap = HashMap<
+            String,
+            String,
+            std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>,
+        >;
+
+        let cookies: FastMap = all_raw_into("a=1; b=2; a=3");
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies["a"], "3");
+
+        let cookies: Vec<(String, String)> = all_raw_into("b=2; a=1");
+        assert_eq!(cookies[0], ("b".to_owned(), "2".to_owned()));
+
+        let cookies: Vec<(Box<str>, std::rc::Rc<str>)> = all_raw_into("b=2; a=1");
+        assert_eq!((&*cookies[1].0, &*cookies[1].1), ("a", "1"));
+
+        #[cfg(feature = "encoding")]
+        {
+            let cookies: BTreeMap<Box<str>, String> = all_into("b%20=%25; a=1").unwrap();
+            assert_eq!(cookies["b "], "%");
+            assert_eq!(cookies["a"], "1");
+            assert!(all_into::<FastMap, _, _>("a=%AA").is_err());
+        }
+    }
+
+    #[cfg(all(feature = "encoding", not(feature = "small")))]
+    #[test]
+    fn test_all_lossy_utf8() {
+        let cookies = all_lossy_utf8("a=1;utma%20=%FFx;%AA=2");
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies["utma "], "\u{FFFD}x");
+        assert_eq!(cookies["\u{FFFD}"], "2");
+    }
+
+    #[cfg(all(feature = "encoding", not(feature = "small")))]
+    #[test]
+    fn test_all_latin1_fallback() {
+        let cookies = all_latin1_fallback("a=%C3%A9;lang=fran%E7ais");
+        assert_eq!(cookies["a"], "é");
+        assert_eq!(cookies["lang"], "français");
+        assert_eq!(
+            get_latin1_fallback("lang=fran%E7ais", "lang"),
+            Some("français".to_owned())
+        );
+    }
+
+    #[cfg(not(feature = "small"))]
+    #[test]
+    fn test_all_raw_with_duplicates() {
+        let cookie_string = "lang=fr; a=1; lang=en";
+        assert_eq!(
+            all_raw_with_duplicates(cookie_string, Duplicates::FirstWins)["lang"],
+            "fr"
+        );
+        assert_eq!(
+            all_raw_with_duplicates(cookie_string, Duplicates::LastWins)["lang"],
+            "en"
+        );
+    }
+
+    #[cfg(all(feature = "encoding", not(feature = "small")))]
+    #[test]
+    fn test_all_with_duplicates() {
+        let cookie_string = "my%20lang=fr; my%20lang=en";
+        assert_eq!(
+            all_with_duplicates(cookie_string, Duplicates::FirstWins).unwrap()["my lang"],
+            "fr"
+        );
+        assert_eq!(
+            all_with_duplicates(cookie_string, Duplicates::LastWins).unwrap()["my lang"],
+            "en"
+        );
+        assert!(all_with_duplicates("a=%AA", Duplicates::FirstWins).is_err());
+    }
+
+    #[test]
+    fn test_all_sorted_raw() {
+        let cookies = all_sorted_raw("b=2; c=3; a=1; b=4");
+        assert_eq!(
+            cookies.iter().collect::<Vec<_>>(),
+            [
+                (&"a".to_owned(), &"1".to_owned()),
+                (&"b".to_owned(), &"4".to_owned()),
+                (&"c".to_owned(), &"3".to_owned())
+            ]
+        );
+        assert!(all_sorted_raw("").is_empty());
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_all_sorted() {
+        let cookies = all_sorted("key%202=value%202; key%201=value%201").unwrap();
+        assert_eq!(cookies.keys().collect::<Vec<_>>(), ["key 1", "key 2"]);
+        assert_eq!(cookies["key 2"], "value 2");
+        assert!(all_sorted("key%AA=value").is_err());
+    }
+
+    #[test]
+    fn test_all_ordered_raw() {
+        let cookies = all_ordered_raw("b=2; a=1; b=3");
+        let cookies: Vec<_> = cookies
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(cookies, [("b", "2"), ("a", "1"), ("b", "3")]);
+        assert!(all_ordered_raw("").is_empty());
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_all_ordered() {
+        let cookies = all_ordered("key%202=a; key%201=b; key%202=c").unwrap();
+        assert_eq!(cookies[0], ("key 2".to_owned(), "a".to_owned()));
+        assert_eq!(cookies[2], ("key 2".to_owned(), "c".to_owned()));
+        assert!(all_ordered("key=%AA").is_err());
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let namespaces = namespaces("_ga=GA1.1; mp_1_mixpanel=%7B%7D; _gid=GA1; theme=dark", "_");
+        let summary: Vec<_> = namespaces
+            .iter()
+            .map(|namespace| (namespace.prefix.as_str(), namespace.count, namespace.size))
+            .collect();
+        assert_eq!(summary, [("mp", 1, 19), ("", 2, 15), ("theme", 1, 9)]);
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            all_names_raw("b=2; key%201=%AA; b=3;"),
+            ["b", "key%201", "b"]
+        );
+        assert!(all_names_raw("").is_empty());
+
+        #[cfg(feature = "encoding")]
+        {
+            assert_eq!(names("key%201=%AA; b=2").unwrap(), ["key 1", "b"]);
+            assert!(names("%AA=1").is_err());
+        }
+    }
+
+    #[test]
+    fn test_domain_matches() {
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(domain_matches("WWW.Example.com", ".example.COM"));
+        assert!(domain_matches("a.b.example.com", "example.com"));
+        assert!(!domain_matches("badexample.com", "example.com"));
+        assert!(!domain_matches("example.com", "www.example.com"));
+        assert!(domain_matches("192.168.0.1", "192.168.0.1"));
+        assert!(!domain_matches("10.0.0.1", "0.0.1"));
+        assert!(domain_matches("[::1]", "[::1]"));
+    }
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches("/", "/"));
+        assert!(path_matches("/docs", "/docs"));
+        assert!(path_matches("/docs/web", "/docs"));
+        assert!(path_matches("/docs/web", "/docs/"));
+        assert!(path_matches("/anything", "/"));
+        assert!(!path_matches("/docsets", "/docs"));
+        assert!(!path_matches("/doc", "/docs"));
+        assert!(!path_matches("/Docs", "/docs"));
+    }
+
+    #[test]
+    fn test_split_set_cookie_header() {
+        let headers = [
+            "a=1; Expires=Thu, 01 Jan 2026 00:00:00 GMT; Path=/",
+            "b=x,y; HttpOnly",
+            "c=3",
+        ];
+        let folded = fold_set_cookie_headers(headers.iter().copied());
+        assert_eq!(
+            folded,
+            "a=1; Expires=Thu, 01 Jan 2026 00:00:00 GMT; Path=/, b=x,y; HttpOnly, c=3"
+        );
+        assert_eq!(split_set_cookie_header(&folded), headers);
+        assert_eq!(split_set_cookie_header("a=1,b=2 ,"), ["a=1", "b=2 ,"]);
+        assert!(split_set_cookie_header("").is_empty());
+    }
+
+    #[test]
+    fn test_sort_by_precedence() {
+        let cookie = |name, path: Option<&'static str>| Cookie {
+            name: Cow::Borrowed(name),
+            options: CookieOptions {
+                path: path.map(Cow::Borrowed),
+                ..CookieOptions::default()
+            },
+            ..Cookie::default()
+        };
+
+        let mut cookies = vec![
+            cookie("old_root", Some("/")),
+            cookie("no_path", None),
+            cookie("docs", Some("/docs")),
+            cookie("new_root", Some("/")),
+            cookie("web", Some("/docs/web")),
+        ];
+        sort_by_precedence(&mut cookies);
+
+        let names: Vec<_> = cookies.iter().map(|cookie| cookie.name.as_ref()).collect();
+        assert_eq!(names, ["web", "docs", "old_root", "no_path", "new_root"]);
+    }
+
+    #[test]
+    fn test_default_path() {
+        assert_eq!(default_path("/docs/web/index.html"), "/docs/web");
+        assert_eq!(default_path("/docs/"), "/docs");
+        assert_eq!(default_path("/index.html"), "/");
+        assert_eq!(default_path("/"), "/");
+        assert_eq!(default_path(""), "/");
+        assert_eq!(default_path("docs/web"), "/");
+    }
+
+    #[test]
+    fn test_would_apply() {
+        let cookie =
+            Cookie::parse_set_cookie("id=1; Domain=example.com; Path=/docs; Secure").unwrap();
+        assert_eq!(
+            would_apply(&cookie, "https://www.example.com/docs/a"),
+            Ok(())
+        );
+        assert_eq!(
+            would_apply(&cookie, "https://example.org/docs"),
+            Err(WhyNot::DomainMismatch(
+                "example.org".to_owned(),
+                "example.com".to_owned()
+            ))
+        );
+        assert_eq!(
+            would_apply(&cookie, "https://example.com/docsets"),
+            Err(WhyNot::PathMismatch(
+                "/docsets".to_owned(),
+                "/docs".to_owned()
+            ))
+        );
+        assert_eq!(
+            would_apply(&cookie, "http://example.com/docs"),
+            Err(WhyNot::InsecureScheme)
+        );
+        assert_eq!(would_apply(&cookie, "/docs"), Err(WhyNot::InvalidUrl));
+
+        let cookie =
+            Cookie::parse_set_cookie("id=1; Expires=Tue, 09 Nov 2004 11:33:20 GMT").unwrap();
+        assert_eq!(
+            would_apply(&cookie, "http://example.com/"),
+            Err(WhyNot::Expired)
+        );
+        assert_eq!(
+            would_apply(&Cookie::new("id", "1"), "http://example.com/"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_get_raw() {
+        assert_eq!(
+            get_raw("key1=value1 ; key2= value2;key3=value3", "key2"),
+            Some("value2".to_owned())
+        );
+
+        assert_eq!(
+            get_raw("key1=value1 ; key2= value2;key3=value3", "key4"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_raw_with_duplicates() {
+        let cookie_string = "lang=fr; a=1; lang=en";
+        assert_eq!(
+            get_raw_with_duplicates(cookie_string, "lang", Duplicates::FirstWins),
+            Some("fr".to_owned())
+        );
+        assert_eq!(
+            get_raw_with_duplicates(cookie_string, "lang", Duplicates::LastWins),
+            Some("en".to_owned())
+        );
+        assert_eq!(
+            get_raw_with_duplicates(cookie_string, "b", Duplicates::LastWins),
+            None
+        );
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_get_with_duplicates() {
+        assert_eq!(
+            get_with_duplicates(
+                "my%20lang=fr; my%20lang=en",
+                "my lang",
+                Duplicates::LastWins
+            )
+            .unwrap()
+            .unwrap(),
+            "en"
+        );
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_get() {
+        assert_eq!(
+            get("key1=value1 ; key%202= value%202=;key3=value3", "key 2")
+                .map(|result| result.unwrap()),
+            Some("value 2=".to_owned())
+        );
+
+        assert!(get("key1=value1 ; key2= value2;key3=value3", "key4").is_none());
+
+        let error = get("key1=value1 ; key%202= value2%AA;key3=value3", "key 2")
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.name, "key 2");
+        assert_eq!(error.raw_value, "value2%AA");
+    }
+
+    #[cfg(feature = "form")]
+    #[test]
+    fn test_form() {
+        let value = [("page", "2"), ("sort", "name asc")];
+        let cookie_string = set_form("list state", &value, &CookieOptions::default()).unwrap();
+        assert_eq!(
+            cookie_string,
+            "list%20state=page=2&sort=name+asc;samesite=lax"
+        );
+
+        let form: BTreeMap<String, String> =
+            get_form("a=1; list%20state=page=2&sort=name+asc", "list state")
+                .unwrap()
+                .unwrap();
+        assert_eq!(form["sort"], "name asc");
+        assert!(get_form::<BTreeMap<String, String>>("a=1", "list state").is_none());
+        assert!(get_form::<BTreeMap<String, u32>>("a=b=c", "a")
+            .unwrap()
+            .is_err());
+    }
+
+    #[cfg(all(feature = "encoding", not(feature = "small")))]
+    #[test]
+    fn test_all_with_errors() {
+        let (cookies, errors) = all_with_errors("a=1;%AA=2;b=%AA;c%203=3");
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies["a"], "1");
+        assert_eq!(cookies["c 3"], "3");
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(&errors[0], AllDecodeError::Key(key, _) if key == "%AA"));
+        assert!(matches!(&errors[1], AllDecodeError::Value(key, _) if key == "b"));
+    }
+
+    #[test]
+    fn test_replace_raw() {
+        assert_eq!(
+            replace_raw("a=1", "a", "2", &CookieOptions::default()),
+            (Some("1".to_owned()), "a=2;samesite=lax".to_owned())
+        );
+        assert_eq!(
+            replace_raw("a=1", "b", "2", &CookieOptions::default()),
+            (None, "b=2;samesite=lax".to_owned())
+        );
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_replace() {
+        assert_eq!(
+            replace("a%20b=1%202", "a b", "3 4", &CookieOptions::default()),
+            (
+                Some("1 2".to_owned()),
+                "a%20b=3%204;samesite=lax".to_owned()
+            )
+        );
+        assert_eq!(
+            replace("a=%AA", "a", "1", &CookieOptions::default()).0,
+            None
+        );
+    }
+
+    #[test]
+    fn test_increment_raw() {
+        let options = CookieOptions::default();
+        assert_eq!(
+            increment_raw("visits=41", "visits", 1, &options),
+            (42, "visits=42;samesite=lax".to_owned())
+        );
+        assert_eq!(increment_raw("", "visits", 2, &options).0, 2);
+        assert_eq!(increment_raw("visits=abc", "visits", 1, &options).0, 1);
+        assert_eq!(
+            increment_raw("visits=9223372036854775807", "visits", 1, &options).0,
+            i64::MAX
+        );
+        assert_eq!(decrement_raw("retries=3", "retries", 1, &options).0, 2);
+        assert_eq!(
+            decrement_raw("retries=-1", "retries", i64::MIN, &options).0,
+            i64::MAX - 1
+        );
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_increment() {
+        let options = CookieOptions::default();
+        assert_eq!(
+            increment("page%20views=1", "page views", 1, &options),
+            (2, "page%20views=2;samesite=lax".to_owned())
+        );
+        assert_eq!(decrement("", "page views", 1, &options).0, -1);
+    }
+
+    #[test]
+    fn test_take_raw() {
+        assert_eq!(
+            take_raw("a=1; b=2", "b", None),
+            Some((
+                "2".to_owned(),
+                "b=;expires=Thu, 01 Jan 1970 00:00:00 GMT".to_owned()
+            ))
+        );
+        assert_eq!(
+            take_raw("a=1", "a", Some(&CookieOptions::ROOT)),
+            Some((
+                "1".to_owned(),
+                "a=;path=/;expires=Thu, 01 Jan 1970 00:00:00 GMT;samesite=lax".to_owned()
+            ))
+        );
+        assert_eq!(take_raw("a=1", "b", None), None);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_take() {
+        let (value, deletion) = take("a%20b=1%202", "a b", None).unwrap();
+        assert_eq!(value.unwrap(), "1 2");
+        assert_eq!(deletion, "a%20b=;expires=Thu, 01 Jan 1970 00:00:00 GMT");
+        assert!(take("a=%AA", "a", None).unwrap().0.is_err());
+    }
+
+    #[test]
+    fn test_delete_existing_raw() {
+        assert_eq!(
+            delete_existing_raw("a=1; b=2", "b"),
+            Some("b=;expires=Thu, 01 Jan 1970 00:00:00 GMT".to_owned())
+        );
+        assert_eq!(delete_existing_raw("a=1; b=2", "c"), None);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_delete_existing() {
+        assert_eq!(
+            delete_existing("a%20b=1", "a b"),
+            Some("a%20b=;expires=Thu, 01 Jan 1970 00:00:00 GMT".to_owned())
+        );
+        assert_eq!(delete_existing("a=1", "a b"), None);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_with_uri_component() {
+        let encoding = Encoding::UriComponent;
+        assert_eq!(
+            set_with("it's", "(1)!", &CookieOptions::default(), encoding),
+            "it's=(1)!;samesite=lax"
+        );
+        assert_eq!(
+            get_with("it's=(1)!", "it's", encoding).unwrap().unwrap(),
+            "(1)!"
+        );
+        assert!(get_with("it%27s=(1)!", "it's", encoding).is_none());
+        assert_eq!(
+            delete_with("it's", encoding),
+            "it's=;expires=Thu, 01 Jan 1970 00:00:00 GMT"
+        );
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_get_lossy() {
+        assert_eq!(
+            get_lossy("a=1; b%20c=x%AAy", "b c"),
+            Some("x\u{FFFD}y".to_owned())
+        );
+        assert_eq!(get_lossy("a=1", "b"), None);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_get2() {
+        assert_eq!(
+            get2("key1=value1;key%202=value%202", "key 2").unwrap(),
+            Some("value 2".to_owned())
+        );
+        assert_eq!(get2("key1=value1", "key2").unwrap(), None);
+        assert!(get2("key1=value1%AA", "key1").is_err());
+    }
+
     #[test]
-    fn test_all() {
-        let cookies =
-            all("key%20%251=value%20%251  ;key%20%252=value%20%252;key%20%253  = value%20%253")
-                .unwrap();
-        assert_eq!(cookies.len(), 3);
-        assert_eq!(cookies["key %1"], "value %1");
-        assert_eq!(cookies["key %2"], "value %2");
-        assert_eq!(cookies["key %3"], "value %3");
-
-        let cookies = all("").unwrap();
-        assert!(cookies.is_empty());
-
-        let error = all("key1=value1;key2%AA=value2").unwrap_err();
+    fn test_set_raw_capacity() {
+        let options = CookieOptions::default()
+            .with_path("/")
+            .with_domain("example.com")
+            .expires_at_timestamp(0)
+            .secure()
+            .with_same_site(SameSite::Strict);
 
-        match error {
-            AllDecodeError::Key(raw_key, _) => assert_eq!(raw_key, "key2%AA"),
-            _ => panic!(),
+        for options in [CookieOptions::default(), options] {
+            let cookie_string = set_raw("session", "abc", &options);
+            assert_eq!(cookie_string.len(), cookie_string.capacity());
+            assert_eq!(
+                options.to_attribute_string().len(),
+                options.attributes_len(AttributeFormat::Compact) - 1
+            );
         }
+    }
 
-        let error = all("key1=value1;key%202=value2%AA").unwrap_err();
+    #[test]
+    fn test_cookie_writer() {
+        let mut buffer = "header: ".to_owned();
+        write_set_cookie(&mut buffer, "a", "1", &CookieOptions::ROOT);
+        assert_eq!(buffer, "header: a=1;path=/;samesite=lax");
 
-        match error {
-            AllDecodeError::Value(key, _) => assert_eq!(key, "key 2"),
-            _ => panic!(),
-        }
+        let mut writer = CookieWriter::with_capacity(64);
+        let capacity = writer.capacity();
+        assert_eq!(
+            writer.write_raw("long_name", "long_value", &CookieOptions::ROOT),
+            "long_name=long_value;path=/;samesite=lax"
+        );
+        assert_eq!(
+            writer.write_raw("b", "2", &CookieOptions::ROOT),
+            "b=2;path=/;samesite=lax"
+        );
+        assert_eq!(writer.capacity(), capacity);
     }
 
     #[test]
-    fn test_get_raw() {
+    fn test_attribute_format() {
+        let options = CookieOptions::default()
+            .with_path("/")
+            .with_domain("example.com")
+            .expires_at_timestamp(0)
+            .secure()
+            .with_same_site(SameSite::Strict);
+
         assert_eq!(
-            get_raw("key1=value1 ; key2= value2;key3=value3", "key2"),
-            Some("value2".to_owned())
+            set_raw_with_format("a", "1", &options, AttributeFormat::Canonical),
+            "a=1; Path=/; Domain=example.com; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Secure; \
+             SameSite=Strict"
         );
-
         assert_eq!(
-            get_raw("key1=value1 ; key2= value2;key3=value3", "key4"),
-            None
+            options.to_attribute_string_with_format(AttributeFormat::Canonical),
+            "Path=/; Domain=example.com; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Secure; \
+             SameSite=Strict"
         );
+        let canonical =
+            CookieOptions::ROOT.to_attribute_string_with_format(AttributeFormat::Canonical);
+        assert_eq!(
+            CookieOptions::parse_attributes(&canonical).to_attribute_string(),
+            CookieOptions::ROOT.to_attribute_string()
+        );
+
+        let mut writer = CookieWriter::new().with_format(AttributeFormat::Canonical);
+        let cookie_string = writer.write_raw("b", "2", &options).to_owned();
+        assert_eq!(cookie_string.len(), writer.capacity());
     }
 
     #[test]
-    fn test_get() {
+    fn test_injection() {
+        let options = CookieOptions::default();
         assert_eq!(
-            get("key1=value1 ; key%202= value%202=;key3=value3", "key 2")
-                .map(|result| result.unwrap()),
-            Some("value 2=".to_owned())
+            set_raw("a", "x;domain=evil.com", &options),
+            "a=x%3Bdomain=evil.com;samesite=lax"
+        );
+        assert_eq!(
+            set_raw("a\r\nb", "1,2", &options),
+            "a%0D%0Ab=1,2;samesite=lax"
+        );
+        assert_eq!(
+            set_raw_with_injection("a", "1,2;", &options, InjectionPolicy::EscapeCommas).unwrap(),
+            "a=1%2C2%3B;samesite=lax"
+        );
+        assert_eq!(
+            set_raw_with_injection("a", "x;secure", &options, InjectionPolicy::Reject),
+            Err(RejectionReason::InvalidValue(';'))
+        );
+        assert_eq!(
+            set_raw_with_injection("a,b", "1", &options, InjectionPolicy::Reject),
+            Err(RejectionReason::InvalidName(','))
+        );
+        assert_eq!(
+            set_raw_with_injection("a", "1;2", &options, InjectionPolicy::Allow).unwrap(),
+            "a=1;2;samesite=lax"
+        );
+        assert_eq!(
+            set_raw_with_injection("a", "1", &options, InjectionPolicy::Reject).unwrap(),
+            "a=1;samesite=lax"
         );
-
-        assert!(get("key1=value1 ; key2= value2;key3=value3", "key4").is_none());
-        assert!(get("key1=value1 ; key2= value2%AA;key3=value3", "key2")
-            .unwrap()
-            .is_err());
     }
 
     #[test]
@@ -412,4 +2875,449 @@ mod tests {
             "key=value;expires=Tue, 09 Nov 2004 11:33:20 GMT;samesite=lax",
         );
     }
+
+    #[test]
+    fn test_owned_options() {
+        fn owned_options(path: &str) -> CookieOptions<'static> {
+            CookieOptions::default()
+                .with_path(format!("/{}", path))
+                .with_domain(String::from("example.com"))
+        }
+
+        assert_eq!(
+            set_raw("key", "value", &owned_options("path")),
+            "key=value;path=/path;domain=example.com;samesite=lax"
+        );
+
+        let path = String::from("/path");
+        let options = CookieOptions::default()
+            .with_path(path.as_str())
+            .into_owned();
+        drop(path);
+
+        assert_eq!(
+            set_raw("key", "value", &options),
+            "key=value;path=/path;samesite=lax"
+        );
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let snapshot = snapshot("key1=value1; key2=value2");
+        assert_eq!(snapshot.cookies().len(), 2);
+
+        assert!(restore(
+            "key1=value1; key2=value2",
+            &snapshot,
+            &CookieOptions::default()
+        )
+        .is_empty());
+
+        let mut cookie_strings = restore(
+            "key1=value1; key2=changed; key3=value3",
+            &snapshot,
+            &CookieOptions::default().with_path("/"),
+        );
+        cookie_strings.sort();
+
+        assert_eq!(
+            cookie_strings,
+            [
+                "key2=value2;path=/;samesite=lax".to_owned(),
+                delete_raw("key3")
+            ]
+        );
+
+        assert_eq!(restore("", &snapshot, &CookieOptions::default()).len(), 2);
+    }
+
+    #[test]
+    fn test_parse_set_cookie() {
+        let cookie =
+            Cookie::parse_set_cookie("key=value; Path=/; Secure; HttpOnly; SameSite=Strict")
+                .unwrap();
+        assert_eq!(cookie.name, "key");
+        assert_eq!(cookie.value, "value");
+        assert!(cookie.http_only);
+        assert_eq!(
+            cookie.options.to_attribute_string(),
+            "path=/;secure;samesite=strict"
+        );
+
+        let cookie = Cookie::parse_set_cookie(" key = value=1 ").unwrap();
+        assert_eq!(cookie.name, "key");
+        assert_eq!(cookie.value, "value=1");
+        assert!(!cookie.http_only);
+
+        assert!(Cookie::parse_set_cookie("key; Path=/").is_none());
+    }
+
+    #[test]
+    fn test_const_options() {
+        static OPTIONS: CookieOptions = CookieOptions::new()
+            .with_static_path("/path")
+            .with_static_domain("example.com");
+
+        assert_eq!(
+            set_raw("key", "value", &OPTIONS),
+            "key=value;path=/path;domain=example.com;samesite=lax"
+        );
+
+        const STRICT: CookieOptions = CookieOptions::new().with_static_same_site(SameSite::Strict);
+        assert_eq!(
+            set_raw("key", "value", &STRICT),
+            "key=value;samesite=strict"
+        );
+
+        let replaced = std::panic::catch_unwind(|| {
+            CookieOptions::new()
+                .with_path("/owned".to_owned())
+                .with_static_path("/path")
+        });
+        assert!(replaced.is_err());
+
+        assert_eq!(
+            set_raw("key", "value", &CookieOptions::new()),
+            set_raw("key", "value", &CookieOptions::default())
+        );
+
+        assert_eq!(
+            set_raw("key", "value", &CookieOptions::ROOT),
+            "key=value;path=/;samesite=lax"
+        );
+
+        assert_eq!(
+            set_raw("key", "value", &CookieOptions::SECURE_ROOT),
+            "key=value;path=/;secure;samesite=lax"
+        );
+
+        assert_eq!(
+            set_raw("key", "value", &CookieOptions::STRICT_SECURE_ROOT),
+            "key=value;path=/;secure;samesite=strict"
+        );
+    }
+
+    #[test]
+    fn test_attribute_string() {
+        assert_eq!(
+            CookieOptions::default().to_attribute_string(),
+            "samesite=lax"
+        );
+
+        let options = CookieOptions::default()
+            .with_path("/path")
+            .with_domain("example.com")
+            .expires_at_timestamp(1100000000000)
+            .secure()
+            .with_same_site(SameSite::Strict);
+
+        assert_eq!(
+            options.to_attribute_string(),
+            "path=/path;domain=example.com;expires=Tue, 09 Nov 2004 11:33:20 GMT;secure;samesite=strict"
+        );
+
+        let attribute_string = options.to_attribute_string();
+        let parsed = CookieOptions::parse_attributes(&attribute_string);
+        assert_eq!(parsed.to_attribute_string(), attribute_string);
+
+        let parsed = CookieOptions::parse_attributes(
+            " Path=/path; Domain = example.com; HttpOnly; Max-Age=60; SameSite=None; Secure",
+        );
+        assert_eq!(parsed.path.as_deref(), Some("/path"));
+        assert_eq!(parsed.domain.as_deref(), Some("example.com"));
+        assert!(parsed.secure);
+        assert_eq!(
+            parsed.to_attribute_string(),
+            "path=/path;domain=example.com;secure;samesite=none"
+        );
+
+        assert_eq!(
+            CookieOptions::parse_attributes("samesite=Future").same_site,
+            SameSite::Other("Future".to_owned())
+        );
+
+        assert_eq!(
+            CookieOptions::default()
+                .with_same_site(SameSite::Other("Future".to_owned()))
+                .to_attribute_string(),
+            "samesite=Future"
+        );
+
+        let injected = CookieOptions::default()
+            .with_same_site(SameSite::Other("x;domain=evil.com".to_owned()));
+        assert_eq!(injected.to_attribute_string(), "samesite=lax");
+        assert_eq!(injected.validate(), [CookieOptionsWarning::InvalidSameSite]);
+        assert_eq!(
+            CookieOptions::parse_attributes("samesite=a b").same_site,
+            SameSite::Lax
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let defaults = CookieOptions::default()
+            .with_path("/")
+            .with_domain("example.com")
+            .with_same_site(SameSite::Strict)
+            .secure();
+
+        assert_eq!(
+            defaults
+                .merge(&CookieOverrides::new())
+                .to_attribute_string(),
+            "path=/;domain=example.com;secure;samesite=strict"
+        );
+
+        assert_eq!(
+            defaults
+                .merge(
+                    &CookieOverrides::new()
+                        .with_path("/path")
+                        .expires_at_date("Tue, 09 Nov 2004 11:33:20 GMT")
+                        .with_same_site(SameSite::None)
+                )
+                .to_attribute_string(),
+            "path=/path;domain=example.com;expires=Tue, 09 Nov 2004 11:33:20 GMT;secure;samesite=none"
+        );
+
+        assert_eq!(
+            defaults
+                .merge(&CookieOverrides::new().with_same_site(SameSite::Lax))
+                .to_attribute_string(),
+            "path=/;domain=example.com;secure;samesite=lax"
+        );
+
+        assert_eq!(
+            defaults
+                .merge(&CookieOverrides::new().with_secure(false))
+                .to_attribute_string(),
+            "path=/;domain=example.com;samesite=strict"
+        );
+
+        let overrides = CookieOverrides::new()
+            .with_path("/path")
+            .or_defaults(&CookieOverrides::new().with_path("/").with_secure(false));
+        assert_eq!(
+            defaults.merge(&overrides).to_attribute_string(),
+            "path=/path;domain=example.com;samesite=strict"
+        );
+    }
+
+    #[test]
+    fn test_expires_in() {
+        let expires = |options: CookieOptions| parse_date(options.expires.as_deref().unwrap());
+        let days = expires(CookieOptions::default().expires_in_days(30)).unwrap();
+        assert!((days - now_timestamp() - 30 * 24 * 60 * 60 * 1000).abs() < 60_000);
+
+        let weeks = expires(CookieOptions::default().expires_in_weeks(2)).unwrap();
+        assert!((weeks - days + 16 * 24 * 60 * 60 * 1000).abs() < 60_000);
+
+        let hours = expires(CookieOptions::default().expires_in_hours(48)).unwrap();
+        assert!((hours - days + 28 * 24 * 60 * 60 * 1000).abs() < 60_000);
+    }
+
+    #[test]
+    fn test_expires_out_of_range() {
+        let options = CookieOptions::default().expires_after(Duration::MAX);
+        assert_eq!(
+            options.expires.as_deref(),
+            Some("Fri, 31 Dec 9999 23:59:59 GMT")
+        );
+
+        let options = CookieOptions::default().expires_in_weeks(u64::MAX);
+        assert_eq!(
+            options.expires.as_deref(),
+            Some("Fri, 31 Dec 9999 23:59:59 GMT")
+        );
+
+        let options = CookieOptions::default().expires_at_timestamp(i64::MIN);
+        assert_eq!(
+            options.expires.as_deref(),
+            Some("Thu, 01 Jan 1970 00:00:00 GMT")
+        );
+    }
+
+    #[test]
+    fn test_clamp_expiry() {
+        let clamped = CookieOptions::default()
+            .expires_after(Duration::from_secs(10 * 365 * 24 * 60 * 60))
+            .clamp_expiry(MAX_EXPIRY);
+        let max_timestamp = now_timestamp() + MAX_EXPIRY.as_millis() as i64;
+        let timestamp = parse_date(clamped.expires.as_deref().unwrap()).unwrap();
+        assert!((timestamp - max_timestamp).abs() < 60_000);
+
+        let options = CookieOptions::default().expires_at_date("Tue, 09 Nov 2004 11:33:20 GMT");
+        assert_eq!(
+            options
+                .clone()
+                .clamp_expiry(SAFARI_SCRIPT_MAX_EXPIRY)
+                .expires,
+            options.expires
+        );
+        assert_eq!(
+            CookieOptions::default()
+                .clamp_expiry(SAFARI_TRACKER_LINK_MAX_EXPIRY)
+                .expires,
+            None
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let options = CookieOptions::strict_security();
+        assert_eq!(
+            options.to_attribute_string(),
+            "path=/;secure;samesite=strict"
+        );
+        assert!(options.validate().is_empty());
+        assert_eq!(
+            validate_set_raw_in("__Host-key", "value", &options, "example.com", true),
+            Ok(())
+        );
+        assert_eq!(
+            validate_set_raw_in("__Secure-key", "value", &options, "example.com", true),
+            Ok(())
+        );
+        assert_eq!(
+            validate_set_raw_in("key", "value", &options, "example.com", false),
+            Err(vec![RejectionReason::InsecureContext])
+        );
+
+        let options = CookieOptions::dev();
+        assert_eq!(options.to_attribute_string(), "path=/;samesite=lax");
+        assert!(options.validate().is_empty());
+        assert_eq!(
+            validate_set_raw_in("key", "value", &options, "localhost", false),
+            Ok(())
+        );
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_validate_set() {
+        assert_eq!(
+            validate_set_raw("key", "value", &CookieOptions::default()),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_set_raw("key 1", "value;", &CookieOptions::default()),
+            Err(vec![
+                RejectionReason::InvalidName(' '),
+                RejectionReason::InvalidValue(';')
+            ])
+        );
+
+        assert_eq!(
+            validate_set("key 1", "value;", &CookieOptions::default()),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_set_raw("key", &"a".repeat(4094), &CookieOptions::default()),
+            Err(vec![RejectionReason::TooLarge(4097)])
+        );
+
+        assert_eq!(
+            validate_set_raw("__Secure-key", "value", &CookieOptions::default()),
+            Err(vec![RejectionReason::PrefixRequiresSecure])
+        );
+
+        assert_eq!(
+            validate_set_raw("__Secure-key", "value", &CookieOptions::default().secure()),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_set_raw(
+                "__Host-key",
+                "value",
+                &CookieOptions::default().with_domain("example.com")
+            ),
+            Err(vec![
+                RejectionReason::PrefixRequiresSecure,
+                RejectionReason::HostPrefixRequiresRootPath,
+                RejectionReason::HostPrefixForbidsDomain
+            ])
+        );
+
+        assert_eq!(
+            validate_set_raw("__Host-key", "value", &CookieOptions::SECURE_ROOT),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_set_raw(
+                "key",
+                "value",
+                &CookieOptions::default().with_same_site(SameSite::None)
+            ),
+            Err(vec![RejectionReason::SameSiteNoneWithoutSecure])
+        );
+
+        let options = CookieOptions::SECURE_ROOT.with_domain("example.com");
+
+        assert_eq!(
+            validate_set_raw_in("key", "value", &options, "www.example.com", true),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_set_raw_in("key", "value", &options, "example.com", false),
+            Err(vec![RejectionReason::InsecureContext])
+        );
+
+        assert_eq!(
+            validate_set_raw_in("key", "value", &options, "badexample.com", true),
+            Err(vec![RejectionReason::DomainMismatch])
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(CookieOptions::default().validate().is_empty());
+        assert!(CookieOptions::STRICT_SECURE_ROOT
+            .expires_after(Duration::from_secs(60))
+            .validate()
+            .is_empty());
+
+        assert_eq!(
+            CookieOptions::default().with_path("path").validate(),
+            [CookieOptionsWarning::RelativePath]
+        );
+
+        assert_eq!(
+            CookieOptions::default()
+                .with_domain(".example.com")
+                .validate(),
+            [CookieOptionsWarning::DomainLeadingDot]
+        );
+
+        assert_eq!(
+            CookieOptions::default()
+                .expires_at_timestamp(1100000000000)
+                .validate(),
+            [CookieOptionsWarning::ExpiresInPast]
+        );
+
+        assert_eq!(
+            CookieOptions::default()
+                .expires_at_date("tomorrow")
+                .validate(),
+            [CookieOptionsWarning::InvalidExpires]
+        );
+
+        assert_eq!(
+            CookieOptions::default()
+                .with_same_site(SameSite::None)
+                .validate(),
+            [CookieOptionsWarning::SameSiteNoneWithoutSecure]
+        );
+
+        assert!(CookieOptions::default()
+            .with_same_site(SameSite::None)
+            .secure()
+            .validate()
+            .is_empty());
+    }
 }
@@ -0,0 +1,120 @@
+//! Deferred writes for isomorphic (server-side rendered) applications, such as Leptos or Yew
+//! apps, whose code runs before the app is hydrated in a real browser document.
+//!
+//! Once [browser::defer_writes](browser/fn.defer_writes.html) is called, the cookie strings
+//! written by the root functions (and by the browser functions of the other modules) are queued
+//! instead of being written. [browser::hydrated](browser/fn.hydrated.html) writes them in order
+//! once the app is mounted, and returns the ones that couldn't be written because there is no
+//! document (when the code runs on the server), so they can be logged.
+//!
+//! The queued writes aren't visible to the reads until they are flushed. So the root functions
+//! that read a cookie before writing it don't act on outdated values while the writes are
+//! deferred: `transaction` returns `TransactionError::Deferred` without writing, `take` and
+//! `take_raw` return `None` and `delete_existing` and `delete_existing_raw` return false without
+//! deleting, and `replace` and `replace_raw` queue the write but return `None`, since the previous
+//! value isn't known.
+
+/// Cookie strings waiting to be written, in the order of the writes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteQueue {
+    cookie_strings: Vec<String>,
+}
+
+impl WriteQueue {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the cookie string `cookie_string`.
+    pub fn push(&mut self, cookie_string: impl Into<String>) {
+        self.cookie_strings.push(cookie_string.into());
+    }
+
+    /// Returns the number of queued cookie strings.
+    pub fn len(&self) -> usize {
+        self.cookie_strings.len()
+    }
+
+    /// Returns true if no cookie string is queued.
+    pub fn is_empty(&self) -> bool {
+        self.cookie_strings.is_empty()
+    }
+
+    /// Removes and returns the queued cookie strings, in the order of the writes.
+    pub fn take(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.cookie_strings)
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::WriteQueue;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static QUEUE: RefCell<Option<WriteQueue>> = const { RefCell::new(None) };
+    }
+
+    /// Queues the writes until [hydrated](fn.hydrated.html) is called.
+    pub fn defer_writes() {
+        QUEUE.with(|queue| {
+            queue.borrow_mut().get_or_insert_with(WriteQueue::new);
+        });
+    }
+
+    /// Returns true if the writes are queued.
+    pub fn is_deferring() -> bool {
+        QUEUE.with(|queue| queue.borrow().is_some())
+    }
+
+    /// Returns the number of queued writes.
+    pub fn pending() -> usize {
+        QUEUE.with(|queue| queue.borrow().as_ref().map_or(0, WriteQueue::len))
+    }
+
+    /// Queues `cookie_string` if the writes are deferred, and returns true if it was queued.
+    pub(crate) fn defer(cookie_string: &str) -> bool {
+        QUEUE.with(|queue| match queue.borrow_mut().as_mut() {
+            Some(queue) => {
+                queue.push(cookie_string);
+                true
+            }
+
+            None => false,
+        })
+    }
+
+    /// Stops deferring the writes, and writes the queued ones in order. Returns the cookie
+    /// strings that couldn't be written, for example because there is no document on the
+    /// server, which are dropped.
+    pub fn hydrated() -> Vec<String> {
+        let cookie_strings =
+            QUEUE.with(|queue| queue.borrow_mut().take().unwrap_or_default().take());
+
+        cookie_strings
+            .into_iter()
+            .filter(|cookie_string| crate::try_set_cookie_string(cookie_string).is_err())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        let mut queue = WriteQueue::new();
+        assert!(queue.is_empty());
+
+        queue.push("a=1");
+        queue.push("b=2".to_owned());
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.take(), ["a=1", "b=2"]);
+        assert!(queue.is_empty());
+    }
+}
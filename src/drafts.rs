@@ -0,0 +1,299 @@
+//! Persistence of form drafts, so half-typed forms survive a page refresh.
+//!
+//! A draft is a set of named fields, stored URI encoded as `field=value&field=value` in one or
+//! more cookies named `<name>_0`, `<name>_1`, ... so drafts larger than a cookie can hold are
+//! split into [CHUNK_SIZE](constant.CHUNK_SIZE.html) chunks.
+//!
+//! In a browser, a [DraftSaver](browser/struct.DraftSaver.html) saves the draft after the user
+//! stops typing for a given delay, instead of on every keystroke.
+
+use crate::cookies::{self, CookieOptions};
+use std::collections::BTreeMap;
+
+/// Maximum size of the value of each chunk cookie, leaving room for its name and attributes.
+pub const CHUNK_SIZE: usize = 3800;
+
+/// The fields of a draft, by name.
+pub type DraftFields = BTreeMap<String, String>;
+
+pub(crate) fn chunk_name(name: &str, index: usize) -> String {
+    format!("{}_{}", name, index)
+}
+
+pub(crate) fn chunk_count(name: &str, cookie_string: &str) -> usize {
+    (0..)
+        .take_while(|index| cookies::get_raw(cookie_string, &chunk_name(name, *index)).is_some())
+        .count()
+}
+
+pub(crate) fn delete_chunks(
+    name: &str,
+    range: std::ops::Range<usize>,
+    options: &CookieOptions,
+) -> impl Iterator<Item = String> {
+    let options = options
+        .clone()
+        .expires_at_date("Thu, 01 Jan 1970 00:00:00 GMT")
+        .into_owned();
+    let name = name.to_owned();
+
+    range.map(move |index| cookies::set_raw(&chunk_name(&name, index), "", &options))
+}
+
+/// Returns the encoded value of a draft.
+pub fn encode(fields: &DraftFields) -> String {
+    fields
+        .iter()
+        .map(|(field, value)| {
+            format!(
+                "{}={}",
+                urlencoding::encode(field),
+                urlencoding::encode(value)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Decodes the encoded value of a draft. Returns `None` if it is malformed.
+pub fn decode(value: &str) -> Option<DraftFields> {
+    value
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (field, value) = pair.split_once('=')?;
+            Some((
+                urlencoding::decode(field).ok()?,
+                urlencoding::decode(value).ok()?,
+            ))
+        })
+        .collect()
+}
+
+/// Returns the cookie strings that save a draft named `name`, including the ones that delete the
+/// chunks of a larger previous draft found in `cookie_string`.
+pub fn save(
+    cookie_string: &str,
+    name: &str,
+    fields: &DraftFields,
+    options: &CookieOptions,
+) -> Vec<String> {
+    let value = encode(fields);
+
+    // The value is URI encoded, so it is ASCII and can be split at any byte.
+    let mut cookie_strings: Vec<String> = value
+        .as_bytes()
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .chain(if value.is_empty() { Some("") } else { None })
+        .enumerate()
+        .map(|(index, chunk)| cookies::set_raw(&chunk_name(name, index), chunk, options))
+        .collect();
+
+    let written = cookie_strings.len();
+    cookie_strings.extend(delete_chunks(
+        name,
+        written..chunk_count(name, cookie_string).max(written),
+        options,
+    ));
+    cookie_strings
+}
+
+/// Returns the draft named `name`, if it exists and is well-formed.
+pub fn restore(cookie_string: &str, name: &str) -> Option<DraftFields> {
+    let count = chunk_count(name, cookie_string);
+
+    if count == 0 {
+        return None;
+    }
+
+    let value: String = (0..count)
+        .filter_map(|index| cookies::get_raw(cookie_string, &chunk_name(name, index)))
+        .collect();
+
+    decode(&value)
+}
+
+/// Returns the cookie strings that delete the draft named `name`.
+pub fn discard(cookie_string: &str, name: &str, options: &CookieOptions) -> Vec<String> {
+    delete_chunks(name, 0..chunk_count(name, cookie_string), options).collect()
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::DraftFields;
+    use crate::cookies::CookieOptions;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    // Kept after the timeout fires, since a closure can't drop itself while running: clearing
+    // an elapsed timeout is a no-op.
+    struct Pending {
+        timeout_id: i32,
+        _on_timeout: Closure<dyn FnMut()>,
+    }
+
+    /// Saves a draft in the document's cookies once it hasn't changed for a given delay.
+    /// A pending save is cancelled if the saver is dropped.
+    pub struct DraftSaver {
+        name: String,
+        delay: Duration,
+        options: CookieOptions<'static>,
+        pending: Rc<RefCell<Option<Pending>>>,
+    }
+
+    impl DraftSaver {
+        /// Creates a saver of the draft named `name`, saving `delay` after the last change.
+        pub fn new(
+            name: impl Into<String>,
+            delay: Duration,
+            options: CookieOptions<'static>,
+        ) -> Self {
+            Self {
+                name: name.into(),
+                delay,
+                options,
+                pending: Rc::new(RefCell::new(None)),
+            }
+        }
+
+        /// Returns the saved draft, if it exists and is well-formed.
+        pub fn restore(&self) -> Option<DraftFields> {
+            super::restore(&crate::cookie_string(), &self.name)
+        }
+
+        /// Schedules saving `fields` after the delay, replacing the previously scheduled save.
+        pub fn schedule(&self, fields: DraftFields) {
+            self.cancel();
+
+            let name = self.name.clone();
+            let options = self.options.clone();
+
+            let on_timeout = Closure::wrap(Box::new(move || {
+                save_now(&name, &fields, &options);
+            }) as Box<dyn FnMut()>);
+
+            let timeout_id = web_sys::window()
+                .unwrap()
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    on_timeout.as_ref().unchecked_ref(),
+                    self.delay.as_millis().min(i32::MAX as u128) as i32,
+                )
+                .unwrap();
+
+            *self.pending.borrow_mut() = Some(Pending {
+                timeout_id,
+                _on_timeout: on_timeout,
+            });
+        }
+
+        /// Saves `fields` immediately, cancelling the scheduled save.
+        pub fn save(&self, fields: &DraftFields) {
+            self.cancel();
+            save_now(&self.name, fields, &self.options);
+        }
+
+        /// Deletes the saved draft, cancelling the scheduled save (for example once the form
+        /// has been submitted).
+        pub fn discard(&self) {
+            self.cancel();
+
+            for cookie_string in super::discard(&crate::cookie_string(), &self.name, &self.options)
+            {
+                crate::set_cookie_string(&cookie_string);
+            }
+        }
+
+        fn cancel(&self) {
+            if let Some(pending) = self.pending.borrow_mut().take() {
+                web_sys::window()
+                    .unwrap()
+                    .clear_timeout_with_handle(pending.timeout_id);
+            }
+        }
+    }
+
+    impl Drop for DraftSaver {
+        fn drop(&mut self) {
+            self.cancel();
+        }
+    }
+
+    fn save_now(name: &str, fields: &DraftFields, options: &CookieOptions) {
+        for cookie_string in super::save(&crate::cookie_string(), name, fields, options) {
+            crate::set_cookie_string(&cookie_string);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> DraftFields {
+        pairs
+            .iter()
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn cookie_pairs(cookie_strings: &[String]) -> String {
+        cookie_strings
+            .iter()
+            .map(|cookie_string| cookie_string.split(';').next().unwrap())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    #[test]
+    fn test_encode() {
+        let draft = fields(&[("title", "Hello; world"), ("body", "a=b&c")]);
+        let value = encode(&draft);
+        assert_eq!(value, "body=a%3Db%26c&title=Hello%3B%20world");
+        assert_eq!(decode(&value), Some(draft));
+        assert_eq!(decode(""), Some(DraftFields::new()));
+        assert_eq!(decode("malformed"), None);
+    }
+
+    #[test]
+    fn test_save_restore() {
+        let options = CookieOptions::ROOT;
+        let large = fields(&[("body", &"x".repeat(CHUNK_SIZE * 2))]);
+
+        let cookie_strings = save("", "post", &large, &options);
+        assert_eq!(cookie_strings.len(), 3);
+        assert!(cookie_strings[2].starts_with("post_2=x"));
+
+        let cookie_string = cookie_pairs(&cookie_strings);
+        assert_eq!(restore(&cookie_string, "post"), Some(large));
+        assert_eq!(restore(&cookie_string, "other"), None);
+
+        let small = fields(&[("body", "short")]);
+        let cookie_strings = save(&cookie_string, "post", &small, &options);
+        assert_eq!(cookie_strings.len(), 3);
+        assert!(cookie_strings[0].starts_with("post_0=body=short;"));
+        assert!(cookie_strings[1].starts_with("post_1=;"));
+        assert!(cookie_strings[2].contains("expires=Thu, 01 Jan 1970"));
+        assert_eq!(
+            restore(&cookie_pairs(&cookie_strings[..1]), "post"),
+            Some(small)
+        );
+
+        let empty = save("", "post", &DraftFields::new(), &options);
+        assert_eq!(
+            restore(&cookie_pairs(&empty), "post"),
+            Some(DraftFields::new())
+        );
+
+        let discarded = discard(&cookie_string, "post", &options);
+        assert_eq!(discarded.len(), 3);
+        assert!(discarded
+            .iter()
+            .all(|cookie_string| cookie_string.contains("expires=Thu, 01 Jan 1970")));
+    }
+}
@@ -0,0 +1,174 @@
+//! Persistence backend for [eframe](https://crates.io/crates/eframe) web apps, storing the UI
+//! state in cookies so it is visible to the server (eframe only persists to `localStorage`).
+//!
+//! A [CookieStorage](struct.CookieStorage.html) implements `eframe::Storage`. Its entries are
+//! kept in memory, and written on `flush` as a compressed save (see [saves](../saves/index.html)),
+//! split into as many cookies as needed. Pass it to `App::save`, or read the entries with
+//! `eframe::get_value` at startup:
+//!
+//! ```ignore
+//! use wasm_cookies::eframe::{browser, COOKIE_NAME};
+//!
+//! let storage = browser::load(COOKIE_NAME, options);
+//! let state: Option<State> = eframe::get_value(&storage, eframe::APP_KEY);
+//! ```
+//!
+//! Browsers limit the size of the cookies of a domain, so only small states should be stored.
+//!
+//! Available only with the `eframe` feature. The `eframe::Storage` implementation is only
+//! available on WebAssembly targets (`wasm32` and `wasm64`).
+
+use crate::cookies::CookieOptions;
+use crate::saves::{self, SaveError};
+use std::collections::BTreeMap;
+
+/// Default name of the storage cookies.
+pub const COOKIE_NAME: &str = "eframe";
+
+/// Slot of the save holding the entries.
+const SLOT: &str = "storage";
+
+/// Key-value storage persisted in cookies.
+#[derive(Clone, Debug)]
+pub struct CookieStorage {
+    name: String,
+    options: CookieOptions<'static>,
+    entries: BTreeMap<String, String>,
+    is_dirty: bool,
+}
+
+impl CookieStorage {
+    /// Creates an empty storage, saved in the cookies named `name` with `options`.
+    ///
+    /// The options should have an expiration date, so the state outlives the browser session.
+    pub fn new(name: impl Into<String>, options: CookieOptions<'static>) -> Self {
+        Self {
+            name: name.into(),
+            options,
+            entries: BTreeMap::new(),
+            is_dirty: false,
+        }
+    }
+
+    /// Loads the storage saved in the cookies named `name`, or creates an empty one if there is
+    /// no valid save.
+    pub fn load(
+        cookie_string: &str,
+        name: impl Into<String>,
+        options: CookieOptions<'static>,
+    ) -> Self {
+        let mut storage = Self::new(name, options);
+
+        if let Some(saved) = saves::load(cookie_string, &storage.name, SLOT) {
+            storage.entries = saved.state;
+        }
+
+        storage
+    }
+
+    /// Returns the value of the entry `key`, if it exists.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// Sets the value of the entry `key`.
+    pub fn set(&mut self, key: &str, value: String) {
+        if self.get(key) != Some(value.as_str()) {
+            self.entries.insert(key.to_owned(), value);
+            self.is_dirty = true;
+        }
+    }
+
+    /// Returns true if entries changed since the storage was loaded or saved.
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    /// Returns the cookie strings that save the entries at `now` (timestamp in milliseconds,
+    /// UTC), including the ones that delete the chunks of a larger previous save found in
+    /// `cookie_string`, and marks the storage as saved.
+    pub fn save(&mut self, cookie_string: &str, now: i64) -> Result<Vec<String>, SaveError> {
+        let cookie_strings = saves::save(
+            cookie_string,
+            &self.name,
+            SLOT,
+            &self.entries,
+            now,
+            &self.options,
+        )?;
+
+        self.is_dirty = false;
+        Ok(cookie_strings)
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::CookieStorage;
+    use crate::cookies::{self, CookieOptions};
+
+    /// Loads the storage saved in the cookies named `name`, or creates an empty one if there is
+    /// no valid save.
+    pub fn load(name: &str, options: CookieOptions<'static>) -> CookieStorage {
+        CookieStorage::load(&crate::cookie_string(), name, options)
+    }
+
+    impl ::eframe::Storage for CookieStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.get(key).map(str::to_owned)
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.set(key, value);
+        }
+
+        /// Writes the entries to the cookies, if they changed.
+        fn flush(&mut self) {
+            if !self.is_dirty() {
+                return;
+            }
+
+            // The entries are strings, so they are always serializable.
+            if let Ok(cookie_strings) = self.save(&crate::cookie_string(), cookies::now_timestamp())
+            {
+                for cookie_string in cookie_strings {
+                    crate::set_cookie_string(&cookie_string);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load() {
+        let mut storage = CookieStorage::new(COOKIE_NAME, CookieOptions::ROOT);
+        storage.set("app", "(zoom: 1.5)".to_owned());
+        assert!(storage.is_dirty());
+
+        let cookie_string = storage
+            .save("", 0)
+            .unwrap()
+            .iter()
+            .map(|cookie_string| cookie_string.split(';').next().unwrap().to_owned())
+            .collect::<Vec<_>>()
+            .join("; ");
+        assert!(cookie_string.starts_with("eframe_storage_0="));
+        assert!(!storage.is_dirty());
+
+        let mut loaded = CookieStorage::load(&cookie_string, COOKIE_NAME, CookieOptions::ROOT);
+        assert_eq!(loaded.get("app"), Some("(zoom: 1.5)"));
+        loaded.set("app", "(zoom: 1.5)".to_owned());
+        assert!(!loaded.is_dirty());
+        assert_eq!(
+            CookieStorage::load("", COOKIE_NAME, CookieOptions::ROOT).get("app"),
+            None
+        );
+    }
+}
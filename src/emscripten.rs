@@ -0,0 +1,86 @@
+//! Document access on `wasm32-unknown-emscripten`, where the `web-sys` bindings aren't usable:
+//! the cookie string and the location are read and written by running JavaScript with
+//! Emscripten's `emscripten_run_script_string`.
+
+#[cfg(target_os = "emscripten")]
+use std::ffi::{CStr, CString};
+#[cfg(target_os = "emscripten")]
+use std::os::raw::c_char;
+
+#[cfg(target_os = "emscripten")]
+extern "C" {
+    fn emscripten_run_script(script: *const c_char);
+    fn emscripten_run_script_string(script: *const c_char) -> *const c_char;
+}
+
+/// Returns `value` as a JavaScript string literal. The literal has no NUL character, so it can
+/// be passed to Emscripten as a C string.
+pub(crate) fn string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+
+    for character in value.chars() {
+        match character {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\u{0}'..='\u{1f}' | '\u{2028}' | '\u{2029}' => {
+                literal.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            _ => literal.push(character),
+        }
+    }
+
+    literal.push('"');
+    literal
+}
+
+#[cfg(target_os = "emscripten")]
+fn run_script(script: &str) {
+    let script = CString::new(script).unwrap();
+    unsafe { emscripten_run_script(script.as_ptr()) };
+}
+
+/// Runs `script`, and returns its result converted to a string.
+#[cfg(target_os = "emscripten")]
+fn run_script_string(script: &str) -> String {
+    let script = CString::new(script).unwrap();
+
+    // The result is owned by Emscripten and only valid until the next call, so it is copied.
+    unsafe { CStr::from_ptr(emscripten_run_script_string(script.as_ptr())) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(target_os = "emscripten")]
+pub(crate) fn cookie_string() -> String {
+    run_script_string("document.cookie")
+}
+
+#[cfg(target_os = "emscripten")]
+pub(crate) fn set_cookie_string(value: &str) {
+    run_script(&format!("document.cookie = {};", string_literal(value)));
+}
+
+#[cfg(target_os = "emscripten")]
+pub(crate) fn hostname() -> String {
+    run_script_string("location.hostname")
+}
+
+#[cfg(target_os = "emscripten")]
+pub(crate) fn pathname() -> String {
+    run_script_string("location.pathname")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_literal() {
+        assert_eq!(string_literal("a=1;path=/"), r#""a=1;path=/""#);
+        assert_eq!(
+            string_literal("\"\\\n\u{0}\u{2028}é"),
+            r#""\"\\\u000a\u0000\u2028é""#
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! URI encodings of cookie names and values.
+//!
+//! Available only with the `encoding` feature.
+
+use urlencoding::FromUrlEncodingError;
+
+/// URI encoding used by the encoding functions, such as
+/// [cookies::set_with](../cookies/fn.set_with.html) and
+/// [cookies::get_with](../cookies/fn.get_with.html).
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// Encoding of the [urlencoding crate](https://crates.io/crates/urlencoding): every byte is
+    /// percent-encoded except alphanumerics and `-`, `_`, `.`, `~`. Malformed escape sequences
+    /// are decoded as is.
+    ///
+    /// This is the default value when calling `Encoding::default()`.
+    #[default]
+    Standard,
+
+    /// Same encoding as JavaScript's `encodeURIComponent` and `decodeURIComponent`: `!`, `'`,
+    /// `(`, `)` and `*` are also left unescaped, and decoding fails on malformed escape sequences.
+    ///
+    /// Use it to share cookies with JavaScript code.
+    UriComponent,
+
+    /// Form encoding (`application/x-www-form-urlencoded`), as written by PHP's `urlencode` and
+    /// older backend frameworks: same as [Standard](#variant.Standard), except that spaces are
+    /// encoded as `+`, `~` is also encoded, and `+` is decoded as a space.
+    ///
+    /// Use it to read cookies whose values contain literal plus signs instead of spaces.
+    Form,
+}
+
+fn is_uri_component_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric()
+        || matches!(
+            byte,
+            b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')'
+        )
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+impl Encoding {
+    fn decode_binary(self, string: &str) -> Vec<u8> {
+        match self {
+            Encoding::Form => {
+                urlencoding::decode_binary(string.replace('+', " ").as_bytes()).into_owned()
+            }
+            _ => urlencoding::decode_binary(string.as_bytes()).into_owned(),
+        }
+    }
+
+    /// URI encodes `string`.
+    pub fn encode(self, string: &str) -> String {
+        match self {
+            Encoding::Standard => urlencoding::encode(string),
+            Encoding::UriComponent => {
+                let mut encoded = String::with_capacity(string.len());
+
+                for byte in string.bytes() {
+                    if is_uri_component_unreserved(byte) {
+                        encoded.push(byte as char);
+                    } else {
+                        encoded.push_str(&format!("%{:02X}", byte));
+                    }
+                }
+
+                encoded
+            }
+
+            Encoding::Form => string
+                .split(' ')
+                .map(|part| urlencoding::encode(part).replace('~', "%7E"))
+                .collect::<Vec<_>>()
+                .join("+"),
+        }
+    }
+
+    /// URI decodes `string`, or returns an error if the decoded bytes aren't valid UTF-8 or, with
+    /// [UriComponent](#variant.UriComponent), if an escape sequence is malformed.
+    pub fn decode(self, string: &str) -> Result<String, FromUrlEncodingError> {
+        match self {
+            Encoding::Standard => urlencoding::decode(string),
+            Encoding::UriComponent => {
+                let bytes = string.as_bytes();
+                let mut decoded = Vec::with_capacity(bytes.len());
+                let mut index = 0;
+
+                while index < bytes.len() {
+                    if bytes[index] == b'%' {
+                        let byte = bytes
+                            .get(index + 1..index + 3)
+                            .and_then(|hex| Some(hex_value(hex[0])? << 4 | hex_value(hex[1])?))
+                            .ok_or(FromUrlEncodingError::UriCharacterError {
+                                character: '%',
+                                index,
+                            })?;
+
+                        decoded.push(byte);
+                        index += 3;
+                    } else {
+                        decoded.push(bytes[index]);
+                        index += 1;
+                    }
+                }
+
+                String::from_utf8(decoded)
+                    .map_err(|error| FromUrlEncodingError::Utf8CharacterError { error })
+            }
+
+            Encoding::Form => urlencoding::decode(&string.replace('+', " ")),
+        }
+    }
+
+    /// URI decodes `string`, replacing the bytes that aren't valid UTF-8 with `U+FFFD` (see
+    /// `String::from_utf8_lossy`) instead of failing. Malformed escape sequences are decoded as
+    /// is.
+    pub fn decode_lossy(self, string: &str) -> String {
+        String::from_utf8_lossy(&self.decode_binary(string)).into_owned()
+    }
+
+    /// URI decodes `string`, reinterpreting the decoded bytes as ISO-8859-1 (Latin-1) if they
+    /// aren't valid UTF-8, as written by some legacy backends, instead of failing. Malformed
+    /// escape sequences are decoded as is.
+    pub fn decode_latin1_fallback(self, string: &str) -> String {
+        String::from_utf8(self.decode_binary(string))
+            .unwrap_or_else(|error| error.as_bytes().iter().map(|byte| *byte as char).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri_component() {
+        let encoding = Encoding::UriComponent;
+        assert_eq!(
+            encoding.encode("it's (nearly) 100%! a*b~c;é"),
+            "it's%20(nearly)%20100%25!%20a*b~c%3B%C3%A9"
+        );
+        assert_eq!(
+            encoding
+                .decode("it's%20(nearly)%20100%25!%20a*b~c%3B%C3%A9")
+                .unwrap(),
+            "it's (nearly) 100%! a*b~c;é"
+        );
+        assert!(encoding.decode("100%").is_err());
+        assert!(encoding.decode("%zz").is_err());
+        assert!(encoding.decode("%AA").is_err());
+        assert!(Encoding::Standard.decode("100%").is_ok());
+        assert_eq!(Encoding::Standard.encode("it's!"), "it%27s%21");
+    }
+
+    #[test]
+    fn test_form() {
+        let encoding = Encoding::Form;
+        assert_eq!(encoding.encode("a b+c~d"), "a+b%2Bc%7Ed");
+        assert_eq!(encoding.decode("a+b%2Bc%7Ed").unwrap(), "a b+c~d");
+        assert_eq!(Encoding::Standard.decode("a+b").unwrap(), "a+b");
+    }
+
+    #[test]
+    fn test_decode_lossy() {
+        assert_eq!(Encoding::Standard.decode_lossy("a%AAb%20c"), "a\u{FFFD}b c");
+        assert_eq!(Encoding::UriComponent.decode_lossy("100%"), "100%");
+        assert_eq!(Encoding::Form.decode_lossy("a+%FF"), "a \u{FFFD}");
+    }
+
+    #[test]
+    fn test_decode_latin1_fallback() {
+        let encoding = Encoding::Standard;
+        assert_eq!(encoding.decode_latin1_fallback("caf%C3%A9"), "café");
+        assert_eq!(encoding.decode_latin1_fallback("caf%E9%20%FC"), "café ü");
+        assert_eq!(Encoding::Form.decode_latin1_fallback("a+%E9"), "a é");
+    }
+}
@@ -0,0 +1,102 @@
+//! Environment name isolation, so deployments on sibling domains (such as `app.example.com` and
+//! `staging.example.com`, sharing the cookies of `example.com`) don't overwrite each other's
+//! cookies.
+//!
+//! An environment adds a prefix and a suffix to the cookie names. Once installed with
+//! [set_environment](../fn.set_environment.html), it is applied to the names given to the root
+//! functions (`get`, `set`, `delete`, and their raw versions), after the middleware and the
+//! policy's prefix. The functions returning all the cookies return the actual names.
+
+use std::borrow::Cow;
+
+/// The `__Host-` and `__Secure-` prefixes, which must stay at the start of the names.
+const RESERVED_PREFIXES: [&str; 2] = ["__Host-", "__Secure-"];
+
+/// A prefix and a suffix added to the cookie names.
+///
+/// They should only contain characters allowed in cookie names, since they are added to raw
+/// names too.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Environment {
+    /// Prefix of the names, inserted after the `__Host-` or `__Secure-` prefix if any.
+    pub prefix: String,
+
+    /// Suffix of the names.
+    pub suffix: String,
+}
+
+impl Environment {
+    /// Creates an environment adding `prefix` to the names.
+    pub fn with_prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            suffix: String::new(),
+        }
+    }
+
+    /// Creates an environment adding `suffix` to the names (such as `_stg`).
+    pub fn with_suffix(suffix: impl Into<String>) -> Self {
+        Self {
+            prefix: String::new(),
+            suffix: suffix.into(),
+        }
+    }
+
+    fn split_reserved(name: &str) -> (&str, &str) {
+        RESERVED_PREFIXES
+            .iter()
+            .find(|reserved| name.starts_with(*reserved))
+            .map_or(("", name), |reserved| name.split_at(reserved.len()))
+    }
+
+    /// Returns the name of the cookie in the environment.
+    pub fn name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.prefix.is_empty() && self.suffix.is_empty() {
+            return Cow::Borrowed(name);
+        }
+
+        let (reserved, name) = Self::split_reserved(name);
+        Cow::Owned(format!(
+            "{}{}{}{}",
+            reserved, self.prefix, name, self.suffix
+        ))
+    }
+
+    /// Returns the name of the cookie without the environment's prefix and suffix, or `None` if
+    /// the cookie doesn't belong to the environment.
+    pub fn strip<'a>(&self, name: &'a str) -> Option<Cow<'a, str>> {
+        if self.prefix.is_empty() && self.suffix.is_empty() {
+            return Some(Cow::Borrowed(name));
+        }
+
+        let (reserved, rest) = Self::split_reserved(name);
+        let stripped = rest
+            .strip_prefix(self.prefix.as_str())?
+            .strip_suffix(self.suffix.as_str())?;
+
+        Some(if reserved.is_empty() {
+            Cow::Borrowed(stripped)
+        } else {
+            Cow::Owned(format!("{}{}", reserved, stripped))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name() {
+        let staging = Environment::with_suffix("_stg");
+        assert_eq!(staging.name("session"), "session_stg");
+        assert_eq!(staging.name("__Host-session"), "__Host-session_stg");
+        assert_eq!(staging.strip("session_stg").unwrap(), "session");
+        assert_eq!(staging.strip("session"), None);
+
+        let preview = Environment::with_prefix("pr42.");
+        assert_eq!(preview.name("__Secure-id"), "__Secure-pr42.id");
+        assert_eq!(preview.strip("__Secure-pr42.id").unwrap(), "__Secure-id");
+        assert_eq!(Environment::default().name("id"), "id");
+    }
+}
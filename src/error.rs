@@ -0,0 +1,150 @@
+//! The error type of the fallible functions of the root module, such as `try_get` and
+//! `try_set`, which gathers the errors of the different steps so they can be propagated with
+//! `?`.
+
+#[cfg(feature = "encoding")]
+use crate::cookies::{AllDecodeError, GetDecodeError};
+use crate::cookies::{RejectionReason, MAX_COOKIE_SIZE};
+use crate::policy::PolicyViolation;
+use std::fmt;
+
+/// Error returned by the fallible functions.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WasmCookiesError {
+    /// URI decoding error on the value of a cookie.
+    #[cfg(feature = "encoding")]
+    Decode(GetDecodeError),
+
+    /// URI decoding error on a name or a value, when reading all the cookies.
+    #[cfg(feature = "encoding")]
+    DecodeAll(AllDecodeError),
+
+    /// A browser API call threw a JavaScript exception.
+    ///
+    /// The field is the exception's message.
+    Js(String),
+
+    /// There is no document, for example in a worker.
+    MissingDocument,
+
+    /// There is no document outside of WebAssembly, and no test backend is installed (see
+    /// [set_test_backend](fn.set_test_backend.html)).
+    UnsupportedTarget,
+
+    /// The cookie would be rejected by browsers (see
+    /// [cookies::validate_set_raw](cookies/fn.validate_set_raw.html)).
+    Rejected(Vec<RejectionReason>),
+
+    /// The name and the value together are larger than
+    /// [MAX_COOKIE_SIZE](constant.MAX_COOKIE_SIZE.html), and it is the only reason why the cookie
+    /// would be rejected.
+    ///
+    /// The field is the actual size in bytes.
+    TooLarge(usize),
+
+    /// The installed cookie policy rejected the cookie.
+    Policy(PolicyViolation),
+}
+
+impl fmt::Display for WasmCookiesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "encoding")]
+            WasmCookiesError::Decode(error) => write!(
+                f,
+                "cannot URI decode the value of the cookie `{}`",
+                error.name
+            ),
+            #[cfg(feature = "encoding")]
+            WasmCookiesError::DecodeAll(_) => write!(f, "cannot URI decode the cookies"),
+            WasmCookiesError::Js(message) => write!(f, "JavaScript exception: {}", message),
+            WasmCookiesError::MissingDocument => write!(f, "no document is available"),
+            WasmCookiesError::UnsupportedTarget => write!(
+                f,
+                "cookies aren't available on this target without a test backend"
+            ),
+            WasmCookiesError::Rejected(reasons) => {
+                write!(f, "the cookie would be rejected by browsers: {:?}", reasons)
+            }
+            WasmCookiesError::TooLarge(size) => write!(
+                f,
+                "the cookie is {} bytes, more than the maximum of {} bytes",
+                size, MAX_COOKIE_SIZE
+            ),
+            WasmCookiesError::Policy(violation) => {
+                write!(f, "the cookie policy rejected the cookie: {:?}", violation)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WasmCookiesError {}
+
+#[cfg(feature = "encoding")]
+impl From<GetDecodeError> for WasmCookiesError {
+    fn from(error: GetDecodeError) -> Self {
+        WasmCookiesError::Decode(error)
+    }
+}
+
+#[cfg(feature = "encoding")]
+impl From<AllDecodeError> for WasmCookiesError {
+    fn from(error: AllDecodeError) -> Self {
+        WasmCookiesError::DecodeAll(error)
+    }
+}
+
+impl From<PolicyViolation> for WasmCookiesError {
+    fn from(violation: PolicyViolation) -> Self {
+        WasmCookiesError::Policy(violation)
+    }
+}
+
+impl From<Vec<RejectionReason>> for WasmCookiesError {
+    fn from(reasons: Vec<RejectionReason>) -> Self {
+        match reasons.as_slice() {
+            [RejectionReason::TooLarge(size)] => WasmCookiesError::TooLarge(*size),
+            _ => WasmCookiesError::Rejected(reasons),
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl From<wasm_bindgen::JsValue> for WasmCookiesError {
+    fn from(error: wasm_bindgen::JsValue) -> Self {
+        let message = js_sys::Reflect::get(&error, &"message".into())
+            .ok()
+            .and_then(|message| message.as_string())
+            .or_else(|| error.as_string())
+            .unwrap_or_default();
+
+        WasmCookiesError::Js(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from() {
+        let error = WasmCookiesError::from(vec![RejectionReason::TooLarge(5000)]);
+        assert_eq!(
+            error.to_string(),
+            "the cookie is 5000 bytes, more than the maximum of 4096 bytes"
+        );
+
+        let error = WasmCookiesError::from(vec![
+            RejectionReason::TooLarge(5000),
+            RejectionReason::PrefixRequiresSecure,
+        ]);
+        assert!(matches!(error, WasmCookiesError::Rejected(reasons) if reasons.len() == 2));
+
+        let error = WasmCookiesError::from(PolicyViolation::NameNotAllowed("a".to_owned()));
+        assert_eq!(
+            error.to_string(),
+            r#"the cookie policy rejected the cookie: NameNotAllowed("a")"#
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Warnings shortly before a cookie expires, for example to tell the user their session is about
+//! to end.
+//!
+//! The expiration date of a cookie isn't readable from `document.cookie`, so it is recorded in a
+//! companion cookie named `<name>__expires` (see [track](fn.track.html)), holding the expiration
+//! timestamp in milliseconds. The companion cookie must be updated each time the cookie is set.
+
+use crate::cookies::{self, CookieOptions};
+use std::time::Duration;
+
+/// Suffix of the name of the companion cookies.
+pub const COMPANION_SUFFIX: &str = "__expires";
+
+fn companion_name(name: &str) -> String {
+    format!("{}{}", name, COMPANION_SUFFIX)
+}
+
+/// Returns the cookie string that records the expiration date of the cookie `name` set with
+/// `options`, in its companion cookie with the same options. Returns `None` if `options` has no
+/// valid expiration date (session cookie).
+pub fn track(name: &str, options: &CookieOptions) -> Option<String> {
+    let expires = cookies::parse_date(options.expires.as_deref()?)?;
+    Some(cookies::set_raw(
+        &companion_name(name),
+        &expires.to_string(),
+        options,
+    ))
+}
+
+/// Returns the recorded expiration timestamp (in milliseconds) of the cookie `name`, if its
+/// companion cookie exists.
+pub fn expires_at(cookie_string: &str, name: &str) -> Option<i64> {
+    cookies::get_raw(cookie_string, &companion_name(name))?
+        .parse()
+        .ok()
+}
+
+/// Returns how long to wait from `now` (in milliseconds) before warning that the cookie `name`
+/// expires in `lead_time`: zero if it's already time. Returns `None` if the expiration date isn't
+/// recorded or has passed.
+pub fn warning_delay(
+    cookie_string: &str,
+    name: &str,
+    lead_time: Duration,
+    now: i64,
+) -> Option<Duration> {
+    let expires = expires_at(cookie_string, name)?;
+
+    if expires <= now {
+        return None;
+    }
+
+    let warning_at = expires - lead_time.as_millis() as i64;
+    Some(Duration::from_millis((warning_at - now).max(0) as u64))
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use crate::cookies::now_timestamp;
+    #[cfg(feature = "encoding")]
+    use crate::cookies::{self, CookieOptions};
+    use std::time::Duration;
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    /// Sets a cookie, with URI encoded name and value
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+    /// and records its expiration date in its companion cookie.
+    #[cfg(feature = "encoding")]
+    pub fn set(name: &str, value: &str, options: &CookieOptions) {
+        crate::set_cookie_string(&cookies::set(name, value, options));
+
+        if let Some(cookie_string) = super::track(&urlencoding::encode(name), options) {
+            crate::set_cookie_string(&cookie_string);
+        }
+    }
+
+    /// A scheduled expiry warning, cancelled when dropped.
+    pub struct ExpiryWarning {
+        timeout_id: i32,
+        _on_timeout: Closure<dyn FnMut()>,
+    }
+
+    impl Drop for ExpiryWarning {
+        fn drop(&mut self) {
+            web_sys::window()
+                .unwrap()
+                .clear_timeout_with_handle(self.timeout_id);
+        }
+    }
+
+    /// Schedules `callback` to be called `lead_time` before the cookie `name` (undecoded) expires,
+    /// with the time remaining until then. Returns `None` if the expiration date isn't recorded
+    /// (see [track](../fn.track.html)) or has passed.
+    ///
+    /// The returned handle must be kept alive until the warning.
+    pub fn on_expiry_warning(
+        name: &str,
+        lead_time: Duration,
+        callback: impl FnOnce(Duration) + 'static,
+    ) -> Option<ExpiryWarning> {
+        let cookie_string = crate::cookie_string();
+        let delay = super::warning_delay(&cookie_string, name, lead_time, now_timestamp())?;
+        let expires = super::expires_at(&cookie_string, name)?;
+        let mut callback = Some(callback);
+
+        let on_timeout = Closure::wrap(Box::new(move || {
+            if let Some(callback) = callback.take() {
+                let remaining = (expires - now_timestamp()).max(0) as u64;
+                callback(Duration::from_millis(remaining));
+            }
+        }) as Box<dyn FnMut()>);
+
+        let timeout_id = web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                on_timeout.as_ref().unchecked_ref(),
+                delay.as_millis().min(i32::MAX as u128) as i32,
+            )
+            .unwrap();
+
+        Some(ExpiryWarning {
+            timeout_id,
+            _on_timeout: on_timeout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track() {
+        let options = CookieOptions::ROOT.expires_at_timestamp(1_700_000_000_000);
+        let cookie_string = track("session", &options).unwrap();
+        assert!(cookie_string.starts_with("session__expires=1700000000000;path=/;expires="));
+        assert_eq!(track("session", &CookieOptions::ROOT), None);
+
+        let cookie_string = "session=abc; session__expires=1700000000000";
+        assert_eq!(
+            expires_at(cookie_string, "session"),
+            Some(1_700_000_000_000)
+        );
+        assert_eq!(expires_at(cookie_string, "other"), None);
+    }
+
+    #[test]
+    fn test_warning_delay() {
+        let cookie_string = "session__expires=1700000000000";
+        let lead_time = Duration::from_secs(120);
+
+        assert_eq!(
+            warning_delay(cookie_string, "session", lead_time, 1_699_999_000_000),
+            Some(Duration::from_secs(880))
+        );
+        assert_eq!(
+            warning_delay(cookie_string, "session", lead_time, 1_699_999_990_000),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            warning_delay(cookie_string, "session", lead_time, 1_700_000_000_000),
+            None
+        );
+        assert_eq!(warning_delay("", "session", lead_time, 0), None);
+    }
+}
@@ -0,0 +1,315 @@
+//! Bindings to the cookies API of browser extensions (`browser.cookies` in Firefox and
+//! `chrome.cookies` in Chromium-based browsers), which reads and writes the cookies of any site
+//! the extension has host permissions for, including `HttpOnly` ones.
+//!
+//! The cookies are exchanged as [Cookie](../cookies/struct.Cookie.html) values. Since the API is
+//! asynchronous, the functions of the [browser](browser/index.html) module take a callback. They
+//! also work in background service workers, where there is no `window`.
+//!
+//! Available only with the `extension` feature.
+
+use crate::cookies::{parse_date, CookieOptions, SameSite};
+
+/// Error passed to the callbacks of the [browser](browser/index.html) module's functions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExtensionError {
+    /// The cookies API isn't available: the code doesn't run in an extension, or the extension
+    /// lacks the `cookies` permission.
+    Unavailable,
+
+    /// The browser rejected the call. The field is the error message.
+    Failed(String),
+}
+
+/// Returns the `sameSite` value of the cookies API for `same_site`. Unknown values give
+/// `unspecified`.
+pub fn same_site_name(same_site: &SameSite) -> &'static str {
+    match same_site {
+        SameSite::Lax => "lax",
+        SameSite::Strict => "strict",
+        SameSite::None => "no_restriction",
+        SameSite::Other(_) => "unspecified",
+    }
+}
+
+/// Returns the `SameSite` value for a `sameSite` value of the cookies API. `unspecified` and
+/// unknown values give `SameSite::Lax`, which is the default of browsers.
+pub fn parse_same_site(name: &str) -> SameSite {
+    match name {
+        "strict" => SameSite::Strict,
+        "no_restriction" => SameSite::None,
+        _ => SameSite::Lax,
+    }
+}
+
+/// Returns the `expirationDate` value of the cookies API (in seconds since the UNIX epoch) for
+/// `options`, or `None` for a session cookie or an unparsable date.
+pub fn expiration_date(options: &CookieOptions) -> Option<f64> {
+    options
+        .expires
+        .as_deref()
+        .and_then(parse_date)
+        .map(|timestamp| timestamp as f64 / 1000.0)
+}
+
+/// Browser functions, which call the extension cookies API.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::ExtensionError;
+    use crate::cookies::{Cookie, CookieOptions};
+    use js_sys::{Function, Object, Reflect};
+    use std::borrow::Cow;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+    type Callback = Box<dyn FnOnce(Result<JsValue, ExtensionError>)>;
+
+    fn property(object: &JsValue, key: &str) -> Option<JsValue> {
+        Reflect::get(object, &key.into())
+            .ok()
+            .filter(|value| !value.is_undefined() && !value.is_null())
+    }
+
+    /// Returns the cookies API, and true if it returns promises (`browser.cookies`) or false if
+    /// it takes callbacks (`chrome.cookies`).
+    fn api() -> Option<(JsValue, bool)> {
+        let global = js_sys::global();
+
+        match property(&global, "browser").and_then(|browser| property(&browser, "cookies")) {
+            Some(cookies) => Some((cookies, true)),
+            None => property(&global, "chrome")
+                .and_then(|chrome| property(&chrome, "cookies"))
+                .map(|cookies| (cookies, false)),
+        }
+    }
+
+    fn error_message(error: &JsValue) -> String {
+        property(error, "message")
+            .and_then(|message| message.as_string())
+            .or_else(|| error.as_string())
+            .unwrap_or_default()
+    }
+
+    /// Calls the method `method` of the cookies API with `details`, and passes its result to
+    /// `callback`.
+    fn call(
+        method: &str,
+        details: &Object,
+        callback: impl FnOnce(Result<JsValue, ExtensionError>) + 'static,
+    ) {
+        let (cookies, promises) = match api() {
+            Some(api) => api,
+            None => return callback(Err(ExtensionError::Unavailable)),
+        };
+
+        let function = match property(&cookies, method) {
+            Some(function) => function.unchecked_into::<Function>(),
+            None => return callback(Err(ExtensionError::Unavailable)),
+        };
+
+        let callback = Rc::new(RefCell::new(Some(Box::new(callback) as Callback)));
+        let reject_callback = Rc::clone(&callback);
+
+        let on_resolve = Closure::once_into_js(move |result: JsValue| {
+            let last_error = if promises {
+                None
+            } else {
+                property(&js_sys::global(), "chrome")
+                    .and_then(|chrome| property(&chrome, "runtime"))
+                    .and_then(|runtime| property(&runtime, "lastError"))
+            };
+
+            let result = match last_error {
+                Some(error) => Err(ExtensionError::Failed(error_message(&error))),
+                None => Ok(result),
+            };
+
+            if let Some(callback) = callback.borrow_mut().take() {
+                callback(result);
+            }
+        });
+
+        if promises {
+            let promise_callback = Rc::clone(&reject_callback);
+
+            let on_reject = Closure::once_into_js(move |error: JsValue| {
+                if let Some(callback) = promise_callback.borrow_mut().take() {
+                    callback(Err(ExtensionError::Failed(error_message(&error))));
+                }
+            });
+
+            let result = function.call1(&cookies, details).and_then(|promise| {
+                Reflect::get(&promise, &"then".into())?
+                    .unchecked_into::<Function>()
+                    .call2(&promise, &on_resolve, &on_reject)
+            });
+
+            if let Err(error) = result {
+                if let Some(callback) = reject_callback.borrow_mut().take() {
+                    callback(Err(ExtensionError::Failed(error_message(&error))));
+                }
+            }
+        } else if let Err(error) = function.call2(&cookies, details, &on_resolve) {
+            if let Some(callback) = reject_callback.borrow_mut().take() {
+                callback(Err(ExtensionError::Failed(error_message(&error))));
+            }
+        }
+    }
+
+    fn details(url: &str, name: Option<&str>, store_id: Option<&str>) -> Object {
+        let details = Object::new();
+        let _ = Reflect::set(&details, &"url".into(), &url.into());
+
+        if let Some(name) = name {
+            let _ = Reflect::set(&details, &"name".into(), &name.into());
+        }
+
+        if let Some(store_id) = store_id {
+            let _ = Reflect::set(&details, &"storeId".into(), &store_id.into());
+        }
+
+        details
+    }
+
+    fn string_property(object: &JsValue, key: &str) -> Option<String> {
+        property(object, key).and_then(|value| value.as_string())
+    }
+
+    fn bool_property(object: &JsValue, key: &str) -> bool {
+        property(object, key).is_some_and(|value| value.is_truthy())
+    }
+
+    /// Converts a `cookies.Cookie` object of the cookies API.
+    fn to_cookie(object: &JsValue) -> Cookie<'static> {
+        let mut options = CookieOptions::new();
+        options.path = string_property(object, "path").map(Cow::Owned);
+        options.secure = bool_property(object, "secure");
+        options.same_site =
+            super::parse_same_site(&string_property(object, "sameSite").unwrap_or_default());
+
+        if !bool_property(object, "hostOnly") {
+            options.domain = string_property(object, "domain").map(Cow::Owned);
+        }
+
+        if let Some(expiration_date) =
+            property(object, "expirationDate").and_then(|date| date.as_f64())
+        {
+            options = options.expires_at_timestamp((expiration_date * 1000.0) as i64);
+        }
+
+        Cookie {
+            name: Cow::Owned(string_property(object, "name").unwrap_or_default()),
+            value: Cow::Owned(string_property(object, "value").unwrap_or_default()),
+            options,
+            http_only: bool_property(object, "httpOnly"),
+        }
+    }
+
+    /// Reads the cookie `name` that would be sent to `url`, from the cookie store `store_id` (the
+    /// current context's store if `None`), and passes it to `callback` if it exists.
+    pub fn get(
+        url: &str,
+        name: &str,
+        store_id: Option<&str>,
+        callback: impl FnOnce(Result<Option<Cookie<'static>>, ExtensionError>) + 'static,
+    ) {
+        call("get", &details(url, Some(name), store_id), move |result| {
+            callback(result.map(|cookie| property(&cookie, "name").map(|_| to_cookie(&cookie))))
+        });
+    }
+
+    /// Reads all the cookies that would be sent to `url`, from the cookie store `store_id` (the
+    /// current context's store if `None`), and passes them to `callback`.
+    pub fn get_all(
+        url: &str,
+        store_id: Option<&str>,
+        callback: impl FnOnce(Result<Vec<Cookie<'static>>, ExtensionError>) + 'static,
+    ) {
+        call("getAll", &details(url, None, store_id), move |result| {
+            callback(result.map(|cookies| {
+                js_sys::Array::from(&cookies)
+                    .iter()
+                    .map(|cookie| to_cookie(&cookie))
+                    .collect()
+            }))
+        });
+    }
+
+    /// Sets `cookie` (including its `http_only` flag) for `url`, in the cookie store `store_id`
+    /// (the current context's store if `None`), and passes the result to `callback`.
+    pub fn set(
+        url: &str,
+        cookie: &Cookie,
+        store_id: Option<&str>,
+        callback: impl FnOnce(Result<(), ExtensionError>) + 'static,
+    ) {
+        let details = details(url, Some(&cookie.name), store_id);
+        let options = &cookie.options;
+        let _ = Reflect::set(&details, &"value".into(), &(&*cookie.value).into());
+        let _ = Reflect::set(&details, &"secure".into(), &options.secure.into());
+        let _ = Reflect::set(&details, &"httpOnly".into(), &cookie.http_only.into());
+        let _ = Reflect::set(
+            &details,
+            &"sameSite".into(),
+            &super::same_site_name(&options.same_site).into(),
+        );
+
+        if let Some(path) = options.path.as_deref() {
+            let _ = Reflect::set(&details, &"path".into(), &path.into());
+        }
+
+        if let Some(domain) = options.domain.as_deref() {
+            let _ = Reflect::set(&details, &"domain".into(), &domain.into());
+        }
+
+        if let Some(expiration_date) = super::expiration_date(options) {
+            let _ = Reflect::set(&details, &"expirationDate".into(), &expiration_date.into());
+        }
+
+        call("set", &details, move |result| callback(result.map(|_| ())));
+    }
+
+    /// Deletes the cookie `name` that would be sent to `url`, from the cookie store `store_id`
+    /// (the current context's store if `None`), and passes the result to `callback`.
+    pub fn remove(
+        url: &str,
+        name: &str,
+        store_id: Option<&str>,
+        callback: impl FnOnce(Result<(), ExtensionError>) + 'static,
+    ) {
+        call(
+            "remove",
+            &details(url, Some(name), store_id),
+            move |result| callback(result.map(|_| ())),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_site() {
+        for same_site in [SameSite::Lax, SameSite::Strict, SameSite::None] {
+            assert_eq!(parse_same_site(same_site_name(&same_site)), same_site);
+        }
+
+        assert_eq!(
+            same_site_name(&SameSite::Other("x".to_owned())),
+            "unspecified"
+        );
+        assert_eq!(parse_same_site("unspecified"), SameSite::Lax);
+    }
+
+    #[test]
+    fn test_expiration_date() {
+        let options = CookieOptions::ROOT.expires_at_timestamp(1_700_000_000_000);
+        assert_eq!(expiration_date(&options), Some(1_700_000_000.0));
+        assert_eq!(expiration_date(&CookieOptions::ROOT), None);
+    }
+}
@@ -0,0 +1,48 @@
+//! Helpers for [gloo-net](https://crates.io/crates/gloo-net) requests.
+//!
+//! [RequestBuilderExt](trait.RequestBuilderExt.html) extends `gloo_net::http::RequestBuilder`:
+//!
+//! ```ignore
+//! use wasm_cookies::gloo_net::RequestBuilderExt;
+//!
+//! let response = Request::get("https://api.example.com/me")
+//!     .with_credentials()
+//!     .send()
+//!     .await?;
+//! ```
+//!
+//! `with_credentials` makes the browser send the document's cookies with cross-origin requests
+//! (`credentials: include`). `with_jar_cookies` attaches the `Cookie` header of a
+//! [SimulatedJar](../jar/struct.SimulatedJar.html), for manual flows where the cookies are kept
+//! by the application. Browsers drop the `Cookie` header of `fetch` requests, so it only works in
+//! runtimes which allow it, such as service and edge workers.
+//!
+//! Available only with the `gloo-net` feature, on WebAssembly targets (`wasm32` and `wasm64`).
+
+use crate::jar::SimulatedJar;
+use ::gloo_net::http::RequestBuilder;
+use web_sys::RequestCredentials;
+
+/// Extension of `gloo_net::http::RequestBuilder` for cookies.
+pub trait RequestBuilderExt {
+    /// Sends the cookies of the document with the request, even if it is cross-origin
+    /// (`credentials: include`).
+    fn with_credentials(self) -> Self;
+
+    /// Attaches the `Cookie` header that `jar` builds for `url` at `now` (timestamp in
+    /// milliseconds, UTC), if the jar has cookies for it. `url` must be the URL of the request.
+    fn with_jar_cookies(self, jar: &SimulatedJar, url: &str, now: i64) -> Self;
+}
+
+impl RequestBuilderExt for RequestBuilder {
+    fn with_credentials(self) -> Self {
+        self.credentials(RequestCredentials::Include)
+    }
+
+    fn with_jar_cookies(self, jar: &SimulatedJar, url: &str, now: i64) -> Self {
+        match jar.cookie_header(url, now) {
+            Some(header) => self.header("Cookie", &header),
+            None => self,
+        }
+    }
+}
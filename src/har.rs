@@ -0,0 +1,215 @@
+//! Extraction of cookies from [HAR](http://www.softwareishard.com/blog/har-12-spec/) captures
+//! (HTTP Archive JSON files), to replay them or inspect them in tests.
+//!
+//! The captures are parsed with the [serde_json crate](https://crates.io/crates/serde_json).
+//!
+//! Available only with the `json` feature.
+
+use crate::cookies::{parse_date, Cookie, CookieOptions, SameSite};
+use serde_json::Value as Json;
+use std::borrow::Cow;
+
+/// The cookies of an entry (a request and its response) of a HAR capture.
+#[derive(Default, Clone, Debug)]
+pub struct HarEntryCookies {
+    /// The URL of the request.
+    pub url: String,
+
+    /// The cookies sent with the request, from its `cookies` array.
+    pub request: Vec<Cookie<'static>>,
+
+    /// The cookies set by the response, from its `Set-Cookie` headers.
+    pub response: Vec<Cookie<'static>>,
+}
+
+/// Error returned by [extract](fn.extract.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HarError {
+    /// The HAR capture isn't valid JSON, or is nested too deeply.
+    InvalidJson,
+
+    /// The HAR capture doesn't have the `log.entries` array.
+    MissingEntries,
+}
+
+fn string_field(json: &Json, key: &str) -> String {
+    json.get(key)
+        .and_then(Json::as_str)
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn optional_string_field(json: &Json, key: &str) -> Option<Cow<'static, str>> {
+    json.get(key)
+        .and_then(Json::as_str)
+        .map(|value| Cow::Owned(value.to_owned()))
+}
+
+fn har_cookie(json: &Json) -> Cookie<'static> {
+    let mut options = CookieOptions::new();
+    options.path = optional_string_field(json, "path");
+    options.domain = optional_string_field(json, "domain");
+    options.secure = json.get("secure").and_then(Json::as_bool) == Some(true);
+
+    if let Some(expires) = json.get("expires").and_then(Json::as_str) {
+        options = match parse_date(expires) {
+            Some(timestamp) => options.expires_at_timestamp(timestamp),
+            None => options.expires_at_date(expires.to_owned()),
+        };
+    }
+
+    if let Some(same_site) = json.get("sameSite").and_then(Json::as_str) {
+        if let Some(same_site) = SameSite::from_cookie_string_value(same_site) {
+            options.same_site = same_site;
+        }
+    }
+
+    Cookie {
+        name: Cow::Owned(string_field(json, "name")),
+        value: Cow::Owned(string_field(json, "value")),
+        options,
+        http_only: json.get("httpOnly").and_then(Json::as_bool) == Some(true),
+    }
+}
+
+fn entry_cookies(entry: &Json) -> HarEntryCookies {
+    let request = entry.get("request");
+    let empty = Vec::new();
+
+    let request_cookies = request
+        .and_then(|request| request.get("cookies"))
+        .and_then(Json::as_array)
+        .unwrap_or(&empty)
+        .iter()
+        .map(har_cookie)
+        .collect();
+
+    let response_cookies = entry
+        .get("response")
+        .and_then(|response| response.get("headers"))
+        .and_then(Json::as_array)
+        .unwrap_or(&empty)
+        .iter()
+        .filter(|header| {
+            header
+                .get("name")
+                .and_then(Json::as_str)
+                .is_some_and(|name| name.eq_ignore_ascii_case("set-cookie"))
+        })
+        .filter_map(|header| header.get("value").and_then(Json::as_str))
+        // Some tools fold several Set-Cookie headers into one value, separated by newlines.
+        .flat_map(str::lines)
+        .filter_map(|header| Cookie::parse_set_cookie(header).map(Cookie::into_owned))
+        .collect();
+
+    HarEntryCookies {
+        url: request
+            .map(|request| string_field(request, "url"))
+            .unwrap_or_default(),
+        request: request_cookies,
+        response: response_cookies,
+    }
+}
+
+/// Extracts the cookies of each entry of a HAR capture.
+pub fn extract(har: &str) -> Result<Vec<HarEntryCookies>, HarError> {
+    let har: Json = serde_json::from_str(har).map_err(|_| HarError::InvalidJson)?;
+
+    let entries = har
+        .get("log")
+        .and_then(|log| log.get("entries"))
+        .and_then(Json::as_array)
+        .ok_or(HarError::MissingEntries)?;
+
+    Ok(entries.iter().map(entry_cookies).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAR: &str = r#"{
+        "log": {
+            "version": "1.2",
+            "entries": [
+                {
+                    "request": {
+                        "method": "GET",
+                        "url": "https://example.com/",
+                        "cookies": [
+                            { "name": "session", "value": "abc" },
+                            {
+                                "name": "theme",
+                                "value": "dark",
+                                "path": "/",
+                                "domain": "example.com",
+                                "expires": "2004-11-09T11:33:20.000Z",
+                                "httpOnly": true,
+                                "secure": true
+                            }
+                        ],
+                        "headers": []
+                    },
+                    "response": {
+                        "status": 200,
+                        "cookies": [],
+                        "headers": [
+                            { "name": "Content-Type", "value": "text/html" },
+                            { "name": "set-cookie", "value": "token=xyz; Path=/; HttpOnly" },
+                            { "name": "Set-Cookie", "value": "a=1\nb=2; SameSite=None; Secure" }
+                        ]
+                    }
+                },
+                { "request": { "url": "https://example.com/empty" }, "response": {} }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_extract() {
+        let entries = extract(HAR).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let entry = &entries[0];
+        assert_eq!(entry.url, "https://example.com/");
+        assert_eq!(entry.request.len(), 2);
+        assert_eq!(entry.request[0].name, "session");
+        assert_eq!(entry.request[0].value, "abc");
+        assert!(!entry.request[0].http_only);
+        assert!(entry.request[1].http_only);
+        assert_eq!(
+            entry.request[1].options.to_attribute_string(),
+            "path=/;domain=example.com;expires=Tue, 09 Nov 2004 11:33:20 GMT;secure;samesite=lax"
+        );
+
+        assert_eq!(entry.response.len(), 3);
+        assert_eq!(entry.response[0].name, "token");
+        assert!(entry.response[0].http_only);
+        assert_eq!(entry.response[1].name, "a");
+        assert_eq!(entry.response[2].name, "b");
+        assert_eq!(entry.response[2].options.same_site, SameSite::None);
+
+        assert_eq!(entries[1].url, "https://example.com/empty");
+        assert!(entries[1].request.is_empty());
+        assert!(entries[1].response.is_empty());
+    }
+
+    #[test]
+    fn test_extract_errors() {
+        assert_eq!(extract("{").unwrap_err(), HarError::InvalidJson);
+        assert_eq!(
+            extract(&"[".repeat(100_000)).unwrap_err(),
+            HarError::InvalidJson
+        );
+        assert_eq!(
+            extract(r#"{ "log": { "entries": [], "a": "\x" } }"#).unwrap_err(),
+            HarError::InvalidJson
+        );
+        assert_eq!(
+            extract("{ \"log\": { \"entries\": [], \"a\": \"\u{1}\" } }").unwrap_err(),
+            HarError::InvalidJson
+        );
+        assert_eq!(extract("{}").unwrap_err(), HarError::MissingEntries);
+    }
+}
@@ -0,0 +1,93 @@
+//! Hashed cookie names, which keep the logical names of the cookies out of the `Cookie` header
+//! and avoid collisions with the cookies of third-party scripts.
+//!
+//! The stored name of a cookie is the prefix, `_`, and the first 8 bytes of the HMAC-SHA-256 of
+//! its logical name in hexadecimal (for example `app_3f1c9a0d5b7e2a41`). The stored names can't
+//! be reversed, so the logical names must be registered to be looked up from stored names.
+//!
+//! Available only with the `crypto` feature.
+
+use crate::cookies;
+use crate::sha256::{hmac_sha256, to_hex};
+use std::collections::HashMap;
+
+/// Mapping between logical cookie names and hashed stored names.
+#[derive(Clone, Debug)]
+pub struct HashedNames {
+    prefix: String,
+    key: Vec<u8>,
+    names: HashMap<String, String>,
+}
+
+impl HashedNames {
+    /// Creates a mapping whose stored names start with `prefix` and `_`, hashed with `key`.
+    pub fn new(prefix: impl Into<String>, key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            key: key.into(),
+            names: HashMap::new(),
+        }
+    }
+
+    /// Registers logical names, so they can be looked up from their stored names.
+    pub fn with_names<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        for name in names {
+            self.register(name);
+        }
+
+        self
+    }
+
+    /// Registers a logical name, so it can be looked up from its stored name, and returns its
+    /// stored name.
+    pub fn register(&mut self, name: impl Into<String>) -> String {
+        let name = name.into();
+        let stored_name = self.stored_name(&name);
+        self.names.insert(stored_name.clone(), name);
+        stored_name
+    }
+
+    /// Returns the stored name of the cookie named `name`.
+    pub fn stored_name(&self, name: &str) -> String {
+        let hash = hmac_sha256(&self.key, name.as_bytes());
+        format!("{}_{}", self.prefix, to_hex(&hash[..8]))
+    }
+
+    /// Returns the registered logical name whose stored name is `stored_name`, if any.
+    pub fn logical_name(&self, stored_name: &str) -> Option<&str> {
+        self.names.get(stored_name).map(String::as_str)
+    }
+
+    /// Returns the cookies of `cookie_string` whose name is the stored name of a registered
+    /// logical name, by logical name, with undecoded values.
+    pub fn all_raw(&self, cookie_string: &str) -> HashMap<String, String> {
+        cookies::all_iter_raw(cookie_string)
+            .filter_map(|(key, value)| Some((self.logical_name(key)?.to_owned(), value.to_owned())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashed_names() {
+        let names = HashedNames::new("app", "secret").with_names(["theme", "cart"]);
+        let stored_name = names.stored_name("theme");
+        assert!(stored_name.starts_with("app_"));
+        assert_eq!(stored_name.len(), 20);
+        assert_ne!(stored_name, names.stored_name("cart"));
+        assert_ne!(
+            stored_name,
+            HashedNames::new("app", "other").stored_name("theme")
+        );
+        assert_eq!(names.logical_name(&stored_name), Some("theme"));
+        assert_eq!(names.logical_name("theme"), None);
+
+        let cookie_string = format!("_ga=1; {}=dark", stored_name);
+        let cookies = names.all_raw(&cookie_string);
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies["theme"], "dark");
+    }
+}
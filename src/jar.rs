@@ -0,0 +1,315 @@
+//! Cookie jar simulation, for HTTP clients without a browser jar (for example wasm code running
+//! in wasmtime with WASI HTTP).
+//!
+//! The jar ingests `Set-Cookie` headers and builds `Cookie` headers following the storage model
+//! of [RFC 6265 section 5.3](https://datatracker.ietf.org/doc/html/rfc6265#section-5.3) and
+//! [section 5.4](https://datatracker.ietf.org/doc/html/rfc6265#section-5.4). The public suffix
+//! list isn't known, so cookies for domains like `com` are accepted.
+//!
+//! Timestamps are in milliseconds and provided by the caller, since the clock isn't always
+//! available outside of browsers.
+
+use crate::cookies::{
+    domain_matches, parse_date, path_matches, sort_by_precedence, Cookie, CookieOptions,
+};
+use crate::url::{self, Url};
+use std::borrow::Cow;
+
+#[derive(Clone, Debug)]
+struct StoredCookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    expires: Option<i64>,
+    creation: u64,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expires, Some(expires) if expires <= now)
+    }
+
+    fn applies_to(&self, url: &Url) -> bool {
+        let domain_ok = if self.host_only {
+            url.host == self.domain
+        } else {
+            domain_matches(&url.host, &self.domain)
+        };
+
+        domain_ok && path_matches(url.path, &self.path) && (!self.secure || url.is_secure())
+    }
+
+    fn to_cookie(&self) -> Cookie<'static> {
+        let mut options = CookieOptions::new().with_path(self.path.clone());
+        options.secure = self.secure;
+
+        if !self.host_only {
+            options.domain = Some(Cow::Owned(self.domain.clone()));
+        }
+
+        if let Some(expires) = self.expires {
+            options = options.expires_at_timestamp(expires);
+        }
+
+        Cookie {
+            name: Cow::Owned(self.name.clone()),
+            value: Cow::Owned(self.value.clone()),
+            options,
+            http_only: self.http_only,
+        }
+    }
+}
+
+/// A simulated client-side cookie jar.
+#[derive(Default, Clone, Debug)]
+pub struct SimulatedJar {
+    cookies: Vec<StoredCookie>,
+    next_creation: u64,
+}
+
+/// Returns the default path of a cookie set by a request to `path`
+/// (see [RFC 6265 section 5.1.4](https://datatracker.ietf.org/doc/html/rfc6265#section-5.1.4)).
+fn default_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(index) => &path[..index],
+    }
+}
+
+/// Returns the value of the last `Max-Age` attribute of a `Set-Cookie` header, if valid.
+fn max_age(header: &str) -> Option<i64> {
+    header
+        .split(';')
+        .skip(1)
+        .filter_map(|attribute| attribute.split_once('='))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("max-age"))
+        .filter_map(|(_, value)| value.trim().parse::<i64>().ok())
+        .last()
+}
+
+impl SimulatedJar {
+    /// Creates an empty jar.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ingests the value of a `Set-Cookie` header received at `now` in response to a request to
+    /// `url`. Returns false if the cookie was ignored: the header or the URL is malformed, the
+    /// domain doesn't match the URL's host, or the cookie is `Secure` and the URL isn't (as
+    /// browsers do, following RFC 6265bis).
+    ///
+    /// A cookie with the same name, domain and path replaces the stored one, and a cookie
+    /// already expired removes it.
+    pub fn set_cookie(&mut self, url: &str, header: &str, now: i64) -> bool {
+        let (url, cookie) = match (url::parse(url), Cookie::parse_set_cookie(header)) {
+            (Some(url), Some(cookie)) => (url, cookie),
+            _ => return false,
+        };
+
+        let (domain, host_only) = match cookie.options.domain.as_deref() {
+            Some(domain) if !domain.trim_start_matches('.').is_empty() => {
+                if !domain_matches(&url.host, domain) {
+                    return false;
+                }
+
+                (domain.trim_start_matches('.').to_ascii_lowercase(), false)
+            }
+            _ => (url.host.clone(), true),
+        };
+
+        if cookie.options.secure && !url.is_secure() {
+            return false;
+        }
+
+        let path = match cookie.options.path.as_deref() {
+            Some(path) if path.starts_with('/') => path,
+            _ => default_path(url.path),
+        };
+
+        let expires = match max_age(header) {
+            Some(max_age) => Some(now.saturating_add(max_age.saturating_mul(1000))),
+            None => cookie.options.expires.as_deref().and_then(parse_date),
+        };
+
+        let mut creation = self.next_creation;
+
+        if let Some(index) = self.cookies.iter().position(|stored| {
+            stored.name == cookie.name && stored.domain == domain && stored.path == path
+        }) {
+            creation = self.cookies.remove(index).creation;
+        } else {
+            self.next_creation += 1;
+        }
+
+        let stored = StoredCookie {
+            name: cookie.name.into_owned(),
+            value: cookie.value.into_owned(),
+            domain,
+            host_only,
+            path: path.to_owned(),
+            secure: cookie.options.secure,
+            http_only: cookie.http_only,
+            expires,
+            creation,
+        };
+
+        if !stored.is_expired(now) {
+            self.cookies.push(stored);
+        }
+
+        true
+    }
+
+    /// Returns the cookies sent with a request to `url` at `now`, longest paths first, then
+    /// oldest first, as browsers order them.
+    pub fn cookies_for(&self, url: &str, now: i64) -> Vec<Cookie<'static>> {
+        let url = match url::parse(url) {
+            Some(url) => url,
+            None => return Vec::new(),
+        };
+
+        let mut stored_cookies = self
+            .cookies
+            .iter()
+            .filter(|stored| !stored.is_expired(now) && stored.applies_to(&url))
+            .collect::<Vec<_>>();
+
+        stored_cookies.sort_by_key(|stored| stored.creation);
+        let mut cookies: Vec<_> = stored_cookies
+            .into_iter()
+            .map(StoredCookie::to_cookie)
+            .collect();
+        sort_by_precedence(&mut cookies);
+        cookies
+    }
+
+    /// Returns all the cookies not expired at `now`, whatever their domain and path, oldest
+    /// first.
+    pub fn all_cookies(&self, now: i64) -> Vec<Cookie<'static>> {
+        let mut cookies = self
+            .cookies
+            .iter()
+            .filter(|stored| !stored.is_expired(now))
+            .collect::<Vec<_>>();
+
+        cookies.sort_by_key(|stored| stored.creation);
+        cookies.into_iter().map(StoredCookie::to_cookie).collect()
+    }
+
+    /// Returns the value of the `Cookie` header of a request to `url` at `now`, or `None` if no
+    /// cookie applies.
+    pub fn cookie_header(&self, url: &str, now: i64) -> Option<String> {
+        let pairs = self
+            .cookies_for(url, now)
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>();
+
+        if pairs.is_empty() {
+            None
+        } else {
+            Some(pairs.join("; "))
+        }
+    }
+
+    /// Removes the cookies expired at `now`.
+    pub fn remove_expired(&mut self, now: i64) {
+        self.cookies.retain(|stored| !stored.is_expired(now));
+    }
+
+    /// Removes all the cookies.
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+
+    /// Returns the number of stored cookies, including expired ones not removed yet.
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    /// Returns true if the jar stores no cookie.
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    #[test]
+    fn test_host_only_and_domain() {
+        let mut jar = SimulatedJar::new();
+        assert!(jar.set_cookie("https://example.com/", "host=1", NOW));
+        assert!(jar.set_cookie("https://example.com/", "shared=2; Domain=.example.com", NOW));
+        assert!(!jar.set_cookie("https://example.com/", "other=3; Domain=example.org", NOW));
+        assert!(!jar.set_cookie("http://example.com/", "secure=4; Secure", NOW));
+
+        assert_eq!(
+            jar.cookie_header("https://example.com/", NOW),
+            Some("host=1; shared=2".to_owned())
+        );
+        assert_eq!(
+            jar.cookie_header("https://www.example.com/", NOW),
+            Some("shared=2".to_owned())
+        );
+        assert_eq!(jar.cookie_header("https://example.org/", NOW), None);
+    }
+
+    #[test]
+    fn test_path_and_order() {
+        let mut jar = SimulatedJar::new();
+        assert!(jar.set_cookie("https://example.com/a/page", "default=1", NOW));
+        assert!(jar.set_cookie("https://example.com/", "root=2; Path=/", NOW));
+        assert!(jar.set_cookie("https://example.com/", "deep=3; Path=/a/b", NOW));
+        assert!(jar.set_cookie("https://example.com/", "secure=4; Path=/; Secure", NOW));
+
+        assert_eq!(
+            jar.cookie_header("https://example.com/a/b/c", NOW),
+            Some("deep=3; default=1; root=2; secure=4".to_owned())
+        );
+        assert_eq!(
+            jar.cookie_header("http://example.com/other", NOW),
+            Some("root=2".to_owned())
+        );
+
+        let names = jar
+            .all_cookies(NOW)
+            .into_iter()
+            .map(|cookie| cookie.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["default", "root", "deep", "secure"]);
+    }
+
+    #[test]
+    fn test_overwrite_and_expiry() {
+        let mut jar = SimulatedJar::new();
+        assert!(jar.set_cookie("https://example.com/", "a=1; Path=/", NOW));
+        assert!(jar.set_cookie("https://example.com/", "b=2; Path=/; Max-Age=60", NOW));
+        assert!(jar.set_cookie("https://example.com/", "a=3; Path=/", NOW));
+        assert_eq!(jar.len(), 2);
+        assert_eq!(
+            jar.cookie_header("https://example.com/", NOW),
+            Some("a=3; b=2".to_owned())
+        );
+        assert_eq!(
+            jar.cookie_header("https://example.com/", NOW + 60_000),
+            Some("a=3".to_owned())
+        );
+
+        assert!(jar.set_cookie(
+            "https://example.com/",
+            "a=; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            NOW
+        ));
+        assert_eq!(jar.len(), 1);
+        jar.remove_expired(NOW + 60_000);
+        assert!(jar.is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! Expiry ledger: a single sidecar cookie recording the expiration dates the application
+//! requested for its cookies, since they aren't readable from `document.cookie`.
+//!
+//! Unlike the companion cookies of the [expiry](../expiry/index.html) module, the ledger uses one
+//! cookie for all the tracked cookies. It is stored as `name:timestamp` entries (timestamps in
+//! milliseconds) separated by `/`, which can't appear in cookie names. The entries of the cookies
+//! that were deleted or have expired are removed by [cleanup](struct.ExpiryLedger.html#method.cleanup).
+
+use crate::cookies::{self, CookieOptions};
+use std::collections::BTreeMap;
+
+/// Name of the ledger cookie.
+pub const LEDGER_NAME: &str = "__expiry_ledger";
+
+/// The recorded expiration timestamps, by undecoded cookie name.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct ExpiryLedger {
+    entries: BTreeMap<String, i64>,
+}
+
+impl ExpiryLedger {
+    /// Reads the ledger from `cookie_string`. A missing ledger gives an empty one, and malformed
+    /// entries are ignored.
+    pub fn load(cookie_string: &str) -> Self {
+        let entries = cookies::get_raw(cookie_string, LEDGER_NAME)
+            .map(|value| {
+                value
+                    .split('/')
+                    .filter_map(|entry| {
+                        let (name, timestamp) = entry.rsplit_once(':')?;
+                        Some((name.to_owned(), timestamp.parse().ok()?))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { entries }
+    }
+
+    /// Returns the recorded expiration timestamp (in milliseconds) of the cookie `name`
+    /// (undecoded), if any.
+    pub fn known_expiry(&self, name: &str) -> Option<i64> {
+        self.entries.get(name).copied()
+    }
+
+    /// Records the expiration date of the cookie `name` (undecoded) set with `options`.
+    /// For a session cookie or an unparsable date, the entry is removed instead.
+    pub fn record(&mut self, name: &str, options: &CookieOptions) {
+        match options.expires.as_deref().and_then(cookies::parse_date) {
+            Some(timestamp) => {
+                self.entries.insert(name.to_owned(), timestamp);
+            }
+            None => self.remove(name),
+        }
+    }
+
+    /// Removes the entry of the cookie `name` (undecoded).
+    pub fn remove(&mut self, name: &str) {
+        self.entries.remove(name);
+    }
+
+    /// Removes the entries of the cookies missing from `cookie_string` and of those expired at
+    /// `now` (in milliseconds). Returns true if an entry was removed.
+    pub fn cleanup(&mut self, cookie_string: &str, now: i64) -> bool {
+        let count = self.entries.len();
+
+        self.entries.retain(|name, timestamp| {
+            *timestamp > now && cookies::get_raw(cookie_string, name).is_some()
+        });
+
+        self.entries.len() != count
+    }
+
+    /// Returns the cookie string that stores the ledger, with the path `/`, expiring with the
+    /// last tracked cookie. If the ledger is empty, it is deleted instead.
+    pub fn to_cookie_string(&self) -> String {
+        let value = self
+            .entries
+            .iter()
+            .map(|(name, timestamp)| format!("{}:{}", name, timestamp))
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let options = match self.entries.values().max() {
+            Some(timestamp) => CookieOptions::ROOT.expires_at_timestamp(*timestamp),
+            None => CookieOptions::ROOT.expires_at_date("Thu, 01 Jan 1970 00:00:00 GMT"),
+        };
+
+        cookies::set_raw(LEDGER_NAME, &value, &options)
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::ExpiryLedger;
+    use crate::cookies::{self, now_timestamp, CookieOptions};
+
+    /// Sets a cookie, with non encoded name and value, and records its expiration date in the
+    /// ledger.
+    pub fn set_raw(name: &str, value: &str, options: &CookieOptions) {
+        crate::set_cookie_string(&cookies::set_raw(name, value, options));
+
+        let mut ledger = ExpiryLedger::load(&crate::cookie_string());
+        ledger.record(name, options);
+        crate::set_cookie_string(&ledger.to_cookie_string());
+    }
+
+    /// Sets a cookie, with URI encoded name and value
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+    /// and records its expiration date in the ledger.
+    #[cfg(feature = "encoding")]
+    pub fn set(name: &str, value: &str, options: &CookieOptions) {
+        set_raw(
+            &urlencoding::encode(name),
+            &urlencoding::encode(value),
+            options,
+        );
+    }
+
+    /// Returns the recorded expiration timestamp (in milliseconds) of the cookie `name`
+    /// (undecoded), if it still exists. The entries of the deleted and expired cookies are
+    /// removed from the ledger.
+    pub fn known_expiry(name: &str) -> Option<i64> {
+        let cookie_string = crate::cookie_string();
+        let mut ledger = ExpiryLedger::load(&cookie_string);
+
+        if ledger.cleanup(&cookie_string, now_timestamp()) {
+            crate::set_cookie_string(&ledger.to_cookie_string());
+        }
+
+        ledger.known_expiry(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger() {
+        let mut ledger = ExpiryLedger::load("");
+        ledger.record(
+            "session",
+            &CookieOptions::ROOT.expires_at_timestamp(1_700_000_000_000),
+        );
+        ledger.record(
+            "theme",
+            &CookieOptions::ROOT.expires_at_timestamp(1_800_000_000_000),
+        );
+        ledger.record("tab", &CookieOptions::ROOT);
+        assert_eq!(ledger.known_expiry("session"), Some(1_700_000_000_000));
+        assert_eq!(ledger.known_expiry("tab"), None);
+
+        let cookie_string = ledger.to_cookie_string();
+        assert_eq!(
+            cookie_string,
+            "__expiry_ledger=session:1700000000000/theme:1800000000000;path=/;\
+             expires=Fri, 15 Jan 2027 08:00:00 GMT;samesite=lax"
+        );
+
+        let (pair, _) = cookie_string.split_once(';').unwrap();
+        assert_eq!(ExpiryLedger::load(pair), ledger);
+    }
+
+    #[test]
+    fn test_cleanup() {
+        let cookie_string = "a=1; b=2; __expiry_ledger=a:100/b:300/c:300/bad";
+        let mut ledger = ExpiryLedger::load(cookie_string);
+        assert!(ledger.cleanup(cookie_string, 200));
+        assert_eq!(ledger.known_expiry("a"), None);
+        assert_eq!(ledger.known_expiry("b"), Some(300));
+        assert_eq!(ledger.known_expiry("c"), None);
+        assert!(!ledger.cleanup(cookie_string, 200));
+
+        ledger.remove("b");
+        assert_eq!(
+            ledger.to_cookie_string(),
+            "__expiry_ledger=;path=/;expires=Thu, 01 Jan 1970 00:00:00 GMT;samesite=lax"
+        );
+    }
+}
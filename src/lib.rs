@@ -1,38 +1,550 @@
+#[cfg(feature = "encoding")]
+pub mod attribution;
+#[cfg(not(target_family = "wasm"))]
+pub mod backend;
+mod base64;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+#[cfg(feature = "encoding")]
+pub mod bridge;
+pub mod changes;
+pub mod checksum;
+pub mod codec;
 pub mod cookies;
-pub use cookies::{AllDecodeError, CookieOptions, SameSite};
+mod crc32;
+pub mod deferred;
+#[cfg(feature = "encoding")]
+pub mod drafts;
+#[cfg(feature = "eframe")]
+pub mod eframe;
+#[cfg(any(target_os = "emscripten", test))]
+mod emscripten;
+#[cfg(feature = "encoding")]
+pub mod encoding;
+pub mod environment;
+pub mod error;
+pub mod expiry;
+#[cfg(feature = "extension")]
+pub mod extension;
+#[cfg(all(feature = "gloo-net", target_family = "wasm"))]
+pub mod gloo_net;
+#[cfg(feature = "json")]
+pub mod har;
+#[cfg(feature = "crypto")]
+pub mod hashed_names;
+pub mod jar;
+pub mod ledger;
+pub mod lifetime;
+#[cfg(feature = "json")]
+pub mod list;
+#[cfg(feature = "locale")]
+pub mod locale;
+mod lz;
+pub mod middleware;
+#[cfg(feature = "encoding")]
+pub mod migration;
+pub mod nonce;
+#[cfg(feature = "crypto")]
+pub mod oauth;
+pub mod page_config;
+pub mod policy;
+#[cfg(feature = "json")]
+pub mod prefs;
+pub mod privacy;
+#[cfg(feature = "debug-provenance")]
+pub mod provenance;
+pub mod rate_limit;
+pub mod read_cache;
+pub mod registry;
+#[cfg(feature = "crypto")]
+pub mod remember_me;
+#[cfg(feature = "json")]
+pub mod saves;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "session")]
+pub mod session;
+mod sha256;
+pub mod shared_cache;
+#[cfg(feature = "tauri")]
+pub mod tauri;
+#[cfg(feature = "tcf")]
+pub mod tcf;
+pub mod timeout;
+pub mod timezone;
+pub mod touch;
+pub mod transaction;
+#[cfg(any(feature = "json", feature = "form"))]
+pub mod typed;
+mod url;
+pub mod view;
+pub mod visitor;
+pub mod worker_proxy;
+#[cfg(not(target_family = "wasm"))]
+pub use backend::TestBackend;
+pub use codec::{CodecRouter, CookieCodec};
+#[cfg(feature = "encoding")]
+pub use cookies::{AllDecodeError, GetDecodeError};
+pub use cookies::{
+    Cookie, CookieOptions, CookieOptionsWarning, CookieOverrides, CookieSnapshot, CookieWriter,
+    Duplicates, Namespace, RejectionReason, SameSite, WhyNot, MAX_COOKIE_SIZE,
+    MAX_EXPIRES_TIMESTAMP, MAX_EXPIRY, SAFARI_SCRIPT_MAX_EXPIRY, SAFARI_TRACKER_LINK_MAX_EXPIRY,
+};
+#[cfg(feature = "encoding")]
+pub use encoding::Encoding;
+pub use environment::Environment;
+pub use error::WasmCookiesError;
+#[cfg(feature = "crypto")]
+pub use hashed_names::HashedNames;
+#[cfg(feature = "json")]
+pub use list::CookieList;
+pub use middleware::{CookieMiddleware, MiddlewareChain};
+pub use policy::{CookiePolicy, CookieRule, PolicyViolation};
+pub use privacy::{GuardAction, GuardMode, PrivacyGuard, PrivacySignals};
+pub use rate_limit::RateLimit;
+pub use registry::{CookieDeclaration, CookieRegistry, Retention};
+pub use touch::{AutoTouch, TouchPolicy};
+pub use transaction::{Transaction, TransactionError};
+#[cfg(any(feature = "json", feature = "form"))]
+pub use typed::{Format, FormatRegistry, TypedError};
+#[cfg(feature = "encoding")]
 pub use urlencoding::FromUrlEncodingError;
+pub use view::CookiesView;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(feature = "encoding")]
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+#[cfg(not(feature = "small"))]
 use std::collections::HashMap;
-#[cfg(target_arch = "wasm32")]
+use std::iter::FromIterator;
+#[cfg(target_family = "wasm")]
+use web_sys::Document;
+#[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
 use web_sys::HtmlDocument;
 
-#[cfg(target_arch = "wasm32")]
-fn document() -> HtmlDocument {
-    use wasm_bindgen::JsCast;
+/// Unwraps the result of a browser API call. With the `small` feature, a failure throws a
+/// JavaScript error instead of panicking, so the panic formatting code isn't linked.
+#[cfg(target_family = "wasm")]
+fn expect_js<T, E: std::fmt::Debug>(result: Result<T, E>) -> T {
+    #[cfg(all(target_family = "wasm", feature = "small"))]
+    let value =
+        result.unwrap_or_else(|_| wasm_bindgen::throw_str("wasm-cookies: browser API error"));
+    #[cfg(not(all(target_family = "wasm", feature = "small")))]
+    let value = result.unwrap();
+    value
+}
 
+#[cfg(target_family = "wasm")]
+fn window() -> web_sys::Window {
+    expect_js(web_sys::window().ok_or(()))
+}
+
+#[cfg(target_family = "wasm")]
+pub(crate) fn try_document() -> Result<Document, WasmCookiesError> {
     web_sys::window()
-        .unwrap()
-        .document()
-        .unwrap()
-        .dyn_into::<HtmlDocument>()
-        .unwrap()
+        .and_then(|window| window.document())
+        .ok_or(WasmCookiesError::MissingDocument)
+}
+
+/// Reads `document.cookie`. Some embedders expose a proxied document, which isn't an
+/// `HTMLDocument` for the cast, so the property is then read through `Reflect`.
+#[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
+fn document_cookie(document: &Document) -> Result<String, WasmCookiesError> {
+    use wasm_bindgen::JsCast;
+
+    match document.dyn_ref::<HtmlDocument>() {
+        Some(document) => Ok(document.cookie()?),
+        None => js_sys::Reflect::get(document, &"cookie".into())?
+            .as_string()
+            .ok_or_else(|| WasmCookiesError::Js("document.cookie isn't a string".to_owned())),
+    }
+}
+
+/// Writes `document.cookie`, through `Reflect` if the document isn't an `HTMLDocument` (see
+/// `document_cookie`).
+#[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
+fn set_document_cookie(document: &Document, value: &str) -> Result<(), WasmCookiesError> {
+    use wasm_bindgen::JsCast;
+
+    match document.dyn_ref::<HtmlDocument>() {
+        Some(document) => document.set_cookie(value)?,
+        None => {
+            js_sys::Reflect::set(document, &"cookie".into(), &value.into())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(target_family = "wasm")]
+fn host() -> String {
+    #[cfg(target_os = "emscripten")]
+    let host = emscripten::hostname();
+    #[cfg(not(target_os = "emscripten"))]
+    let host = expect_js(window().location().hostname());
+    host
+}
+
+#[cfg(target_family = "wasm")]
+fn is_secure_context() -> bool {
+    window().is_secure_context()
+}
+
+#[cfg(target_family = "wasm")]
+pub(crate) fn pathname() -> String {
+    #[cfg(target_os = "emscripten")]
+    let path = emscripten::pathname();
+    #[cfg(not(target_os = "emscripten"))]
+    let path = expect_js(window().location().pathname());
+    path
+}
+
+#[cfg(not(target_family = "wasm"))]
+thread_local! {
+    static TEST_BACKEND: RefCell<Option<TestBackend>> = const { RefCell::new(None) };
+}
+
+/// Calls `f` with the installed test backend, or returns an error if there is none.
+#[cfg(not(target_family = "wasm"))]
+fn with_test_backend<T>(f: impl FnOnce(&mut TestBackend) -> T) -> Result<T, WasmCookiesError> {
+    TEST_BACKEND.with(|backend| match backend.borrow_mut().as_mut() {
+        Some(backend) => Ok(f(backend)),
+        None => Err(WasmCookiesError::UnsupportedTarget),
+    })
+}
+
+/// Calls `f` with the installed test backend, installing an empty one first if there is none, for
+/// the infallible root functions.
+#[cfg(not(target_family = "wasm"))]
+fn with_default_test_backend<T>(f: impl FnOnce(&mut TestBackend) -> T) -> T {
+    TEST_BACKEND.with(|backend| f(backend.borrow_mut().get_or_insert_with(TestBackend::new)))
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn host() -> String {
+    with_default_test_backend(|backend| backend.host())
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn is_secure_context() -> bool {
+    with_default_test_backend(|backend| backend.is_secure_context())
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn pathname() -> String {
+    with_default_test_backend(|backend| backend.path().to_owned())
+}
+
+/// Installs an in-memory backend, which the root functions then read and write, or removes the
+/// installed one if `None` (see [backend](backend/index.html)). Without a backend, the first
+/// call to an infallible root function installs an empty one.
+///
+/// Available only on targets other than WebAssembly.
+#[cfg(not(target_family = "wasm"))]
+pub fn set_test_backend(backend: Option<TestBackend>) {
+    TEST_BACKEND.with(|current| *current.borrow_mut() = backend);
+}
+
+/// Returns a copy of the installed test backend, to inspect its cookies, if any.
+///
+/// Available only on targets other than WebAssembly.
+#[cfg(not(target_family = "wasm"))]
+pub fn test_backend() -> Option<TestBackend> {
+    TEST_BACKEND.with(|backend| backend.borrow().clone())
+}
+
+#[cfg(target_family = "wasm")]
+pub(crate) fn fill_random(bytes: &mut [u8]) {
+    expect_js(expect_js(window().crypto()).get_random_values_with_u8_array(bytes));
+}
+
+#[cfg(target_family = "wasm")]
+pub(crate) fn cookie_string() -> String {
+    expect_js(try_cookie_string())
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn cookie_string() -> String {
+    with_default_test_backend(|backend| backend.cookie_string(cookies::now_timestamp()))
+}
+
+#[cfg(target_family = "wasm")]
+pub(crate) fn try_cookie_string() -> Result<String, WasmCookiesError> {
+    #[cfg(feature = "tauri")]
+    if let Some(cookie_string) = tauri::browser::cookie_string() {
+        return Ok(cookie_string);
+    }
+
+    #[cfg(target_os = "emscripten")]
+    let cookie_string = Ok(emscripten::cookie_string());
+    #[cfg(not(target_os = "emscripten"))]
+    let cookie_string = document_cookie(&try_document()?);
+    cookie_string
+}
+
+#[cfg(all(not(target_family = "wasm"), any(feature = "encoding", test)))]
+pub(crate) fn try_cookie_string() -> Result<String, WasmCookiesError> {
+    with_test_backend(|backend| backend.cookie_string(cookies::now_timestamp()))
+}
+
+#[cfg(target_family = "wasm")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub(crate) fn set_cookie_string(value: &str) {
+    expect_js(try_set_cookie_string(value));
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn set_cookie_string(value: &str) {
+    with_default_test_backend(|backend| {
+        backend.set_cookie_string(value, cookies::now_timestamp());
+    });
+}
+
+/// Returns true if the writes are queued (see [deferred](deferred/index.html)), so the reads
+/// don't see them yet.
+fn writes_deferred() -> bool {
+    #[cfg(target_family = "wasm")]
+    let deferred = deferred::browser::is_deferring();
+    #[cfg(not(target_family = "wasm"))]
+    let deferred = false;
+    deferred
+}
+
+#[cfg(target_family = "wasm")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub(crate) fn try_set_cookie_string(value: &str) -> Result<(), WasmCookiesError> {
+    #[cfg(feature = "debug-provenance")]
+    provenance::browser::record(std::panic::Location::caller(), value);
+
+    if deferred::browser::defer(value) {
+        return Ok(());
+    }
+
+    read_cache::browser::invalidate();
+
+    #[cfg(feature = "tauri")]
+    if tauri::browser::set_cookie_string(value) {
+        return Ok(());
+    }
+
+    #[cfg(target_os = "emscripten")]
+    emscripten::set_cookie_string(value);
+    #[cfg(not(target_os = "emscripten"))]
+    set_document_cookie(&try_document()?, value)?;
+    Ok(())
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn try_set_cookie_string(value: &str) -> Result<(), WasmCookiesError> {
+    with_test_backend(|backend| {
+        backend.set_cookie_string(value, cookies::now_timestamp());
+    })
+}
+
+/// Callback of the installed privacy guard, called when it blocks or downgrades a write.
+type GuardCallback = Box<dyn Fn(&str, GuardAction)>;
+
+thread_local! {
+    static POLICY: RefCell<Option<CookiePolicy>> = const { RefCell::new(None) };
+    static ENVIRONMENT: RefCell<Environment> = RefCell::new(Environment::default());
+    static CONSENTED_CATEGORIES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static PRIVACY_GUARD: RefCell<Option<(PrivacyGuard, GuardCallback)>> =
+        const { RefCell::new(None) };
+}
+
+#[cfg(feature = "encoding")]
+thread_local! {
+    static ENCODING: Cell<Encoding> = const { Cell::new(Encoding::Standard) };
+    static MIDDLEWARE: RefCell<MiddlewareChain> = RefCell::new(MiddlewareChain::new());
+    static AUTO_TOUCH: RefCell<Option<AutoTouch>> = const { RefCell::new(None) };
+}
+
+#[cfg(any(feature = "json", feature = "form"))]
+thread_local! {
+    static FORMAT_REGISTRY: RefCell<FormatRegistry> = RefCell::new(FormatRegistry::new());
+}
+
+/// Returns the name of the cookie in the installed environment.
+fn environment_name(name: &str) -> String {
+    ENVIRONMENT.with(|environment| environment.borrow().name(name).into_owned())
+}
+
+/// Returns the name of the cookie rewritten by the registered middleware, if any (see
+/// [register_middleware](fn.register_middleware.html)).
+fn middleware_name(name: &str) -> String {
+    #[cfg(feature = "encoding")]
+    let name = MIDDLEWARE.with(|chain| chain.borrow().name(name));
+    #[cfg(not(feature = "encoding"))]
+    let name = name.to_owned();
+    name
+}
+
+/// Returns the decoded value of the cookie `name` (as rewritten by the middleware) rewritten by
+/// the registered middleware, or `None` if one of them hid the cookie.
+#[cfg(feature = "encoding")]
+fn middleware_get(name: &str, value: String) -> Option<String> {
+    MIDDLEWARE.with(|chain| chain.borrow().get(name, value))
+}
+
+/// Returns the name of the cookie with the installed policy's prefix, if any, in the installed
+/// environment.
+#[cfg(feature = "encoding")]
+fn policy_name(name: &str) -> String {
+    POLICY.with(|policy| match &*policy.borrow() {
+        Some(policy) => environment_name(&policy.name(name)),
+        None => environment_name(name),
+    })
+}
+
+/// Checks that the installed policy, if any, allows a root function to write the cookie `name`
+/// (before the policy's prefix is prepended). Deletions only check the name, so cookies can
+/// still be deleted once consent for their category is revoked.
+fn check_policy(name: &str, deletion: bool) -> Result<(), PolicyViolation> {
+    POLICY.with(|policy| match &*policy.borrow() {
+        Some(policy) if deletion => policy.check_name(name),
+        Some(policy) => {
+            CONSENTED_CATEGORIES.with(|categories| policy.check(name, &categories.borrow()))
+        }
+        None => Ok(()),
+    })
+}
+
+/// Applies the installed policy, if any, to a cookie set by a root function with a URI encoded
+/// name, and returns its name in the installed environment, with the policy's prefix, and its
+/// options filled from the matching rule.
+#[cfg(feature = "encoding")]
+fn apply_policy(
+    name: &str,
+    options: &CookieOptions,
+) -> Result<(String, CookieOptions<'static>), PolicyViolation> {
+    let (name, options) = POLICY.with(|policy| match &*policy.borrow() {
+        Some(policy) => CONSENTED_CATEGORIES.with(|categories| {
+            policy
+                .apply(name, options, &categories.borrow())
+                .map(|(name, options)| (name, options.into_owned()))
+        }),
+        None => Ok((name.to_owned(), options.clone().into_owned())),
+    })?;
+
+    Ok((environment_name(&name), options))
+}
+
+/// Returns the options to delete the cookie `name` (before the policy's prefix is prepended)
+/// with: `options`, with the path and the domain of the installed policy's matching rule if
+/// unset, so the deletion matches the cookie written by `set`.
+#[cfg(feature = "encoding")]
+fn deletion_options(name: &str, options: Option<&CookieOptions>) -> Option<CookieOptions<'static>> {
+    POLICY.with(|policy| match &*policy.borrow() {
+        Some(policy) => Some(
+            policy
+                .options(name, options.unwrap_or(&CookieOptions::new()))
+                .into_owned(),
+        ),
+        None => options.map(|options| options.clone().into_owned()),
+    })
 }
 
-#[cfg(target_arch = "wasm32")]
-fn cookie_string() -> String {
-    document().cookie().unwrap()
+/// Sets the URI encoding used by `get`, `get2`, `set` and `delete`.
+/// The default is `Encoding::Standard`.
+#[cfg(feature = "encoding")]
+pub fn set_encoding(encoding: Encoding) {
+    ENCODING.with(|current| current.set(encoding));
+}
+
+#[cfg(feature = "encoding")]
+fn encoding() -> Encoding {
+    ENCODING.with(Cell::get)
+}
+
+/// Registers a middleware, which is then applied after the already registered ones (see
+/// [MiddlewareChain](middleware/struct.MiddlewareChain.html)): names given to the root
+/// functions are rewritten by every middleware, the writes of `set` and `set_form` go through
+/// `on_set`, and the decoded values read by `get`, `take`, `get_lossy` and the other decoded reads
+/// go through `on_get`.
+#[cfg(feature = "encoding")]
+pub fn register_middleware(middleware: impl CookieMiddleware + 'static) {
+    MIDDLEWARE.with(|chain| chain.borrow_mut().push(middleware));
+}
+
+/// Removes all the registered middleware.
+#[cfg(feature = "encoding")]
+pub fn clear_middleware() {
+    MIDDLEWARE.with(|chain| chain.borrow_mut().clear());
+}
+
+/// Installs a cookie policy, which is then applied by `set`, `get` and `delete`,
+/// or removes the installed one if `None`.
+///
+/// The root functions writing cookies (`set_raw`, `increment`, `take`, `restore`,
+/// `transaction`...) don't write the cookies whose name the policy doesn't allow, or whose
+/// consent category hasn't been granted. Deletions don't require consent.
+pub fn set_policy(policy: Option<CookiePolicy>) {
+    POLICY.with(|current| *current.borrow_mut() = policy);
 }
 
-#[cfg(target_arch = "wasm32")]
-fn set_cookie_string(value: &str) {
-    document().set_cookie(value).unwrap();
+/// Installs an auto-touch table, whose matching cookies are then set again with a refreshed
+/// expiration date when read by `get`, or removes the installed one if `None` (see
+/// [touch](touch/index.html)).
+#[cfg(feature = "encoding")]
+pub fn set_auto_touch(auto_touch: Option<AutoTouch>) {
+    AUTO_TOUCH.with(|current| *current.borrow_mut() = auto_touch);
+}
+
+/// Installs a format registry, which then gives the format of the cookies read by `get_typed`
+/// and written by `set_typed`, or removes the installed one if `None`.
+///
+/// Available only with the `json` or `form` feature.
+#[cfg(any(feature = "json", feature = "form"))]
+pub fn set_format_registry(registry: Option<FormatRegistry>) {
+    FORMAT_REGISTRY.with(|current| *current.borrow_mut() = registry.unwrap_or_default());
+}
+
+/// Installs an environment, whose prefix and suffix are then added to the names given to
+/// `get`, `set`, `delete` and the other functions operating on one cookie, or removes the
+/// installed one if `None` (see [Environment](environment/struct.Environment.html)).
+pub fn set_environment(environment: Option<Environment>) {
+    ENVIRONMENT.with(|current| *current.borrow_mut() = environment.unwrap_or_default());
+}
+
+/// Installs a privacy guard, which is then applied by `set` to the cookies whose consent
+/// category (given by the installed policy) is guarded, or removes the installed one if `None`.
+///
+/// `callback` is called with the name of the cookie and the guard's action each time the guard
+/// blocks or downgrades a write, so the application can react.
+pub fn set_privacy_guard(
+    guard: Option<PrivacyGuard>,
+    callback: impl Fn(&str, GuardAction) + 'static,
+) {
+    PRIVACY_GUARD.with(|current| {
+        *current.borrow_mut() = guard.map(|guard| (guard, Box::new(callback) as GuardCallback))
+    });
+}
+
+/// Grants consent for a consent category of the installed policy.
+pub fn grant_consent(category: &str) {
+    CONSENTED_CATEGORIES.with(|categories| {
+        let mut categories = categories.borrow_mut();
+
+        if !categories.iter().any(|consented| consented == category) {
+            categories.push(category.to_owned());
+        }
+    });
+}
+
+/// Revokes consent for a consent category of the installed policy.
+pub fn revoke_consent(category: &str) {
+    CONSENTED_CATEGORIES.with(|categories| {
+        categories
+            .borrow_mut()
+            .retain(|consented| consented != category)
+    });
 }
 
 /// Returns all cookies, with undecoded keys and values.
 ///
-/// Available only on `wasm32-unknown-unknown` target.
-#[cfg(target_arch = "wasm32")]
+/// Not available with the `small` feature.
+#[cfg(not(feature = "small"))]
 pub fn all_raw() -> HashMap<String, String> {
     cookies::all_raw(&cookie_string())
 }
@@ -41,59 +553,863 @@ pub fn all_raw() -> HashMap<String, String> {
 /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
 /// or an error if URI decoding fails on a key or a value.
 ///
-/// Available only on `wasm32-unknown-unknown` target.
-#[cfg(target_arch = "wasm32")]
+/// Not available with the `small` feature.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
 pub fn all() -> Result<HashMap<String, String>, AllDecodeError> {
     cookies::all(&cookie_string())
 }
 
-/// Returns undecoded cookie if it exists.
+/// Collects all cookies, with undecoded keys and values, into any collection of pairs of any
+/// string types (see [cookies::all_raw_into](cookies/fn.all_raw_into.html)).
+pub fn all_raw_into<M, K, V>() -> M
+where
+    K: for<'a> From<&'a str>,
+    V: for<'a> From<&'a str>,
+    M: FromIterator<(K, V)>,
+{
+    cookies::all_raw_into(&cookie_string())
+}
+
+/// Collects all cookies, with URI decoded keys and values, into any collection of pairs of any
+/// string types (see [cookies::all_into](cookies/fn.all_into.html)), or returns an error if URI
+/// decoding fails on a key or a value.
+#[cfg(feature = "encoding")]
+pub fn all_into<M, K, V>() -> Result<M, AllDecodeError>
+where
+    K: for<'a> From<&'a str>,
+    V: for<'a> From<&'a str>,
+    M: FromIterator<(K, V)>,
+{
+    cookies::all_into(&cookie_string())
+}
+
+/// Returns all cookies, with undecoded keys and values, keeping the cookie chosen by
+/// `duplicates` when several cookies have the same name.
 ///
-/// Available only on `wasm32-unknown-unknown` target.
-#[cfg(target_arch = "wasm32")]
+/// Not available with the `small` feature.
+#[cfg(not(feature = "small"))]
+pub fn all_raw_with_duplicates(duplicates: Duplicates) -> HashMap<String, String> {
+    cookies::all_raw_with_duplicates(&cookie_string(), duplicates)
+}
+
+/// Returns all cookies, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+/// or an error if URI decoding fails on a key or a value, keeping the cookie chosen by
+/// `duplicates` when several cookies have the same name.
+///
+/// Not available with the `small` feature.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all_with_duplicates(
+    duplicates: Duplicates,
+) -> Result<HashMap<String, String>, AllDecodeError> {
+    cookies::all_with_duplicates(&cookie_string(), duplicates)
+}
+
+/// Returns all cookies whose key and value are successfully URI decoded
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+/// and the decoding errors of the others, so a malformed cookie doesn't hide the valid ones.
+///
+/// Not available with the `small` feature.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all_with_errors() -> (HashMap<String, String>, Vec<AllDecodeError>) {
+    cookies::all_with_errors(&cookie_string())
+}
+
+/// Returns all cookies, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)). The bytes that aren't
+/// valid UTF-8 once decoded are replaced with `U+FFFD`, so malformed cookies can't make it fail.
+///
+/// Not available with the `small` feature.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all_lossy_utf8() -> HashMap<String, String> {
+    cookies::all_lossy_utf8(&cookie_string())
+}
+
+/// Returns all cookies, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)). The keys and values that
+/// aren't valid UTF-8 once decoded are reinterpreted as ISO-8859-1 (Latin-1), so a cookie written
+/// by a legacy backend can't make it fail.
+///
+/// Not available with the `small` feature.
+#[cfg(all(feature = "encoding", not(feature = "small")))]
+pub fn all_latin1_fallback() -> HashMap<String, String> {
+    cookies::all_latin1_fallback(&cookie_string())
+}
+
+/// Returns all cookies sorted by name, with undecoded keys and values.
+pub fn all_sorted_raw() -> BTreeMap<String, String> {
+    cookies::all_sorted_raw(&cookie_string())
+}
+
+/// Returns all cookies sorted by name, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+/// or an error if URI decoding fails on a key or a value.
+#[cfg(feature = "encoding")]
+pub fn all_sorted() -> Result<BTreeMap<String, String>, AllDecodeError> {
+    cookies::all_sorted(&cookie_string())
+}
+
+/// Returns all cookies in the order the browser supplies them, with undecoded keys and values.
+/// Cookies with the same name are all kept, the most specific (longest path) first.
+pub fn all_ordered_raw() -> Vec<(String, String)> {
+    cookies::all_ordered_raw(&cookie_string())
+}
+
+/// Returns all cookies in the order the browser supplies them, with URI decoded keys and values
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+/// or an error if URI decoding fails on a key or a value.
+/// Cookies with the same name are all kept, the most specific (longest path) first.
+#[cfg(feature = "encoding")]
+pub fn all_ordered() -> Result<Vec<(String, String)>, AllDecodeError> {
+    cookies::all_ordered(&cookie_string())
+}
+
+/// Returns the recorded cookie writes, oldest first, with the source location of the call to
+/// the root function that made each one (see [provenance](provenance/index.html)).
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`), with the `debug-provenance`
+/// feature.
+#[cfg(all(target_family = "wasm", feature = "debug-provenance"))]
+pub fn debug_log() -> Vec<provenance::WriteRecord> {
+    provenance::browser::records()
+}
+
+/// Returns the names of all cookies, undecoded, without copying the values (see
+/// [cookies::all_names_raw](cookies/fn.all_names_raw.html)).
+pub fn all_names_raw() -> Vec<String> {
+    cookies::all_names_raw(&cookie_string())
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Returns the names of all cookies, URI decoded
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)), without decoding the
+/// values, or an error if URI decoding fails on a name.
+#[cfg(feature = "encoding")]
+pub fn names() -> Result<Vec<String>, AllDecodeError> {
+    cookies::names(&cookie_string())
+}
+
+/// Groups the cookies by the prefix of their names, up to the first `separator`, sorted by
+/// decreasing size (see [cookies::namespaces](cookies/fn.namespaces.html)).
+pub fn namespaces(separator: &str) -> Vec<Namespace> {
+    cookies::namespaces(&cookie_string(), separator)
+}
+
+/// Returns undecoded cookie if it exists.
 pub fn get_raw(name: &str) -> Option<String> {
-    cookies::get_raw(&cookie_string(), name)
+    cookies::get_raw(&cookie_string(), &environment_name(&middleware_name(name)))
+}
+
+/// Returns undecoded cookie if it exists, choosing the cookie with `duplicates` when several
+/// cookies have the same name.
+pub fn get_raw_with_duplicates(name: &str, duplicates: Duplicates) -> Option<String> {
+    let name = environment_name(&middleware_name(name));
+    cookies::get_raw_with_duplicates(&cookie_string(), &name, duplicates)
+}
+
+/// Same as [get](fn.get.html), choosing the cookie with `duplicates` when several cookies have
+/// the same name.
+#[cfg(feature = "encoding")]
+pub fn get_with_duplicates(
+    name: &str,
+    duplicates: Duplicates,
+) -> Option<Result<String, GetDecodeError>> {
+    let name = middleware_name(name);
+
+    match cookies::get_with_duplicates(&cookie_string(), &policy_name(&name), duplicates)? {
+        Ok(value) => middleware_get(&name, value).map(Ok),
+        Err(error) => Some(Err(error)),
+    }
 }
 
 /// If it exists, returns URI decoded cookie
-/// (with the [urlencoding crate](https://crates.io/crates/urlencoding))
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding), or the encoding set with
+/// [set_encoding](fn.set_encoding.html))
 /// or an error if the value's URI decoding fails.
 ///
-/// Available only on `wasm32-unknown-unknown` target.
-#[cfg(target_arch = "wasm32")]
-pub fn get(name: &str) -> Option<Result<String, FromUrlEncodingError>> {
-    cookies::get(&cookie_string(), name)
+/// If middleware are registered (see [register_middleware](fn.register_middleware.html)), they
+/// are applied to the name and to the decoded value.
+///
+/// If an auto-touch table is installed (see [set_auto_touch](fn.set_auto_touch.html)) and the
+/// cookie matches one of its routes, the cookie is set again with a refreshed expiration date.
+#[cfg(feature = "encoding")]
+pub fn get(name: &str) -> Option<Result<String, GetDecodeError>> {
+    get_with(name, encoding())
+}
+
+/// Same as [get](fn.get.html), with the given URI encoding.
+#[cfg(feature = "encoding")]
+pub fn get_with(name: &str, encoding: Encoding) -> Option<Result<String, GetDecodeError>> {
+    get_in(&cookie_string(), name, encoding)
+}
+
+#[cfg(feature = "encoding")]
+fn get_in(
+    cookie_string: &str,
+    name: &str,
+    encoding: Encoding,
+) -> Option<Result<String, GetDecodeError>> {
+    MIDDLEWARE.with(|chain| {
+        let chain = chain.borrow();
+        let name = chain.name(name);
+        let stored_name = policy_name(&name);
+        let value = cookies::get_with(cookie_string, &stored_name, encoding)?;
+        auto_touch(cookie_string, &name, &encoding.encode(&stored_name));
+
+        match value {
+            Ok(value) => chain.get(&name, value).map(Ok),
+            Err(error) => Some(Err(error)),
+        }
+    })
+}
+
+/// Sets the cookie `name`, stored as `raw_name`, again with a refreshed expiration date if the
+/// installed auto-touch table requires it. Failed writes are ignored, since reading the cookie
+/// succeeded.
+#[cfg(feature = "encoding")]
+fn auto_touch(cookie_string: &str, name: &str, raw_name: &str) {
+    let touch = AUTO_TOUCH.with(|auto_touch| {
+        auto_touch.borrow_mut().as_mut()?.touch(
+            cookie_string,
+            name,
+            raw_name,
+            cookies::now_timestamp(),
+        )
+    });
+
+    if let Some(touch) = touch {
+        let _ = try_set_cookie_string(&touch);
+    }
 }
 
-/// Sets a cookie, with non encoded name and value.
+/// Same as [get](fn.get.html), but returns an error instead of panicking if the document's
+/// cookies can't be read.
+#[cfg(feature = "encoding")]
+pub fn try_get(name: &str) -> Result<Option<String>, WasmCookiesError> {
+    try_get_with(name, encoding())
+}
+
+/// Same as [try_get](fn.try_get.html), with the given URI encoding.
+#[cfg(feature = "encoding")]
+pub fn try_get_with(name: &str, encoding: Encoding) -> Result<Option<String>, WasmCookiesError> {
+    Ok(get_in(&try_cookie_string()?, name, encoding).transpose()?)
+}
+
+/// Returns URI decoded cookie if it exists
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)). The bytes of the value
+/// that aren't valid UTF-8 once decoded are replaced with `U+FFFD`, instead of failing.
+#[cfg(feature = "encoding")]
+pub fn get_lossy(name: &str) -> Option<String> {
+    let name = middleware_name(name);
+    let value = cookies::get_lossy(&cookie_string(), &policy_name(&name))?;
+    middleware_get(&name, value)
+}
+
+/// Returns URI decoded cookie if it exists
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)). If the decoded value
+/// isn't valid UTF-8, it is reinterpreted as ISO-8859-1 (Latin-1), as written by some legacy
+/// backends, instead of failing.
+#[cfg(feature = "encoding")]
+pub fn get_latin1_fallback(name: &str) -> Option<String> {
+    let name = middleware_name(name);
+    let value = cookies::get_latin1_fallback(&cookie_string(), &policy_name(&name))?;
+    middleware_get(&name, value)
+}
+
+/// If it exists, returns the cookie deserialized to `T` from the format given by the installed
+/// format registry (see [set_format_registry](fn.set_format_registry.html)), or an error if no
+/// format is registered for `name` or if the decoding or the deserialization fails.
+///
+/// The cookie is read with [get](fn.get.html), so the policy and the middleware are applied.
+///
+/// Available only with the `json` or `form` feature.
+#[cfg(any(feature = "json", feature = "form"))]
+pub fn get_typed<T: serde::de::DeserializeOwned>(name: &str) -> Option<Result<T, TypedError>> {
+    let serialized = get(name)?;
+    Some(FORMAT_REGISTRY.with(|registry| registry.borrow().deserialize(name, serialized)))
+}
+
+/// Same as [get](fn.get.html), with the result and the option swapped so the `?` operator
+/// can be used on the decoding error: returns `Ok(None)` if the cookie doesn't exist.
+#[cfg(feature = "encoding")]
+pub fn get2(name: &str) -> Result<Option<String>, GetDecodeError> {
+    get(name).transpose()
+}
+
+/// If it exists, returns the cookie whose value is a form (`a=1&b=2`) deserialized to `T`
+/// (with the [serde_urlencoded crate](https://crates.io/crates/serde_urlencoded)),
+/// or an error if the deserialization fails.
+///
+/// Available only with the `form` feature.
+#[cfg(feature = "form")]
+pub fn get_form<T: serde::de::DeserializeOwned>(
+    name: &str,
+) -> Option<Result<T, serde_urlencoded::de::Error>> {
+    let name = middleware_name(name);
+    let value = cookies::get_raw(&cookie_string(), &urlencoding::encode(&policy_name(&name)))?;
+    middleware_get(&name, value).map(|value| serde_urlencoded::from_str(&value))
+}
+
+/// Sets a cookie, with non encoded name and value. The characters that would add attributes
+/// (`;`, CR and LF) are percent-encoded (see [cookies::set_raw](cookies/fn.set_raw.html)).
+///
+/// Commas pass through, since they are harmless in `document.cookie`. To build `Set-Cookie`
+/// header fields, which may be folded at commas, use
+/// [cookies::set_raw_with_injection](cookies/fn.set_raw_with_injection.html) instead.
 ///
-/// Available only on `wasm32-unknown-unknown` target.
-#[cfg(target_arch = "wasm32")]
+/// If a policy is installed (see [set_policy](fn.set_policy.html)), the cookie isn't set if its
+/// name isn't allowed or if consent hasn't been granted for its category (use
+/// [try_set_raw](fn.try_set_raw.html) to get the violation). The policy's prefix isn't prepended
+/// to non encoded names.
+#[cfg_attr(feature = "debug-provenance", track_caller)]
 pub fn set_raw(name: &str, value: &str, options: &CookieOptions) {
-    set_cookie_string(&cookies::set_raw(name, value, options));
+    let _ = set_raw_checked(name, value, options, false);
+}
+
+/// Same as [set_raw](fn.set_raw.html), but returns an error instead of silently not setting the
+/// cookie if the installed policy rejects it, if browsers would reject it (see
+/// [cookies::validate_set_raw](cookies/fn.validate_set_raw.html)), or if the document's cookies
+/// can't be written.
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn try_set_raw(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+) -> Result<(), WasmCookiesError> {
+    set_raw_checked(name, value, options, true)
+}
+
+/// Sets a cookie with non encoded name and value if the installed policy allows it. If `checked`
+/// is true, the cookie is validated and the write errors are returned instead of panicking.
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+fn set_raw_checked(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    checked: bool,
+) -> Result<(), WasmCookiesError> {
+    let name = middleware_name(name);
+    check_policy(&name, false)?;
+    let name = environment_name(&name);
+
+    if checked {
+        cookies::validate_set_raw(&name, value, options)?;
+        try_set_cookie_string(&cookies::set_raw(&name, value, options))
+    } else {
+        set_cookie_string(&cookies::set_raw(&name, value, options));
+        Ok(())
+    }
 }
 
 /// Sets a cookie, with URI encoded name and value
-/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)).
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding), or the encoding set with
+/// [set_encoding](fn.set_encoding.html)).
 ///
-/// Available only on `wasm32-unknown-unknown` target.
-#[cfg(target_arch = "wasm32")]
+/// Every encoding percent-encodes `;`, `,`, CR and LF, so the name and the value can't add
+/// attributes. Unlike [set_raw](fn.set_raw.html), which lets commas pass through, the result is
+/// therefore safe in `Set-Cookie` header fields too.
+///
+/// If a policy is installed (see [set_policy](fn.set_policy.html)), it is applied: the name is
+/// prefixed, the unset options are filled from the matching rule, and the cookie isn't set if
+/// consent hasn't been granted for its category or if its name isn't allowed (use
+/// [try_set](fn.try_set.html) to get the violation).
+///
+/// If middleware are registered (see [register_middleware](fn.register_middleware.html)), they
+/// are applied first, and can rewrite or cancel the write.
+///
+/// If a privacy guard is installed (see [set_privacy_guard](fn.set_privacy_guard.html)) and the
+/// user has opted out, the write is blocked or downgraded if the cookie's category is guarded.
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
 pub fn set(name: &str, value: &str, options: &CookieOptions) {
-    set_cookie_string(&cookies::set(name, value, options));
+    set_with(name, value, options, encoding());
+}
+
+/// Same as [set](fn.set.html), with the given URI encoding.
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn set_with(name: &str, value: &str, options: &CookieOptions, encoding: Encoding) {
+    let _ = set_checked(name, value, options, encoding, false);
+}
+
+/// Same as [set](fn.set.html), but returns an error instead of silently not setting the cookie
+/// if the installed policy rejects it (for example because its name is blocked or not allowed),
+/// if browsers would reject it (see [cookies::validate_set_raw](cookies/fn.validate_set_raw.html)),
+/// or if the document's cookies can't be written.
+///
+/// Writes cancelled by a middleware or blocked by the privacy guard aren't errors.
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn try_set(name: &str, value: &str, options: &CookieOptions) -> Result<(), WasmCookiesError> {
+    try_set_with(name, value, options, encoding())
+}
+
+/// Same as [try_set](fn.try_set.html), with the given URI encoding.
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn try_set_with(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    encoding: Encoding,
+) -> Result<(), WasmCookiesError> {
+    set_checked(name, value, options, encoding, true)
+}
+
+/// Sets a cookie with the policy, the middleware and the privacy guard applied. If `checked` is
+/// true, the cookie is validated and the write errors are returned instead of panicking.
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+fn set_checked(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+    encoding: Encoding,
+    checked: bool,
+) -> Result<(), WasmCookiesError> {
+    let cookie = match MIDDLEWARE.with(|chain| chain.borrow().set(name, value, options)) {
+        Some(cookie) => cookie,
+        None => return Ok(()),
+    };
+
+    let (name, value, options) = (&*cookie.name, &*cookie.value, &cookie.options);
+
+    // The cookie string is written outside of the closure, so the caller's location is tracked.
+    let cookie_string = POLICY.with(|policy| -> Result<_, WasmCookiesError> {
+        let policy = policy.borrow();
+
+        let category = policy
+            .as_ref()
+            .and_then(|policy| policy.rule(name))
+            .and_then(|rule| rule.category.as_deref());
+
+        let options = PRIVACY_GUARD.with(|guard| match &*guard.borrow() {
+            Some((guard, callback)) => {
+                // There are no privacy signals outside of a browser.
+                #[cfg(target_family = "wasm")]
+                let signals = PrivacySignals::detect();
+                #[cfg(not(target_family = "wasm"))]
+                let signals = PrivacySignals::default();
+
+                let (action, options) = guard.check(&signals, category, options);
+
+                if action != GuardAction::Allowed {
+                    callback(name, action);
+                }
+
+                options
+            }
+
+            None => Some(options.clone()),
+        });
+
+        let options = match options {
+            Some(options) => options,
+            None => return Ok(None),
+        };
+
+        let (name, options) = match &*policy {
+            Some(policy) => CONSENTED_CATEGORIES
+                .with(|categories| policy.apply(name, &options, &categories.borrow()))?,
+            None => (name.to_owned(), options),
+        };
+        let name = environment_name(&name);
+
+        if checked {
+            cookies::validate_set_raw(&encoding.encode(&name), &encoding.encode(value), &options)?;
+        }
+
+        Ok(Some(cookies::set_with(&name, value, &options, encoding)))
+    })?;
+
+    match cookie_string {
+        Some(cookie_string) if checked => try_set_cookie_string(&cookie_string),
+        Some(cookie_string) => {
+            set_cookie_string(&cookie_string);
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Sets a cookie whose value is `value` serialized in the format given by the installed format
+/// registry (see [set_format_registry](fn.set_format_registry.html)), or returns an error if no
+/// format is registered for `name` or if the serialization fails.
+///
+/// The cookie is written with [set](fn.set.html), so the policy and the middleware are applied.
+///
+/// Available only with the `json` or `form` feature.
+#[cfg(any(feature = "json", feature = "form"))]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn set_typed<T: serde::Serialize>(
+    name: &str,
+    value: &T,
+    options: &CookieOptions,
+) -> Result<(), TypedError> {
+    let format = FORMAT_REGISTRY.with(|registry| registry.borrow().format(name))?;
+    set(name, &format.serialize(value)?, options);
+    Ok(())
+}
+
+/// Sets a cookie whose value is `value` serialized as a form (`a=1&b=2`, with the
+/// [serde_urlencoded crate](https://crates.io/crates/serde_urlencoded)), with URI encoded name,
+/// or returns an error if `value` can't be serialized as a form.
+///
+/// The installed policy is applied as by [set](fn.set.html): the cookie isn't set if the policy
+/// rejects it.
+///
+/// Available only with the `form` feature.
+#[cfg(feature = "form")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn set_form<T: serde::Serialize>(
+    name: &str,
+    value: &T,
+    options: &CookieOptions,
+) -> Result<(), serde_urlencoded::ser::Error> {
+    let form = serde_urlencoded::to_string(value)?;
+    let cookie = match MIDDLEWARE.with(|chain| chain.borrow().set(name, &form, options)) {
+        Some(cookie) => cookie,
+        None => return Ok(()),
+    };
+
+    if let Ok((name, options)) = apply_policy(&cookie.name, &cookie.options) {
+        set_cookie_string(&cookies::set_raw(
+            &urlencoding::encode(&name),
+            &cookie.value,
+            &options,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Sets a cookie, with non encoded name and value, and returns its previous undecoded value,
+/// if any.
+///
+/// While the writes are deferred (see [deferred](deferred/index.html)), the previous value
+/// isn't known, so the write is queued and `None` is returned.
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn replace_raw(name: &str, value: &str, options: &CookieOptions) -> Option<String> {
+    let previous = get_raw(name).filter(|_| !writes_deferred());
+    set_raw(name, value, options);
+    previous
+}
+
+/// Sets a cookie like [set](fn.set.html), and returns its previous URI decoded value, if any.
+/// A previous value whose URI decoding fails is returned as `None`.
+///
+/// While the writes are deferred (see [deferred](deferred/index.html)), the previous value
+/// isn't known, so the write is queued and `None` is returned.
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn replace(name: &str, value: &str, options: &CookieOptions) -> Option<String> {
+    let previous = get(name)
+        .and_then(Result::ok)
+        .filter(|_| !writes_deferred());
+    set(name, value, options);
+    previous
+}
+
+/// Adds `by` to the integer value of a cookie, without encoding its name, and returns the new
+/// value. The addition saturates, and a missing or corrupted value counts as 0.
+///
+/// If the installed policy doesn't allow writing the cookie (see
+/// [set_raw](fn.set_raw.html)), it isn't written and its current value is returned.
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn increment_raw(name: &str, by: i64, options: &CookieOptions) -> i64 {
+    let name = middleware_name(name);
+
+    if check_policy(&name, false).is_err() {
+        return cookies::increment_raw(&cookie_string(), &environment_name(&name), 0, options).0;
+    }
+
+    let (value, cookie_string) =
+        cookies::increment_raw(&cookie_string(), &environment_name(&name), by, options);
+    set_cookie_string(&cookie_string);
+    value
+}
+
+/// Adds `by` to the integer value of a cookie, URI encoding its name, and returns the new
+/// value. The addition saturates, and a missing or corrupted value counts as 0.
+///
+/// The installed policy is applied as by [set](fn.set.html). If it rejects the cookie, the
+/// cookie isn't written and its current value is returned (use
+/// [try_increment](fn.try_increment.html) to get the violation).
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn increment(name: &str, by: i64, options: &CookieOptions) -> i64 {
+    let name = middleware_name(name);
+
+    match apply_policy(&name, options) {
+        Ok((name, options)) => {
+            let (value, cookie_string) = cookies::increment(&cookie_string(), &name, by, &options);
+            set_cookie_string(&cookie_string);
+            value
+        }
+        Err(_) => cookies::increment(&cookie_string(), &policy_name(&name), 0, options).0,
+    }
+}
+
+/// Same as [increment](fn.increment.html), but returns an error instead of not writing the
+/// cookie if the installed policy rejects it, or if the document's cookies can't be read or
+/// written.
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn try_increment(
+    name: &str,
+    by: i64,
+    options: &CookieOptions,
+) -> Result<i64, WasmCookiesError> {
+    let (name, options) = apply_policy(&middleware_name(name), options)?;
+    let (value, cookie_string) = cookies::increment(&try_cookie_string()?, &name, by, &options);
+    try_set_cookie_string(&cookie_string)?;
+    Ok(value)
+}
+
+/// Subtracts `by` from the integer value of a cookie, without encoding its name, and returns
+/// the new value (see [increment_raw](fn.increment_raw.html)).
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn decrement_raw(name: &str, by: i64, options: &CookieOptions) -> i64 {
+    increment_raw(name, by.saturating_neg(), options)
+}
+
+/// Subtracts `by` from the integer value of a cookie, URI encoding its name, and returns the
+/// new value (see [increment](fn.increment.html)).
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn decrement(name: &str, by: i64, options: &CookieOptions) -> i64 {
+    increment(name, by.saturating_neg(), options)
+}
+
+/// If it exists, returns the undecoded value of a cookie and deletes it, without encoding its
+/// name. Pass the `options` the cookie was set with to delete a cookie scoped to a path or a
+/// domain.
+///
+/// Returns `None` without deleting the cookie if the installed policy doesn't allow its name,
+/// or while the writes are deferred (see [deferred](deferred/index.html)).
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn take_raw(name: &str, options: Option<&CookieOptions>) -> Option<String> {
+    let name = middleware_name(name);
+    check_policy(&name, true).ok()?;
+
+    if writes_deferred() {
+        return None;
+    }
+
+    let (value, deletion) = cookies::take_raw(&cookie_string(), &environment_name(&name), options)?;
+    set_cookie_string(&deletion);
+    Some(value)
+}
+
+/// If it exists, returns the URI decoded value of a cookie
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding)), or an error if the
+/// value's URI decoding fails, and deletes it in both cases. Pass the `options` the cookie was
+/// set with to delete a cookie scoped to a path or a domain.
+///
+/// Returns `None` without deleting the cookie if the installed policy doesn't allow its name,
+/// or while the writes are deferred (see [deferred](deferred/index.html)).
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn take(name: &str, options: Option<&CookieOptions>) -> Option<Result<String, GetDecodeError>> {
+    let name = middleware_name(name);
+    check_policy(&name, true).ok()?;
+
+    if writes_deferred() {
+        return None;
+    }
+
+    let options = deletion_options(&name, options);
+    let (value, deletion) = cookies::take(&cookie_string(), &policy_name(&name), options.as_ref())?;
+    set_cookie_string(&deletion);
+
+    match value {
+        Ok(value) => middleware_get(&name, value).map(Ok),
+        Err(error) => Some(Err(error)),
+    }
+}
+
+/// Returns the path the browser applies to a cookie set without path
+/// (`CookieOptions::path` is `None`): the directory of the current document location
+/// (see [cookies::default_path](cookies/fn.default_path.html)).
+pub fn current_path() -> String {
+    cookies::default_path(&pathname()).to_owned()
+}
+
+/// Returns the domain the browser applies to a cookie set without domain
+/// (`CookieOptions::domain` is `None`): the host of the current document location.
+/// Such a cookie is only sent to this exact host, not to its subdomains.
+pub fn current_domain() -> String {
+    host()
+}
+
+/// Checks, without setting it, if a cookie with non encoded name and value would likely be
+/// rejected by the browser, and returns all the reasons why.
+pub fn validate_set_raw(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+) -> Result<(), Vec<RejectionReason>> {
+    cookies::validate_set_raw_in(name, value, options, &host(), is_secure_context())
+}
+
+/// Checks, without setting it, if a cookie with URI encoded name and value
+/// (with the [urlencoding crate](https://crates.io/crates/urlencoding))
+/// would likely be rejected by the browser, and returns all the reasons why.
+#[cfg(feature = "encoding")]
+pub fn validate_set(
+    name: &str,
+    value: &str,
+    options: &CookieOptions,
+) -> Result<(), Vec<RejectionReason>> {
+    cookies::validate_set_in(name, value, options, &host(), is_secure_context())
+}
+
+/// Captures the state of all accessible cookies, to be restored later with
+/// [restore](fn.restore.html).
+pub fn snapshot() -> CookieSnapshot {
+    cookies::snapshot(&cookie_string())
+}
+
+/// Brings the cookies back to the state of `snapshot`: cookies that changed or disappeared are
+/// set again, and cookies that didn't exist are deleted.
+///
+/// The attributes of the cookies aren't accessible, so restoring is best effort: cookies are set
+/// again with `CookieOptions::default()`. Use [cookies::restore](cookies/fn.restore.html) to
+/// provide other options.
+///
+/// The cookies the installed policy doesn't allow writing are left as they are.
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn restore(snapshot: &CookieSnapshot) {
+    for cookie_string in cookies::restore(&cookie_string(), snapshot, &CookieOptions::default()) {
+        let allowed = Cookie::parse_set_cookie(&cookie_string).is_some_and(|cookie| {
+            check_policy(
+                &cookie.name,
+                !snapshot.cookies().contains_key(&*cookie.name),
+            )
+            .is_ok()
+        });
+
+        if allowed {
+            set_cookie_string(&cookie_string);
+        }
+    }
+}
+
+/// Runs a transaction: `f` stages writes, which are applied if it returns `Ok`.
+/// If the browser rejects one of the writes, the already applied ones are rolled back
+/// (see [transaction::run](transaction/fn.run.html)).
+///
+/// If the installed policy doesn't allow one of the staged writes, nothing is written and
+/// `TransactionError::Policy` is returned. While the writes are deferred (see
+/// [deferred](deferred/index.html)), they can't be checked, so nothing is written and
+/// `TransactionError::Deferred` is returned.
+///
+/// ```no_run
+/// # use wasm_cookies::CookieOptions;
+/// let options = CookieOptions::default();
+///
+/// wasm_cookies::transaction(|tx| -> Result<(), ()> {
+///     tx.set_raw("token", "value", &options);
+///     tx.set_raw("refresh_marker", "1", &options);
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn transaction<'a, E>(
+    f: impl FnOnce(&mut Transaction<'a>) -> Result<(), E>,
+) -> Result<(), TransactionError<E>> {
+    if writes_deferred() {
+        return Err(TransactionError::Deferred);
+    }
+
+    transaction::run_checked(cookie_string, set_cookie_string, check_policy, f)
 }
 
 /// Deletes a cookie without encoding its name.
 ///
-/// Available only on `wasm32-unknown-unknown` target.
-#[cfg(target_arch = "wasm32")]
+/// The cookie isn't deleted if the installed policy doesn't allow its name.
+#[cfg_attr(feature = "debug-provenance", track_caller)]
 pub fn delete_raw(name: &str) {
-    set_cookie_string(&cookies::delete_raw(name));
+    let name = middleware_name(name);
+
+    if check_policy(&name, true).is_err() {
+        return;
+    }
+
+    set_cookie_string(&cookies::delete_raw(&environment_name(&name)));
 }
 
-/// Deletes a cookie, URI encoding its name.
+/// Deletes a cookie, URI encoding its name
+/// (with the encoding set with [set_encoding](fn.set_encoding.html)).
 ///
-/// Available only on `wasm32-unknown-unknown` target.
-#[cfg(target_arch = "wasm32")]
+/// If a policy is installed (see [set_policy](fn.set_policy.html)), the name is prefixed and the
+/// deletion has the path and the domain of the matching rule, so it deletes the cookie written
+/// by [set](fn.set.html).
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
 pub fn delete(name: &str) {
-    set_cookie_string(&cookies::delete(name));
+    delete_with(name, encoding());
+}
+
+/// Same as [delete](fn.delete.html), with the given URI encoding.
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn delete_with(name: &str, encoding: Encoding) {
+    let name = middleware_name(name);
+
+    if check_policy(&name, true).is_err() {
+        return;
+    }
+
+    set_cookie_string(&cookies::scoped_delete_raw(
+        &encoding.encode(&policy_name(&name)),
+        deletion_options(&name, None).as_ref(),
+    ));
+}
+
+/// Deletes a cookie without encoding its name, if it exists, and returns true if it existed.
+/// Returns false without deleting the cookie if the installed policy doesn't allow its name,
+/// or while the writes are deferred (see [deferred](deferred/index.html)).
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn delete_existing_raw(name: &str) -> bool {
+    let name = middleware_name(name);
+
+    if check_policy(&name, true).is_err() || writes_deferred() {
+        return false;
+    }
+
+    match cookies::delete_existing_raw(&cookie_string(), &environment_name(&name)) {
+        Some(deletion) => {
+            set_cookie_string(&deletion);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Deletes a cookie, URI encoding its name, if it exists, and returns true if it existed.
+/// Returns false without deleting the cookie if the installed policy doesn't allow its name,
+/// or while the writes are deferred (see [deferred](deferred/index.html)).
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "debug-provenance", track_caller)]
+pub fn delete_existing(name: &str) -> bool {
+    let name = middleware_name(name);
+
+    if check_policy(&name, true).is_err() || writes_deferred() {
+        return false;
+    }
+
+    let stored_name = urlencoding::encode(&policy_name(&name));
+
+    if cookies::get_raw(&cookie_string(), &stored_name).is_none() {
+        return false;
+    }
+
+    set_cookie_string(&cookies::scoped_delete_raw(
+        &stored_name,
+        deletion_options(&name, None).as_ref(),
+    ));
+    true
 }
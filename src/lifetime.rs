@@ -0,0 +1,168 @@
+//! Detection of browsers capping the lifetime of cookies set by scripts, such as Safari's
+//! Intelligent Tracking Prevention, which caps them to 7 days (or 24 hours in some cases).
+//!
+//! A probe cookie is set with a long expiration date, and its actual expiration date is read
+//! back with the [Cookie Store API](https://developer.mozilla.org/en-US/docs/Web/API/Cookie_Store_API).
+//! When the lifetime is capped, cookies that must persist should be set by the server instead.
+
+use crate::cookies;
+use std::time::Duration;
+
+/// Name of the probe cookie.
+pub const PROBE_COOKIE_NAME: &str = "wasm_cookies_lifetime_probe";
+
+/// Margin under which a shorter expiration date isn't considered as capped, to absorb the
+/// rounding of expiration dates to the second and the time taken by the check.
+pub const TOLERANCE: Duration = Duration::from_secs(60);
+
+/// The lifetime the browser actually grants to cookies set by scripts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LifetimeCapping {
+    /// The requested lifetime is granted.
+    Uncapped,
+
+    /// The lifetime is capped to the given duration.
+    Capped(Duration),
+
+    /// The expiration date is removed: cookies only last for the browser session.
+    Session,
+}
+
+/// Error returned by the check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LifetimeCheckError {
+    /// The Cookie Store API isn't available, so expiration dates can't be read.
+    Unsupported,
+
+    /// The probe cookie wasn't stored.
+    ProbeRejected,
+}
+
+/// Evaluates the capping from the lifetime `requested` for a cookie set at `set_at`, and its
+/// actual expiration timestamp (`None` for a session cookie). Timestamps are in milliseconds.
+pub fn evaluate(requested: Duration, set_at: i64, actual_expires: Option<i64>) -> LifetimeCapping {
+    let actual_expires = match actual_expires {
+        Some(actual_expires) => actual_expires,
+        None => return LifetimeCapping::Session,
+    };
+
+    let requested_expires = set_at.saturating_add(cookies::duration_millis(requested));
+
+    if actual_expires.saturating_add(cookies::duration_millis(TOLERANCE)) >= requested_expires {
+        LifetimeCapping::Uncapped
+    } else {
+        LifetimeCapping::Capped(Duration::from_millis(
+            actual_expires.saturating_sub(set_at).max(0) as u64,
+        ))
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{LifetimeCapping, LifetimeCheckError, PROBE_COOKIE_NAME};
+    use crate::cookies::{self, now_timestamp, CookieOptions};
+    use js_sys::{Function, Reflect};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+    type Callback = Box<dyn FnOnce(Result<LifetimeCapping, LifetimeCheckError>)>;
+
+    /// Checks if the browser caps the lifetime of cookies set by scripts to less than
+    /// `requested` (for example 400 days), and passes the result to `callback`. The probe cookie
+    /// is deleted afterwards.
+    pub fn check(
+        requested: Duration,
+        callback: impl FnOnce(Result<LifetimeCapping, LifetimeCheckError>) + 'static,
+    ) {
+        let window = web_sys::window().unwrap();
+        let store = Reflect::get(&window, &"cookieStore".into()).unwrap_or(JsValue::UNDEFINED);
+
+        if store.is_undefined() || store.is_null() {
+            callback(Err(LifetimeCheckError::Unsupported));
+            return;
+        }
+
+        let set_at = now_timestamp();
+        let options = CookieOptions::ROOT.expires_after(requested);
+        crate::set_cookie_string(&cookies::set_raw(PROBE_COOKIE_NAME, "1", &options));
+
+        let callback = Rc::new(RefCell::new(Some(Box::new(callback) as Callback)));
+        let reject_callback = Rc::clone(&callback);
+
+        let on_resolve = Closure::once_into_js(move |cookie: JsValue| {
+            crate::set_cookie_string(&cookies::delete_raw(PROBE_COOKIE_NAME));
+
+            let result = if cookie.is_null() || cookie.is_undefined() {
+                Err(LifetimeCheckError::ProbeRejected)
+            } else {
+                let expires = Reflect::get(&cookie, &"expires".into())
+                    .ok()
+                    .and_then(|expires| expires.as_f64())
+                    .map(|expires| expires as i64);
+
+                Ok(super::evaluate(requested, set_at, expires))
+            };
+
+            if let Some(callback) = callback.borrow_mut().take() {
+                callback(result);
+            }
+        });
+
+        let on_reject = Closure::once_into_js(move |_: JsValue| {
+            crate::set_cookie_string(&cookies::delete_raw(PROBE_COOKIE_NAME));
+
+            if let Some(callback) = reject_callback.borrow_mut().take() {
+                callback(Err(LifetimeCheckError::Unsupported));
+            }
+        });
+
+        let promise = Reflect::get(&store, &"get".into())
+            .unwrap()
+            .unchecked_into::<Function>()
+            .call1(&store, &PROBE_COOKIE_NAME.into())
+            .unwrap();
+
+        Reflect::get(&promise, &"then".into())
+            .unwrap()
+            .unchecked_into::<Function>()
+            .call2(&promise, &on_resolve, &on_reject)
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let day = 24 * 60 * 60 * 1000;
+        let requested = Duration::from_secs(400 * 24 * 60 * 60);
+        let set_at = 1_700_000_000_000;
+
+        assert_eq!(
+            evaluate(requested, set_at, Some(set_at + 400 * day)),
+            LifetimeCapping::Uncapped
+        );
+        assert_eq!(
+            evaluate(requested, set_at, Some(set_at + 400 * day - 1000)),
+            LifetimeCapping::Uncapped
+        );
+        assert_eq!(
+            evaluate(requested, set_at, Some(set_at + 7 * day)),
+            LifetimeCapping::Capped(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert_eq!(evaluate(requested, set_at, None), LifetimeCapping::Session);
+        assert_eq!(
+            evaluate(Duration::MAX, set_at, Some(set_at + 400 * day)),
+            LifetimeCapping::Capped(Duration::from_secs(400 * 24 * 60 * 60))
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Lists of values stored in a single cookie, such as recently viewed items.
+//!
+//! The items are stored as a URI encoded JSON array. When an item is pushed and the cookie would
+//! become larger than the maximum size, the oldest items are evicted, so the list always fits in
+//! the cookie.
+//!
+//! Available only with the `json` feature.
+
+use crate::cookies::{self, CookieOptions, MAX_COOKIE_SIZE};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A list of items stored in one cookie, oldest first.
+#[derive(Clone, Debug)]
+pub struct CookieList<T> {
+    name: String,
+    items: Vec<T>,
+    options: CookieOptions<'static>,
+    max_size: usize,
+}
+
+impl<T: Serialize + DeserializeOwned> CookieList<T> {
+    /// Creates an empty list stored in the cookie `name`, with `CookieOptions::ROOT` and a
+    /// maximum size of [MAX_COOKIE_SIZE](../constant.MAX_COOKIE_SIZE.html).
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            items: Vec::new(),
+            options: CookieOptions::ROOT,
+            max_size: MAX_COOKIE_SIZE,
+        }
+    }
+
+    /// Reads the list stored in the cookie `name` of `cookie_string`. A missing or malformed
+    /// cookie gives an empty list.
+    pub fn load(cookie_string: &str, name: impl Into<String>) -> Self {
+        let mut list = Self::new(name);
+
+        list.items = cookies::get(cookie_string, &list.name)
+            .and_then(Result::ok)
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default();
+
+        list
+    }
+
+    /// Sets the options of the cookie.
+    pub fn with_options(mut self, options: CookieOptions<'static>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the maximum size in bytes of the cookie's encoded name and value.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    fn encoded_value(&self) -> Result<String, serde_json::Error> {
+        Ok(urlencoding::encode(&serde_json::to_string(&self.items)?))
+    }
+
+    fn fits(&self) -> Result<bool, serde_json::Error> {
+        Ok(urlencoding::encode(&self.name).len() + self.encoded_value()?.len() <= self.max_size)
+    }
+
+    /// Appends an item, and evicts the oldest items until the cookie fits in the maximum size.
+    /// Returns the evicted items, oldest first, which include `item` if it can't fit on its own.
+    /// Fails, leaving the list unchanged, if `item` can't be serialized to JSON.
+    pub fn push(&mut self, item: T) -> Result<Vec<T>, serde_json::Error> {
+        serde_json::to_string(&item)?;
+        self.items.push(item);
+        let mut evicted = Vec::new();
+
+        while !self.items.is_empty() && !self.fits()? {
+            evicted.push(self.items.remove(0));
+        }
+
+        Ok(evicted)
+    }
+
+    /// Removes and returns the item at `index`, if it exists.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index < self.items.len() {
+            Some(self.items.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Keeps only the items for which `f` returns true.
+    pub fn retain(&mut self, f: impl FnMut(&T) -> bool) {
+        self.items.retain(f);
+    }
+
+    /// Keeps only the `len` oldest items.
+    pub fn truncate(&mut self, len: usize) {
+        self.items.truncate(len);
+    }
+
+    /// Returns an iterator over the items, oldest first.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    /// Returns the number of items.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns true if the list has no item.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the cookie string that stores the list, or deletes the cookie if the list is
+    /// empty. Fails if the items can't be serialized to JSON.
+    pub fn to_cookie_string(&self) -> Result<String, serde_json::Error> {
+        if self.items.is_empty() {
+            let options = self
+                .options
+                .clone()
+                .expires_at_date("Thu, 01 Jan 1970 00:00:00 GMT");
+
+            Ok(cookies::set_raw(
+                &urlencoding::encode(&self.name),
+                "",
+                &options,
+            ))
+        } else {
+            Ok(cookies::set_raw(
+                &urlencoding::encode(&self.name),
+                &self.encoded_value()?,
+                &self.options,
+            ))
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a CookieList<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::CookieList;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    /// Reads the list stored in the cookie `name`. A missing or malformed cookie gives an empty
+    /// list.
+    pub fn load<T: Serialize + DeserializeOwned>(name: impl Into<String>) -> CookieList<T> {
+        CookieList::load(&crate::cookie_string(), name)
+    }
+
+    /// Writes the list to its cookie, or deletes the cookie if the list is empty. Fails if the
+    /// items can't be serialized to JSON.
+    pub fn save<T: Serialize + DeserializeOwned>(
+        list: &CookieList<T>,
+    ) -> Result<(), serde_json::Error> {
+        crate::set_cookie_string(&list.to_cookie_string()?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let list: CookieList<u32> =
+            CookieList::load("a=1; recent%20items=%5B3%2C1%5D", "recent items");
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), [3, 1]);
+
+        let list: CookieList<u32> = CookieList::load("recent=%5B3%2C", "recent");
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_push() {
+        let mut list = CookieList::new("recent").with_max_size(30);
+        assert!(list.push("a".to_owned()).unwrap().is_empty());
+        assert!(list.push("b".to_owned()).unwrap().is_empty());
+        assert_eq!(
+            list.to_cookie_string().unwrap(),
+            "recent=%5B%22a%22%2C%22b%22%5D;path=/;samesite=lax"
+        );
+
+        assert_eq!(list.push("c".to_owned()).unwrap(), ["a"]);
+        assert_eq!(list.iter().collect::<Vec<_>>(), ["b", "c"]);
+        assert_eq!(
+            list.push("much too long for the cookie".to_owned())
+                .unwrap(),
+            ["b", "c", "much too long for the cookie"]
+        );
+        assert!(list.is_empty());
+
+        // Maps with non-string keys can't be serialized to JSON.
+        let mut list = CookieList::new("recent");
+        let item = std::collections::HashMap::from([((1, 2), 3)]);
+        assert!(list.push(item).is_err());
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = CookieList::new("recent");
+        list.push(1).unwrap();
+        list.push(2).unwrap();
+        list.push(3).unwrap();
+        assert_eq!(list.remove(1), Some(2));
+        assert_eq!(list.remove(2), None);
+        list.truncate(1);
+        assert_eq!(list.len(), 1);
+        list.retain(|item| *item != 1);
+        assert_eq!(
+            list.to_cookie_string().unwrap(),
+            "recent=;path=/;expires=Thu, 01 Jan 1970 00:00:00 GMT;samesite=lax"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Locale cookie, holding the user's preferred language as a BCP 47 language tag (such as
+//! `en-US`), to be used by the server for content negotiation.
+//!
+//! The tags are validated and normalized with the
+//! [unic-langid crate](https://crates.io/crates/unic-langid) before being stored, so the server
+//! never receives a malformed tag: `EN_us` is stored as `en-US`, and `not a tag` is rejected.
+//!
+//! Available only with the `locale` feature.
+
+use crate::cookies::{self, CookieOptions};
+pub use unic_langid::{LanguageIdentifier, LanguageIdentifierError};
+
+/// Default name of the locale cookie.
+pub const COOKIE_NAME: &str = "locale";
+
+/// Parses and normalizes a language tag. `_` is accepted as a separator.
+pub fn normalize(locale: &str) -> Result<LanguageIdentifier, LanguageIdentifierError> {
+    locale.parse()
+}
+
+/// Returns the cookie string that sets the locale cookie `name` to the normalized `locale`, or
+/// an error if `locale` isn't a valid language tag.
+pub fn set_locale(
+    name: &str,
+    locale: &str,
+    options: &CookieOptions,
+) -> Result<String, LanguageIdentifierError> {
+    Ok(cookies::set_raw(
+        name,
+        &normalize(locale)?.to_string(),
+        options,
+    ))
+}
+
+/// Returns the locale stored in the cookie `name`, if it exists and is a valid language tag.
+pub fn get_locale(cookie_string: &str, name: &str) -> Option<LanguageIdentifier> {
+    normalize(&cookies::get_raw(cookie_string, name)?).ok()
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{LanguageIdentifier, LanguageIdentifierError};
+    use crate::cookies::CookieOptions;
+
+    /// Sets the locale cookie `name` to the normalized `locale`, or returns an error if `locale`
+    /// isn't a valid language tag.
+    pub fn set_locale(
+        name: &str,
+        locale: &str,
+        options: &CookieOptions,
+    ) -> Result<(), LanguageIdentifierError> {
+        crate::set_cookie_string(&super::set_locale(name, locale, options)?);
+        Ok(())
+    }
+
+    /// Returns the locale stored in the cookie `name`, if it exists and is a valid language tag.
+    pub fn get_locale(name: &str) -> Option<LanguageIdentifier> {
+        super::get_locale(&crate::cookie_string(), name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_locale() {
+        assert_eq!(
+            set_locale(COOKIE_NAME, "EN_us", &CookieOptions::ROOT).unwrap(),
+            "locale=en-US;path=/;samesite=lax"
+        );
+        assert_eq!(
+            set_locale(COOKIE_NAME, "zh-hant-tw", &CookieOptions::ROOT).unwrap(),
+            "locale=zh-Hant-TW;path=/;samesite=lax"
+        );
+        assert!(set_locale(COOKIE_NAME, "not a tag", &CookieOptions::ROOT).is_err());
+    }
+
+    #[test]
+    fn test_get_locale() {
+        assert_eq!(
+            get_locale("a=1; locale=fr-ca", COOKIE_NAME)
+                .unwrap()
+                .to_string(),
+            "fr-CA"
+        );
+        assert_eq!(get_locale("locale=%3Cscript%3E", COOKIE_NAME), None);
+        assert_eq!(get_locale("a=1", COOKIE_NAME), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! LZ77 compression, used to shrink large values (such as save states) without depending on a
+//! compression crate.
+//!
+//! The compressed data is a sequence of tokens: a byte below `0x80` is followed by that many
+//! literal bytes plus one, and a byte from `0x80` is a match of `(byte & 0x7f) + 3` bytes,
+//! followed by the big-endian 16-bit distance back to the match.
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 0x7f + MIN_MATCH;
+const MAX_LITERALS: usize = 0x80;
+const MAX_DISTANCE: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 12;
+
+fn hash(bytes: &[u8]) -> usize {
+    let key = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (key.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn push_literals(compressed: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        compressed.push((chunk.len() - 1) as u8);
+        compressed.extend_from_slice(chunk);
+    }
+}
+
+/// Compresses `data`.
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::with_capacity(data.len() / 2);
+    let mut last_positions = vec![usize::MAX; 1 << HASH_BITS];
+    let mut literals_start = 0;
+    let mut i = 0;
+
+    while i + MIN_MATCH <= data.len() {
+        let hash = hash(&data[i..]);
+        let candidate = last_positions[hash];
+        last_positions[hash] = i;
+
+        if candidate != usize::MAX
+            && i - candidate <= MAX_DISTANCE
+            && data[candidate..candidate + MIN_MATCH] == data[i..i + MIN_MATCH]
+        {
+            let mut length = MIN_MATCH;
+
+            while length < MAX_MATCH
+                && i + length < data.len()
+                && data[candidate + length] == data[i + length]
+            {
+                length += 1;
+            }
+
+            push_literals(&mut compressed, &data[literals_start..i]);
+            compressed.push(0x80 | (length - MIN_MATCH) as u8);
+            compressed.extend_from_slice(&((i - candidate) as u16).to_be_bytes());
+            i += length;
+            literals_start = i;
+        } else {
+            i += 1;
+        }
+    }
+
+    push_literals(&mut compressed, &data[literals_start..]);
+    compressed
+}
+
+/// Decompresses data compressed by `compress`. Returns `None` if it is malformed.
+pub(crate) fn decompress(compressed: &[u8]) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(compressed.len() * 2);
+    let mut i = 0;
+
+    while let Some(&token) = compressed.get(i) {
+        let token = token as usize;
+
+        if token < 0x80 {
+            data.extend_from_slice(compressed.get(i + 1..i + token + 2)?);
+            i += token + 2;
+        } else {
+            let distance = u16::from_be_bytes([*compressed.get(i + 1)?, *compressed.get(i + 2)?]);
+            let start = data
+                .len()
+                .checked_sub(distance as usize)
+                .filter(|_| distance > 0)?;
+
+            // The match can overlap the bytes it produces, so it is copied byte by byte.
+            for offset in 0..(token & 0x7f) + MIN_MATCH {
+                data.push(data[start + offset]);
+            }
+
+            i += 3;
+        }
+    }
+
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let repeated = r#"{"x":1,"y":2},"#.repeat(200);
+        let long_literals: Vec<u8> = (0..=255).collect();
+
+        for data in [
+            &b""[..],
+            b"ab",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            repeated.as_bytes(),
+            &long_literals,
+        ] {
+            let compressed = compress(data);
+            assert_eq!(decompress(&compressed).unwrap(), data);
+        }
+
+        assert!(compress(repeated.as_bytes()).len() < repeated.len() / 10);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert_eq!(decompress(&[0x02, b'a']), None);
+        assert_eq!(decompress(&[0x00, b'a', 0x80, 0x00]), None);
+        assert_eq!(decompress(&[0x00, b'a', 0x80, 0x00, 0x02]), None);
+        assert_eq!(decompress(&[0x00, b'a', 0x80, 0x00, 0x00]), None);
+    }
+}
@@ -0,0 +1,154 @@
+//! Middleware, which observe or rewrite the cookies on their way to and from the browser, so
+//! cross-cutting concerns (encryption, prefixing, consent checks...) are composed as layers
+//! instead of wrapping every call site.
+//!
+//! In a browser, middleware can be registered with `wasm_cookies::register_middleware`, and are
+//! then applied by the root module's functions: the names given to them are rewritten, the
+//! writes of `set` go through `on_set`, and the values read by `get`, `take` and the other
+//! decoded reads go through `on_get`. Outside a browser, use a
+//! [MiddlewareChain](struct.MiddlewareChain.html) around the functions of the
+//! [cookies](../cookies/index.html) module.
+
+use crate::cookies::{Cookie, CookieOptions};
+use std::borrow::Cow;
+
+/// A layer applied to the cookies that are set and read. All the methods have a default
+/// implementation which leaves the cookie unchanged.
+pub trait CookieMiddleware {
+    /// Rewrites the name of a cookie, before it is set or read (for example to prefix it).
+    fn name(&self, name: String) -> String {
+        name
+    }
+
+    /// Observes or rewrites a cookie before it is set, after its name went through
+    /// [name](#method.name). Returns false to cancel the write.
+    fn on_set(&self, _cookie: &mut Cookie<'static>) -> bool {
+        true
+    }
+
+    /// Observes or rewrites the decoded value of a cookie after it is read. `name` is the name
+    /// returned by [name](#method.name). Returns `None` to hide the cookie.
+    fn on_get(&self, _name: &str, value: String) -> Option<String> {
+        Some(value)
+    }
+}
+
+/// An ordered list of middleware.
+///
+/// Names and writes go through the middleware in the order they were added, and read values go
+/// through them in the reverse order, so each layer undoes on read what it did on write.
+#[derive(Default)]
+pub struct MiddlewareChain {
+    layers: Vec<Box<dyn CookieMiddleware>>,
+}
+
+impl std::fmt::Debug for MiddlewareChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MiddlewareChain")
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+impl MiddlewareChain {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a middleware at the end of the chain.
+    pub fn push(&mut self, middleware: impl CookieMiddleware + 'static) {
+        self.layers.push(Box::new(middleware));
+    }
+
+    /// Removes all the middleware.
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Returns true if the chain has no middleware.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Returns the name of a cookie rewritten by all the middleware.
+    pub fn name(&self, name: &str) -> String {
+        self.layers
+            .iter()
+            .fold(name.to_owned(), |name, layer| layer.name(name))
+    }
+
+    /// Returns the cookie to set, rewritten by all the middleware, or `None` if one of them
+    /// cancelled the write.
+    pub fn set(&self, name: &str, value: &str, options: &CookieOptions) -> Option<Cookie<'static>> {
+        let mut cookie = Cookie {
+            name: Cow::Owned(self.name(name)),
+            value: Cow::Owned(value.to_owned()),
+            options: options.clone().into_owned(),
+            http_only: false,
+        };
+
+        for layer in &self.layers {
+            if !layer.on_set(&mut cookie) {
+                return None;
+            }
+        }
+
+        Some(cookie)
+    }
+
+    /// Returns the read value of the cookie `name` (as returned by [name](#method.name)),
+    /// rewritten by all the middleware, or `None` if one of them hid the cookie.
+    pub fn get(&self, name: &str, value: String) -> Option<String> {
+        self.layers
+            .iter()
+            .rev()
+            .try_fold(value, |value, layer| layer.on_get(name, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Prefix;
+
+    impl CookieMiddleware for Prefix {
+        fn name(&self, name: String) -> String {
+            format!("app_{}", name)
+        }
+    }
+
+    struct Reverse;
+
+    impl CookieMiddleware for Reverse {
+        fn on_set(&self, cookie: &mut Cookie<'static>) -> bool {
+            cookie.value = Cow::Owned(cookie.value.chars().rev().collect());
+            cookie.name != "app_blocked"
+        }
+
+        fn on_get(&self, _name: &str, value: String) -> Option<String> {
+            Some(value.chars().rev().collect())
+        }
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut chain = MiddlewareChain::new();
+        chain.push(Prefix);
+        chain.push(Reverse);
+
+        let cookie = chain.set("theme", "dark", &CookieOptions::ROOT).unwrap();
+        assert_eq!(cookie.name, "app_theme");
+        assert_eq!(cookie.value, "krad");
+        assert_eq!(cookie.options.path.as_deref(), Some("/"));
+
+        assert_eq!(chain.name("theme"), "app_theme");
+        assert_eq!(chain.get("app_theme", "krad".to_owned()).unwrap(), "dark");
+        assert!(chain.set("blocked", "1", &CookieOptions::ROOT).is_none());
+
+        chain.clear();
+        assert!(chain.is_empty());
+        assert_eq!(chain.name("theme"), "theme");
+    }
+}
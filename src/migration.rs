@@ -0,0 +1,170 @@
+//! Migration of the stored values from a legacy URI encoding to a codec (such as
+//! [codec::Base64](../codec/struct.Base64.html)), so the encoding of the values can change
+//! without invalidating the cookies of the existing users.
+//!
+//! The values written by a [Migration](struct.Migration.html) are stored as is, prefixed with a
+//! marker that none of the URI encodings leave unescaped (`$` by default). Values without the
+//! marker are decoded with the legacy encoding, and reading them returns the cookie string that
+//! rewrites them with the codec. In a browser, [browser::get](browser/fn.get.html) writes it on
+//! the first read.
+//!
+//! The names are URI encoded with the legacy encoding, so the cookies keep their names.
+//!
+//! Available only with the `encoding` feature.
+
+use crate::codec::CookieCodec;
+use crate::cookies::{self, CookieOptions};
+use crate::encoding::Encoding;
+
+/// Default marker prefixing the values written with the codec.
+pub const DEFAULT_MARKER: &str = "$";
+
+/// Value of a cookie read through a migration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Migrated {
+    /// Decoded value.
+    pub value: String,
+
+    /// Cookie string that rewrites the value with the codec, if it was stored with the legacy
+    /// encoding.
+    pub rewrite: Option<String>,
+}
+
+/// A migration from a legacy URI encoding to a codec.
+#[derive(Clone, Debug)]
+pub struct Migration<C> {
+    legacy: Encoding,
+    codec: C,
+    marker: String,
+}
+
+impl<C: CookieCodec> Migration<C> {
+    /// Creates a migration of the values URI encoded with `legacy` to `codec`.
+    ///
+    /// The codec's output is stored without URI encoding, so it must only contain characters
+    /// allowed in cookie values.
+    pub fn new(legacy: Encoding, codec: C) -> Self {
+        Self {
+            legacy,
+            codec,
+            marker: DEFAULT_MARKER.to_owned(),
+        }
+    }
+
+    /// Sets the marker prefixing the values written with the codec. It must start with a
+    /// character that the legacy encoding escapes, so legacy values are never mistaken for new
+    /// ones.
+    pub fn with_marker(mut self, marker: impl Into<String>) -> Self {
+        self.marker = marker.into();
+        self
+    }
+
+    /// Returns the stored value of the cookie `name` for `value`.
+    pub fn encode(&self, name: &str, value: &str) -> String {
+        format!("{}{}", self.marker, self.codec.encode(name, value))
+    }
+
+    /// Returns the value of the cookie `name` from its stored value, and true if it was stored
+    /// with the legacy encoding, or `None` if it can't be decoded.
+    pub fn decode(&self, name: &str, stored: &str) -> Option<(String, bool)> {
+        match stored.strip_prefix(self.marker.as_str()) {
+            Some(encoded) => Some((self.codec.decode(name, encoded)?, false)),
+            None => Some((self.legacy.decode(stored).ok()?, true)),
+        }
+    }
+
+    /// Returns the cookie string that sets the cookie `name` to `value`, stored with the codec.
+    pub fn set(&self, name: &str, value: &str, options: &CookieOptions) -> String {
+        cookies::set_raw(
+            &self.legacy.encode(name),
+            &self.encode(name, value),
+            options,
+        )
+    }
+
+    /// If it exists, returns the value of the cookie `name`, and the cookie string that rewrites
+    /// it with `options` if it was stored with the legacy encoding, or `None` if it is missing or
+    /// can't be decoded.
+    pub fn get(
+        &self,
+        cookie_string: &str,
+        name: &str,
+        options: &CookieOptions,
+    ) -> Option<Migrated> {
+        let stored = cookies::get_raw(cookie_string, &self.legacy.encode(name))?;
+        let (value, is_legacy) = self.decode(name, &stored)?;
+        let rewrite = is_legacy.then(|| self.set(name, &value, options));
+        Some(Migrated { value, rewrite })
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::Migration;
+    use crate::codec::CookieCodec;
+    use crate::cookies::CookieOptions;
+
+    /// If it exists, returns the value of the cookie `name`, or `None` if it is missing or can't
+    /// be decoded. A value stored with the legacy encoding is rewritten with the codec and
+    /// `options`.
+    pub fn get<C: CookieCodec>(
+        migration: &Migration<C>,
+        name: &str,
+        options: &CookieOptions,
+    ) -> Option<String> {
+        let migrated = migration.get(&crate::cookie_string(), name, options)?;
+
+        if let Some(rewrite) = &migrated.rewrite {
+            crate::set_cookie_string(rewrite);
+        }
+
+        Some(migrated.value)
+    }
+
+    /// Sets the cookie `name` to `value`, stored with the codec.
+    pub fn set<C: CookieCodec>(
+        migration: &Migration<C>,
+        name: &str,
+        value: &str,
+        options: &CookieOptions,
+    ) {
+        crate::set_cookie_string(&migration.set(name, value, options));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Base64;
+
+    #[test]
+    fn test_migration() {
+        let migration = Migration::new(Encoding::Standard, Base64);
+        let migrated = migration
+            .get(
+                "user%20name=J%C3%BCrgen%20M",
+                "user name",
+                &CookieOptions::ROOT,
+            )
+            .unwrap();
+        assert_eq!(migrated.value, "Jürgen M");
+
+        let rewrite = migrated.rewrite.unwrap();
+        assert_eq!(
+            rewrite.split(';').next().unwrap(),
+            "user%20name=$SsO8cmdlbiBN"
+        );
+
+        let migrated = migration
+            .get(&rewrite, "user name", &CookieOptions::ROOT)
+            .unwrap();
+        assert_eq!(migrated.value, "Jürgen M");
+        assert_eq!(migrated.rewrite, None);
+
+        assert_eq!(migration.get("a=$%", "a", &CookieOptions::ROOT), None);
+        assert_eq!(migration.get("b=1", "a", &CookieOptions::ROOT), None);
+    }
+}
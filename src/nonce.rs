@@ -0,0 +1,217 @@
+//! Single-use nonce tokens, for example to protect download links or forms against replays.
+//!
+//! A nonce is a random token issued for a purpose (such as `download` or `checkout`), stored
+//! with its expiration date in a short-lived cookie named `nonce_<purpose>`. Consuming the nonce
+//! deletes the cookie, whether the presented token matches or not, so each nonce can only be
+//! tried once. The tokens are compared in constant time.
+//!
+//! Only the last nonce issued for a purpose is valid: issuing a new one replaces it.
+//!
+//! The nonces aren't signed: a key shipped to the browser proves nothing, and the scripts of the
+//! page can read and write the cookie anyway. They protect against replays of links and forms by
+//! third parties, which can't write the page's cookies. If the server must trust a nonce, it has
+//! to issue and verify it itself.
+
+use crate::cookies::{self, CookieOptions};
+use crate::sha256::{constant_time_eq, to_hex};
+use std::time::Duration;
+
+/// Number of random bytes needed to generate a nonce.
+pub const RANDOM_BYTES: usize = 16;
+
+/// Returns true if `purpose` is a valid purpose: non-empty, made of ASCII letters, digits, `-`
+/// and `_`.
+pub fn is_valid_purpose(purpose: &str) -> bool {
+    !purpose.is_empty()
+        && purpose
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// Returns the name of the cookie holding the nonce issued for `purpose`.
+pub fn cookie_name(purpose: &str) -> String {
+    format!("nonce_{}", purpose)
+}
+
+/// Returns true if the stored nonce `value` is unexpired at `now` and matches `token`, or `None`
+/// if it is malformed.
+fn verify(value: &str, token: &str, now: i64) -> Option<bool> {
+    let (stored_token, expires) = value.split_once('.')?;
+    let expires: i64 = expires.parse().ok()?;
+    let is_token = constant_time_eq(stored_token.as_bytes(), token.as_bytes());
+
+    Some(is_token && now < expires)
+}
+
+/// Issues a nonce for `purpose` at `now` (timestamp in milliseconds, UTC), valid for `ttl`, from
+/// cryptographically secure random bytes. Returns the token, to be embedded in the link or the
+/// form, and the cookie string that stores it, or `None` if `purpose` is invalid (see
+/// [is_valid_purpose](fn.is_valid_purpose.html)).
+///
+/// The expiration date of `options` is replaced with the one of the nonce.
+pub fn issue(
+    purpose: &str,
+    random: &[u8; RANDOM_BYTES],
+    now: i64,
+    ttl: Duration,
+    options: &CookieOptions,
+) -> Option<(String, String)> {
+    if !is_valid_purpose(purpose) {
+        return None;
+    }
+
+    let token = to_hex(random);
+    let expires = now.saturating_add(ttl.as_millis().min(i64::MAX as u128) as i64);
+    let value = format!("{}.{}", token, expires);
+    let options = options.clone().expires_at_timestamp(expires);
+
+    Some((
+        token,
+        cookies::set_raw(&cookie_name(purpose), &value, &options),
+    ))
+}
+
+/// Consumes the nonce issued for `purpose` at `now` (timestamp in milliseconds, UTC). Returns
+/// true if `token` is the nonce and is unexpired, with the cookie string that
+/// deletes it, whatever the result. Returns `None` if there is no nonce for `purpose`.
+///
+/// Pass the `options` the nonce was issued with, so its cookie is deleted even if it is scoped
+/// to a path or a domain.
+pub fn consume(
+    cookie_string: &str,
+    purpose: &str,
+    token: &str,
+    now: i64,
+    options: &CookieOptions,
+) -> Option<(bool, String)> {
+    if !is_valid_purpose(purpose) {
+        return None;
+    }
+
+    let (value, deletion) = cookies::take_raw(cookie_string, &cookie_name(purpose), Some(options))?;
+
+    let is_valid = verify(&value, token, now).unwrap_or(false);
+
+    Some((is_valid, deletion))
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::RANDOM_BYTES;
+    use crate::cookies::{self, CookieOptions};
+    use std::time::Duration;
+
+    /// Issues a nonce for `purpose`, valid for `ttl`, from `crypto.getRandomValues`. Returns its
+    /// token, or `None` if `purpose` is invalid.
+    ///
+    /// The expiration date of `options` is replaced with the one of the nonce.
+    pub fn issue_nonce(purpose: &str, ttl: Duration, options: &CookieOptions) -> Option<String> {
+        let mut random = [0; RANDOM_BYTES];
+        crate::fill_random(&mut random);
+
+        let (token, cookie_string) =
+            super::issue(purpose, &random, cookies::now_timestamp(), ttl, options)?;
+        crate::set_cookie_string(&cookie_string);
+        Some(token)
+    }
+
+    /// Consumes the nonce issued for `purpose`, and returns true if `token` is the nonce and is
+    /// unexpired. The nonce is deleted whatever the result.
+    pub fn consume_nonce(purpose: &str, token: &str, options: &CookieOptions) -> bool {
+        match super::consume(
+            &crate::cookie_string(),
+            purpose,
+            token,
+            cookies::now_timestamp(),
+            options,
+        ) {
+            Some((is_valid, deletion)) => {
+                crate::set_cookie_string(&deletion);
+                is_valid
+            }
+
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn issued() -> (String, String) {
+        let (token, cookie_string) = issue(
+            "download",
+            &[7; RANDOM_BYTES],
+            NOW,
+            Duration::from_secs(60),
+            &CookieOptions::ROOT,
+        )
+        .unwrap();
+
+        (token, cookie_string.split(';').next().unwrap().to_owned())
+    }
+
+    #[test]
+    fn test_consume() {
+        let (token, cookie_string) = issued();
+        assert_eq!(token, "07".repeat(RANDOM_BYTES));
+        assert_eq!(
+            cookie_string,
+            format!("nonce_download={}.1700000060000", token)
+        );
+
+        let consume_at = |token: &str, now| {
+            consume(&cookie_string, "download", token, now, &CookieOptions::ROOT)
+                .unwrap()
+                .0
+        };
+        assert!(consume_at(&token, NOW + 1000));
+        assert!(!consume_at(&token, NOW + 60_000));
+        assert!(!consume_at("0707", NOW));
+
+        let (_, deletion) =
+            consume(&cookie_string, "download", "x", NOW, &CookieOptions::ROOT).unwrap();
+        assert!(deletion.starts_with("nonce_download=;"));
+        assert_eq!(
+            consume("", "download", &token, NOW, &CookieOptions::ROOT),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let (token, cookie_string) = issued();
+        let moved = cookie_string.replace("nonce_download=", "nonce_checkout=");
+        assert_eq!(
+            consume(&moved, "download", &token, NOW, &CookieOptions::ROOT),
+            None
+        );
+        assert!(
+            !consume(
+                "nonce_download=malformed",
+                "download",
+                "malformed",
+                NOW,
+                &CookieOptions::ROOT
+            )
+            .unwrap()
+            .0
+        );
+        assert_eq!(
+            issue(
+                "a;b",
+                &[0; RANDOM_BYTES],
+                NOW,
+                Duration::ZERO,
+                &CookieOptions::ROOT
+            ),
+            None
+        );
+    }
+}
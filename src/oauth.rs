@@ -0,0 +1,211 @@
+//! Storage of the OAuth 2.0 PKCE `code_verifier` and `state` between the redirect to the
+//! authorization server and the callback
+//! (see [RFC 7636](https://datatracker.ietf.org/doc/html/rfc7636)).
+//!
+//! Both values are stored in short-lived cookies with SameSite `Lax`, so they are sent back when
+//! the authorization server redirects to the application (a top-level navigation), but not along
+//! with other cross-site requests.
+//!
+//! Available only with the `crypto` feature.
+
+use crate::base64::encode_url_safe;
+use crate::cookies::{self, CookieOptions, SameSite};
+use crate::sha256::{constant_time_eq, sha256};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// Name of the cookie storing the code verifier.
+pub const VERIFIER_COOKIE_NAME: &str = "oauth_code_verifier";
+
+/// Name of the cookie storing the state.
+pub const STATE_COOKIE_NAME: &str = "oauth_state";
+
+/// Lifetime of the cookies, which should be enough to complete the authorization.
+pub const LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+/// Number of random bytes needed to generate a flow.
+pub const RANDOM_BYTES: usize = 48;
+
+/// Error returned when finishing a flow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OAuthError {
+    /// The cookies don't exist: the flow hasn't been started or has expired.
+    NotStarted,
+
+    /// The state returned by the authorization server doesn't match the stored one.
+    StateMismatch,
+}
+
+/// The PKCE code verifier and the state of an authorization request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PkceFlow {
+    /// The code verifier, sent to the token endpoint after the callback.
+    pub code_verifier: String,
+
+    /// The state, sent to the authorization server and compared with the returned one.
+    pub state: String,
+}
+
+impl PkceFlow {
+    /// Generates a flow from cryptographically secure random bytes: the first 32 bytes are used
+    /// for the code verifier, the last 16 bytes for the state.
+    pub fn from_random_bytes(random: &[u8; RANDOM_BYTES]) -> Self {
+        let (verifier, state) = random.split_at(32);
+
+        Self {
+            code_verifier: encode_url_safe(verifier),
+            state: encode_url_safe(state),
+        }
+    }
+
+    /// Generates a flow from `crypto.getRandomValues`.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    #[cfg(target_family = "wasm")]
+    pub fn generate() -> Self {
+        let mut random = [0; RANDOM_BYTES];
+        crate::fill_random(&mut random);
+        Self::from_random_bytes(&random)
+    }
+
+    /// Returns the `S256` code challenge, to be sent to the authorization server.
+    pub fn code_challenge(&self) -> String {
+        encode_url_safe(&sha256(self.code_verifier.as_bytes()))
+    }
+}
+
+/// Returns the options of the cookies: path `/`, SameSite `Lax`, and expiration after
+/// [LIFETIME](constant.LIFETIME.html). Set `secure` if the application is served over HTTPS.
+pub fn options() -> CookieOptions<'static> {
+    CookieOptions::new()
+        .with_path(Cow::Borrowed("/"))
+        .with_same_site(SameSite::Lax)
+        .expires_after(LIFETIME)
+}
+
+/// Return the cookie strings that store the flow.
+pub fn store(flow: &PkceFlow, options: &CookieOptions) -> [String; 2] {
+    [
+        cookies::set_raw(VERIFIER_COOKIE_NAME, &flow.code_verifier, options),
+        cookies::set_raw(STATE_COOKIE_NAME, &flow.state, options),
+    ]
+}
+
+/// Returns the stored code verifier if the stored state matches `returned_state` (the `state`
+/// parameter of the callback).
+pub fn verify(cookie_string: &str, returned_state: &str) -> Result<String, OAuthError> {
+    let code_verifier = cookies::get_raw(cookie_string, VERIFIER_COOKIE_NAME);
+    let state = cookies::get_raw(cookie_string, STATE_COOKIE_NAME);
+
+    match (code_verifier, state) {
+        (Some(code_verifier), Some(state)) => {
+            if constant_time_eq(state.as_bytes(), returned_state.as_bytes()) {
+                Ok(code_verifier)
+            } else {
+                Err(OAuthError::StateMismatch)
+            }
+        }
+
+        _ => Err(OAuthError::NotStarted),
+    }
+}
+
+/// Return the cookie strings that delete the flow's cookies.
+pub fn clear(options: &CookieOptions) -> [String; 2] {
+    let options = options
+        .clone()
+        .expires_at_date("Thu, 01 Jan 1970 00:00:00 GMT");
+
+    [
+        cookies::set_raw(VERIFIER_COOKIE_NAME, "", &options),
+        cookies::set_raw(STATE_COOKIE_NAME, "", &options),
+    ]
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{OAuthError, PkceFlow};
+
+    /// Generates and stores a new flow with [options](../fn.options.html), before redirecting
+    /// to the authorization server.
+    pub fn start(secure: bool) -> PkceFlow {
+        let flow = PkceFlow::generate();
+        let mut options = super::options();
+        options.secure = secure;
+
+        for cookie_string in &super::store(&flow, &options) {
+            crate::set_cookie_string(cookie_string);
+        }
+
+        flow
+    }
+
+    /// After the callback, deletes the flow's cookies and returns the stored code verifier if
+    /// the stored state matches `returned_state`.
+    pub fn finish(returned_state: &str) -> Result<String, OAuthError> {
+        let result = super::verify(&crate::cookie_string(), returned_state);
+
+        for cookie_string in &super::clear(&super::options()) {
+            crate::set_cookie_string(cookie_string);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_challenge() {
+        // Example of RFC 7636 appendix B.
+        let flow = PkceFlow {
+            code_verifier: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_owned(),
+            state: String::new(),
+        };
+
+        assert_eq!(
+            flow.code_challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        let flow = PkceFlow::from_random_bytes(&[7; RANDOM_BYTES]);
+        assert_eq!(flow.code_verifier.len(), 43);
+        assert_eq!(flow.state.len(), 22);
+    }
+
+    #[test]
+    fn test_flow() {
+        let flow = PkceFlow::from_random_bytes(&[1; RANDOM_BYTES]);
+        let options = options();
+        assert_eq!(options.path.as_deref(), Some("/"));
+        assert!(options.validate().is_empty());
+
+        let [verifier_cookie, state_cookie] = store(&flow, &options);
+        assert!(verifier_cookie.starts_with(&format!(
+            "oauth_code_verifier={};path=/;expires=",
+            flow.code_verifier
+        )));
+        assert!(state_cookie.ends_with(";samesite=lax"));
+
+        let cookie_string = format!(
+            "oauth_code_verifier={}; oauth_state={}",
+            flow.code_verifier, flow.state
+        );
+
+        assert_eq!(verify(&cookie_string, &flow.state), Ok(flow.code_verifier));
+        assert_eq!(
+            verify(&cookie_string, "forged"),
+            Err(OAuthError::StateMismatch)
+        );
+        assert_eq!(verify("", &flow.state), Err(OAuthError::NotStarted));
+
+        let [verifier_cookie, _] = clear(&options);
+        assert!(verifier_cookie.contains("expires=Thu, 01 Jan 1970 00:00:00 GMT"));
+    }
+}
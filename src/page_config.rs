@@ -0,0 +1,177 @@
+//! Default cookie options configured by the page rather than compiled into the wasm bundle, so
+//! the cookie policy can change per deployment without rebuilding.
+//!
+//! The configuration is read from a `<meta name="wasm-cookies-config">` tag, whose content uses
+//! the syntax of cookie attributes plus a `Prefix` attribute:
+//!
+//! ```html
+//! <meta name="wasm-cookies-config" content="Domain=example.com; Secure; SameSite=Strict; Prefix=app_">
+//! ```
+//!
+//! or, if there is no such tag, from a global `wasmCookiesConfig` object, defined before the wasm
+//! module is loaded:
+//!
+//! ```html
+//! <script>
+//!     window.wasmCookiesConfig = { domain: "example.com", secure: true, sameSite: "Strict", prefix: "app_" };
+//! </script>
+//! ```
+//!
+//! [browser::init](browser/fn.init.html) installs it as a [policy](../policy/index.html) with a
+//! single rule matching every cookie, so its options fill the options left unset by the call
+//! sites.
+
+use crate::cookies::SameSite;
+use crate::policy::{CookiePolicy, CookieRule};
+
+/// Name of the meta tag holding the configuration.
+pub const META_NAME: &str = "wasm-cookies-config";
+
+/// Name of the global object holding the configuration.
+pub const GLOBAL_NAME: &str = "wasmCookiesConfig";
+
+/// Default options and naming prefix configured by the page.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageConfig {
+    /// Default domain of the cookies.
+    pub domain: Option<String>,
+
+    /// If true, the cookies are always set with the `secure` flag.
+    pub secure: bool,
+
+    /// Default SameSite value of the cookies.
+    pub same_site: Option<SameSite>,
+
+    /// Prefix prepended to the name of every cookie.
+    pub prefix: String,
+}
+
+impl PageConfig {
+    /// Parses the content of the meta tag (for example `Domain=example.com; Secure; Prefix=app_`).
+    ///
+    /// Attribute names are case-insensitive, unknown attributes are ignored, and if an attribute
+    /// appears several times, the last one wins.
+    pub fn parse(content: &str) -> Self {
+        let mut config = Self::default();
+
+        for attribute in content.split(';') {
+            let (name, value) = match attribute.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+
+            if name.eq_ignore_ascii_case("domain") {
+                config.domain = Some(value.to_owned()).filter(|domain| !domain.is_empty());
+            } else if name.eq_ignore_ascii_case("secure") {
+                config.secure = true;
+            } else if name.eq_ignore_ascii_case("samesite") {
+                config.same_site = SameSite::from_cookie_string_value(value);
+            } else if name.eq_ignore_ascii_case("prefix") {
+                config.prefix = value.to_owned();
+            }
+        }
+
+        config
+    }
+
+    /// Returns the policy applying the configuration to every cookie.
+    pub fn to_policy(&self) -> CookiePolicy {
+        CookiePolicy {
+            name_prefix: self.prefix.clone(),
+            rules: vec![CookieRule {
+                pattern: "*".to_owned(),
+                domain: self.domain.clone(),
+                secure: self.secure,
+                same_site: self.same_site.clone(),
+                ..CookieRule::default()
+            }],
+            ..CookiePolicy::default()
+        }
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{PageConfig, GLOBAL_NAME, META_NAME};
+    use crate::cookies::SameSite;
+    use js_sys::Reflect;
+    use wasm_bindgen::JsValue;
+
+    fn from_meta() -> Option<PageConfig> {
+        let document = web_sys::window()?.document()?;
+        let meta = document
+            .query_selector(&format!("meta[name=\"{}\"]", META_NAME))
+            .ok()??;
+        Some(PageConfig::parse(&meta.get_attribute("content")?))
+    }
+
+    fn from_global() -> Option<PageConfig> {
+        let window = web_sys::window()?;
+        let global = Reflect::get(&window, &GLOBAL_NAME.into()).ok()?;
+
+        if !global.is_object() {
+            return None;
+        }
+
+        let field = |name: &str| Reflect::get(&global, &name.into()).unwrap_or(JsValue::UNDEFINED);
+
+        Some(PageConfig {
+            domain: field("domain").as_string(),
+            secure: field("secure").as_bool().unwrap_or(false),
+            same_site: field("sameSite")
+                .as_string()
+                .and_then(|same_site| SameSite::from_cookie_string_value(&same_site)),
+            prefix: field("prefix").as_string().unwrap_or_default(),
+        })
+    }
+
+    /// Returns the configuration of the page, from the meta tag or else from the global object,
+    /// if any.
+    pub fn load() -> Option<PageConfig> {
+        from_meta().or_else(from_global)
+    }
+
+    /// Installs the configuration of the page as the cookie policy (see
+    /// [set_policy](../../fn.set_policy.html)), replacing the installed one, and returns it.
+    /// Leaves the installed policy if the page has no configuration.
+    ///
+    /// Call it at startup, before the first cookie is read or written.
+    pub fn init() -> Option<PageConfig> {
+        let config = load()?;
+        crate::set_policy(Some(config.to_policy()));
+        Some(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookies::CookieOptions;
+
+    #[test]
+    fn test_parse() {
+        let config = PageConfig::parse("domain=example.com; SECURE; SameSite=Strict; Prefix=app_");
+        assert_eq!(
+            config,
+            PageConfig {
+                domain: Some("example.com".to_owned()),
+                secure: true,
+                same_site: Some(SameSite::Strict),
+                prefix: "app_".to_owned(),
+            }
+        );
+        assert_eq!(
+            PageConfig::parse("Domain=; unknown=1"),
+            PageConfig::default()
+        );
+
+        let policy = config.to_policy();
+        let (name, options) = policy.apply("session", &CookieOptions::ROOT, &[]).unwrap();
+        assert_eq!(name, "app_session");
+        assert_eq!(options.domain.as_deref(), Some("example.com"));
+        assert!(options.secure);
+    }
+}
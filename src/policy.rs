@@ -0,0 +1,358 @@
+//! Cookie policies, which encapsulate cookie governance rules (default options, naming prefix
+//! and consent category per cookie name pattern) so they can be enforced in code.
+//!
+//! In a browser, a policy can be installed with `wasm_cookies::set_policy`, and is then applied
+//! by the root module's `set`, `get` and `delete` functions: `delete` uses the path and the
+//! domain of the matching rule, so it deletes the cookie `set` wrote. Outside a browser, use
+//! [CookiePolicy::apply](struct.CookiePolicy.html#method.apply) before calling the functions of
+//! the [cookies](../cookies/index.html) module, and
+//! [CookiePolicy::options](struct.CookiePolicy.html#method.options) to delete the cookies.
+
+use crate::cookies::{CookieOptions, SameSite};
+use std::borrow::Cow;
+
+/// A set of cookie governance rules.
+///
+/// With the `json` feature, it can be loaded from a server-delivered configuration with
+/// [from_json](#method.from_json). All the fields are optional in the JSON object.
+#[derive(Default, Clone, Debug)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "json", serde(default))]
+pub struct CookiePolicy {
+    /// Prefix prepended to the name of every cookie.
+    pub name_prefix: String,
+
+    /// The rules of the policy. For a given cookie name, only the first matching rule applies.
+    pub rules: Vec<CookieRule>,
+
+    /// Cookie name patterns (see [CookieRule::pattern](struct.CookieRule.html#structfield.pattern))
+    /// of the only cookies that can be set. If empty, all names are allowed.
+    pub allowed_names: Vec<String>,
+
+    /// Cookie name patterns of the cookies that can't be set, even if they are allowed.
+    pub blocked_names: Vec<String>,
+}
+
+/// A rule of a [CookiePolicy](struct.CookiePolicy.html), which applies to the cookies whose name
+/// matches its pattern.
+#[derive(Default, Clone, Debug)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "json", serde(default))]
+pub struct CookieRule {
+    /// Cookie name pattern, where `*` matches any sequence of characters
+    /// (for example `analytics_*`).
+    ///
+    /// The pattern is matched against the name before the policy's prefix is prepended.
+    pub pattern: String,
+
+    /// Consent category of the matching cookies. If `Some`, the cookies can only be set when
+    /// consent has been granted for this category.
+    pub category: Option<String>,
+
+    /// Default path of the matching cookies.
+    pub path: Option<String>,
+
+    /// Default domain of the matching cookies.
+    pub domain: Option<String>,
+
+    /// If true, the matching cookies are always set with the `secure` flag.
+    pub secure: bool,
+
+    /// Default SameSite value of the matching cookies, used when they are set with the default
+    /// value (`Lax`).
+    pub same_site: Option<SameSite>,
+}
+
+/// Error returned by [CookiePolicy::apply](struct.CookiePolicy.html#method.apply).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PolicyViolation {
+    /// The cookie belongs to a consent category for which consent hasn't been granted.
+    ///
+    /// The field is the consent category.
+    ConsentRequired(String),
+
+    /// The cookie name doesn't match any of the allowed name patterns.
+    ///
+    /// The field is the cookie name.
+    NameNotAllowed(String),
+
+    /// The cookie name matches a blocked name pattern.
+    ///
+    /// - The first field is the cookie name.
+    /// - The second field is the matching pattern.
+    NameBlocked(String, String),
+}
+
+impl CookieRule {
+    /// Returns true if the rule applies to the cookie named `name`.
+    pub fn matches(&self, name: &str) -> bool {
+        matches_pattern(&self.pattern, name)
+    }
+
+    /// Returns the default options of the rule.
+    pub fn options(&self) -> CookieOptions<'_> {
+        let mut options = CookieOptions::new();
+        options.path = self.path.as_deref().map(Cow::Borrowed);
+        options.domain = self.domain.as_deref().map(Cow::Borrowed);
+        options.secure = self.secure;
+
+        if let Some(same_site) = &self.same_site {
+            options.same_site = same_site.clone();
+        }
+
+        options
+    }
+}
+
+impl CookiePolicy {
+    /// Loads a policy from JSON, for example:
+    ///
+    /// ```
+    /// # #[cfg(feature = "json")]
+    /// # {
+    /// use wasm_cookies::CookiePolicy;
+    ///
+    /// let policy = CookiePolicy::from_json(
+    ///     r#"{
+    ///         "name_prefix": "app_",
+    ///         "rules": [
+    ///             { "pattern": "analytics_*", "category": "analytics" },
+    ///             { "pattern": "*", "path": "/", "secure": true, "same_site": "Strict" }
+    ///         ]
+    ///     }"#,
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(policy.rules.len(), 2);
+    /// # }
+    /// ```
+    ///
+    /// Available only with the `json` feature.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Returns the rule that applies to the cookie named `name`, if any.
+    pub fn rule(&self, name: &str) -> Option<&CookieRule> {
+        self.rules.iter().find(|rule| rule.matches(name))
+    }
+
+    /// Returns the name of the cookie with the policy's prefix.
+    pub fn name(&self, name: &str) -> String {
+        format!("{}{}", self.name_prefix, name)
+    }
+
+    /// Checks that the cookie named `name` (without the policy's prefix) can be set according to
+    /// the allowed and blocked name patterns.
+    pub fn check_name(&self, name: &str) -> Result<(), PolicyViolation> {
+        if let Some(pattern) = self
+            .blocked_names
+            .iter()
+            .find(|pattern| matches_pattern(pattern, name))
+        {
+            return Err(PolicyViolation::NameBlocked(
+                name.to_owned(),
+                pattern.clone(),
+            ));
+        }
+
+        if !self.allowed_names.is_empty()
+            && !self
+                .allowed_names
+                .iter()
+                .any(|pattern| matches_pattern(pattern, name))
+        {
+            return Err(PolicyViolation::NameNotAllowed(name.to_owned()));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the cookie named `name` (without the policy's prefix) can be set: its name must
+    /// be allowed (see [check_name](#method.check_name)), and consent must have been granted for
+    /// the consent category of the matching rule, if any.
+    ///
+    /// `consented_categories` are the consent categories for which consent has been granted.
+    pub fn check(
+        &self,
+        name: &str,
+        consented_categories: &[String],
+    ) -> Result<(), PolicyViolation> {
+        self.check_name(name)?;
+
+        match self.rule(name).and_then(|rule| rule.category.as_ref()) {
+            Some(category) if !consented_categories.contains(category) => {
+                Err(PolicyViolation::ConsentRequired(category.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies the policy to a cookie that is about to be set, and returns its prefixed name and
+    /// its options with the unset fields filled from the matching rule.
+    ///
+    /// `consented_categories` are the consent categories for which consent has been granted.
+    pub fn apply<'a>(
+        &'a self,
+        name: &str,
+        options: &CookieOptions<'a>,
+        consented_categories: &[String],
+    ) -> Result<(String, CookieOptions<'a>), PolicyViolation> {
+        self.check(name, consented_categories)?;
+        Ok((self.name(name), self.options(name, options)))
+    }
+
+    /// Returns the options of the cookie named `name` (without the policy's prefix) with the
+    /// unset fields filled from the matching rule, without checking that it can be set. Pass
+    /// them to delete a cookie set with the policy, so the deletion has its path and domain.
+    pub fn options<'a>(&'a self, name: &str, options: &CookieOptions<'a>) -> CookieOptions<'a> {
+        match self.rule(name) {
+            Some(rule) => {
+                let defaults = rule.options();
+
+                CookieOptions {
+                    path: options.path.clone().or(defaults.path),
+                    domain: options.domain.clone().or(defaults.domain),
+                    expires: options.expires.clone(),
+                    secure: options.secure || rule.secure,
+                    same_site: match (&options.same_site, &rule.same_site) {
+                        (SameSite::Lax, Some(same_site)) => same_site.clone(),
+                        (same_site, _) => same_site.clone(),
+                    },
+                }
+            }
+
+            None => options.clone(),
+        }
+    }
+}
+
+pub(crate) fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((head, tail)) => match name.strip_prefix(head) {
+            Some(name) => name
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(name.len()))
+                .any(|i| matches_pattern(tail, &name[i..])),
+            None => false,
+        },
+
+        None => pattern == name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> CookiePolicy {
+        CookiePolicy {
+            name_prefix: "app_".to_owned(),
+            rules: vec![
+                CookieRule {
+                    pattern: "analytics_*".to_owned(),
+                    category: Some("analytics".to_owned()),
+                    path: Some("/".to_owned()),
+                    ..CookieRule::default()
+                },
+                CookieRule {
+                    pattern: "*".to_owned(),
+                    secure: true,
+                    same_site: Some(SameSite::Strict),
+                    ..CookieRule::default()
+                },
+            ],
+            ..CookiePolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("key", "key"));
+        assert!(!matches_pattern("key", "key2"));
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("key*", "key"));
+        assert!(matches_pattern("key*", "key2"));
+        assert!(matches_pattern("*_id", "session_id"));
+        assert!(matches_pattern("a*b*c", "a_b_b_c"));
+        assert!(!matches_pattern("a*b*c", "a_c_b"));
+    }
+
+    #[test]
+    fn test_check_name() {
+        let mut policy = CookiePolicy {
+            allowed_names: vec!["sdk_*".to_owned()],
+            blocked_names: vec!["sdk_internal_*".to_owned()],
+            ..CookiePolicy::default()
+        };
+
+        assert_eq!(policy.check_name("sdk_session"), Ok(()));
+        assert_eq!(
+            policy.check_name("session"),
+            Err(PolicyViolation::NameNotAllowed("session".to_owned()))
+        );
+        assert_eq!(
+            policy
+                .apply("sdk_internal_id", &CookieOptions::default(), &[])
+                .unwrap_err(),
+            PolicyViolation::NameBlocked("sdk_internal_id".to_owned(), "sdk_internal_*".to_owned())
+        );
+
+        policy.allowed_names.clear();
+        assert_eq!(policy.check_name("session"), Ok(()));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_from_json() {
+        let policy = policy();
+        let json = serde_json::to_string(&policy).unwrap();
+        let loaded = CookiePolicy::from_json(&json).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+
+        let (name, options) = loaded
+            .apply("session", &CookieOptions::default(), &[])
+            .unwrap();
+        assert_eq!(name, "app_session");
+        assert_eq!(options.to_attribute_string(), "secure;samesite=strict");
+
+        let loaded = CookiePolicy::from_json(r#"{ "rules": [{ "pattern": "*" }] }"#).unwrap();
+        assert_eq!(loaded.name_prefix, "");
+        assert_eq!(loaded.rules[0].same_site, None);
+        assert!(CookiePolicy::from_json(r#"{ "rules": [{ "same_site": "a;b" }] }"#).is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let policy = policy();
+
+        assert_eq!(
+            policy
+                .apply("analytics_id", &CookieOptions::default(), &[])
+                .unwrap_err(),
+            PolicyViolation::ConsentRequired("analytics".to_owned())
+        );
+
+        let (name, options) = policy
+            .apply(
+                "analytics_id",
+                &CookieOptions::default(),
+                &["analytics".to_owned()],
+            )
+            .unwrap();
+        assert_eq!(name, "app_analytics_id");
+        assert_eq!(options.to_attribute_string(), "path=/;samesite=lax");
+
+        let (name, options) = policy
+            .apply("session", &CookieOptions::default().with_path("/path"), &[])
+            .unwrap();
+        assert_eq!(name, "app_session");
+        assert_eq!(
+            options.to_attribute_string(),
+            "path=/path;secure;samesite=strict"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Preferences bundles: several small typed preferences (theme, density, sidebar state...)
+//! packed into a single cookie, instead of one cookie each, which saves header bytes on every
+//! request.
+//!
+//! The preferences are stored as a URI encoded JSON object, whose fields are read and written
+//! one by one: setting a preference rewrites only its field, and keeps the others, even those
+//! unknown to the current version of the application.
+//!
+//! Available only with the `json` feature.
+
+use crate::cookies::{self, CookieOptions};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// A bundle of preferences stored in one cookie.
+#[derive(Clone, Debug)]
+pub struct Prefs {
+    name: String,
+    fields: Map<String, Value>,
+    options: CookieOptions<'static>,
+}
+
+impl Prefs {
+    /// Creates an empty bundle stored in the cookie `name`, with `CookieOptions::ROOT`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            fields: Map::new(),
+            options: CookieOptions::ROOT,
+        }
+    }
+
+    /// Reads the bundle stored in the cookie `name` of `cookie_string`. A missing or malformed
+    /// cookie gives an empty bundle.
+    pub fn load(cookie_string: &str, name: impl Into<String>) -> Self {
+        let mut prefs = Self::new(name);
+
+        prefs.fields = cookies::get(cookie_string, &prefs.name)
+            .and_then(Result::ok)
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default();
+
+        prefs
+    }
+
+    /// Sets the options of the cookie.
+    pub fn with_options(mut self, options: CookieOptions<'static>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the preference `key`, if it exists and can be deserialized as `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        T::deserialize(self.fields.get(key)?).ok()
+    }
+
+    /// Sets the preference `key`, or returns an error if `value` can't be serialized.
+    pub fn set<T: Serialize>(&mut self, key: &str, value: &T) -> Result<(), serde_json::Error> {
+        self.fields
+            .insert(key.to_owned(), serde_json::to_value(value)?);
+        Ok(())
+    }
+
+    /// Removes the preference `key`, and returns true if it existed.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.fields.remove(key).is_some()
+    }
+
+    /// Returns all the preferences deserialized as `T` (a struct with a field per preference),
+    /// or `None` if they can't be.
+    pub fn to_struct<T: DeserializeOwned>(&self) -> Option<T> {
+        T::deserialize(&Value::Object(self.fields.clone())).ok()
+    }
+
+    /// Replaces all the preferences with the fields of `value`, or returns an error if it
+    /// isn't serialized as an object.
+    pub fn set_struct<T: Serialize>(&mut self, value: &T) -> Result<(), serde_json::Error> {
+        match serde_json::to_value(value)? {
+            Value::Object(fields) => {
+                self.fields = fields;
+                Ok(())
+            }
+
+            _ => Err(serde::ser::Error::custom("preferences must be an object")),
+        }
+    }
+
+    /// Returns true if the bundle has no preference.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the cookie string that stores the bundle, or deletes the cookie if it is empty.
+    pub fn to_cookie_string(&self) -> String {
+        let name = urlencoding::encode(&self.name);
+
+        if self.fields.is_empty() {
+            let options = self
+                .options
+                .clone()
+                .expires_at_date("Thu, 01 Jan 1970 00:00:00 GMT");
+
+            cookies::set_raw(&name, "", &options)
+        } else {
+            let value = serde_json::to_string(&self.fields).unwrap();
+            cookies::set_raw(&name, &urlencoding::encode(&value), &self.options)
+        }
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::Prefs;
+    use crate::cookies::CookieOptions;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    /// Reads the bundle stored in the cookie `name`. A missing or malformed cookie gives an
+    /// empty bundle.
+    pub fn load(name: impl Into<String>) -> Prefs {
+        Prefs::load(&crate::cookie_string(), name)
+    }
+
+    /// Writes the bundle to its cookie, or deletes the cookie if the bundle is empty.
+    pub fn save(prefs: &Prefs) {
+        crate::set_cookie_string(&prefs.to_cookie_string());
+    }
+
+    /// Returns the preference `key` of the bundle stored in the cookie `name`, if it exists and
+    /// can be deserialized as `T`.
+    pub fn get<T: DeserializeOwned>(name: &str, key: &str) -> Option<T> {
+        load(name).get(key)
+    }
+
+    /// Sets the preference `key` of the bundle stored in the cookie `name`, keeping the other
+    /// preferences, or returns an error if `value` can't be serialized.
+    pub fn set<T: Serialize>(
+        name: &str,
+        key: &str,
+        value: &T,
+        options: &CookieOptions,
+    ) -> Result<(), serde_json::Error> {
+        let mut prefs = load(name).with_options(options.clone().into_owned());
+        prefs.set(key, value)?;
+        save(&prefs);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_fields() {
+        let mut prefs = Prefs::load(
+            "a=1; prefs=%7B%22theme%22%3A%22dark%22%2C%22x%22%3A1%7D",
+            "prefs",
+        );
+        assert_eq!(prefs.get::<String>("theme").unwrap(), "dark");
+        assert_eq!(prefs.get::<bool>("theme"), None);
+
+        prefs.set("sidebar", &true).unwrap();
+        assert_eq!(
+            prefs.to_cookie_string(),
+            "prefs=%7B%22sidebar%22%3Atrue%2C%22theme%22%3A%22dark%22%2C%22x%22%3A1%7D;path=/;\
+             samesite=lax"
+        );
+    }
+
+    #[test]
+    fn test_struct() {
+        let mut prefs = Prefs::new("prefs");
+        assert!(prefs.set_struct(&1).is_err());
+
+        let ui = json!({ "theme": "light", "sidebar": false });
+        prefs.set_struct(&ui).unwrap();
+        assert_eq!(prefs.get::<bool>("sidebar"), Some(false));
+        assert_eq!(
+            prefs.to_struct::<BTreeMap<String, Value>>().unwrap().len(),
+            2
+        );
+
+        assert!(prefs.remove("theme"));
+        assert!(prefs.remove("sidebar"));
+        assert!(prefs.is_empty());
+        assert_eq!(
+            prefs.to_cookie_string(),
+            "prefs=;path=/;expires=Thu, 01 Jan 1970 00:00:00 GMT;samesite=lax"
+        );
+    }
+}
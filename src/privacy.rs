@@ -0,0 +1,169 @@
+//! Guard honoring the [Global Privacy Control](https://globalprivacycontrol.org) signal (and
+//! optionally Do Not Track) at the storage layer: writes of cookies belonging to tracking or
+//! marketing consent categories are blocked or downgraded to session cookies when the user has
+//! opted out.
+//!
+//! In a browser, a guard can be installed with `wasm_cookies::set_privacy_guard`, and is then
+//! applied by the root module's `set` to the cookies whose category is given by the installed
+//! [CookiePolicy](../policy/struct.CookiePolicy.html).
+
+use crate::cookies::CookieOptions;
+
+/// Privacy preference signals sent by the browser.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrivacySignals {
+    /// `navigator.globalPrivacyControl` is true.
+    pub global_privacy_control: bool,
+
+    /// `navigator.doNotTrack` is `"1"`.
+    pub do_not_track: bool,
+}
+
+impl PrivacySignals {
+    /// Reads the signals from `navigator`.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    #[cfg(target_family = "wasm")]
+    pub fn detect() -> Self {
+        let navigator = web_sys::window().unwrap().navigator();
+        let global_privacy_control =
+            js_sys::Reflect::get(&navigator, &"globalPrivacyControl".into())
+                .ok()
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false);
+
+        Self {
+            global_privacy_control,
+            do_not_track: navigator.do_not_track() == "1",
+        }
+    }
+}
+
+/// What a [PrivacyGuard](struct.PrivacyGuard.html) does with a write it applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GuardMode {
+    /// The cookie isn't written.
+    Block,
+
+    /// The cookie is written as a session cookie (its expiration date is removed).
+    Downgrade,
+}
+
+/// The outcome of a write checked by a [PrivacyGuard](struct.PrivacyGuard.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GuardAction {
+    /// The write is allowed unchanged.
+    Allowed,
+
+    /// The write is blocked.
+    Blocked,
+
+    /// The write is downgraded to a session cookie.
+    Downgraded,
+}
+
+/// Guard blocking or downgrading writes of cookies of some consent categories when the user has
+/// opted out.
+#[derive(Clone, Debug)]
+pub struct PrivacyGuard {
+    /// The guarded consent categories. The default ones are `tracking` and `marketing`.
+    pub categories: Vec<String>,
+
+    /// What to do with writes of cookies of the guarded categories.
+    pub mode: GuardMode,
+
+    /// If true, Do Not Track is honored like Global Privacy Control.
+    pub honor_do_not_track: bool,
+}
+
+impl Default for PrivacyGuard {
+    fn default() -> Self {
+        Self {
+            categories: vec!["tracking".to_owned(), "marketing".to_owned()],
+            mode: GuardMode::Block,
+            honor_do_not_track: false,
+        }
+    }
+}
+
+impl PrivacyGuard {
+    /// Returns true if the user has opted out according to `signals`.
+    pub fn opted_out(&self, signals: &PrivacySignals) -> bool {
+        signals.global_privacy_control || (self.honor_do_not_track && signals.do_not_track)
+    }
+
+    /// Checks the write of a cookie of the consent category `category`, and returns the action
+    /// and, unless the write is blocked, the options to write it with.
+    pub fn check<'a>(
+        &self,
+        signals: &PrivacySignals,
+        category: Option<&str>,
+        options: &CookieOptions<'a>,
+    ) -> (GuardAction, Option<CookieOptions<'a>>) {
+        let guarded = category.is_some_and(|category| {
+            self.categories
+                .iter()
+                .any(|guarded| guarded.as_str() == category)
+        });
+
+        if !guarded || !self.opted_out(signals) {
+            return (GuardAction::Allowed, Some(options.clone()));
+        }
+
+        match self.mode {
+            GuardMode::Block => (GuardAction::Blocked, None),
+            GuardMode::Downgrade => {
+                let mut options = options.clone();
+                options.expires = None;
+                (GuardAction::Downgraded, Some(options))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_check() {
+        let options = CookieOptions::default().expires_after(Duration::from_secs(60));
+        let gpc = PrivacySignals {
+            global_privacy_control: true,
+            do_not_track: false,
+        };
+        let dnt = PrivacySignals {
+            global_privacy_control: false,
+            do_not_track: true,
+        };
+        let mut guard = PrivacyGuard::default();
+
+        assert_eq!(
+            guard.check(&gpc, Some("tracking"), &options).0,
+            GuardAction::Blocked
+        );
+        assert_eq!(
+            guard.check(&gpc, Some("functional"), &options).0,
+            GuardAction::Allowed
+        );
+        assert_eq!(guard.check(&gpc, None, &options).0, GuardAction::Allowed);
+        assert_eq!(
+            guard
+                .check(&PrivacySignals::default(), Some("tracking"), &options)
+                .0,
+            GuardAction::Allowed
+        );
+        assert_eq!(
+            guard.check(&dnt, Some("marketing"), &options).0,
+            GuardAction::Allowed
+        );
+
+        guard.honor_do_not_track = true;
+        guard.mode = GuardMode::Downgrade;
+        let (action, downgraded) = guard.check(&dnt, Some("marketing"), &options);
+        assert_eq!(action, GuardAction::Downgraded);
+        assert!(downgraded.unwrap().expires.is_none());
+    }
+}
@@ -0,0 +1,170 @@
+//! Write provenance debugging: the source location and the time of every cookie write made
+//! through the root functions (`set`, `delete`, and their variants) are recorded in memory, to
+//! find which code path wrote a cookie with an unexpected value.
+//!
+//! The root functions are `#[track_caller]`, so the recorded location is the call site in the
+//! application. Writes made by the browser functions of the other modules are recorded with a
+//! location inside this crate.
+//!
+//! Only the last [DEFAULT_CAPACITY](constant.DEFAULT_CAPACITY.html) writes are kept. Read them
+//! with `wasm_cookies::debug_log`.
+//!
+//! Available only with the `debug-provenance` feature.
+
+use std::collections::VecDeque;
+use std::panic::Location;
+
+/// Default number of writes kept in the log.
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// A recorded cookie write.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WriteRecord {
+    /// Source location of the call that wrote the cookie.
+    pub location: &'static Location<'static>,
+
+    /// Time of the write (timestamp in milliseconds, UTC).
+    pub timestamp: i64,
+
+    /// Written cookie string, with its attributes.
+    pub cookie_string: String,
+}
+
+impl WriteRecord {
+    /// Returns the undecoded name of the written cookie.
+    pub fn name(&self) -> &str {
+        self.cookie_string
+            .split_once('=')
+            .map_or("", |(name, _)| name.trim())
+    }
+}
+
+/// The last recorded writes, oldest first.
+#[derive(Clone, Debug)]
+pub struct ProvenanceLog {
+    records: VecDeque<WriteRecord>,
+    capacity: usize,
+}
+
+impl Default for ProvenanceLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ProvenanceLog {
+    /// Creates an empty log keeping the last `capacity` writes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Records a write, dropping the oldest one if the log is full.
+    pub fn push(&mut self, record: WriteRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+
+        self.records.push_back(record);
+    }
+
+    /// Returns the recorded writes, oldest first.
+    pub fn records(&self) -> impl Iterator<Item = &WriteRecord> {
+        self.records.iter()
+    }
+
+    /// Returns the recorded writes of the cookie `name` (undecoded), oldest first.
+    pub fn for_cookie<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a WriteRecord> {
+        self.records
+            .iter()
+            .filter(move |record| record.name() == name)
+    }
+
+    /// Returns the number of recorded writes.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns true if no write is recorded.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Removes all the recorded writes.
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{ProvenanceLog, WriteRecord};
+    use crate::cookies;
+    use std::cell::RefCell;
+    use std::panic::Location;
+
+    thread_local! {
+        static LOG: RefCell<ProvenanceLog> = RefCell::new(ProvenanceLog::default());
+    }
+
+    /// Records the write of `cookie_string` from `location`.
+    pub(crate) fn record(location: &'static Location<'static>, cookie_string: &str) {
+        let record = WriteRecord {
+            location,
+            timestamp: cookies::now_timestamp(),
+            cookie_string: cookie_string.to_owned(),
+        };
+
+        LOG.with(|log| log.borrow_mut().push(record));
+    }
+
+    /// Returns the recorded writes, oldest first.
+    pub fn records() -> Vec<WriteRecord> {
+        LOG.with(|log| log.borrow().records().cloned().collect())
+    }
+
+    /// Removes all the recorded writes.
+    pub fn clear() {
+        LOG.with(|log| log.borrow_mut().clear());
+    }
+
+    /// Sets the number of writes kept in the log, and removes all the recorded ones.
+    pub fn set_capacity(capacity: usize) {
+        LOG.with(|log| *log.borrow_mut() = ProvenanceLog::new(capacity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log() {
+        let record = |cookie_string: &str| WriteRecord {
+            location: Location::caller(),
+            timestamp: 0,
+            cookie_string: cookie_string.to_owned(),
+        };
+
+        let mut log = ProvenanceLog::new(2);
+        log.push(record("a=1;path=/"));
+        log.push(record("b=1"));
+        log.push(record("a=2;max-age=0"));
+        assert_eq!(log.len(), 2);
+        assert_eq!(log.records().next().unwrap().name(), "b");
+
+        let writes: Vec<_> = log.for_cookie("a").collect();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].cookie_string, "a=2;max-age=0");
+        assert_eq!(writes[0].location.file(), file!());
+    }
+}
@@ -0,0 +1,198 @@
+//! Client-side rate limiting across page reloads and tabs: the timestamps of the recent events
+//! are stored in a cookie, so they are shared by all the tabs of the site and also visible to
+//! the server.
+//!
+//! The timestamps are stored compactly: the first one in milliseconds, the following ones as
+//! differences from the previous one, all in base 36 and separated by `.`.
+
+use crate::cookies::{self, CookieOptions};
+use std::time::Duration;
+
+/// Rate limit of an action: at most `max_events` events per sliding `window`.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    /// Name of the cookie storing the timestamps.
+    pub name: String,
+
+    /// Maximum number of events in a window.
+    pub max_events: usize,
+
+    /// Duration of the sliding window.
+    pub window: Duration,
+
+    /// Options of the cookie, whose expiration date is set to the end of the window.
+    /// The default is `CookieOptions::ROOT`.
+    pub options: CookieOptions<'static>,
+}
+
+fn to_base36(mut n: u64) -> String {
+    let mut digits = Vec::new();
+
+    loop {
+        digits.push(std::char::from_digit((n % 36) as u32, 36).unwrap());
+        n /= 36;
+
+        if n == 0 {
+            break;
+        }
+    }
+
+    digits.iter().rev().collect()
+}
+
+/// Decodes a timestamp list. Returns an empty list if it is malformed, which includes negative
+/// differences and timestamps overflowing `i64`.
+fn decode_timestamps(value: &str) -> Vec<i64> {
+    let mut timestamp: i64 = 0;
+
+    value
+        .split('.')
+        .map(|part| {
+            let delta = i64::from_str_radix(part, 36)
+                .ok()
+                .filter(|delta| *delta >= 0)?;
+            timestamp = timestamp.checked_add(delta)?;
+            Some(timestamp)
+        })
+        .collect::<Option<_>>()
+        .unwrap_or_default()
+}
+
+fn encode_timestamps(timestamps: &[i64]) -> String {
+    let mut previous = 0;
+
+    timestamps
+        .iter()
+        .map(|timestamp| {
+            let part = to_base36((timestamp - previous) as u64);
+            previous = *timestamp;
+            part
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl RateLimit {
+    /// Creates a rate limit of `max_events` events per `window`, stored in the cookie `name`.
+    pub fn new(name: impl Into<String>, max_events: usize, window: Duration) -> Self {
+        Self {
+            name: name.into(),
+            max_events,
+            window,
+            options: CookieOptions::ROOT,
+        }
+    }
+
+    /// Sets the options of the cookie.
+    pub fn with_options(mut self, options: CookieOptions<'static>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the timestamps of the events still in the window at `now` (in milliseconds),
+    /// in ascending order.
+    pub fn events(&self, cookie_string: &str, now: i64) -> Vec<i64> {
+        let window_start = now.saturating_sub(cookies::duration_millis(self.window));
+        let mut timestamps = cookies::get_raw(cookie_string, &self.name)
+            .map(|value| decode_timestamps(&value))
+            .unwrap_or_default();
+
+        timestamps.retain(|timestamp| *timestamp > window_start && *timestamp <= now);
+        timestamps.sort_unstable();
+        timestamps
+    }
+
+    /// Returns true if an event is allowed at `now` (in milliseconds).
+    pub fn is_allowed(&self, cookie_string: &str, now: i64) -> bool {
+        self.events(cookie_string, now).len() < self.max_events
+    }
+
+    /// Records an event at `now` (in milliseconds) if it is allowed, and returns the cookie
+    /// string that stores it. Returns `None` if the event isn't allowed.
+    pub fn record(&self, cookie_string: &str, now: i64) -> Option<String> {
+        let mut timestamps = self.events(cookie_string, now);
+
+        if timestamps.len() >= self.max_events {
+            return None;
+        }
+
+        timestamps.push(now);
+
+        let options = self
+            .options
+            .clone()
+            .expires_at_timestamp(now.saturating_add(cookies::duration_millis(self.window)));
+
+        Some(cookies::set_raw(
+            &self.name,
+            &encode_timestamps(&timestamps),
+            &options,
+        ))
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::RateLimit;
+    use crate::cookies::now_timestamp;
+
+    /// Returns true if an event is allowed now.
+    pub fn is_allowed(limit: &RateLimit) -> bool {
+        limit.is_allowed(&crate::cookie_string(), now_timestamp())
+    }
+
+    /// Records an event now if it is allowed, and returns whether it was.
+    pub fn record(limit: &RateLimit) -> bool {
+        match limit.record(&crate::cookie_string(), now_timestamp()) {
+            Some(cookie_string) => {
+                crate::set_cookie_string(&cookie_string);
+                true
+            }
+
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        let timestamps = [1_700_000_000_000, 1_700_000_000_000, 1_700_000_001_296];
+        let value = encode_timestamps(&timestamps);
+        assert_eq!(value, "loyw3v28.0.100");
+        assert_eq!(decode_timestamps(&value), timestamps);
+        assert_eq!(decode_timestamps("loyw3v28.!"), Vec::<i64>::new());
+        assert_eq!(decode_timestamps("1y2p0ij32e8e7.1"), Vec::<i64>::new());
+        assert_eq!(decode_timestamps("loyw3v28.-1"), Vec::<i64>::new());
+
+        let limit = RateLimit::new("exports", 2, Duration::MAX);
+        assert_eq!(limit.events("exports=loyw3v28", 1_700_000_000_000).len(), 1);
+    }
+
+    #[test]
+    fn test_record() {
+        let limit = RateLimit::new("exports", 2, Duration::from_secs(60));
+        let now = 1_700_000_000_000;
+
+        let cookie_string = limit.record("", now).unwrap();
+        assert!(cookie_string.starts_with("exports=loyw3v28;"));
+        let cookie_string = cookie_string.split(';').next().unwrap().to_owned();
+
+        let cookie_string = limit.record(&cookie_string, now + 1000).unwrap();
+        let cookie_string = cookie_string.split(';').next().unwrap().to_owned();
+        assert_eq!(limit.events(&cookie_string, now + 1000), [now, now + 1000]);
+
+        assert!(!limit.is_allowed(&cookie_string, now + 2000));
+        assert_eq!(limit.record(&cookie_string, now + 2000), None);
+
+        assert!(limit.is_allowed(&cookie_string, now + 60_000));
+        let cookie_string = limit.record(&cookie_string, now + 60_000).unwrap();
+        assert!(cookie_string.starts_with("exports=loyw3vu0.19iw;"));
+    }
+}
@@ -0,0 +1,273 @@
+//! Read-through cache of the parsed cookies, so reading a cookie doesn't parse the whole cookie
+//! string each time.
+//!
+//! Where the `CookieStore` API is available (Chromium based browsers), its change events tell
+//! when the cookies change, so the cache is trusted until the next event, and reads don't even
+//! access `document.cookie`. Elsewhere, each read gets the cookie string and compares it with
+//! the cached one, and the cookies are only parsed again if it changed (see
+//! [CookieCache](../shared_cache/struct.CookieCache.html)).
+//!
+//! Cookies written through this crate invalidate the cache immediately, since change events are
+//! only dispatched asynchronously.
+//!
+//! The names are read as is: the policy, the environment and the middleware aren't applied.
+
+use crate::shared_cache::CookieCache;
+
+/// How the cache learns that the cookies changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Invalidation {
+    /// The cache is trusted until [invalidate](struct.ReadThroughCache.html#method.invalidate)
+    /// is called, for example by a change event.
+    Events,
+
+    /// The cookie string is compared with the cached one on each read.
+    StringCompare,
+}
+
+/// Parsed cookies, read through from the cookie string when they may have changed.
+#[derive(Clone, Debug)]
+pub struct ReadThroughCache {
+    cache: CookieCache,
+    invalidation: Invalidation,
+    is_fresh: bool,
+}
+
+impl ReadThroughCache {
+    /// Creates an empty cache, which reads the cookie string on the first read.
+    pub fn new(invalidation: Invalidation) -> Self {
+        Self {
+            cache: CookieCache::new(),
+            invalidation,
+            is_fresh: false,
+        }
+    }
+
+    /// Returns how the cache is invalidated.
+    pub fn invalidation(&self) -> Invalidation {
+        self.invalidation
+    }
+
+    /// Marks the cached cookies as possibly outdated, so the next read gets the cookie string.
+    pub fn invalidate(&mut self) {
+        self.is_fresh = false;
+    }
+
+    /// Returns true if the cookies were parsed since the last invalidation, so the next read
+    /// won't parse the cookie string (unless it changed, with string comparison).
+    pub fn is_warm(&self) -> bool {
+        self.is_fresh
+    }
+
+    /// Returns the up to date parsed cookies, calling `cookie_string` to get the current cookie
+    /// string only if they may have changed.
+    pub fn read(&mut self, cookie_string: impl FnOnce() -> String) -> &CookieCache {
+        if !self.is_fresh || self.invalidation == Invalidation::StringCompare {
+            self.cache.update(&cookie_string());
+            self.is_fresh = true;
+        }
+
+        &self.cache
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{Invalidation, ReadThroughCache};
+    use crate::cookies;
+    #[cfg(feature = "encoding")]
+    use crate::cookies::GetDecodeError;
+    #[cfg(feature = "encoding")]
+    use crate::encoding::Encoding;
+    use js_sys::Reflect;
+    use std::cell::RefCell;
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_sys::EventTarget;
+
+    struct Installed {
+        cache: ReadThroughCache,
+        store: Option<EventTarget>,
+        _on_change: Option<Closure<dyn FnMut(JsValue)>>,
+    }
+
+    thread_local! {
+        static CACHE: RefCell<Option<Installed>> = const { RefCell::new(None) };
+    }
+
+    fn cookie_store() -> Option<EventTarget> {
+        let window = web_sys::window()?;
+        let store = Reflect::get(&window, &"cookieStore".into()).ok()?;
+
+        if store.is_undefined() || store.is_null() {
+            None
+        } else {
+            Some(store.unchecked_into())
+        }
+    }
+
+    /// Enables the cache, invalidated by the `CookieStore` change events if the API is
+    /// available, and by string comparison otherwise. Returns the invalidation in use.
+    pub fn enable() -> Invalidation {
+        disable();
+
+        let store = cookie_store();
+        let on_change = store.as_ref().map(|store| {
+            let on_change = Closure::wrap(Box::new(|_: JsValue| invalidate()) as Box<_>);
+            crate::expect_js(
+                store
+                    .add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref()),
+            );
+            on_change
+        });
+        let invalidation = if store.is_some() {
+            Invalidation::Events
+        } else {
+            Invalidation::StringCompare
+        };
+
+        CACHE.with(|cache| {
+            *cache.borrow_mut() = Some(Installed {
+                cache: ReadThroughCache::new(invalidation),
+                store,
+                _on_change: on_change,
+            })
+        });
+
+        invalidation
+    }
+
+    /// Disables the cache and removes its change event listener.
+    pub fn disable() {
+        let installed = CACHE.with(|cache| cache.borrow_mut().take());
+
+        if let Some(Installed {
+            store: Some(store),
+            _on_change: Some(on_change),
+            ..
+        }) = installed
+        {
+            let _ = store
+                .remove_event_listener_with_callback("change", on_change.as_ref().unchecked_ref());
+        }
+    }
+
+    /// Enables the cache if needed, and reads and parses the cookie string now, so the next reads
+    /// don't pay for it. Call it during the startup of the application, before time-critical
+    /// rendering.
+    pub fn prefetch() {
+        if !is_enabled() {
+            enable();
+        }
+
+        CACHE.with(|cache| {
+            if let Some(installed) = cache.borrow_mut().as_mut() {
+                installed.cache.read(crate::cookie_string);
+            }
+        });
+    }
+
+    /// Returns true if the cache is enabled.
+    pub fn is_enabled() -> bool {
+        CACHE.with(|cache| cache.borrow().is_some())
+    }
+
+    /// Returns true if the cache is enabled and the cookies were parsed since the last change,
+    /// so the next read won't parse the cookie string.
+    pub fn is_warm() -> bool {
+        CACHE.with(|cache| {
+            cache
+                .borrow()
+                .as_ref()
+                .is_some_and(|installed| installed.cache.is_warm())
+        })
+    }
+
+    /// Marks the cached cookies as possibly outdated. The cookies written through this crate
+    /// already do it.
+    pub fn invalidate() {
+        CACHE.with(|cache| {
+            if let Some(installed) = cache.borrow_mut().as_mut() {
+                installed.cache.invalidate();
+            }
+        });
+    }
+
+    /// Returns undecoded cookie if it exists, from the cache if it is enabled, or from the
+    /// cookie string otherwise.
+    pub fn get_raw(name: &str) -> Option<String> {
+        CACHE.with(|cache| match cache.borrow_mut().as_mut() {
+            Some(installed) => installed
+                .cache
+                .read(crate::cookie_string)
+                .get_raw(name)
+                .map(str::to_owned),
+            None => cookies::get_raw(&crate::cookie_string(), name),
+        })
+    }
+
+    /// If it exists, returns URI decoded cookie
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding))
+    /// or an error if the value's URI decoding fails.
+    #[cfg(feature = "encoding")]
+    pub fn get(name: &str) -> Option<Result<String, GetDecodeError>> {
+        let value = get_raw(&Encoding::Standard.encode(name))?;
+        Some(cookies::decode_value(name, value, Encoding::Standard))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_read() {
+        let reads = Cell::new(0);
+        let cookie_string = |value: &'static str| {
+            let reads = &reads;
+            move || {
+                reads.set(reads.get() + 1);
+                value.to_owned()
+            }
+        };
+
+        let mut cache = ReadThroughCache::new(Invalidation::Events);
+        assert!(!cache.is_warm());
+        assert_eq!(cache.read(cookie_string("a=1")).get_raw("a"), Some("1"));
+        assert!(cache.is_warm());
+        assert_eq!(cache.read(cookie_string("a=2")).get_raw("a"), Some("1"));
+        assert_eq!(reads.get(), 1);
+
+        cache.invalidate();
+        assert!(!cache.is_warm());
+        assert_eq!(cache.read(cookie_string("a=2")).get_raw("a"), Some("2"));
+        assert_eq!(reads.get(), 2);
+
+        let mut cache = ReadThroughCache::new(Invalidation::StringCompare);
+        assert_eq!(cache.read(cookie_string("a=1")).get_raw("a"), Some("1"));
+        assert_eq!(cache.read(cookie_string("a=2")).get_raw("a"), Some("2"));
+        assert_eq!(reads.get(), 4);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let cookie_string = "a=specific; b=2; a=general";
+        let mut cache = ReadThroughCache::new(Invalidation::StringCompare);
+
+        assert_eq!(
+            cache
+                .read(|| cookie_string.to_owned())
+                .get_raw("a")
+                .map(str::to_owned),
+            crate::cookies::get_raw(cookie_string, "a")
+        );
+        assert_eq!(
+            cache.read(|| cookie_string.to_owned()).get_raw("a"),
+            Some("specific")
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Cookie registry, where the application declares each of its cookies with its purpose, consent
+//! category and retention.
+//!
+//! The registry is the cookie inventory kept for GDPR records: it can be exported (see
+//! [CookieRegistry::audit_csv](struct.CookieRegistry.html#method.audit_csv)), checked against
+//! the cookies actually present (see
+//! [CookieRegistry::undeclared](struct.CookieRegistry.html#method.undeclared)), and turned into a
+//! [CookiePolicy](../policy/struct.CookiePolicy.html) enforcing the declared categories (see
+//! [CookieRegistry::to_policy](struct.CookieRegistry.html#method.to_policy)).
+
+use crate::cookies;
+use crate::policy::{matches_pattern, CookiePolicy, CookieRule};
+use std::time::Duration;
+
+/// How long a cookie is kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Retention {
+    /// Until the end of the browser session.
+    Session,
+
+    /// For the given duration after being set.
+    Duration(Duration),
+}
+
+impl Retention {
+    fn describe(&self) -> String {
+        match self {
+            Retention::Session => "session".to_owned(),
+            Retention::Duration(duration) => {
+                let secs = duration.as_secs();
+
+                match secs {
+                    _ if secs >= 86400 && secs % 86400 == 0 => format!("{} days", secs / 86400),
+                    _ if secs >= 3600 && secs % 3600 == 0 => format!("{} hours", secs / 3600),
+                    _ if secs >= 60 && secs % 60 == 0 => format!("{} minutes", secs / 60),
+                    _ => format!("{} seconds", secs),
+                }
+            }
+        }
+    }
+}
+
+/// The declaration of a cookie, or of a family of cookies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CookieDeclaration {
+    /// Cookie name pattern, where `*` matches any sequence of characters
+    /// (for example `analytics_*`).
+    pub name: String,
+
+    /// Why the cookie is set, in plain words.
+    pub purpose: String,
+
+    /// Consent category of the cookie, or `None` if it is strictly necessary.
+    pub category: Option<String>,
+
+    /// How long the cookie is kept.
+    pub retention: Retention,
+}
+
+impl CookieDeclaration {
+    /// Creates the declaration of a strictly necessary cookie.
+    pub fn new(name: impl Into<String>, purpose: impl Into<String>, retention: Retention) -> Self {
+        Self {
+            name: name.into(),
+            purpose: purpose.into(),
+            category: None,
+            retention,
+        }
+    }
+
+    /// Sets the consent category of the cookie.
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+}
+
+/// The inventory of the cookies of an application.
+#[derive(Default, Clone, Debug)]
+pub struct CookieRegistry {
+    /// The declarations, in declaration order.
+    pub declarations: Vec<CookieDeclaration>,
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+impl CookieRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a cookie.
+    pub fn declare(mut self, declaration: CookieDeclaration) -> Self {
+        self.declarations.push(declaration);
+        self
+    }
+
+    /// Returns the first declaration matching the cookie named `name`, if any.
+    pub fn declaration(&self, name: &str) -> Option<&CookieDeclaration> {
+        self.declarations
+            .iter()
+            .find(|declaration| matches_pattern(&declaration.name, name))
+    }
+
+    /// Returns the names of the cookies of `cookie_string` that aren't declared, in order of
+    /// appearance.
+    pub fn undeclared(&self, cookie_string: &str) -> Vec<String> {
+        cookies::all_iter_raw(cookie_string)
+            .map(|(name, _)| name)
+            .filter(|name| self.declaration(name).is_none())
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// Returns the consent categories of the declared cookies, in declaration order and without
+    /// duplicates.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories = Vec::new();
+
+        for category in self
+            .declarations
+            .iter()
+            .filter_map(|declaration| declaration.category.as_deref())
+        {
+            if !categories.contains(&category) {
+                categories.push(category);
+            }
+        }
+
+        categories
+    }
+
+    /// Returns a policy with one rule per declaration, so the declared consent categories are
+    /// enforced. Cookies are prefixed with `name_prefix`.
+    pub fn to_policy(&self, name_prefix: impl Into<String>) -> CookiePolicy {
+        CookiePolicy {
+            name_prefix: name_prefix.into(),
+            rules: self
+                .declarations
+                .iter()
+                .map(|declaration| CookieRule {
+                    pattern: declaration.name.clone(),
+                    category: declaration.category.clone(),
+                    ..CookieRule::default()
+                })
+                .collect(),
+            ..CookiePolicy::default()
+        }
+    }
+
+    /// Exports the registry as CSV, with a header line and the columns `name`, `purpose`,
+    /// `category` (`necessary` for strictly necessary cookies) and `retention`.
+    pub fn audit_csv(&self) -> String {
+        let mut csv = String::from("name,purpose,category,retention\n");
+
+        for declaration in &self.declarations {
+            let fields = [
+                csv_field(&declaration.name),
+                csv_field(&declaration.purpose),
+                csv_field(declaration.category.as_deref().unwrap_or("necessary")),
+                csv_field(&declaration.retention.describe()),
+            ];
+
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::CookieRegistry;
+
+    /// Returns the names of the document's cookies that aren't declared in `registry`.
+    pub fn undeclared(registry: &CookieRegistry) -> Vec<String> {
+        registry.undeclared(&crate::cookie_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> CookieRegistry {
+        CookieRegistry::new()
+            .declare(CookieDeclaration::new(
+                "session",
+                "Keeps the user signed in",
+                Retention::Session,
+            ))
+            .declare(
+                CookieDeclaration::new(
+                    "analytics_*",
+                    "Measures audience, anonymously",
+                    Retention::Duration(Duration::from_secs(13 * 30 * 86400)),
+                )
+                .with_category("analytics"),
+            )
+            .declare(
+                CookieDeclaration::new(
+                    "ads",
+                    "Personalizes \"relevant\" ads",
+                    Retention::Duration(Duration::from_secs(2 * 3600)),
+                )
+                .with_category("marketing"),
+            )
+            .declare(
+                CookieDeclaration::new("ads_*", "Ads frequency capping", Retention::Session)
+                    .with_category("marketing"),
+            )
+    }
+
+    #[test]
+    fn test_lookup() {
+        let registry = registry();
+        assert_eq!(
+            registry.declaration("analytics_id").unwrap().category,
+            Some("analytics".to_owned())
+        );
+        assert_eq!(registry.declaration("other"), None);
+        assert_eq!(
+            registry.undeclared("session=1; tracker=2; analytics_id=3; x=4"),
+            ["tracker", "x"]
+        );
+        assert_eq!(registry.categories(), ["analytics", "marketing"]);
+    }
+
+    #[test]
+    fn test_to_policy() {
+        let policy = registry().to_policy("app_");
+        assert_eq!(policy.name_prefix, "app_");
+        assert_eq!(policy.rules.len(), 4);
+        assert_eq!(policy.rule("session").unwrap().category, None);
+        assert_eq!(
+            policy.rule("analytics_id").unwrap().category,
+            Some("analytics".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_audit_csv() {
+        assert_eq!(
+            registry().audit_csv(),
+            "name,purpose,category,retention\n\
+             session,Keeps the user signed in,necessary,session\n\
+             analytics_*,\"Measures audience, anonymously\",analytics,390 days\n\
+             ads,\"Personalizes \"\"relevant\"\" ads\",marketing,2 hours\n\
+             ads_*,Ads frequency capping,marketing,session\n"
+        );
+    }
+}
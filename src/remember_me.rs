@@ -0,0 +1,179 @@
+//! Remember-me tokens, implementing the selector:validator pattern.
+//!
+//! The token cookie holds a random selector and a random validator. The server stores the
+//! selector with the SHA-256 hash of the validator (see
+//! [hashed_validator](struct.RememberMeToken.html#method.hashed_validator)), looks the hash up by
+//! selector when the cookie is presented, and compares it in constant time (see
+//! [verify](struct.RememberMeToken.html#method.verify)). This way, a leak of the server's
+//! storage doesn't allow forging tokens, and lookups aren't vulnerable to timing attacks.
+//!
+//! The token should be rotated (replaced with a new one) each time it is used.
+//!
+//! Available only with the `crypto` feature.
+
+use crate::cookies::{self, CookieOptions};
+use crate::sha256::{constant_time_eq, sha256, to_hex};
+
+/// Default name of the token cookie.
+pub const COOKIE_NAME: &str = "remember_me";
+
+/// Number of random bytes needed to generate a token.
+pub const RANDOM_BYTES: usize = 32;
+
+/// A remember-me token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RememberMeToken {
+    /// Random identifier of the token, used by the server to look it up.
+    pub selector: String,
+
+    /// Random secret of the token, whose hash is stored by the server.
+    pub validator: String,
+}
+
+impl RememberMeToken {
+    /// Generates a token from cryptographically secure random bytes: the first half is used for
+    /// the selector, the second half for the validator.
+    pub fn from_random_bytes(random: &[u8; RANDOM_BYTES]) -> Self {
+        let (selector, validator) = random.split_at(RANDOM_BYTES / 2);
+
+        Self {
+            selector: to_hex(selector),
+            validator: to_hex(validator),
+        }
+    }
+
+    /// Generates a token from `crypto.getRandomValues`.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    #[cfg(target_family = "wasm")]
+    pub fn generate() -> Self {
+        let mut random = [0; RANDOM_BYTES];
+        crate::fill_random(&mut random);
+        Self::from_random_bytes(&random)
+    }
+
+    /// Returns the value of the token cookie (`selector:validator`).
+    pub fn cookie_value(&self) -> String {
+        format!("{}:{}", self.selector, self.validator)
+    }
+
+    /// Parses the value of the token cookie. Returns `None` if it is malformed.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (selector, validator) = value.split_once(':')?;
+        let is_hex = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_hexdigit());
+
+        if is_hex(selector) && is_hex(validator) {
+            Some(Self {
+                selector: selector.to_owned(),
+                validator: validator.to_owned(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the hexadecimal SHA-256 hash of the validator, to be stored by the server.
+    pub fn hashed_validator(&self) -> String {
+        to_hex(&sha256(self.validator.as_bytes()))
+    }
+
+    /// Returns true if the validator matches `hashed_validator`, comparing in constant time.
+    pub fn verify(&self, hashed_validator: &str) -> bool {
+        constant_time_eq(
+            self.hashed_validator().as_bytes(),
+            hashed_validator.to_ascii_lowercase().as_bytes(),
+        )
+    }
+}
+
+/// Return the cookie string that sets the token cookie.
+///
+/// Recommended options are `CookieOptions::strict_security()` with an expiration date.
+pub fn set(token: &RememberMeToken, options: &CookieOptions) -> String {
+    cookies::set_raw(COOKIE_NAME, &token.cookie_value(), options)
+}
+
+/// Returns the token stored in the token cookie, if it exists and is well-formed.
+pub fn get(cookie_string: &str) -> Option<RememberMeToken> {
+    RememberMeToken::parse(&cookies::get_raw(cookie_string, COOKIE_NAME)?)
+}
+
+/// Return the cookie string that deletes the token cookie.
+pub fn clear() -> String {
+    cookies::delete_raw(COOKIE_NAME)
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::RememberMeToken;
+    use crate::cookies::CookieOptions;
+
+    /// Returns the token stored in the token cookie, if it exists and is well-formed.
+    pub fn current() -> Option<RememberMeToken> {
+        super::get(&crate::cookie_string())
+    }
+
+    /// Replaces the token cookie with a new random token, and returns it so its selector and
+    /// hashed validator can be sent to the server.
+    pub fn rotate(options: &CookieOptions) -> RememberMeToken {
+        let token = RememberMeToken::generate();
+        crate::set_cookie_string(&super::set(&token, options));
+        token
+    }
+
+    /// Deletes the token cookie.
+    pub fn clear() {
+        crate::set_cookie_string(&super::clear());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token() {
+        let mut random = [0; RANDOM_BYTES];
+
+        for (i, byte) in random.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        let token = RememberMeToken::from_random_bytes(&random);
+        assert_eq!(token.selector, "000102030405060708090a0b0c0d0e0f");
+        assert_eq!(token.validator, "101112131415161718191a1b1c1d1e1f");
+        assert_eq!(
+            RememberMeToken::parse(&token.cookie_value()),
+            Some(token.clone())
+        );
+
+        let hashed_validator = token.hashed_validator();
+        assert_eq!(hashed_validator.len(), 64);
+        assert!(token.verify(&hashed_validator));
+        assert!(token.verify(&hashed_validator.to_ascii_uppercase()));
+        assert!(!RememberMeToken::from_random_bytes(&[0; RANDOM_BYTES]).verify(&hashed_validator));
+
+        assert_eq!(RememberMeToken::parse("abc"), None);
+        assert_eq!(RememberMeToken::parse("abc:"), None);
+        assert_eq!(RememberMeToken::parse("abc:xyz"), None);
+    }
+
+    #[test]
+    fn test_cookies() {
+        let token = RememberMeToken::from_random_bytes(&[0xab; RANDOM_BYTES]);
+        let cookie_string = set(&token, &CookieOptions::strict_security());
+        assert!(cookie_string.starts_with("remember_me=abab"));
+        assert!(cookie_string.ends_with(";path=/;secure;samesite=strict"));
+
+        assert_eq!(
+            get(&format!("other=1; remember_me={}", token.cookie_value())),
+            Some(token)
+        );
+        assert_eq!(get("remember_me=malformed"), None);
+        assert_eq!(get(""), None);
+        assert_eq!(clear(), cookies::delete_raw("remember_me"));
+    }
+}
@@ -0,0 +1,302 @@
+//! Save states of games, persisted in cookies so they are visible to the server (for example in
+//! builds hosted on sites which don't give access to any other storage).
+//!
+//! A save state is any `serde` serializable struct. It is serialized as JSON, compressed,
+//! encoded in URL-safe Base64, and stored in one or more cookies named `<name>_<slot>_0`,
+//! `<name>_<slot>_1`... (see [drafts](../drafts/index.html)), with the time it was saved and a
+//! checksum, so a partially written save is detected instead of loaded.
+//!
+//! Several saves can be kept in named slots. Browsers limit the number of cookies per domain
+//! (usually to 50), so large saves should use few slots.
+//!
+//! Available only with the `json` feature.
+
+use crate::base64::{decode_url_safe, encode_url_safe};
+use crate::cookies::{self, CookieOptions};
+use crate::crc32::crc32;
+use crate::drafts::{chunk_count, chunk_name, delete_chunks, CHUNK_SIZE};
+use crate::lz;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Default name of the save cookies.
+pub const COOKIE_NAME: &str = "save";
+
+/// Error returned when saving a state.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SaveError {
+    /// The slot name is empty, or contains characters other than ASCII letters, digits and `-`.
+    InvalidSlot,
+
+    /// The state can't be serialized.
+    Serialize(serde_json::Error),
+}
+
+/// A saved state, with the time it was saved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Saved<T> {
+    /// The state.
+    pub state: T,
+
+    /// Timestamp in milliseconds, UTC, of the save.
+    pub saved_at: i64,
+}
+
+/// A used slot, with the time of its save.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveSlot {
+    /// The name of the slot.
+    pub slot: String,
+
+    /// Timestamp in milliseconds, UTC, of the save.
+    pub saved_at: i64,
+}
+
+/// Returns true if `slot` is a valid slot name: non-empty, made of ASCII letters, digits and
+/// `-`.
+pub fn is_valid_slot(slot: &str) -> bool {
+    !slot.is_empty() && slot.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn slot_name(name: &str, slot: &str) -> String {
+    format!("{}_{}", name, slot)
+}
+
+/// Parses the header of a stored save: `saved_at.checksum.`, followed by the data.
+fn parse_header(value: &str) -> Option<(i64, u32, &str)> {
+    let (saved_at, rest) = value.split_once('.')?;
+    let (checksum, data) = rest.split_once('.')?;
+    Some((
+        saved_at.parse().ok()?,
+        u32::from_str_radix(checksum, 16).ok()?,
+        data,
+    ))
+}
+
+/// Returns the cookie strings that save `state` at `now` (timestamp in milliseconds, UTC) in the
+/// slot `slot`, including the ones that delete the chunks of a larger previous save found in
+/// `cookie_string`.
+///
+/// The options should have an expiration date, so the save outlives the browser session.
+pub fn save<T: Serialize>(
+    cookie_string: &str,
+    name: &str,
+    slot: &str,
+    state: &T,
+    now: i64,
+    options: &CookieOptions,
+) -> Result<Vec<String>, SaveError> {
+    if !is_valid_slot(slot) {
+        return Err(SaveError::InvalidSlot);
+    }
+
+    let json = serde_json::to_vec(state).map_err(SaveError::Serialize)?;
+    let data = encode_url_safe(&lz::compress(&json));
+    let value = format!("{}.{:08x}.{}", now, crc32(data.as_bytes()), data);
+    let name = slot_name(name, slot);
+
+    // The value is ASCII, so it can be split at any byte.
+    let mut cookie_strings: Vec<String> = value
+        .as_bytes()
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .enumerate()
+        .map(|(index, chunk)| cookies::set_raw(&chunk_name(&name, index), chunk, options))
+        .collect();
+
+    let written = cookie_strings.len();
+    cookie_strings.extend(delete_chunks(
+        &name,
+        written..chunk_count(&name, cookie_string).max(written),
+        options,
+    ));
+    Ok(cookie_strings)
+}
+
+/// Returns the state saved in the slot `slot`, if it exists, is complete and can be
+/// deserialized as `T`.
+pub fn load<T: DeserializeOwned>(cookie_string: &str, name: &str, slot: &str) -> Option<Saved<T>> {
+    if !is_valid_slot(slot) {
+        return None;
+    }
+
+    let name = slot_name(name, slot);
+    let value: String = (0..chunk_count(&name, cookie_string))
+        .filter_map(|index| cookies::get_raw(cookie_string, &chunk_name(&name, index)))
+        .collect();
+
+    let (saved_at, checksum, data) = parse_header(&value)?;
+
+    if crc32(data.as_bytes()) != checksum {
+        return None;
+    }
+
+    let json = lz::decompress(&decode_url_safe(data)?)?;
+
+    Some(Saved {
+        state: serde_json::from_slice(&json).ok()?,
+        saved_at,
+    })
+}
+
+/// Returns the used slots, sorted by name. The saves aren't checked, so a listed slot can fail
+/// to load.
+pub fn slots(cookie_string: &str, name: &str) -> Vec<SaveSlot> {
+    let prefix = format!("{}_", name);
+
+    let mut slots: Vec<SaveSlot> = cookies::all_iter_raw(cookie_string)
+        .filter_map(|(cookie_name, value)| {
+            let slot = cookie_name.strip_prefix(&prefix)?.strip_suffix("_0")?;
+
+            if is_valid_slot(slot) {
+                Some(SaveSlot {
+                    slot: slot.to_owned(),
+                    saved_at: parse_header(value)?.0,
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    slots.sort_by(|a, b| a.slot.cmp(&b.slot));
+    slots.dedup_by(|a, b| a.slot == b.slot);
+    slots
+}
+
+/// Returns the cookie strings that delete the save in the slot `slot`.
+pub fn delete(cookie_string: &str, name: &str, slot: &str, options: &CookieOptions) -> Vec<String> {
+    let name = slot_name(name, slot);
+    delete_chunks(&name, 0..chunk_count(&name, cookie_string), options).collect()
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{SaveError, SaveSlot, Saved};
+    use crate::cookies::{self, CookieOptions};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    /// Saves `state` in the slot `slot`.
+    ///
+    /// The options should have an expiration date, so the save outlives the browser session.
+    pub fn save<T: Serialize>(
+        name: &str,
+        slot: &str,
+        state: &T,
+        options: &CookieOptions,
+    ) -> Result<(), SaveError> {
+        let cookie_strings = super::save(
+            &crate::cookie_string(),
+            name,
+            slot,
+            state,
+            cookies::now_timestamp(),
+            options,
+        )?;
+
+        for cookie_string in cookie_strings {
+            crate::set_cookie_string(&cookie_string);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the state saved in the slot `slot`, if it exists, is complete and can be
+    /// deserialized as `T`.
+    pub fn load<T: DeserializeOwned>(name: &str, slot: &str) -> Option<Saved<T>> {
+        super::load(&crate::cookie_string(), name, slot)
+    }
+
+    /// Returns the used slots, sorted by name.
+    pub fn slots(name: &str) -> Vec<SaveSlot> {
+        super::slots(&crate::cookie_string(), name)
+    }
+
+    /// Deletes the save in the slot `slot`.
+    pub fn delete(name: &str, slot: &str, options: &CookieOptions) {
+        for cookie_string in super::delete(&crate::cookie_string(), name, slot, options) {
+            crate::set_cookie_string(&cookie_string);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    type State = BTreeMap<String, Vec<u32>>;
+
+    fn cookie_pairs(cookie_strings: &[String]) -> String {
+        cookie_strings
+            .iter()
+            .map(|cookie_string| cookie_string.split(';').next().unwrap())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    #[test]
+    fn test_save_load() {
+        let options = CookieOptions::ROOT;
+        let mut state = State::new();
+        state.insert("level".to_owned(), vec![3]);
+        state.insert("map".to_owned(), (0..2000).map(|i| i % 7).collect());
+
+        let cookie_strings = save("", COOKIE_NAME, "slot-1", &state, 1000, &options).unwrap();
+        assert!(cookie_strings[0].starts_with("save_slot-1_0=1000."));
+        let mut cookie_string = cookie_pairs(&cookie_strings);
+
+        let other = save("", COOKIE_NAME, "b", &State::new(), 2000, &options).unwrap();
+        cookie_string = format!("{}; {}", cookie_string, cookie_pairs(&other));
+
+        assert_eq!(
+            load(&cookie_string, COOKIE_NAME, "slot-1"),
+            Some(Saved {
+                state,
+                saved_at: 1000
+            })
+        );
+        assert_eq!(
+            slots(&cookie_string, COOKIE_NAME)
+                .iter()
+                .map(|slot| (slot.slot.as_str(), slot.saved_at))
+                .collect::<Vec<_>>(),
+            [("b", 2000), ("slot-1", 1000)]
+        );
+
+        assert!(matches!(
+            save("", COOKIE_NAME, "a_b", &1, 0, &options),
+            Err(SaveError::InvalidSlot)
+        ));
+        assert_eq!(load::<State>(&cookie_string, COOKIE_NAME, "c"), None);
+
+        let deleted = delete(&cookie_string, COOKIE_NAME, "b", &options);
+        assert_eq!(deleted.len(), 1);
+        assert!(deleted[0].contains("expires=Thu, 01 Jan 1970"));
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let options = CookieOptions::ROOT;
+        let state: Vec<String> = (0..3000).map(|i| format!("item {:x}", i * 7919)).collect();
+        let cookie_strings = save("", COOKIE_NAME, "a", &state, 0, &options).unwrap();
+        assert!(cookie_strings.len() > 1);
+
+        // A save whose last chunk wasn't written isn't loaded.
+        let partial = cookie_pairs(&cookie_strings[..cookie_strings.len() - 1]);
+        assert_eq!(load::<Vec<String>>(&partial, COOKIE_NAME, "a"), None);
+
+        let complete = cookie_pairs(&cookie_strings);
+        assert_eq!(
+            load::<Vec<String>>(&complete, COOKIE_NAME, "a")
+                .unwrap()
+                .state,
+            state
+        );
+    }
+}
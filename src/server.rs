@@ -0,0 +1,149 @@
+//! `Set-Cookie` headers for servers, such as handlers running on a WASI HTTP runtime, so the
+//! same crate manages cookies in the browser and on the server.
+//!
+//! Available only with the `server` feature.
+
+use crate::cookies::{self, Cookie, CookieOptions};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// A cookie set by a response, with the attributes only servers can set.
+#[derive(Default, Clone, Debug)]
+pub struct ResponseCookie<'a> {
+    /// The cookie, with non encoded name and value. Its `http_only` flag is honored.
+    pub cookie: Cookie<'a>,
+
+    /// The `Max-Age` attribute, which takes precedence over the expiration date in browsers.
+    pub max_age: Option<Duration>,
+}
+
+impl<'a> ResponseCookie<'a> {
+    /// Creates a response cookie with default options.
+    pub fn new(name: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) -> Self {
+        Self::from(Cookie::new(name, value))
+    }
+
+    /// Creates a response cookie deleting the cookie `name` set with `options` (only the path
+    /// and domain matter).
+    pub fn removal(name: impl Into<Cow<'a, str>>, options: &CookieOptions<'a>) -> Self {
+        let mut cookie = Cookie::new(name, "");
+        cookie.options.path = options.path.clone();
+        cookie.options.domain = options.domain.clone();
+
+        Self {
+            cookie: Cookie {
+                options: cookie.options.expires_at_timestamp(0),
+                ..cookie
+            },
+            max_age: Some(Duration::from_secs(0)),
+        }
+    }
+
+    /// Sets the `HttpOnly` flag, making the cookie inaccessible to JavaScript.
+    pub fn http_only(mut self) -> Self {
+        self.cookie.http_only = true;
+        self
+    }
+
+    /// Sets the `Max-Age` attribute.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Returns the value of the `Set-Cookie` header.
+    pub fn header_value(&self) -> String {
+        let mut header =
+            cookies::set_raw(&self.cookie.name, &self.cookie.value, &self.cookie.options);
+
+        if let Some(max_age) = self.max_age {
+            header.push_str(";max-age=");
+            header.push_str(&max_age.as_secs().to_string());
+        }
+
+        if self.cookie.http_only {
+            header.push_str(";httponly");
+        }
+
+        header
+    }
+}
+
+impl<'a> From<Cookie<'a>> for ResponseCookie<'a> {
+    fn from(cookie: Cookie<'a>) -> Self {
+        Self {
+            cookie,
+            max_age: None,
+        }
+    }
+}
+
+/// Returns the `Set-Cookie` header fields setting `cookies`, as `(name, value)` pairs ready for
+/// a `wasi:http` `Fields` list, one field per cookie since `Set-Cookie` values can't be joined.
+///
+/// Browsers apply the headers in order, so when several cookies have the same name, path and
+/// domain, only the last one is kept, at its position.
+pub fn build_set_cookie_headers<'a>(
+    cookies: impl IntoIterator<Item = ResponseCookie<'a>>,
+) -> Vec<(String, Vec<u8>)> {
+    let cookies = cookies.into_iter().collect::<Vec<_>>();
+
+    cookies
+        .iter()
+        .enumerate()
+        .filter(|(index, cookie)| {
+            !cookies[index + 1..].iter().any(|later| {
+                later.cookie.name == cookie.cookie.name
+                    && later.cookie.options.path == cookie.cookie.options.path
+                    && later.cookie.options.domain == cookie.cookie.options.domain
+            })
+        })
+        .map(|(_, cookie)| ("set-cookie".to_owned(), cookie.header_value().into_bytes()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_value() {
+        assert_eq!(
+            ResponseCookie::from(Cookie::new("id", "1"))
+                .http_only()
+                .with_max_age(Duration::from_secs(60))
+                .header_value(),
+            "id=1;samesite=lax;max-age=60;httponly"
+        );
+        assert_eq!(
+            ResponseCookie::removal("id", &CookieOptions::ROOT).header_value(),
+            "id=;path=/;expires=Thu, 01 Jan 1970 00:00:00 GMT;samesite=lax;max-age=0"
+        );
+    }
+
+    #[test]
+    fn test_build_set_cookie_headers() {
+        let root = CookieOptions::ROOT;
+        let headers = build_set_cookie_headers(vec![
+            ResponseCookie::new("a", "1"),
+            ResponseCookie::from(Cookie {
+                options: root.clone(),
+                ..Cookie::new("b", "2")
+            }),
+            ResponseCookie::new("a", "3"),
+            ResponseCookie::removal("b", &CookieOptions::new()),
+        ]);
+
+        assert_eq!(
+            headers,
+            [
+                ("set-cookie".to_owned(), b"b=2;path=/;samesite=lax".to_vec()),
+                ("set-cookie".to_owned(), b"a=3;samesite=lax".to_vec()),
+                (
+                    "set-cookie".to_owned(),
+                    b"b=;expires=Thu, 01 Jan 1970 00:00:00 GMT;samesite=lax;max-age=0".to_vec()
+                ),
+            ]
+        );
+    }
+}
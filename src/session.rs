@@ -0,0 +1,385 @@
+//! Encrypted sessions stored in cookies, for client-only apps without a backend session store.
+//!
+//! A session is stored in two cookies: `session_id` holds a random session ID and the
+//! expiration date of the session, and `session_data` holds the data of the session, serialized
+//! as JSON and encrypted with AES-256-GCM (with the [aes-gcm crate](https://crates.io/crates/aes-gcm)).
+//! The value of the ID cookie is authenticated with the data, so the data can't be moved to
+//! another session, and the expiration date can't be pushed back without the decryption failing.
+//!
+//! The key is provided by the application. A key shipped in the bundle can be read by the
+//! scripts of the page, so the encryption hides the data from whoever only sees the cookies (the
+//! servers of the domain, their logs, proxies...), not from the page itself.
+//!
+//! Available only with the `session` feature.
+
+use crate::base64::{decode_url_safe, encode_url_safe};
+use crate::cookies::{self, CookieOptions};
+use crate::sha256::to_hex;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+/// Name of the cookie holding the session ID.
+pub const ID_COOKIE_NAME: &str = "session_id";
+
+/// Name of the cookie holding the encrypted data.
+pub const DATA_COOKIE_NAME: &str = "session_data";
+
+/// Size in bytes of the AES-256 key.
+pub const KEY_BYTES: usize = 32;
+
+/// Number of random bytes needed to generate a session ID.
+pub const ID_BYTES: usize = 16;
+
+/// Size in bytes of the nonce of each encryption.
+pub const NONCE_BYTES: usize = 12;
+
+/// Error returned when reading or writing the data of a [Session](struct.Session.html).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SessionError {
+    /// The session has expired.
+    Expired,
+
+    /// The data cookie is malformed, has been tampered with, or was encrypted with another key
+    /// or for another session.
+    Decrypt,
+
+    /// The data can't be serialized to JSON or deserialized from it.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionError::Expired => write!(f, "the session has expired"),
+            SessionError::Decrypt => write!(f, "cannot decrypt the session data"),
+            SessionError::Json(error) => write!(f, "invalid session data: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<serde_json::Error> for SessionError {
+    fn from(error: serde_json::Error) -> Self {
+        SessionError::Json(error)
+    }
+}
+
+/// An encrypted session.
+#[derive(Clone)]
+pub struct Session<'a> {
+    id: String,
+    expires: i64,
+    cipher: Aes256Gcm,
+    options: CookieOptions<'a>,
+}
+
+impl fmt::Debug for Session<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("id", &self.id)
+            .field("expires", &self.expires)
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Session<'a> {
+    /// Creates a session at `now` (timestamp in milliseconds, UTC), valid for `lifetime`, whose
+    /// ID is generated from cryptographically secure random bytes. Its cookies are set with
+    /// `options`, whose expiration date is replaced with the one of the session.
+    pub fn from_random_bytes(
+        key: &[u8; KEY_BYTES],
+        random: &[u8; ID_BYTES],
+        now: i64,
+        lifetime: Duration,
+        options: &CookieOptions<'a>,
+    ) -> Self {
+        let expires = now.saturating_add(cookies::duration_millis(lifetime));
+
+        Self {
+            id: to_hex(random),
+            expires,
+            cipher: Aes256Gcm::new(key.into()),
+            options: options.clone().expires_at_timestamp(expires),
+        }
+    }
+
+    /// Returns the session whose ID cookie is stored in `cookie_string`, if it exists, is
+    /// well-formed and is unexpired at `now` (timestamp in milliseconds, UTC).
+    pub fn resume_in(
+        cookie_string: &str,
+        key: &[u8; KEY_BYTES],
+        now: i64,
+        options: &CookieOptions<'a>,
+    ) -> Option<Self> {
+        let value = cookies::get_raw(cookie_string, ID_COOKIE_NAME)?;
+        let (id, expires) = value.split_once('.')?;
+        let expires: i64 = expires.parse().ok()?;
+        let is_id = id.len() == ID_BYTES * 2 && id.chars().all(|c| c.is_ascii_hexdigit());
+
+        if !is_id || now >= expires {
+            return None;
+        }
+
+        Some(Self {
+            id: id.to_owned(),
+            expires,
+            cipher: Aes256Gcm::new(key.into()),
+            options: options.clone().expires_at_timestamp(expires),
+        })
+    }
+
+    /// Returns the ID of the session.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the expiration date of the session (timestamp in milliseconds, UTC).
+    pub fn expires(&self) -> i64 {
+        self.expires
+    }
+
+    /// Returns the value of the ID cookie, which is authenticated with the data.
+    fn id_value(&self) -> String {
+        format!("{}.{}", self.id, self.expires)
+    }
+
+    /// Returns the cookie string that sets the ID cookie.
+    pub fn id_cookie(&self) -> String {
+        cookies::set_raw(ID_COOKIE_NAME, &self.id_value(), &self.options)
+    }
+
+    /// Returns the data of the session stored in `cookie_string`, decrypted and deserialized,
+    /// or `None` if there is no data cookie. Fails if the session has expired at `now`
+    /// (timestamp in milliseconds, UTC), or if the data can't be decrypted or deserialized.
+    pub fn data_in<T: DeserializeOwned>(
+        &self,
+        cookie_string: &str,
+        now: i64,
+    ) -> Result<Option<T>, SessionError> {
+        if now >= self.expires {
+            return Err(SessionError::Expired);
+        }
+
+        let value = match cookies::get_raw(cookie_string, DATA_COOKIE_NAME) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let bytes = decode_url_safe(&value).ok_or(SessionError::Decrypt)?;
+
+        if bytes.len() < NONCE_BYTES {
+            return Err(SessionError::Decrypt);
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_BYTES);
+        let id_value = self.id_value();
+        let payload = Payload {
+            msg: ciphertext,
+            aad: id_value.as_bytes(),
+        };
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| SessionError::Decrypt)?;
+
+        Ok(Some(serde_json::from_slice(&plaintext)?))
+    }
+
+    /// Returns the cookie string that stores `data`, serialized to JSON and encrypted with
+    /// `nonce`, which must be cryptographically secure random bytes: a nonce must never be
+    /// reused with the same key.
+    pub fn set_data_in<T: Serialize>(
+        &self,
+        data: &T,
+        nonce: &[u8; NONCE_BYTES],
+    ) -> Result<String, SessionError> {
+        let plaintext = serde_json::to_vec(data)?;
+        let id_value = self.id_value();
+        let payload = Payload {
+            msg: &plaintext,
+            aad: id_value.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(nonce), payload)
+            .expect("AES-GCM encrypts payloads of any cookie size");
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend(ciphertext);
+        Ok(cookies::set_raw(
+            DATA_COOKIE_NAME,
+            &encode_url_safe(&bytes),
+            &self.options,
+        ))
+    }
+
+    /// Returns the cookie strings that delete the cookies of the session.
+    pub fn destroy_cookies(&self) -> [String; 2] {
+        [
+            cookies::scoped_delete_raw(ID_COOKIE_NAME, Some(&self.options)),
+            cookies::scoped_delete_raw(DATA_COOKIE_NAME, Some(&self.options)),
+        ]
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl<'a> Session<'a> {
+    /// Starts a new session valid for `lifetime`, with an ID from `crypto.getRandomValues`: sets
+    /// its ID cookie, and deletes the data of the previous session, if any.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    pub fn start(key: &[u8; KEY_BYTES], lifetime: Duration, options: &CookieOptions<'a>) -> Self {
+        let mut random = [0; ID_BYTES];
+        crate::fill_random(&mut random);
+
+        let session =
+            Self::from_random_bytes(key, &random, cookies::now_timestamp(), lifetime, options);
+        crate::set_cookie_string(&session.id_cookie());
+        crate::set_cookie_string(&cookies::scoped_delete_raw(
+            DATA_COOKIE_NAME,
+            Some(&session.options),
+        ));
+        session
+    }
+
+    /// Returns the current session, if its ID cookie exists, is well-formed and is unexpired.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    pub fn resume(key: &[u8; KEY_BYTES], options: &CookieOptions<'a>) -> Option<Self> {
+        Self::resume_in(
+            &crate::cookie_string(),
+            key,
+            cookies::now_timestamp(),
+            options,
+        )
+    }
+
+    /// Returns the data of the session, decrypted and deserialized, or `None` if no data has
+    /// been stored.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    pub fn data<T: DeserializeOwned>(&self) -> Result<Option<T>, SessionError> {
+        self.data_in(&crate::cookie_string(), cookies::now_timestamp())
+    }
+
+    /// Stores `data` in the session, encrypted with a nonce from `crypto.getRandomValues`.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    pub fn set_data<T: Serialize>(&self, data: &T) -> Result<(), SessionError> {
+        let mut nonce = [0; NONCE_BYTES];
+        crate::fill_random(&mut nonce);
+        crate::set_cookie_string(&self.set_data_in(data, &nonce)?);
+        Ok(())
+    }
+
+    /// Deletes the cookies of the session.
+    ///
+    /// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+    pub fn destroy(self) {
+        for cookie_string in &self.destroy_cookies() {
+            crate::set_cookie_string(cookie_string);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; KEY_BYTES] = [7; KEY_BYTES];
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn cookie_pair(cookie_string: &str) -> &str {
+        cookie_string.split(';').next().unwrap()
+    }
+
+    #[test]
+    fn test_data() {
+        let session =
+            Session::from_random_bytes(&KEY, &[0xab; ID_BYTES], 0, HOUR, &CookieOptions::ROOT);
+        assert_eq!(session.id(), "ab".repeat(ID_BYTES));
+        assert_eq!(session.expires(), 3_600_000);
+        assert!(session
+            .id_cookie()
+            .contains(";expires=Thu, 01 Jan 1970 01:00:00 GMT"));
+
+        let data = session
+            .set_data_in(&("user".to_owned(), 42), &[1; NONCE_BYTES])
+            .unwrap();
+        let cookie_string = format!(
+            "{}; {}",
+            cookie_pair(&session.id_cookie()),
+            cookie_pair(&data)
+        );
+        assert!(!cookie_string.contains("user"));
+
+        let resumed = Session::resume_in(&cookie_string, &KEY, 1000, &CookieOptions::ROOT).unwrap();
+        assert_eq!(resumed.id(), session.id());
+        assert_eq!(
+            resumed
+                .data_in::<(String, u32)>(&cookie_string, 1000)
+                .unwrap(),
+            Some(("user".to_owned(), 42))
+        );
+        assert!(matches!(
+            resumed.data_in::<(String, u32)>(&cookie_string, 3_600_000),
+            Err(SessionError::Expired)
+        ));
+        assert_eq!(
+            session
+                .data_in::<(String, u32)>(cookie_pair(&session.id_cookie()), 0)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            Session::resume_in(&cookie_string, &KEY, 3_600_000, &CookieOptions::ROOT).map(|_| ()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_tampering() {
+        let session =
+            Session::from_random_bytes(&KEY, &[1; ID_BYTES], 0, HOUR, &CookieOptions::ROOT);
+        let data = cookie_pair(&session.set_data_in(&1, &[2; NONCE_BYTES]).unwrap()).to_owned();
+
+        // Another key, another session, or an expiration date pushed back.
+        let other_key = Session::from_random_bytes(
+            &[8; KEY_BYTES],
+            &[1; ID_BYTES],
+            0,
+            HOUR,
+            &CookieOptions::ROOT,
+        );
+        let other_id =
+            Session::from_random_bytes(&KEY, &[3; ID_BYTES], 0, HOUR, &CookieOptions::ROOT);
+        let extended =
+            Session::from_random_bytes(&KEY, &[1; ID_BYTES], 1000, HOUR, &CookieOptions::ROOT);
+
+        for session in [other_key, other_id, extended] {
+            assert!(matches!(
+                session.data_in::<i32>(&data, 0),
+                Err(SessionError::Decrypt)
+            ));
+        }
+
+        let mut tampered = data.clone();
+        tampered.pop();
+        assert!(matches!(
+            session.data_in::<i32>(&tampered, 0),
+            Err(SessionError::Decrypt)
+        ));
+        assert_eq!(session.data_in::<i32>(&data, 0).unwrap(), Some(1));
+
+        let [id, data] = session.destroy_cookies();
+        assert!(id.starts_with("session_id=;"));
+        assert!(data.starts_with("session_data=;"));
+    }
+}
@@ -0,0 +1,345 @@
+//! Cookie cache coordinated by a `SharedWorker`, which holds a canonical parsed cookie map and
+//! serves reads to all the connected tabs, so the cookie string is parsed once for all of them.
+//!
+//! Since `document.cookie` is unavailable in workers, the tabs keep the coordinator up to date:
+//! a [SharedCacheClient](struct.SharedCacheClient.html) sends the current cookie string when it
+//! connects, and again each time [invalidate](struct.SharedCacheClient.html#method.invalidate)
+//! is called (typically after a write, or when a cookie change event is received).
+//! The coordinator only parses the cookie string again if it actually changed.
+//!
+//! The cookies visible to a tab depend on its path, so the coordinator keeps one map per path,
+//! and each tab reads the map of its own path.
+//!
+//! The cache and the message protocol are available on every target.
+
+#[cfg(all(target_family = "wasm", feature = "encoding"))]
+use crate::cookies::{self, GetDecodeError};
+#[cfg(all(target_family = "wasm", feature = "encoding"))]
+use crate::encoding::Encoding;
+#[cfg(target_family = "wasm")]
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(target_family = "wasm")]
+use std::rc::Rc;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+#[cfg(target_family = "wasm")]
+use web_sys::{MessageEvent, MessagePort, SharedWorker, SharedWorkerGlobalScope};
+
+const MESSAGE_PREFIX: &str = "wasm-cookies:";
+
+/// Canonical parsed cookie map, which is only parsed again when the cookie string changes.
+#[derive(Default, Clone, Debug)]
+pub struct CookieCache {
+    cookie_string: String,
+    cookies: HashMap<String, String>,
+}
+
+impl CookieCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the cache with the current cookie string, and returns true if it changed. When
+    /// several cookies have the same name, the first one is kept, as
+    /// [get_raw](../cookies/fn.get_raw.html) does.
+    pub fn update(&mut self, cookie_string: &str) -> bool {
+        if self.cookie_string == cookie_string {
+            return false;
+        }
+
+        self.cookie_string = cookie_string.to_owned();
+        self.cookies =
+            crate::cookies::collect_raw(cookie_string, crate::cookies::Duplicates::FirstWins);
+        true
+    }
+
+    /// Returns all cookies, with undecoded keys and values.
+    pub fn all_raw(&self) -> &HashMap<String, String> {
+        &self.cookies
+    }
+
+    /// Returns undecoded cookie if it exists.
+    pub fn get_raw(&self, name: &str) -> Option<&str> {
+        self.cookies.get(name).map(String::as_str)
+    }
+}
+
+/// A message sent by a tab to the coordinator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Request {
+    /// The cookie string has possibly changed. The first field is the path of the tab, the
+    /// second one is its current cookie string.
+    Update(String, String),
+
+    /// Get the undecoded cookie. The first field is the request identifier, the second one is
+    /// the path of the tab, the third one is the undecoded name.
+    Get(u32, String, String),
+}
+
+/// Returns the message that sends `request`.
+pub fn encode_request(request: &Request) -> String {
+    match request {
+        Request::Update(path, cookie_string) => {
+            format!("{}update={} {}", MESSAGE_PREFIX, path, cookie_string)
+        }
+        Request::Get(id, path, name) => format!("{}{}:get={} {}", MESSAGE_PREFIX, id, path, name),
+    }
+}
+
+/// Parses a message sent with [encode_request](fn.encode_request.html).
+/// Returns `None` if the message isn't a request of this protocol.
+pub fn decode_request(message: &str) -> Option<Request> {
+    let (kind, data) = message.strip_prefix(MESSAGE_PREFIX)?.split_once('=')?;
+    // Paths are percent-encoded, so they contain no space.
+    let (path, data) = data.split_once(' ')?;
+
+    if kind == "update" {
+        Some(Request::Update(path.to_owned(), data.to_owned()))
+    } else {
+        let id = kind.strip_suffix(":get")?.parse().ok()?;
+        Some(Request::Get(id, path.to_owned(), data.to_owned()))
+    }
+}
+
+/// Returns the message that answers the `Get` request identified by `id`.
+pub fn encode_response(id: u32, value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("{}{}={}", MESSAGE_PREFIX, id, value),
+        None => format!("{}{}!", MESSAGE_PREFIX, id),
+    }
+}
+
+/// Parses a message sent with [encode_response](fn.encode_response.html), and returns the
+/// identifier of the answered request and the undecoded cookie value, if the cookie exists.
+/// Returns `None` if the message isn't a response of this protocol.
+pub fn decode_response(message: &str) -> Option<(u32, Option<&str>)> {
+    let message = message.strip_prefix(MESSAGE_PREFIX)?;
+
+    match message.split_once('=') {
+        Some((id, value)) => Some((id.parse().ok()?, Some(value))),
+        None => Some((message.strip_suffix('!')?.parse().ok()?, None)),
+    }
+}
+
+/// Coordinator side of the cache, which runs inside the `SharedWorker`.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub struct SharedCacheCoordinator {
+    caches: Rc<RefCell<HashMap<String, CookieCache>>>,
+    _on_connect: Closure<dyn FnMut(MessageEvent)>,
+}
+
+#[cfg(target_family = "wasm")]
+impl SharedCacheCoordinator {
+    /// Starts serving the tabs that connect to the shared worker.
+    /// Must be called inside a shared worker, and the coordinator must be kept alive.
+    pub fn start() -> Self {
+        let scope = js_sys::global().unchecked_into::<SharedWorkerGlobalScope>();
+        let caches = Rc::new(RefCell::new(HashMap::<String, CookieCache>::new()));
+        let connect_caches = Rc::clone(&caches);
+
+        let on_connect = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let port = event.ports().get(0).unchecked_into::<MessagePort>();
+            let reply_port = port.clone();
+            let caches = Rc::clone(&connect_caches);
+
+            let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+                let request = event
+                    .data()
+                    .as_string()
+                    .and_then(|message| decode_request(&message));
+
+                match request {
+                    Some(Request::Update(path, cookie_string)) => {
+                        caches
+                            .borrow_mut()
+                            .entry(path)
+                            .or_default()
+                            .update(&cookie_string);
+                    }
+
+                    Some(Request::Get(id, path, name)) => {
+                        let caches = caches.borrow();
+                        let value = caches.get(&path).and_then(|cache| cache.get_raw(&name));
+                        let response = encode_response(id, value);
+                        reply_port
+                            .post_message(&JsValue::from_str(&response))
+                            .unwrap();
+                    }
+
+                    None => {}
+                }
+            }) as Box<dyn FnMut(MessageEvent)>);
+
+            port.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            // The handler lives as long as the port, which lives as long as the tab.
+            on_message.forget();
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        scope.set_onconnect(Some(on_connect.as_ref().unchecked_ref()));
+        Self {
+            caches,
+            _on_connect: on_connect,
+        }
+    }
+
+    /// Returns the canonical parsed cookie map of the tabs at `path`, if one of them connected.
+    pub fn cache(&self, path: &str) -> Option<std::cell::Ref<'_, CookieCache>> {
+        std::cell::Ref::filter_map(self.caches.borrow(), |caches| caches.get(path)).ok()
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl Drop for SharedCacheCoordinator {
+    fn drop(&mut self) {
+        js_sys::global()
+            .unchecked_into::<SharedWorkerGlobalScope>()
+            .set_onconnect(None);
+    }
+}
+
+/// Callbacks waiting for a cookie value, by request identifier.
+#[cfg(target_family = "wasm")]
+type PendingCallbacks = HashMap<u32, Box<dyn FnOnce(Option<&str>)>>;
+
+#[cfg(target_family = "wasm")]
+#[derive(Default)]
+struct ClientState {
+    next_id: u32,
+    pending: PendingCallbacks,
+}
+
+/// Tab side of the cache, which reads cookies from the coordinator.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub struct SharedCacheClient {
+    port: MessagePort,
+    state: Rc<RefCell<ClientState>>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+#[cfg(target_family = "wasm")]
+impl SharedCacheClient {
+    /// Connects to the coordinator running in `worker`, and sends it the current cookie string.
+    pub fn connect(worker: &SharedWorker) -> Self {
+        let port = worker.port();
+        let state = Rc::new(RefCell::new(ClientState::default()));
+        let handler_state = Rc::clone(&state);
+
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let message = match event.data().as_string() {
+                Some(message) => message,
+                None => return,
+            };
+
+            if let Some((id, value)) = decode_response(&message) {
+                let callback = handler_state.borrow_mut().pending.remove(&id);
+
+                if let Some(callback) = callback {
+                    callback(value);
+                }
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        port.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let client = Self {
+            port,
+            state,
+            _on_message: on_message,
+        };
+
+        client.invalidate();
+        client
+    }
+
+    fn post(&self, request: &Request) {
+        self.port
+            .post_message(&JsValue::from_str(&encode_request(request)))
+            .unwrap();
+    }
+
+    /// Sends the current cookie string to the coordinator.
+    /// Call it after writing cookies, or when a cookie change event is received.
+    pub fn invalidate(&self) {
+        self.post(&Request::Update(crate::pathname(), crate::cookie_string()));
+    }
+
+    /// Passes the undecoded cookie, if it exists, to `callback`.
+    pub fn get_raw(&self, name: &str, callback: impl FnOnce(Option<String>) + 'static) {
+        let id = {
+            let mut state = self.state.borrow_mut();
+            let id = state.next_id;
+            state.next_id = state.next_id.wrapping_add(1);
+            state.pending.insert(
+                id,
+                Box::new(move |value| callback(value.map(str::to_owned))),
+            );
+            id
+        };
+
+        self.post(&Request::Get(id, crate::pathname(), name.to_owned()));
+    }
+
+    /// If it exists, passes the URI decoded cookie
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding))
+    /// or an error if the value's URI decoding fails, to `callback`.
+    #[cfg(feature = "encoding")]
+    pub fn get(
+        &self,
+        name: &str,
+        callback: impl FnOnce(Option<Result<String, GetDecodeError>>) + 'static,
+    ) {
+        let name = name.to_owned();
+
+        self.get_raw(&urlencoding::encode(&name), move |value| {
+            callback(value.map(|value| cookies::decode_value(&name, value, Encoding::Standard)))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let mut cache = CookieCache::new();
+        assert!(cache.all_raw().is_empty());
+
+        assert!(cache.update("key1=value1; key2=value2"));
+        assert_eq!(cache.get_raw("key1"), Some("value1"));
+        assert_eq!(cache.get_raw("key3"), None);
+
+        assert!(!cache.update("key1=value1; key2=value2"));
+        assert!(cache.update("key1=value1"));
+        assert_eq!(cache.get_raw("key2"), None);
+        assert_eq!(cache.all_raw().len(), 1);
+
+        // Like `get_raw`, the most specific cookie wins.
+        assert!(cache.update("key1=specific; key1=general"));
+        assert_eq!(cache.get_raw("key1"), Some("specific"));
+    }
+
+    #[test]
+    fn test_protocol() {
+        let request = Request::Update("/app".to_owned(), "key1=value1; key2=value2".to_owned());
+        assert_eq!(decode_request(&encode_request(&request)), Some(request));
+
+        let request = Request::Get(3, "/app".to_owned(), "key 1".to_owned());
+        assert_eq!(encode_request(&request), "wasm-cookies:3:get=/app key 1");
+        assert_eq!(decode_request(&encode_request(&request)), Some(request));
+        assert_eq!(decode_request("other message"), None);
+
+        assert_eq!(
+            decode_response(&encode_response(3, Some("value=1"))),
+            Some((3, Some("value=1")))
+        );
+        assert_eq!(decode_response(&encode_response(3, None)), Some((3, None)));
+        assert_eq!(decode_response("wasm-cookies:update=key=value"), None);
+    }
+}
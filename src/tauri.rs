@@ -0,0 +1,230 @@
+//! Cookie shim for Tauri webviews, where `document.cookie` is unreliable: pages served with a
+//! custom protocol (`tauri://localhost`) may not store cookies at all, or not persist them across
+//! launches.
+//!
+//! When the shim is enabled, the root module's functions read and write a
+//! [SimulatedJar](../jar/struct.SimulatedJar.html) instead of `document.cookie`. The jar is
+//! persisted as one `Set-Cookie` string per line (see [save](fn.save.html) and
+//! [restore](fn.restore.html)), to `localStorage` by default, or to any store (such as the Tauri
+//! store plugin) with [browser::enable_with_store](browser/fn.enable_with_store.html).
+//!
+//! Available only with the `tauri` feature.
+
+use crate::cookies::{self, Cookie};
+use crate::jar::SimulatedJar;
+
+/// Key of the `localStorage` entry where the shim persists the cookies by default.
+pub const STORAGE_KEY: &str = "wasm-cookies";
+
+/// Returns the URL used to match the cookies of a page whose hostname is `host` and path is
+/// `path`. The webview's origin is a secure context, so the scheme is always `https`, even with a
+/// custom protocol.
+pub fn page_url(host: &str, path: &str) -> String {
+    format!("https://{}{}", host, path)
+}
+
+/// Returns the cookie string of `jar` as `document.cookie` would return it for `url` at `now`.
+pub fn cookie_string(jar: &SimulatedJar, url: &str, now: i64) -> String {
+    jar.cookie_header(url, now).unwrap_or_default()
+}
+
+/// Stores a cookie string as setting `document.cookie` would do on the page `url` at `now`.
+/// Returns false if the cookie was ignored, for example because it is `HttpOnly`.
+pub fn set_cookie_string(jar: &mut SimulatedJar, url: &str, value: &str, now: i64) -> bool {
+    match Cookie::parse_set_cookie(value) {
+        Some(cookie) if !cookie.http_only => jar.set_cookie(url, value, now),
+        _ => false,
+    }
+}
+
+/// Returns the persisted form of the cookies of `jar` not expired at `now`.
+pub fn save(jar: &SimulatedJar, now: i64) -> String {
+    jar.all_cookies(now)
+        .iter()
+        .map(|cookie| cookies::set_raw(&cookie.name, &cookie.value, &cookie.options))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the jar persisted with [save](fn.save.html) for the page `url`, without the cookies
+/// expired at `now`. Malformed lines are ignored.
+pub fn restore(url: &str, saved: &str, now: i64) -> SimulatedJar {
+    let mut jar = SimulatedJar::new();
+
+    for line in saved.lines() {
+        jar.set_cookie(url, line, now);
+    }
+
+    jar
+}
+
+/// Browser functions, which enable the shim in the current page.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::STORAGE_KEY;
+    use crate::cookies::now_timestamp;
+    use crate::jar::SimulatedJar;
+    use std::cell::RefCell;
+    use wasm_bindgen::JsValue;
+
+    /// Callback persisting the serialized jar.
+    type Persist = Box<dyn Fn(&str)>;
+
+    thread_local! {
+        static SHIM: RefCell<Option<SimulatedJar>> = const { RefCell::new(None) };
+        static PERSIST: RefCell<Option<Persist>> = const { RefCell::new(None) };
+    }
+
+    fn url() -> String {
+        let location = crate::window().location();
+
+        super::page_url(
+            &crate::expect_js(location.hostname()),
+            &crate::expect_js(location.pathname()),
+        )
+    }
+
+    /// Returns true if the page runs in a Tauri webview (Tauri 1 or 2).
+    pub fn is_tauri() -> bool {
+        let window = crate::window();
+        let location = window.location();
+
+        ["__TAURI_INTERNALS__", "__TAURI__"]
+            .iter()
+            .any(|key| js_sys::Reflect::has(&window, &JsValue::from_str(key)).unwrap_or(false))
+            || location
+                .protocol()
+                .is_ok_and(|protocol| protocol == "tauri:")
+            || location
+                .hostname()
+                .is_ok_and(|hostname| hostname == "tauri.localhost")
+    }
+
+    /// Enables the shim, persisted to `localStorage` under
+    /// [STORAGE_KEY](../constant.STORAGE_KEY.html). The cookies previously persisted there are
+    /// restored.
+    pub fn enable() {
+        let storage = crate::window().local_storage().ok().flatten();
+
+        let saved = storage
+            .as_ref()
+            .and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten())
+            .unwrap_or_default();
+
+        enable_with_store(&saved, move |saved| {
+            if let Some(storage) = &storage {
+                let _ = storage.set_item(STORAGE_KEY, saved);
+            }
+        });
+    }
+
+    /// Enables the shim with a custom store, such as the Tauri store plugin: `saved` is the
+    /// value previously passed to `persist`, which is called after each write.
+    ///
+    /// Since the store plugin is asynchronous, load `saved` from it before calling this function.
+    pub fn enable_with_store(saved: &str, persist: impl Fn(&str) + 'static) {
+        let jar = super::restore(&url(), saved, now_timestamp());
+        SHIM.with(|shim| *shim.borrow_mut() = Some(jar));
+        PERSIST.with(|current| *current.borrow_mut() = Some(Box::new(persist)));
+    }
+
+    /// Enables the shim persisted to `localStorage` if the page runs in a Tauri webview (see
+    /// [is_tauri](fn.is_tauri.html)). Returns true if the shim was enabled.
+    pub fn enable_if_tauri() -> bool {
+        let tauri = is_tauri();
+
+        if tauri {
+            enable();
+        }
+
+        tauri
+    }
+
+    /// Disables the shim, so `document.cookie` is used again. The persisted cookies are kept.
+    pub fn disable() {
+        SHIM.with(|shim| *shim.borrow_mut() = None);
+        PERSIST.with(|persist| *persist.borrow_mut() = None);
+    }
+
+    /// Returns true if the shim is enabled.
+    pub fn is_enabled() -> bool {
+        SHIM.with(|shim| shim.borrow().is_some())
+    }
+
+    pub(crate) fn cookie_string() -> Option<String> {
+        SHIM.with(|shim| {
+            shim.borrow()
+                .as_ref()
+                .map(|jar| super::cookie_string(jar, &url(), now_timestamp()))
+        })
+    }
+
+    /// Returns false if the shim isn't enabled.
+    pub(crate) fn set_cookie_string(value: &str) -> bool {
+        let now = now_timestamp();
+
+        let saved = SHIM.with(|shim| {
+            shim.borrow_mut().as_mut().map(|jar| {
+                super::set_cookie_string(jar, &url(), value, now);
+                super::save(jar, now)
+            })
+        });
+
+        match saved {
+            Some(saved) => {
+                PERSIST.with(|persist| {
+                    if let Some(persist) = &*persist.borrow() {
+                        persist(&saved);
+                    }
+                });
+
+                true
+            }
+
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    #[test]
+    fn test_shim() {
+        let url = page_url("tauri.localhost", "/settings");
+        let mut jar = SimulatedJar::new();
+        assert!(set_cookie_string(&mut jar, &url, "theme=dark;path=/", NOW));
+        assert!(set_cookie_string(
+            &mut jar,
+            &url,
+            "sid=1;path=/;secure",
+            NOW
+        ));
+        assert!(set_cookie_string(
+            &mut jar,
+            &url,
+            "tab=2;path=/;max-age=60",
+            NOW
+        ));
+        assert!(!set_cookie_string(
+            &mut jar,
+            &url,
+            "token=3;path=/;httponly",
+            NOW
+        ));
+        assert_eq!(cookie_string(&jar, &url, NOW), "theme=dark; sid=1; tab=2");
+
+        let saved = save(&jar, NOW);
+        assert_eq!(saved.lines().count(), 3);
+        assert!(saved.starts_with("theme=dark;path=/;samesite=lax\nsid=1;path=/;secure;"));
+
+        let restored = restore(&url, &saved, NOW + 60_000);
+        assert_eq!(cookie_string(&restored, &url, NOW), "theme=dark; sid=1");
+        assert_eq!(cookie_string(&restore(&url, "", NOW), &url, NOW), "");
+    }
+}
@@ -0,0 +1,385 @@
+//! Decoder of the IAB Transparency and Consent Framework v2 consent string, stored in the
+//! `euconsent-v2` cookie (see the
+//! [TCF v2 specification](https://github.com/InteractiveAdvertisingBureau/GDPR-Transparency-and-Consent-Framework/blob/master/TCFv2/IAB%20Tech%20Lab%20-%20Consent%20string%20and%20vendor%20list%20formats%20v2.md)).
+//!
+//! Only the core segment is decoded: publisher restrictions and the other segments are ignored.
+//!
+//! Available only with the `tcf` feature.
+
+use crate::base64::decode_url_safe;
+use crate::cookies;
+use std::collections::BTreeSet;
+
+/// Name of the cookie storing the consent string.
+pub const COOKIE_NAME: &str = "euconsent-v2";
+
+/// Error returned by [TcfConsent::decode](struct.TcfConsent.html#method.decode).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TcfError {
+    /// The core segment isn't valid URL-safe Base64.
+    InvalidBase64,
+
+    /// The version isn't 2.
+    UnsupportedVersion(u8),
+
+    /// The core segment is shorter than its fields require.
+    Truncated,
+
+    /// A vendor range is reversed, exceeds the maximum vendor ID, or the ranges cover more
+    /// vendors than the maximum vendor ID.
+    InvalidRange,
+}
+
+/// Decoded core segment of a TCF v2 consent string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TcfConsent {
+    /// Creation time, in deciseconds since the Unix epoch.
+    pub created: u64,
+
+    /// Last update time, in deciseconds since the Unix epoch.
+    pub last_updated: u64,
+
+    /// Identifier of the Consent Management Platform.
+    pub cmp_id: u16,
+
+    /// Version of the Consent Management Platform.
+    pub cmp_version: u16,
+
+    /// Screen number of the Consent Management Platform's UI where consent was given.
+    pub consent_screen: u8,
+
+    /// Two-letter ISO 639-1 language code of the consent UI (for example `EN`).
+    pub consent_language: String,
+
+    /// Version of the Global Vendor List used.
+    pub vendor_list_version: u16,
+
+    /// Version of the TCF policy used.
+    pub policy_version: u8,
+
+    /// True if the consent string applies only to the service that created it.
+    pub is_service_specific: bool,
+
+    /// True if the publisher used customized stack descriptions.
+    pub use_non_standard_texts: bool,
+
+    /// Identifiers of the special features opted in (1 to 12).
+    pub special_feature_opt_ins: BTreeSet<u8>,
+
+    /// Identifiers of the purposes consented to (1 to 24).
+    pub purpose_consents: BTreeSet<u8>,
+
+    /// Identifiers of the purposes whose legitimate interest has been disclosed (1 to 24).
+    pub purpose_legitimate_interests: BTreeSet<u8>,
+
+    /// True if purpose 1 wasn't disclosed (for countries where it doesn't apply).
+    pub purpose_one_treatment: bool,
+
+    /// Two-letter ISO 3166-1 country code of the publisher (for example `FR`).
+    pub publisher_country_code: String,
+
+    /// Identifiers of the vendors consented to.
+    pub vendor_consents: BTreeSet<u16>,
+
+    /// Identifiers of the vendors whose legitimate interest has been disclosed.
+    pub vendor_legitimate_interests: BTreeSet<u16>,
+}
+
+struct BitReader {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl BitReader {
+    fn read(&mut self, bits: usize) -> Result<u64, TcfError> {
+        let mut value = 0;
+
+        for _ in 0..bits {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or(TcfError::Truncated)?;
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u64;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn read_bool(&mut self) -> Result<bool, TcfError> {
+        Ok(self.read(1)? == 1)
+    }
+
+    fn read_letters(&mut self) -> Result<String, TcfError> {
+        (0..2)
+            .map(|_| Ok((b'A' + self.read(6)? as u8) as char))
+            .collect()
+    }
+
+    fn read_bit_field(&mut self, bits: usize) -> Result<BTreeSet<u8>, TcfError> {
+        let mut set = BTreeSet::new();
+
+        for id in 1..=bits {
+            if self.read_bool()? {
+                set.insert(id as u8);
+            }
+        }
+
+        Ok(set)
+    }
+
+    fn read_vendors(&mut self) -> Result<BTreeSet<u16>, TcfError> {
+        let max_vendor_id = self.read(16)? as u16;
+        let mut vendors = BTreeSet::new();
+
+        if self.read_bool()? {
+            // The ranges come from an untrusted string, so the total number of IDs they expand
+            // to is capped at the maximum vendor ID.
+            let mut expanded = 0;
+
+            for _ in 0..self.read(12)? {
+                let is_range = self.read_bool()?;
+                let start = self.read(16)? as u16;
+                let end = if is_range {
+                    self.read(16)? as u16
+                } else {
+                    start
+                };
+
+                expanded += u32::from(end.wrapping_sub(start)) + 1;
+
+                if start > end || end > max_vendor_id || expanded > u32::from(max_vendor_id) {
+                    return Err(TcfError::InvalidRange);
+                }
+
+                vendors.extend(start..=end);
+            }
+        } else {
+            for id in 1..=max_vendor_id {
+                if self.read_bool()? {
+                    vendors.insert(id);
+                }
+            }
+        }
+
+        Ok(vendors)
+    }
+}
+
+impl TcfConsent {
+    /// Decodes a consent string.
+    pub fn decode(consent_string: &str) -> Result<Self, TcfError> {
+        let core = consent_string.split('.').next().unwrap_or_default();
+
+        let mut reader = BitReader {
+            bytes: decode_url_safe(core).ok_or(TcfError::InvalidBase64)?,
+            position: 0,
+        };
+
+        let version = reader.read(6)? as u8;
+
+        if version != 2 {
+            return Err(TcfError::UnsupportedVersion(version));
+        }
+
+        Ok(Self {
+            created: reader.read(36)?,
+            last_updated: reader.read(36)?,
+            cmp_id: reader.read(12)? as u16,
+            cmp_version: reader.read(12)? as u16,
+            consent_screen: reader.read(6)? as u8,
+            consent_language: reader.read_letters()?,
+            vendor_list_version: reader.read(12)? as u16,
+            policy_version: reader.read(6)? as u8,
+            is_service_specific: reader.read_bool()?,
+            use_non_standard_texts: reader.read_bool()?,
+            special_feature_opt_ins: reader.read_bit_field(12)?,
+            purpose_consents: reader.read_bit_field(24)?,
+            purpose_legitimate_interests: reader.read_bit_field(24)?,
+            purpose_one_treatment: reader.read_bool()?,
+            publisher_country_code: reader.read_letters()?,
+            vendor_consents: reader.read_vendors()?,
+            vendor_legitimate_interests: reader.read_vendors()?,
+        })
+    }
+
+    /// Returns true if the purpose has been consented to.
+    pub fn has_purpose_consent(&self, purpose: u8) -> bool {
+        self.purpose_consents.contains(&purpose)
+    }
+
+    /// Returns true if the vendor has been consented to.
+    pub fn has_vendor_consent(&self, vendor: u16) -> bool {
+        self.vendor_consents.contains(&vendor)
+    }
+}
+
+/// Decodes the consent string of the `euconsent-v2` cookie, if it exists.
+pub fn get(cookie_string: &str) -> Option<Result<TcfConsent, TcfError>> {
+    cookies::get_raw(cookie_string, COOKIE_NAME).map(|value| TcfConsent::decode(&value))
+}
+
+/// Decodes the consent string of the document's `euconsent-v2` cookie, if it exists.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub fn read() -> Option<Result<TcfConsent, TcfError>> {
+    get(&crate::cookie_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base64::encode_url_safe;
+
+    #[derive(Default)]
+    struct BitWriter {
+        bits: Vec<bool>,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u64, bits: usize) -> &mut Self {
+            for i in (0..bits).rev() {
+                self.bits.push((value >> i) & 1 == 1);
+            }
+
+            self
+        }
+
+        fn encode(&self) -> String {
+            let bytes: Vec<u8> = self
+                .bits
+                .chunks(8)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0, |byte, (i, bit)| byte | (*bit as u8) << (7 - i))
+                })
+                .collect();
+
+            encode_url_safe(&bytes)
+        }
+    }
+
+    fn core_fields(writer: &mut BitWriter) -> &mut BitWriter {
+        writer
+            .write(2, 6)
+            .write(15_000_000_000, 36)
+            .write(15_000_000_001, 36)
+            .write(7, 12)
+            .write(3, 12)
+            .write(1, 6)
+            .write(4, 6) // E
+            .write(13, 6) // N
+            .write(42, 12)
+            .write(2, 6)
+            .write(0, 1)
+            .write(1, 1)
+            .write(0b0100_0000_0000, 12)
+            .write(0b1010_0000_0000_0000_0000_0001, 24)
+            .write(0b0000_0000_0000_0000_0000_0010, 24)
+            .write(0, 1)
+            .write(5, 6) // F
+            .write(17, 6) // R
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut writer = BitWriter::default();
+        core_fields(&mut writer)
+            // Vendor consents, as a bit field.
+            .write(5, 16)
+            .write(0, 1)
+            .write(0b10011, 5)
+            // Vendor legitimate interests, as ranges.
+            .write(300, 16)
+            .write(1, 1)
+            .write(2, 12)
+            .write(0, 1)
+            .write(8, 16)
+            .write(1, 1)
+            .write(297, 16)
+            .write(300, 16);
+
+        let consent = TcfConsent::decode(&format!("{}.segment", writer.encode())).unwrap();
+        assert_eq!(consent.created, 15_000_000_000);
+        assert_eq!(consent.last_updated, 15_000_000_001);
+        assert_eq!(consent.cmp_id, 7);
+        assert_eq!(consent.cmp_version, 3);
+        assert_eq!(consent.consent_screen, 1);
+        assert_eq!(consent.consent_language, "EN");
+        assert_eq!(consent.vendor_list_version, 42);
+        assert_eq!(consent.policy_version, 2);
+        assert!(!consent.is_service_specific);
+        assert!(consent.use_non_standard_texts);
+        assert_eq!(
+            consent.special_feature_opt_ins,
+            [2].iter().copied().collect()
+        );
+        assert_eq!(
+            consent.purpose_consents,
+            [1, 3, 24].iter().copied().collect()
+        );
+        assert_eq!(
+            consent.purpose_legitimate_interests,
+            [23].iter().copied().collect()
+        );
+        assert!(!consent.purpose_one_treatment);
+        assert_eq!(consent.publisher_country_code, "FR");
+        assert_eq!(consent.vendor_consents, [1, 4, 5].iter().copied().collect());
+        assert_eq!(
+            consent.vendor_legitimate_interests,
+            [8, 297, 298, 299, 300].iter().copied().collect()
+        );
+        assert!(consent.has_purpose_consent(3));
+        assert!(!consent.has_purpose_consent(2));
+        assert!(consent.has_vendor_consent(4));
+        assert!(!consent.has_vendor_consent(2));
+
+        assert_eq!(
+            get(&format!("other=1; euconsent-v2={}", writer.encode())),
+            Some(Ok(consent))
+        );
+        assert_eq!(get("other=1"), None);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(TcfConsent::decode("a+b"), Err(TcfError::InvalidBase64));
+        assert_eq!(
+            TcfConsent::decode(&BitWriter::default().write(1, 6).write(0, 2).encode()),
+            Err(TcfError::UnsupportedVersion(1))
+        );
+
+        let mut writer = BitWriter::default();
+        core_fields(&mut writer);
+        assert_eq!(
+            TcfConsent::decode(&writer.encode()),
+            Err(TcfError::Truncated)
+        );
+
+        let ranges = |max_vendor_id, ranges: &[(u64, u64)]| {
+            let mut writer = BitWriter::default();
+            core_fields(&mut writer)
+                .write(max_vendor_id, 16)
+                .write(1, 1)
+                .write(ranges.len() as u64, 12);
+
+            for &(start, end) in ranges {
+                writer.write(1, 1).write(start, 16).write(end, 16);
+            }
+
+            TcfConsent::decode(&writer.encode())
+        };
+        assert_eq!(ranges(10, &[(5, 3)]), Err(TcfError::InvalidRange));
+        assert_eq!(ranges(10, &[(5, 11)]), Err(TcfError::InvalidRange));
+        assert_eq!(ranges(10, &[(1, 10), (1, 10)]), Err(TcfError::InvalidRange));
+        assert_eq!(
+            ranges(65535, &[(1, 65535); 4095]),
+            Err(TcfError::InvalidRange)
+        );
+    }
+}
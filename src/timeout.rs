@@ -0,0 +1,202 @@
+//! Timeouts and cancellation for the asynchronous operations of this crate, such as the Cookie
+//! Store API call of [lifetime](../lifetime/index.html), the cookies API of extensions or the
+//! requests of a worker, whose callback may never be called (for example when a permission
+//! prompt hangs in an embedded webview).
+//!
+//! An operation is wrapped with [browser::with_timeout](browser/fn.with_timeout.html), which
+//! returns a handle: the callback is called with `Err(Timeout)` if the operation doesn't complete
+//! in time, and is never called once the handle is dropped.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Error passed to the callback of an operation that didn't complete in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeout;
+
+type Callback<T> = Box<dyn FnOnce(Result<T, Timeout>)>;
+
+/// The callback of a pending operation, called at most once: with the result of the operation,
+/// or with `Err(Timeout)`, whichever comes first. Once cancelled, it is never called.
+pub struct Pending<T> {
+    callback: Rc<RefCell<Option<Callback<T>>>>,
+}
+
+impl<T> Clone for Pending<T> {
+    fn clone(&self) -> Self {
+        Self {
+            callback: Rc::clone(&self.callback),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Pending<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pending")
+            .field("pending", &self.is_pending())
+            .finish()
+    }
+}
+
+impl<T> Pending<T> {
+    /// Creates a pending operation whose result is passed to `callback`.
+    pub fn new(callback: impl FnOnce(Result<T, Timeout>) + 'static) -> Self {
+        Self {
+            callback: Rc::new(RefCell::new(Some(Box::new(callback)))),
+        }
+    }
+
+    fn call(&self, result: Result<T, Timeout>) -> bool {
+        // The callback is taken out first, so it can drop or cancel this operation.
+        let callback = self.callback.borrow_mut().take();
+
+        match callback {
+            Some(callback) => {
+                callback(result);
+                true
+            }
+
+            None => false,
+        }
+    }
+
+    /// Passes the result of the operation to the callback. Returns false if the operation had
+    /// already timed out or been cancelled, in which case `value` is dropped.
+    pub fn complete(&self, value: T) -> bool {
+        self.call(Ok(value))
+    }
+
+    /// Passes `Err(Timeout)` to the callback. Returns false if the operation had already
+    /// completed or been cancelled.
+    pub fn time_out(&self) -> bool {
+        self.call(Err(Timeout))
+    }
+
+    /// Cancels the operation, so the callback is never called.
+    pub fn cancel(&self) {
+        self.callback.borrow_mut().take();
+    }
+
+    /// Returns true if the operation hasn't completed, timed out or been cancelled.
+    pub fn is_pending(&self) -> bool {
+        self.callback.borrow().is_some()
+    }
+}
+
+/// Browser functions, which schedule the timeouts.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::{Pending, Timeout};
+    use std::time::Duration;
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    /// A handle on an operation started with [with_timeout](fn.with_timeout.html). Dropping it
+    /// cancels the operation: its callback is never called.
+    pub struct OperationHandle<T> {
+        pending: Pending<T>,
+        timeout_id: i32,
+        _on_timeout: Closure<dyn FnMut()>,
+    }
+
+    impl<T> OperationHandle<T> {
+        /// Returns true if the operation hasn't completed or timed out yet.
+        pub fn is_pending(&self) -> bool {
+            self.pending.is_pending()
+        }
+    }
+
+    impl<T> Drop for OperationHandle<T> {
+        fn drop(&mut self) {
+            self.pending.cancel();
+
+            web_sys::window()
+                .unwrap()
+                .clear_timeout_with_handle(self.timeout_id);
+        }
+    }
+
+    /// Starts `operation`, passing it the function to call with its result, and passes that
+    /// result to `callback`, or `Err(Timeout)` if it isn't available after `duration`.
+    ///
+    /// The returned handle must be kept alive until the operation completes.
+    ///
+    /// ```ignore
+    /// let handle = timeout::browser::with_timeout(
+    ///     Duration::from_secs(5),
+    ///     |done| lifetime::browser::check(requested, done),
+    ///     |result| { /* Result<Result<LifetimeCapping, LifetimeCheckError>, Timeout> */ },
+    /// );
+    /// ```
+    pub fn with_timeout<T: 'static>(
+        duration: Duration,
+        operation: impl FnOnce(Box<dyn FnOnce(T)>),
+        callback: impl FnOnce(Result<T, Timeout>) + 'static,
+    ) -> OperationHandle<T> {
+        let pending = Pending::new(callback);
+        let timeout_pending = pending.clone();
+
+        let on_timeout = Closure::wrap(Box::new(move || {
+            timeout_pending.time_out();
+        }) as Box<dyn FnMut()>);
+
+        let timeout_id = web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                on_timeout.as_ref().unchecked_ref(),
+                duration.as_millis().min(i32::MAX as u128) as i32,
+            )
+            .unwrap();
+
+        let operation_pending = pending.clone();
+        operation(Box::new(move |value| {
+            operation_pending.complete(value);
+        }));
+
+        OperationHandle {
+            pending,
+            timeout_id,
+            _on_timeout: on_timeout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Results = Rc<RefCell<Vec<Result<u32, Timeout>>>>;
+
+    fn tracked() -> (Pending<u32>, Results) {
+        let results = Rc::new(RefCell::new(Vec::new()));
+        let callback_results = Rc::clone(&results);
+        let pending = Pending::new(move |result| callback_results.borrow_mut().push(result));
+        (pending, results)
+    }
+
+    #[test]
+    fn test_complete() {
+        let (pending, results) = tracked();
+        assert!(pending.is_pending());
+        assert!(pending.clone().complete(1));
+        assert!(!pending.time_out());
+        assert!(!pending.complete(2));
+        assert!(!pending.is_pending());
+        assert_eq!(*results.borrow(), [Ok(1)]);
+    }
+
+    #[test]
+    fn test_time_out_and_cancel() {
+        let (pending, results) = tracked();
+        assert!(pending.time_out());
+        assert!(!pending.complete(1));
+        assert_eq!(*results.borrow(), [Err(Timeout)]);
+
+        let (pending, results) = tracked();
+        pending.cancel();
+        assert!(!pending.complete(1));
+        assert!(!pending.time_out());
+        assert!(results.borrow().is_empty());
+    }
+}
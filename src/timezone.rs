@@ -0,0 +1,117 @@
+//! Timezone cookie, holding the IANA time zone name of the client (such as `Europe/Paris`), so
+//! the server can format dates in the user's time zone.
+//!
+//! The names are validated before being stored and when read, so the server never receives an
+//! arbitrary string. The validation checks the syntax of the names, not that they exist in the
+//! time zone database.
+
+use crate::cookies::{self, CookieOptions};
+
+/// Default name of the timezone cookie.
+pub const COOKIE_NAME: &str = "timezone";
+
+/// Returns true if `timezone` is a syntactically valid IANA time zone name: `/`-separated parts
+/// (such as `America/Argentina/Buenos_Aires`, `Etc/GMT+5` or `UTC`), each starting with a letter
+/// and made of ASCII letters, digits, `_`, `-` and `+`.
+pub fn is_valid(timezone: &str) -> bool {
+    timezone.len() <= 64
+        && timezone.split('/').all(|part| {
+            part.starts_with(|c: char| c.is_ascii_alphabetic())
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+        })
+}
+
+/// Returns the cookie string that sets the timezone cookie `name` to `timezone`, or `None` if
+/// `timezone` isn't a valid IANA time zone name (see [is_valid](fn.is_valid.html)).
+pub fn set_timezone(name: &str, timezone: &str, options: &CookieOptions) -> Option<String> {
+    if is_valid(timezone) {
+        Some(cookies::set_raw(name, timezone, options))
+    } else {
+        None
+    }
+}
+
+/// Returns the time zone stored in the cookie `name`, if it exists and is a valid IANA time zone
+/// name.
+pub fn get_timezone(cookie_string: &str, name: &str) -> Option<String> {
+    cookies::get_raw(cookie_string, name).filter(|timezone| is_valid(timezone))
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use crate::cookies::CookieOptions;
+    use js_sys::{Array, Intl, Object, Reflect};
+
+    /// Returns the time zone of the browser
+    /// (`Intl.DateTimeFormat().resolvedOptions().timeZone`), if it is known and valid.
+    pub fn current_timezone() -> Option<String> {
+        let options = Intl::DateTimeFormat::new(&Array::new(), &Object::new()).resolved_options();
+
+        Reflect::get(&options, &"timeZone".into())
+            .ok()?
+            .as_string()
+            .filter(|timezone| super::is_valid(timezone))
+    }
+
+    /// Sets the timezone cookie `name` to the time zone of the browser, and returns it. Returns
+    /// `None`, without setting the cookie, if the time zone isn't known or valid.
+    pub fn set_timezone(name: &str, options: &CookieOptions) -> Option<String> {
+        let timezone = current_timezone()?;
+        crate::set_cookie_string(&super::set_timezone(name, &timezone, options)?);
+        Some(timezone)
+    }
+
+    /// Returns the time zone stored in the cookie `name`, if it exists and is a valid IANA time
+    /// zone name.
+    pub fn get_timezone(name: &str) -> Option<String> {
+        super::get_timezone(&crate::cookie_string(), name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid() {
+        for timezone in [
+            "UTC",
+            "Europe/Paris",
+            "America/Argentina/Buenos_Aires",
+            "Etc/GMT+5",
+            "America/Port-au-Prince",
+        ] {
+            assert!(is_valid(timezone), "{}", timezone);
+        }
+
+        for timezone in [
+            "",
+            "/Paris",
+            "Europe//Paris",
+            "Europe/Paris;x=1",
+            "../etc",
+            "+05:00",
+        ] {
+            assert!(!is_valid(timezone), "{}", timezone);
+        }
+    }
+
+    #[test]
+    fn test_cookie() {
+        assert_eq!(
+            set_timezone(COOKIE_NAME, "Asia/Tokyo", &CookieOptions::ROOT).unwrap(),
+            "timezone=Asia/Tokyo;path=/;samesite=lax"
+        );
+        assert_eq!(set_timezone(COOKIE_NAME, "x;y", &CookieOptions::ROOT), None);
+        assert_eq!(
+            get_timezone("timezone=Asia/Tokyo", COOKIE_NAME).unwrap(),
+            "Asia/Tokyo"
+        );
+        assert_eq!(get_timezone("timezone=%3Cb%3E", COOKIE_NAME), None);
+    }
+}
@@ -0,0 +1,153 @@
+//! Auto-touch: cookies whose expiration date is pushed back each time they are read, for
+//! sliding sessions, without calls to refresh them scattered across the code.
+//!
+//! An [AutoTouch](struct.AutoTouch.html) maps cookie name patterns to
+//! [TouchPolicy](struct.TouchPolicy.html) values. In a browser, install it with
+//! `wasm_cookies::set_auto_touch`: each `get` of a matching cookie then sets it again with its
+//! current value and a refreshed expiration date. A cookie is touched at most once per
+//! `min_interval`, so frequent reads don't each write the cookie.
+//!
+//! Cookies are set again with the options of the policy, which must have the path and the
+//! domain the cookies were set with, or a second cookie is created instead.
+
+use crate::cookies::{self, CookieOptions};
+use crate::policy::matches_pattern;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Default minimum interval between two touches of a cookie.
+pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How the cookies matching a pattern are touched.
+#[derive(Clone, Debug)]
+pub struct TouchPolicy {
+    /// Lifetime of the cookies from their last read.
+    pub lifetime: Duration,
+
+    /// Minimum interval between two touches of a cookie. The default is
+    /// [DEFAULT_MIN_INTERVAL](constant.DEFAULT_MIN_INTERVAL.html).
+    pub min_interval: Duration,
+
+    /// Options the cookies are set again with. Their expiration date is replaced.
+    pub options: CookieOptions<'static>,
+}
+
+impl TouchPolicy {
+    /// Creates a policy keeping the cookies for `lifetime` after their last read.
+    pub fn new(lifetime: Duration, options: CookieOptions<'static>) -> Self {
+        Self {
+            lifetime,
+            min_interval: DEFAULT_MIN_INTERVAL,
+            options,
+        }
+    }
+
+    /// Sets the minimum interval between two touches of a cookie.
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+}
+
+/// A routing table from cookie name patterns to touch policies, with the time of the last touch
+/// of each cookie. For a given cookie name, only the first matching route applies, and the
+/// cookies matching no route aren't touched.
+#[derive(Clone, Debug, Default)]
+pub struct AutoTouch {
+    routes: Vec<(String, TouchPolicy)>,
+    touched_at: HashMap<String, i64>,
+}
+
+impl AutoTouch {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a route at the end of the table. `pattern` is a cookie name, where `*` matches any
+    /// sequence of characters.
+    pub fn route(mut self, pattern: impl Into<String>, policy: TouchPolicy) -> Self {
+        self.routes.push((pattern.into(), policy));
+        self
+    }
+
+    /// Returns the policy of the first route matching the cookie `name`, if any.
+    pub fn policy(&self, name: &str) -> Option<&TouchPolicy> {
+        self.routes
+            .iter()
+            .find(|(pattern, _)| matches_pattern(pattern, name))
+            .map(|(_, policy)| policy)
+    }
+
+    /// Touches the cookie `name`, stored as `raw_name` in `cookie_string`, at `now` (timestamp in
+    /// milliseconds, UTC). Returns the cookie string that sets it again with its undecoded value
+    /// and a refreshed expiration date, or `None` if it doesn't exist, matches no route, or was
+    /// touched less than `min_interval` ago.
+    pub fn touch(
+        &mut self,
+        cookie_string: &str,
+        name: &str,
+        raw_name: &str,
+        now: i64,
+    ) -> Option<String> {
+        let policy = self.policy(name)?;
+
+        if let Some(touched_at) = self.touched_at.get(raw_name) {
+            if now.saturating_sub(*touched_at) < cookies::duration_millis(policy.min_interval) {
+                return None;
+            }
+        }
+
+        let value = cookies::get_raw(cookie_string, raw_name)?;
+        let options = policy
+            .options
+            .clone()
+            .expires_at_timestamp(now.saturating_add(cookies::duration_millis(policy.lifetime)));
+        self.touched_at.insert(raw_name.to_owned(), now);
+        Some(cookies::set_raw(raw_name, &value, &options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_touch() {
+        let mut auto_touch = AutoTouch::new().route(
+            "session*",
+            TouchPolicy::new(Duration::from_secs(1800), CookieOptions::ROOT),
+        );
+        let cookie_string = "session_id=abc; theme=dark";
+
+        assert_eq!(
+            auto_touch.touch(cookie_string, "session_id", "session_id", 0),
+            Some(
+                "session_id=abc;path=/;expires=Thu, 01 Jan 1970 00:30:00 GMT;samesite=lax"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            auto_touch.touch(cookie_string, "session_id", "session_id", 59_999),
+            None
+        );
+        assert!(auto_touch
+            .touch(cookie_string, "session_id", "session_id", 60_000)
+            .is_some());
+        assert_eq!(auto_touch.touch(cookie_string, "theme", "theme", 0), None);
+        assert_eq!(
+            auto_touch.touch(cookie_string, "session", "session", 0),
+            None
+        );
+
+        // A huge lifetime saturates instead of wrapping to a date in the past.
+        let mut auto_touch = AutoTouch::new().route(
+            "theme",
+            TouchPolicy::new(Duration::MAX, CookieOptions::ROOT),
+        );
+        assert!(!auto_touch
+            .touch(cookie_string, "theme", "theme", 0)
+            .unwrap()
+            .contains("1970"));
+    }
+}
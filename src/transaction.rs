@@ -0,0 +1,310 @@
+//! Transactional writes: several writes are staged, then applied together, and the already
+//! applied ones are rolled back if one of them fails.
+//!
+//! In a browser, use the root module's `transaction` function. Outside a browser, use
+//! [run](fn.run.html) with functions that read and write the cookie string.
+
+use crate::cookies::{self, Cookie, CookieOptions};
+use crate::policy::PolicyViolation;
+
+enum Write<'a> {
+    Set {
+        name: String,
+        value: String,
+        options: CookieOptions<'a>,
+    },
+
+    Delete {
+        name: String,
+        options: CookieOptions<'a>,
+    },
+}
+
+impl Write<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Write::Set { name, .. } | Write::Delete { name, .. } => name,
+        }
+    }
+
+    fn options(&self) -> &CookieOptions<'_> {
+        match self {
+            Write::Set { options, .. } | Write::Delete { options, .. } => options,
+        }
+    }
+}
+
+/// Writes staged inside a transaction. Nothing is written until the transaction's closure
+/// returns `Ok`.
+#[derive(Default)]
+pub struct Transaction<'a> {
+    writes: Vec<Write<'a>>,
+}
+
+/// Error returned by a transaction.
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TransactionError<E> {
+    /// The transaction's closure returned an error, so nothing has been written.
+    Aborted(E),
+
+    /// The browser rejected a write, and the already applied writes have been rolled back.
+    ///
+    /// The field is the undecoded name of the rejected cookie.
+    Rejected(String),
+
+    /// The installed cookie policy doesn't allow one of the writes, so nothing has been written.
+    Policy(PolicyViolation),
+
+    /// The writes are deferred (see [deferred](../deferred/index.html)), so they can't be
+    /// checked, and nothing has been written.
+    Deferred,
+}
+
+impl<'a> Transaction<'a> {
+    /// Stages the write of a cookie, with non encoded name and value.
+    pub fn set_raw(&mut self, name: &str, value: &str, options: &CookieOptions<'a>) {
+        self.writes.push(Write::Set {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            options: options.clone(),
+        });
+    }
+
+    /// Stages the write of a cookie, with URI encoded name and value
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)).
+    #[cfg(feature = "encoding")]
+    pub fn set(&mut self, name: &str, value: &str, options: &CookieOptions<'a>) {
+        self.set_raw(
+            &urlencoding::encode(name),
+            &urlencoding::encode(value),
+            options,
+        );
+    }
+
+    /// Stages the deletion of a cookie without encoding its name. The cookie is deleted at the
+    /// path and domain of `options`, which are also used to set it again on rollback.
+    pub fn delete_raw(&mut self, name: &str, options: &CookieOptions<'a>) {
+        self.writes.push(Write::Delete {
+            name: name.to_owned(),
+            options: options.clone(),
+        });
+    }
+
+    /// Stages the deletion of a cookie, URI encoding its name. The cookie is deleted at the path
+    /// and domain of `options`, which are also used to set it again on rollback.
+    #[cfg(feature = "encoding")]
+    pub fn delete(&mut self, name: &str, options: &CookieOptions<'a>) {
+        self.delete_raw(&urlencoding::encode(name), options);
+    }
+}
+
+/// Runs a transaction: `f` stages writes, which are applied with `set_cookie_string` if it
+/// returns `Ok`.
+///
+/// After each write, the cookie string is read with `cookie_string` to check that the write has
+/// been accepted. If it hasn't, the already applied writes are rolled back: their cookies are set
+/// again to their previous value (with the options of the write), or deleted if they didn't exist.
+pub fn run<'a, E>(
+    cookie_string: impl FnMut() -> String,
+    set_cookie_string: impl FnMut(&str),
+    f: impl FnOnce(&mut Transaction<'a>) -> Result<(), E>,
+) -> Result<(), TransactionError<E>> {
+    run_checked(cookie_string, set_cookie_string, |_, _| Ok(()), f)
+}
+
+/// Same as [run](fn.run.html), but each staged write is first checked with `check`, called with
+/// the undecoded name of the cookie and true for a deletion. Nothing is written if one of the
+/// checks fails.
+pub(crate) fn run_checked<'a, E>(
+    mut cookie_string: impl FnMut() -> String,
+    mut set_cookie_string: impl FnMut(&str),
+    check: impl Fn(&str, bool) -> Result<(), PolicyViolation>,
+    f: impl FnOnce(&mut Transaction<'a>) -> Result<(), E>,
+) -> Result<(), TransactionError<E>> {
+    let mut transaction = Transaction::default();
+    f(&mut transaction).map_err(TransactionError::Aborted)?;
+
+    for write in &transaction.writes {
+        check(write.name(), matches!(write, Write::Delete { .. }))
+            .map_err(TransactionError::Policy)?;
+    }
+
+    let mut applied: Vec<(&Write, Option<String>)> = Vec::new();
+
+    for write in &transaction.writes {
+        let previous = cookies::get_raw(&cookie_string(), write.name());
+
+        let accepted = match write {
+            Write::Set {
+                name,
+                value,
+                options,
+            } => {
+                // The name and the value are compared as written, since `set_raw` escapes some
+                // of their characters.
+                let written = cookies::set_raw(name, value, options);
+                set_cookie_string(&written);
+
+                Cookie::parse_set_cookie(&written).is_some_and(|cookie| {
+                    cookies::get_raw(&cookie_string(), &cookie.name).as_deref()
+                        == Some(&*cookie.value)
+                })
+            }
+
+            Write::Delete { name, options } => {
+                set_cookie_string(&cookies::scoped_delete_raw(name, Some(options)));
+                cookies::get_raw(&cookie_string(), name).is_none()
+            }
+        };
+
+        if !accepted {
+            for (write, previous) in applied.into_iter().rev() {
+                match previous {
+                    Some(value) => {
+                        set_cookie_string(&cookies::set_raw(write.name(), &value, write.options()))
+                    }
+
+                    None => set_cookie_string(&cookies::scoped_delete_raw(
+                        write.name(),
+                        Some(write.options()),
+                    )),
+                }
+            }
+
+            return Err(TransactionError::Rejected(write.name().to_owned()));
+        }
+
+        applied.push((write, previous));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "encoding"))]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    /// Minimal document cookie jar, which rejects the cookies whose name starts with `rejected`.
+    #[derive(Default)]
+    struct Jar(RefCell<BTreeMap<String, String>>);
+
+    impl Jar {
+        fn cookie_string(&self) -> String {
+            self.0
+                .borrow()
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ")
+        }
+
+        fn set_cookie_string(&self, cookie_string: &str) {
+            let (pair, attributes) = cookie_string.split_once(';').unwrap_or((cookie_string, ""));
+            let (name, value) = pair.split_once('=').unwrap();
+
+            if name.starts_with("rejected") {
+                return;
+            }
+
+            if attributes.contains("1970") {
+                self.0.borrow_mut().remove(name);
+            } else {
+                self.0
+                    .borrow_mut()
+                    .insert(name.to_owned(), value.to_owned());
+            }
+        }
+
+        fn run<E>(
+            &self,
+            f: impl FnOnce(&mut Transaction) -> Result<(), E>,
+        ) -> Result<(), TransactionError<E>> {
+            run(
+                || self.cookie_string(),
+                |cookie_string| self.set_cookie_string(cookie_string),
+                f,
+            )
+        }
+    }
+
+    #[test]
+    fn test_commit() {
+        let jar = Jar::default();
+        jar.set_cookie_string("old=value");
+
+        let result = jar.run(|tx| -> Result<(), ()> {
+            tx.set("token", "a b", &CookieOptions::default());
+            tx.set("expiry", "1", &CookieOptions::default());
+            tx.delete("old", &CookieOptions::default());
+            Ok(())
+        });
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(jar.cookie_string(), "expiry=1; token=a%20b");
+
+        let result = jar.run(|tx| -> Result<(), ()> {
+            tx.set_raw("list", "a;b", &CookieOptions::default());
+            Ok(())
+        });
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(jar.cookie_string(), "expiry=1; list=a%3Bb; token=a%20b");
+    }
+
+    #[test]
+    fn test_aborted() {
+        let jar = Jar::default();
+
+        let result = jar.run(|tx| {
+            tx.set("token", "value", &CookieOptions::default());
+            Err("failed")
+        });
+
+        assert_eq!(result, Err(TransactionError::Aborted("failed")));
+        assert_eq!(jar.cookie_string(), "");
+    }
+
+    #[test]
+    fn test_rollback() {
+        let jar = Jar::default();
+        jar.set_cookie_string("token=old");
+        jar.set_cookie_string("marker=1");
+
+        let result = jar.run(|tx| -> Result<(), ()> {
+            tx.set("token", "new", &CookieOptions::default());
+            tx.set("expiry", "1", &CookieOptions::default());
+            tx.delete("marker", &CookieOptions::default().with_path("/app"));
+            tx.set("rejected", "value", &CookieOptions::default());
+            Ok(())
+        });
+
+        assert_eq!(
+            result,
+            Err(TransactionError::Rejected("rejected".to_owned()))
+        );
+        assert_eq!(jar.cookie_string(), "marker=1; token=old");
+
+        // The deletion and its rollback use the options of the staged deletion.
+        let written = RefCell::new(Vec::new());
+        let _ = run(
+            || jar.cookie_string(),
+            |cookie_string| {
+                jar.set_cookie_string(cookie_string);
+                written.borrow_mut().push(cookie_string.to_owned());
+            },
+            |tx| -> Result<(), ()> {
+                tx.delete("marker", &CookieOptions::default().with_path("/app"));
+                tx.set("rejected", "value", &CookieOptions::default());
+                Ok(())
+            },
+        );
+
+        let written = written.into_inner();
+        assert!(written[0].starts_with("marker=;") && written[0].contains(";path=/app"));
+        assert!(written[2].starts_with("marker=1;") && written[2].contains(";path=/app"));
+        assert_eq!(jar.cookie_string(), "marker=1; token=old");
+    }
+}
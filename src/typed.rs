@@ -0,0 +1,213 @@
+//! Typed cookies, whose values are `serde` serializable types, with the serialization format of
+//! each cookie chosen in one place.
+//!
+//! A [FormatRegistry](struct.FormatRegistry.html) maps cookie name patterns to formats, so the
+//! call sites only give the name and the type of a cookie, and can't store it in a different
+//! format than the other call sites. In a browser, install a registry with
+//! `wasm_cookies::set_format_registry`, and use `wasm_cookies::get_typed` and
+//! `wasm_cookies::set_typed`.
+//!
+//! The serialized values are URI encoded like the other cookies.
+//!
+//! Available only with the `json` or `form` feature.
+
+use crate::cookies::{self, CookieOptions, GetDecodeError};
+use crate::policy::matches_pattern;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Serialization format of a typed cookie.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    /// JSON (with the [serde_json crate](https://crates.io/crates/serde_json)).
+    ///
+    /// Available only with the `json` feature.
+    #[cfg(feature = "json")]
+    Json,
+
+    /// Form (`a=1&b=2`, with the
+    /// [serde_urlencoded crate](https://crates.io/crates/serde_urlencoded)), which is more
+    /// compact than JSON for flat structs.
+    ///
+    /// Available only with the `form` feature.
+    #[cfg(feature = "form")]
+    Form,
+}
+
+/// Error returned when reading or writing a typed cookie.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TypedError {
+    /// No route of the registry matches the cookie.
+    ///
+    /// The field is the name of the cookie.
+    NoFormat(String),
+
+    /// URI decoding error on the value.
+    Decode(GetDecodeError),
+
+    /// JSON serialization or deserialization error.
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+
+    /// Form serialization error.
+    #[cfg(feature = "form")]
+    FormSerialize(serde_urlencoded::ser::Error),
+
+    /// Form deserialization error.
+    #[cfg(feature = "form")]
+    FormDeserialize(serde_urlencoded::de::Error),
+}
+
+impl Format {
+    /// Serializes `value` in this format.
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, TypedError> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::to_string(value).map_err(TypedError::Json),
+            #[cfg(feature = "form")]
+            Format::Form => serde_urlencoded::to_string(value).map_err(TypedError::FormSerialize),
+        }
+    }
+
+    /// Deserializes `serialized` from this format.
+    pub fn deserialize<T: DeserializeOwned>(self, serialized: &str) -> Result<T, TypedError> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::from_str(serialized).map_err(TypedError::Json),
+            #[cfg(feature = "form")]
+            Format::Form => {
+                serde_urlencoded::from_str(serialized).map_err(TypedError::FormDeserialize)
+            }
+        }
+    }
+}
+
+/// A routing table from cookie name patterns to formats. For a given cookie name, only the first
+/// matching route applies.
+#[derive(Clone, Debug, Default)]
+pub struct FormatRegistry {
+    routes: Vec<(String, Format)>,
+}
+
+impl FormatRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a route at the end of the table. `pattern` is a cookie name, where `*` matches any
+    /// sequence of characters.
+    pub fn route(mut self, pattern: impl Into<String>, format: Format) -> Self {
+        self.routes.push((pattern.into(), format));
+        self
+    }
+
+    /// Returns the format of the first route matching the cookie `name`, or an error if there is
+    /// none.
+    pub fn format(&self, name: &str) -> Result<Format, TypedError> {
+        self.routes
+            .iter()
+            .find(|(pattern, _)| matches_pattern(pattern, name))
+            .map(|(_, format)| *format)
+            .ok_or_else(|| TypedError::NoFormat(name.to_owned()))
+    }
+
+    /// Returns the cookie string that sets the cookie `name` to `value`, serialized in the
+    /// cookie's format, with URI encoded name and value.
+    pub fn set_typed<T: Serialize>(
+        &self,
+        name: &str,
+        value: &T,
+        options: &CookieOptions,
+    ) -> Result<String, TypedError> {
+        let serialized = self.format(name)?.serialize(value)?;
+        Ok(cookies::set(name, &serialized, options))
+    }
+
+    /// If it exists, returns the value of the cookie `name`, deserialized from the cookie's
+    /// format, or an error if it can't be decoded or deserialized.
+    pub fn get_typed<T: DeserializeOwned>(
+        &self,
+        cookie_string: &str,
+        name: &str,
+    ) -> Option<Result<T, TypedError>> {
+        let serialized = cookies::get(cookie_string, name)?;
+        Some(self.deserialize(name, serialized))
+    }
+
+    /// Deserializes the URI decoded value of the cookie `name` from the cookie's format.
+    pub(crate) fn deserialize<T: DeserializeOwned>(
+        &self,
+        name: &str,
+        serialized: Result<String, GetDecodeError>,
+    ) -> Result<T, TypedError> {
+        let format = self.format(name)?;
+        format.deserialize(&serialized.map_err(TypedError::Decode)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    type Settings = BTreeMap<String, String>;
+
+    fn registry() -> FormatRegistry {
+        let registry = FormatRegistry::new();
+        #[cfg(feature = "form")]
+        let registry = registry.route("filters_*", Format::Form);
+        #[cfg(feature = "json")]
+        let registry = registry.route("*", Format::Json);
+        registry
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = registry();
+        let mut settings = Settings::new();
+        settings.insert("sort".to_owned(), "price asc".to_owned());
+
+        #[cfg(feature = "json")]
+        {
+            let cookie_string = registry
+                .set_typed("settings", &settings, &CookieOptions::ROOT)
+                .unwrap();
+            assert!(cookie_string.starts_with("settings=%7B%22sort%22%3A%22price%20asc%22%7D;"));
+
+            let cookie_string = cookie_string.split(';').next().unwrap();
+            let read: Settings = registry
+                .get_typed(cookie_string, "settings")
+                .unwrap()
+                .unwrap();
+            assert_eq!(read, settings);
+            assert!(registry
+                .get_typed::<Vec<u32>>(cookie_string, "settings")
+                .unwrap()
+                .is_err());
+        }
+
+        #[cfg(feature = "form")]
+        {
+            let cookie_string = registry
+                .set_typed("filters_a", &settings, &CookieOptions::ROOT)
+                .unwrap();
+            let cookie_string = cookie_string.split(';').next().unwrap();
+            assert_eq!(cookie_string, "filters_a=sort%3Dprice%2Basc");
+            assert_eq!(
+                registry
+                    .get_typed::<Settings>(cookie_string, "filters_a")
+                    .unwrap()
+                    .unwrap(),
+                settings
+            );
+        }
+
+        assert!(matches!(
+            FormatRegistry::new().set_typed("a", &1, &CookieOptions::ROOT),
+            Err(TypedError::NoFormat(name)) if name == "a"
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+//! A borrowed, indexed view of a cookie string, for code that only reads a few cookies of each
+//! cookie string (such as a server handling requests).
+//!
+//! The pairs are split and indexed by name once, when the view is created, and the values are
+//! only URI decoded when they are read. Values that don't need decoding are borrowed from the
+//! cookie string instead of being copied.
+
+#[cfg(feature = "encoding")]
+use crate::cookies::GetDecodeError;
+#[cfg(feature = "encoding")]
+use crate::encoding::Encoding;
+#[cfg(feature = "encoding")]
+use std::borrow::Cow;
+
+/// A borrowed view of the cookies of a cookie string.
+///
+/// When several cookies have the same name, the first one wins (see
+/// [Duplicates](../cookies/enum.Duplicates.html)).
+#[derive(Clone, Debug, Default)]
+pub struct CookiesView<'a> {
+    /// The undecoded pairs, in the order of the cookie string.
+    pairs: Vec<(&'a str, &'a str)>,
+
+    /// Indexes of the pairs, sorted by name, then by position.
+    index: Vec<usize>,
+}
+
+impl<'a> CookiesView<'a> {
+    /// Splits and indexes the pairs of `cookie_string`, without decoding them.
+    pub fn new(cookie_string: &'a str) -> Self {
+        let pairs: Vec<_> = crate::cookies::all_iter_raw(cookie_string).collect();
+        let mut index: Vec<usize> = (0..pairs.len()).collect();
+        index.sort_by_key(|i| pairs[*i].0);
+
+        Self { pairs, index }
+    }
+
+    /// Returns the number of cookies, including the duplicates.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns true if there is no cookie.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Returns true if a cookie has the undecoded name `name`.
+    pub fn contains_raw(&self, name: &str) -> bool {
+        self.get_raw(name).is_some()
+    }
+
+    /// Returns the undecoded value of the cookie whose undecoded name is `name`, if it exists.
+    pub fn get_raw(&self, name: &str) -> Option<&'a str> {
+        let first = self.index.partition_point(|i| self.pairs[*i].0 < name);
+        let (key, value) = self.pairs[*self.index.get(first)?];
+
+        if key == name {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// If it exists, returns the URI decoded value of the cookie named `name`, borrowed if it
+    /// doesn't need decoding, or an error if its URI decoding fails.
+    #[cfg(feature = "encoding")]
+    pub fn get(&self, name: &str) -> Option<Result<Cow<'a, str>, GetDecodeError>> {
+        self.get_with(name, Encoding::Standard)
+    }
+
+    /// Same as [get](#method.get), with the given URI encoding.
+    #[cfg(feature = "encoding")]
+    pub fn get_with(
+        &self,
+        name: &str,
+        encoding: Encoding,
+    ) -> Option<Result<Cow<'a, str>, GetDecodeError>> {
+        let value = self.get_raw(&encoding.encode(name))?;
+
+        let needs_decoding =
+            value.contains('%') || (encoding == Encoding::Form && value.contains('+'));
+
+        if !needs_decoding {
+            return Some(Ok(Cow::Borrowed(value)));
+        }
+
+        Some(crate::cookies::decode_value(name, value.to_owned(), encoding).map(Cow::Owned))
+    }
+
+    /// Returns the undecoded pairs, in the order of the cookie string.
+    pub fn iter_raw(&self) -> impl ExactSizeIterator<Item = (&'a str, &'a str)> + '_ {
+        self.pairs.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_raw() {
+        let view = CookiesView::new("b=2; a=1; invalid; b=3; c=");
+        assert_eq!(view.len(), 4);
+        assert_eq!(view.get_raw("a"), Some("1"));
+        assert_eq!(view.get_raw("b"), Some("2"));
+        assert_eq!(view.get_raw("c"), Some(""));
+        assert_eq!(view.get_raw("d"), None);
+        assert!(!view.contains_raw("invalid"));
+        assert_eq!(
+            view.iter_raw().collect::<Vec<_>>(),
+            [("b", "2"), ("a", "1"), ("b", "3"), ("c", "")]
+        );
+        assert!(CookiesView::new("").is_empty());
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_get() {
+        let view = CookiesView::new("plain=abc; my%20name=a%20b; form=a+b; bad=%FF");
+        assert!(matches!(view.get("plain"), Some(Ok(Cow::Borrowed("abc")))));
+        assert!(matches!(view.get("my name"), Some(Ok(Cow::Owned(value))) if value == "a b"));
+        assert!(matches!(view.get("form"), Some(Ok(Cow::Borrowed("a+b")))));
+        assert_eq!(
+            view.get_with("form", Encoding::Form).unwrap().unwrap(),
+            "a b"
+        );
+        assert!(matches!(view.get("bad"), Some(Err(_))));
+        assert!(view.get("missing").is_none());
+    }
+}
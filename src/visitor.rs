@@ -0,0 +1,229 @@
+//! First-visit and returning-visitor tracking, in a compact cookie holding the time of the first
+//! visit, the time of the last activity and the number of visits.
+//!
+//! A visit is a series of page views without a gap longer than
+//! [VISIT_GAP](constant.VISIT_GAP.html): reloading a page or navigating within the site doesn't
+//! count as a new visit, and the visitor stays new during their whole first visit. To avoid
+//! rewriting the cookie on every page view, the time of the last activity is only updated once
+//! per [UPDATE_INTERVAL](constant.UPDATE_INTERVAL.html).
+
+use crate::cookies::{self, CookieOptions};
+
+/// Default name of the visitor cookie.
+pub const COOKIE_NAME: &str = "visitor";
+
+/// Inactivity, in milliseconds, after which the next page view starts a new visit (30 minutes).
+pub const VISIT_GAP: i64 = 30 * 60 * 1000;
+
+/// Minimum time, in milliseconds, between two updates of the cookie during a visit (5 minutes).
+pub const UPDATE_INTERVAL: i64 = 5 * 60 * 1000;
+
+/// Information about the current visitor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VisitorInfo {
+    /// Timestamp in milliseconds, UTC, of the first visit (with a precision of one second).
+    pub first_seen: i64,
+
+    /// Number of visits, including the current one.
+    pub visit_count: u32,
+
+    /// True during the first visit.
+    pub is_new: bool,
+}
+
+/// The state stored in the cookie, with timestamps in seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct VisitorState {
+    first_seen: i64,
+    last_seen: i64,
+    visit_count: u32,
+}
+
+impl VisitorState {
+    /// Parses `first_seen.last_seen.visit_count`, in base 36.
+    fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split('.');
+        let first_seen = i64::from_str_radix(parts.next()?, 36).ok()?;
+        let last_seen = i64::from_str_radix(parts.next()?, 36).ok()?;
+        let visit_count = u32::from_str_radix(parts.next()?, 36).ok()?;
+
+        if parts.next().is_some() || first_seen < 0 || first_seen > last_seen || visit_count == 0 {
+            None
+        } else {
+            Some(Self {
+                first_seen,
+                last_seen,
+                visit_count,
+            })
+        }
+    }
+
+    fn to_value(self) -> String {
+        format!(
+            "{}.{}.{}",
+            to_base36(self.first_seen as u64),
+            to_base36(self.last_seen as u64),
+            to_base36(self.visit_count as u64)
+        )
+    }
+}
+
+fn to_base36(mut n: u64) -> String {
+    let mut digits = Vec::new();
+
+    loop {
+        digits.push(std::char::from_digit((n % 36) as u32, 36).unwrap());
+        n /= 36;
+
+        if n == 0 {
+            break;
+        }
+    }
+
+    digits.iter().rev().collect()
+}
+
+/// Records a page view at `now` (timestamp in milliseconds, UTC) in the visitor cookie `name`
+/// of `cookie_string`. Returns the information about the visitor, with the cookie string that
+/// updates the cookie, or `None` if it doesn't need to be updated.
+///
+/// A missing or malformed cookie starts a new visitor. A last activity in the future (after a
+/// change of the clock) doesn't start a new visit.
+///
+/// The options should have an expiration date, so the cookie outlives the browser session.
+pub fn visit(
+    cookie_string: &str,
+    name: &str,
+    now: i64,
+    options: &CookieOptions,
+) -> (VisitorInfo, Option<String>) {
+    let now_secs = now.max(0) / 1000;
+    let previous =
+        cookies::get_raw(cookie_string, name).and_then(|value| VisitorState::parse(&value));
+
+    let state = match previous {
+        None => VisitorState {
+            first_seen: now_secs,
+            last_seen: now_secs,
+            visit_count: 1,
+        },
+
+        Some(state) if now_secs - state.last_seen > VISIT_GAP / 1000 => VisitorState {
+            last_seen: now_secs,
+            visit_count: state.visit_count.saturating_add(1),
+            ..state
+        },
+
+        Some(state) if now_secs - state.last_seen >= UPDATE_INTERVAL / 1000 => VisitorState {
+            last_seen: now_secs,
+            ..state
+        },
+
+        Some(state) => state,
+    };
+
+    let info = VisitorInfo {
+        first_seen: state.first_seen * 1000,
+        visit_count: state.visit_count,
+        is_new: state.visit_count == 1,
+    };
+
+    let update = if previous == Some(state) {
+        None
+    } else {
+        Some(cookies::set_raw(name, &state.to_value(), options))
+    };
+
+    (info, update)
+}
+
+/// Browser functions, which operate directly on the document's cookies.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub mod browser {
+    use super::VisitorInfo;
+    use crate::cookies::{self, CookieOptions};
+
+    /// Records a page view in the visitor cookie, and returns the information about the
+    /// visitor.
+    ///
+    /// The options should have an expiration date, so the cookie outlives the browser session.
+    pub fn visitor_info(options: &CookieOptions) -> VisitorInfo {
+        let (info, update) = super::visit(
+            &crate::cookie_string(),
+            super::COOKIE_NAME,
+            cookies::now_timestamp(),
+            options,
+        );
+
+        if let Some(update) = update {
+            crate::set_cookie_string(&update);
+        }
+
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn cookie_string(update: Option<String>) -> String {
+        update.unwrap().split(';').next().unwrap().to_owned()
+    }
+
+    #[test]
+    fn test_visits() {
+        let options = CookieOptions::ROOT;
+        let (info, update) = visit("", COOKIE_NAME, NOW, &options);
+        assert_eq!(
+            info,
+            VisitorInfo {
+                first_seen: NOW,
+                visit_count: 1,
+                is_new: true
+            }
+        );
+        let cookie = cookie_string(update);
+        assert_eq!(cookie, "visitor=s44we8.s44we8.1");
+
+        // Same visit: not rewritten before the update interval, still new.
+        let (info, update) = visit(&cookie, COOKIE_NAME, NOW + 60_000, &options);
+        assert!(info.is_new && update.is_none());
+        let (info, update) = visit(&cookie, COOKIE_NAME, NOW + UPDATE_INTERVAL, &options);
+        assert!(info.is_new);
+        let cookie = cookie_string(update);
+
+        // New visit after the gap.
+        let later = NOW + UPDATE_INTERVAL + VISIT_GAP + 1000;
+        let (info, _) = visit(&cookie, COOKIE_NAME, later, &options);
+        assert_eq!(
+            info,
+            VisitorInfo {
+                first_seen: NOW,
+                visit_count: 2,
+                is_new: false
+            }
+        );
+
+        // Clock moved backwards.
+        let (info, update) = visit(&cookie, COOKIE_NAME, NOW - VISIT_GAP * 4, &options);
+        assert_eq!((info.visit_count, update), (1, None));
+    }
+
+    #[test]
+    fn test_malformed() {
+        for value in ["", "a.b", "1.2.3.4", "2.1.1", "1.2.0", "-1.2.1", "x!.1.1"] {
+            let (info, update) = visit(
+                &format!("visitor={}", value),
+                COOKIE_NAME,
+                NOW,
+                &CookieOptions::ROOT,
+            );
+            assert!(info.is_new && update.is_some(), "{}", value);
+        }
+    }
+}
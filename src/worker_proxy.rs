@@ -0,0 +1,336 @@
+//! Cookie access from dedicated Web Workers, where `document.cookie` is unavailable.
+//!
+//! A [WorkerAgent](struct.WorkerAgent.html) is attached to a `Worker` on the main thread, and a
+//! [WorkerClient](struct.WorkerClient.html) is created inside the worker. The client forwards
+//! reads and writes of the cookie string to the agent over `postMessage`, and all the parsing and
+//! formatting is done on the worker side with the functions of the [cookies](../cookies/index.html)
+//! module. Since the main thread answers asynchronously, the client's functions take a callback.
+//!
+//! The message protocol itself is available on every target, so it can be tested or reused with
+//! other transports.
+
+#[cfg(all(target_family = "wasm", feature = "encoding", not(feature = "small")))]
+use crate::cookies::AllDecodeError;
+#[cfg(all(target_family = "wasm", feature = "encoding"))]
+use crate::cookies::GetDecodeError;
+#[cfg(target_family = "wasm")]
+use crate::cookies::{self, CookieOptions};
+#[cfg(target_family = "wasm")]
+use std::cell::RefCell;
+#[cfg(target_family = "wasm")]
+use std::collections::HashMap;
+#[cfg(target_family = "wasm")]
+use std::rc::Rc;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+#[cfg(target_family = "wasm")]
+use web_sys::{DedicatedWorkerGlobalScope, MessageEvent, Worker};
+
+const MESSAGE_PREFIX: &str = "wasm-cookies:";
+
+/// A request sent by a worker to the main thread.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Request {
+    /// Read the cookie string.
+    Read,
+
+    /// Write the given cookie string (as returned by `cookies::set` or `cookies::delete`).
+    Write(String),
+}
+
+/// Returns the message that sends `request`, identified by `id`.
+pub fn encode_request(id: u32, request: &Request) -> String {
+    match request {
+        Request::Read => format!("{}{}:read", MESSAGE_PREFIX, id),
+        Request::Write(cookie_string) => {
+            format!("{}{}:write:{}", MESSAGE_PREFIX, id, cookie_string)
+        }
+    }
+}
+
+/// Parses a message sent with [encode_request](fn.encode_request.html), and returns its
+/// identifier and its request. Returns `None` if the message isn't a request of this protocol.
+pub fn decode_request(message: &str) -> Option<(u32, Request)> {
+    let (id, request) = message.strip_prefix(MESSAGE_PREFIX)?.split_once(':')?;
+    let id = id.parse().ok()?;
+
+    if request == "read" {
+        Some((id, Request::Read))
+    } else {
+        let cookie_string = request.strip_prefix("write:")?;
+        Some((id, Request::Write(cookie_string.to_owned())))
+    }
+}
+
+/// Returns the message that answers the request identified by `id` with the cookie string
+/// (after the write, for a write request).
+pub fn encode_response(id: u32, cookie_string: &str) -> String {
+    format!("{}{}={}", MESSAGE_PREFIX, id, cookie_string)
+}
+
+/// Parses a message sent with [encode_response](fn.encode_response.html), and returns the
+/// identifier of the answered request and the cookie string. Returns `None` if the message isn't
+/// a response of this protocol.
+pub fn decode_response(message: &str) -> Option<(u32, &str)> {
+    let (id, cookie_string) = message.strip_prefix(MESSAGE_PREFIX)?.split_once('=')?;
+    Some((id.parse().ok()?, cookie_string))
+}
+
+/// Main thread side of the proxy, which answers the requests of a worker.
+///
+/// The agent stops answering when dropped.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub struct WorkerAgent {
+    worker: Worker,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+#[cfg(target_family = "wasm")]
+impl WorkerAgent {
+    /// Starts answering the cookie requests of `worker`.
+    pub fn attach(worker: &Worker) -> Self {
+        let target = worker.clone();
+
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let request = event
+                .data()
+                .as_string()
+                .and_then(|message| decode_request(&message));
+
+            if let Some((id, request)) = request {
+                if let Request::Write(cookie_string) = request {
+                    crate::set_cookie_string(&cookie_string);
+                }
+
+                let response = encode_response(id, &crate::cookie_string());
+                target.post_message(&JsValue::from_str(&response)).unwrap();
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        worker
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())
+            .unwrap();
+
+        Self {
+            worker: worker.clone(),
+            on_message,
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl Drop for WorkerAgent {
+    fn drop(&mut self) {
+        let _ = self.worker.remove_event_listener_with_callback(
+            "message",
+            self.on_message.as_ref().unchecked_ref(),
+        );
+    }
+}
+
+/// Callbacks waiting for the cookie string, by request identifier.
+#[cfg(target_family = "wasm")]
+type PendingCallbacks = HashMap<u32, Box<dyn FnOnce(&str)>>;
+
+#[cfg(target_family = "wasm")]
+#[derive(Default)]
+struct ClientState {
+    next_id: u32,
+    pending: PendingCallbacks,
+}
+
+/// Worker side of the proxy, which provides the same functions as the root module, but
+/// asynchronous: the result is passed to a callback once the main thread has answered.
+///
+/// The main thread must have attached a [WorkerAgent](struct.WorkerAgent.html) to the worker.
+/// Pending callbacks are never called if the client is dropped.
+///
+/// Available only on WebAssembly targets (`wasm32` and `wasm64`).
+#[cfg(target_family = "wasm")]
+pub struct WorkerClient {
+    scope: DedicatedWorkerGlobalScope,
+    state: Rc<RefCell<ClientState>>,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+#[cfg(target_family = "wasm")]
+impl WorkerClient {
+    /// Creates a client. Must be called inside a dedicated worker.
+    pub fn new() -> Self {
+        let scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+        let state = Rc::new(RefCell::new(ClientState::default()));
+        let handler_state = Rc::clone(&state);
+
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let message = match event.data().as_string() {
+                Some(message) => message,
+                None => return,
+            };
+
+            if let Some((id, cookie_string)) = decode_response(&message) {
+                let callback = handler_state.borrow_mut().pending.remove(&id);
+
+                if let Some(callback) = callback {
+                    callback(cookie_string);
+                }
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        scope
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())
+            .unwrap();
+
+        Self {
+            scope,
+            state,
+            on_message,
+        }
+    }
+
+    fn request(&self, request: Request, callback: impl FnOnce(&str) + 'static) {
+        let id = {
+            let mut state = self.state.borrow_mut();
+            let id = state.next_id;
+            state.next_id = state.next_id.wrapping_add(1);
+            state.pending.insert(id, Box::new(callback));
+            id
+        };
+
+        self.scope
+            .post_message(&JsValue::from_str(&encode_request(id, &request)))
+            .unwrap();
+    }
+
+    /// Passes all cookies, with undecoded keys and values, to `callback`.
+    ///
+    /// Not available with the `small` feature.
+    #[cfg(not(feature = "small"))]
+    pub fn all_raw(&self, callback: impl FnOnce(HashMap<String, String>) + 'static) {
+        self.request(Request::Read, move |cookie_string| {
+            callback(cookies::all_raw(cookie_string))
+        });
+    }
+
+    /// Passes all cookies, with URI decoded keys and values
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)),
+    /// or an error if URI decoding fails on a key or a value, to `callback`.
+    ///
+    /// Not available with the `small` feature.
+    #[cfg(all(feature = "encoding", not(feature = "small")))]
+    pub fn all(
+        &self,
+        callback: impl FnOnce(Result<HashMap<String, String>, AllDecodeError>) + 'static,
+    ) {
+        self.request(Request::Read, move |cookie_string| {
+            callback(cookies::all(cookie_string))
+        });
+    }
+
+    /// Passes the undecoded cookie, if it exists, to `callback`.
+    pub fn get_raw(&self, name: &str, callback: impl FnOnce(Option<String>) + 'static) {
+        let name = name.to_owned();
+
+        self.request(Request::Read, move |cookie_string| {
+            callback(cookies::get_raw(cookie_string, &name))
+        });
+    }
+
+    /// If it exists, passes the URI decoded cookie
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding))
+    /// or an error if the value's URI decoding fails, to `callback`.
+    #[cfg(feature = "encoding")]
+    pub fn get(
+        &self,
+        name: &str,
+        callback: impl FnOnce(Option<Result<String, GetDecodeError>>) + 'static,
+    ) {
+        let name = name.to_owned();
+
+        self.request(Request::Read, move |cookie_string| {
+            callback(cookies::get(cookie_string, &name))
+        });
+    }
+
+    /// Sets a cookie, with non encoded name and value.
+    pub fn set_raw(&self, name: &str, value: &str, options: &CookieOptions) {
+        self.request(
+            Request::Write(cookies::set_raw(name, value, options)),
+            |_| {},
+        );
+    }
+
+    /// Sets a cookie, with URI encoded name and value
+    /// (with the [urlencoding crate](https://crates.io/crates/urlencoding)).
+    #[cfg(feature = "encoding")]
+    pub fn set(&self, name: &str, value: &str, options: &CookieOptions) {
+        self.request(Request::Write(cookies::set(name, value, options)), |_| {});
+    }
+
+    /// Deletes a cookie without encoding its name.
+    pub fn delete_raw(&self, name: &str) {
+        self.request(Request::Write(cookies::delete_raw(name)), |_| {});
+    }
+
+    /// Deletes a cookie, URI encoding its name.
+    #[cfg(feature = "encoding")]
+    pub fn delete(&self, name: &str) {
+        self.request(Request::Write(cookies::delete(name)), |_| {});
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl Default for WorkerClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl Drop for WorkerClient {
+    fn drop(&mut self) {
+        let _ = self.scope.remove_event_listener_with_callback(
+            "message",
+            self.on_message.as_ref().unchecked_ref(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request() {
+        assert_eq!(encode_request(1, &Request::Read), "wasm-cookies:1:read");
+        assert_eq!(
+            decode_request("wasm-cookies:1:read"),
+            Some((1, Request::Read))
+        );
+
+        let request = Request::Write("key=value;path=/;samesite=lax".to_owned());
+        assert_eq!(
+            decode_request(&encode_request(42, &request)),
+            Some((42, request))
+        );
+
+        assert_eq!(decode_request("other message"), None);
+        assert_eq!(decode_request("wasm-cookies:a:read"), None);
+        assert_eq!(decode_request("wasm-cookies:1:unknown"), None);
+    }
+
+    #[test]
+    fn test_response() {
+        let response = encode_response(7, "key1=value1; key2=value2");
+        assert_eq!(response, "wasm-cookies:7=key1=value1; key2=value2");
+        assert_eq!(
+            decode_response(&response),
+            Some((7, "key1=value1; key2=value2"))
+        );
+
+        assert_eq!(decode_response("wasm-cookies:7="), Some((7, "")));
+        assert_eq!(decode_response("wasm-cookies:1:read"), None);
+        assert_eq!(decode_response("other message"), None);
+    }
+}